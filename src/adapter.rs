@@ -0,0 +1,1400 @@
+//! Version files of other ecosystems. Besides the Cargo manifest, a project
+//! can list files in the `sync` configuration whose version is kept in step
+//! with it; each format is rewritten in place, changing only the version
+//! literal and preserving everything around it.
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use semver::Version;
+use toml_edit::{value, Document};
+
+use super::read_version;
+use crate::cache;
+use crate::config::Config;
+use crate::error::{Code, Error};
+use crate::plugin;
+use crate::scan;
+use crate::transaction::Transaction;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// `Cargo.toml`: `[package] version`.
+    #[default]
+    Cargo,
+    /// Python `pyproject.toml`: `[project] version` (PEP 621) or, failing
+    /// that, `[tool.poetry] version`.
+    Pyproject,
+    /// CocoaPods `*.podspec`: `s.version = 'x.y.z'`.
+    Podspec,
+    /// npm `package.json`: `"version"`.
+    PackageJson,
+    /// `package-lock.json`/`npm-shrinkwrap.json`: the root `"version"` and,
+    /// for lockfile v2/v3, `"packages": {"": {"version"}}`.
+    NpmLock,
+    /// Tauri `tauri.conf.json`: `"package": {"version"}` (Tauri 1) or a
+    /// top-level `"version"` (Tauri 2).
+    Tauri,
+    /// `electron-builder.yml`: `buildVersion` and `extraMetadata.version`.
+    ElectronBuilder,
+    /// Helm `Chart.yaml`: the top-level `version` of the chart.
+    Helm,
+    /// Nix `flake.nix`/`default.nix`: the outermost `version = "x.y.z";`.
+    Nix,
+    /// Zig `build.zig.zon`: the top-level `.version = "x.y.z"`.
+    Zon,
+    /// Go `*.go` files: `const Version = "x.y.z"`.
+    Go,
+    /// CycloneDX SBOM `bom.json`/`*.cdx.json`: `"metadata": {"component":
+    /// {"version"}}`, the component the SBOM describes.
+    CycloneDx,
+    /// SPDX SBOM `*.spdx.json`: the `"versionInfo"` of the first of the
+    /// `"packages"`, the one an SBOM skeleton describes.
+    Spdx,
+    /// A `VERSION` or `version.txt` file holding nothing but the version.
+    Plain,
+    /// A format provided by the loaded plugin with the given index.
+    Plugin(usize),
+}
+
+impl Format {
+    /// Detects the format of a version file from its name.
+    pub fn detect(path: &str) -> Result<Format, Error> {
+        let name = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        if name == "pyproject.toml" {
+            Ok(Format::Pyproject)
+        } else if name.ends_with(".toml") {
+            Ok(Format::Cargo)
+        } else if name.ends_with(".podspec") {
+            Ok(Format::Podspec)
+        } else if name == "package.json" {
+            Ok(Format::PackageJson)
+        } else if name == "package-lock.json" || name == "npm-shrinkwrap.json" {
+            Ok(Format::NpmLock)
+        } else if name == "tauri.conf.json" {
+            Ok(Format::Tauri)
+        } else if name == "bom.json" || name.ends_with(".cdx.json") {
+            Ok(Format::CycloneDx)
+        } else if name.ends_with(".spdx.json") {
+            Ok(Format::Spdx)
+        } else if name == "electron-builder.yml" || name == "electron-builder.yaml" {
+            Ok(Format::ElectronBuilder)
+        } else if name == "Chart.yaml" {
+            Ok(Format::Helm)
+        } else if name.ends_with(".nix") {
+            Ok(Format::Nix)
+        } else if name.ends_with(".zon") {
+            Ok(Format::Zon)
+        } else if name.ends_with(".go") {
+            Ok(Format::Go)
+        } else if name == "VERSION" || name == "version.txt" {
+            Ok(Format::Plain)
+        } else if let Some(plugin) = plugin::detect(path)? {
+            Ok(Format::Plugin(plugin))
+        } else {
+            Err(Error::new(
+                Code::Adapter,
+                format!("Unsupported version file format: {}", path),
+            ))
+        }
+    }
+
+    /// The file name of a manifest of the format, for the formats
+    /// `--manifest-format` reads besides Cargo's.
+    pub fn manifest_name(self) -> Option<&'static str> {
+        match self {
+            Format::PackageJson => Some("package.json"),
+            Format::Pyproject => Some("pyproject.toml"),
+            Format::Plain => Some("VERSION"),
+            _ => None,
+        }
+    }
+
+    /// The format of a manifest with the file name of `path`, for
+    /// `--manifest-format auto`: Cargo's unless the name is that of another
+    /// format's manifest.
+    pub fn detect_manifest(path: &str) -> Format {
+        match Path::new(path).file_name().and_then(|name| name.to_str()) {
+            Some("package.json") => Format::PackageJson,
+            Some("pyproject.toml") => Format::Pyproject,
+            Some("VERSION") | Some("version.txt") => Format::Plain,
+            _ => Format::Cargo,
+        }
+    }
+
+    /// The package name recorded in the file, for formats that have one.
+    pub fn package_name(self, contents: &str) -> Option<String> {
+        match self {
+            Format::Cargo => contents
+                .parse::<Document>()
+                .ok()
+                .and_then(|manifest| manifest["package"]["name"].as_str().map(String::from)),
+            Format::PackageJson => {
+                json_string_at(contents, &["name"]).map(|range| contents[range].to_string())
+            }
+            Format::Pyproject => contents.parse::<Document>().ok().and_then(|manifest| {
+                manifest["project"]["name"]
+                    .as_str()
+                    .or_else(|| manifest["tool"]["poetry"]["name"].as_str())
+                    .map(String::from)
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn read_version(self, contents: &str, path: &str) -> Result<Version, Error> {
+        match self {
+            Format::Cargo => read_version(&parse_manifest(contents, path)?),
+            Format::Plugin(plugin) => plugin::read_version(plugin, contents, path),
+            _ => {
+                let ranges = self.version_ranges(contents);
+                let range = ranges.first().ok_or_else(|| missing_version(path))?;
+                parse_version(&contents[range.clone()], path)
+            }
+        }
+    }
+
+    /// Returns `contents` with the version replaced by `version`.
+    pub fn write_version(
+        self,
+        contents: &str,
+        version: &Version,
+        path: &str,
+    ) -> Result<String, Error> {
+        match self {
+            Format::Cargo => {
+                let mut manifest = parse_manifest(contents, path)?;
+                manifest["package"]["version"] = value(version.to_string());
+                Ok(manifest.to_string())
+            }
+            Format::Plugin(plugin) => plugin::write_version(plugin, contents, version, path),
+            _ => {
+                let ranges = self.version_ranges(contents);
+                if ranges.is_empty() {
+                    return Err(missing_version(path));
+                }
+
+                let mut updated = contents.to_string();
+                for range in ranges.into_iter().rev() {
+                    updated.replace_range(range, &version.to_string());
+                }
+                Ok(updated)
+            }
+        }
+    }
+
+    /// The byte ranges of every version literal in `contents`, in order. A
+    /// Cargo manifest is only located in the common layout `scan` reads;
+    /// plugin formats are never located.
+    pub fn locate(self, contents: &str) -> Vec<Range<usize>> {
+        match self {
+            Format::Cargo => scan::package_version_range(contents).into_iter().collect(),
+            _ => self.version_ranges(contents),
+        }
+    }
+
+    /// The byte ranges of every version literal of a text format, in order;
+    /// the first one is the version the file is read as.
+    fn version_ranges(self, contents: &str) -> Vec<Range<usize>> {
+        let ranges = match self {
+            Format::Cargo | Format::Plugin(_) => vec![],
+            Format::Pyproject => vec![pyproject_version(contents)],
+            Format::Podspec => vec![podspec_version(contents)],
+            Format::PackageJson => vec![json_string_at(contents, &["version"])],
+            Format::NpmLock => vec![
+                json_string_at(contents, &["version"]),
+                json_string_at(contents, &["packages", "", "version"]),
+            ],
+            Format::Tauri => vec![json_string_at(contents, &["package", "version"])
+                .or_else(|| json_string_at(contents, &["version"]))],
+            Format::ElectronBuilder => vec![
+                yaml_scalar_at(contents, &["buildVersion"]),
+                yaml_scalar_at(contents, &["extraMetadata", "version"]),
+            ],
+            Format::Helm => vec![yaml_scalar_at(contents, &["version"])],
+            Format::Nix => vec![nix_version(contents)],
+            Format::Zon => vec![zon_string_at(contents, &["version"])],
+            Format::Go => vec![go_version(contents)],
+            Format::CycloneDx => vec![json_string_at(
+                contents,
+                &["metadata", "component", "version"],
+            )],
+            Format::Spdx => vec![json_string_at(contents, &["packages", "0", "versionInfo"])],
+            Format::Plain => vec![plain_version(contents)],
+        };
+
+        ranges.into_iter().flatten().collect()
+    }
+}
+
+/// The 1-based line and column (in characters) of the byte `offset` of
+/// `contents`.
+pub fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+pub fn read_file(path: &str) -> Result<String, Error> {
+    fs::read_to_string(path)
+        .map_err(|err| Error::new(Code::Io, format!("Could not read {}: {}", path, err)))
+}
+
+/// Reads the version of the file at `path`, whatever its format.
+pub fn read_file_version(path: &str) -> Result<Version, Error> {
+    Format::detect(path)?.read_version(&read_file(path)?, path)
+}
+
+/// The files to keep in sync with the manifest: the configured ones, each
+/// followed by the existing files that must agree with it, such as the npm
+/// lockfile next to a `package.json` (npm refuses to install otherwise).
+pub fn sync_files(config: &Config) -> Vec<String> {
+    let mut files = vec![];
+    for path in config.sync_paths() {
+        let companions = match Format::detect(&path) {
+            Ok(Format::PackageJson) => ["package-lock.json", "npm-shrinkwrap.json"]
+                .iter()
+                .map(|name| Path::new(&path).with_file_name(name))
+                .filter(|companion| companion.exists())
+                .map(|companion| companion.to_string_lossy().into_owned())
+                .collect(),
+            _ => vec![],
+        };
+        files.push(path);
+        files.extend(companions);
+    }
+
+    files
+}
+
+/// Stages rewriting the version of the file at `path` to `version`.
+pub fn stage_version(
+    transaction: &mut Transaction,
+    path: &str,
+    version: &Version,
+) -> Result<(), Error> {
+    let contents = read_file(path)?;
+    let updated = Format::detect(path)?.write_version(&contents, version, path)?;
+    transaction.stage(path, updated);
+
+    Ok(())
+}
+
+/// Reads the version at the dotted `key` of the TOML, JSON or YAML file at
+/// `path`, for a configured version field.
+pub fn read_field_version(path: &str, key: &str) -> Result<Version, Error> {
+    let contents = read_file(path)?;
+    let keys = key.split('.').collect::<Vec<_>>();
+
+    if path.ends_with(".toml") {
+        let document = parse_manifest(&contents, path)?;
+        let version = keys
+            .iter()
+            .fold(&document.root, |item, key| &item[*key])
+            .as_str()
+            .ok_or_else(|| missing_field(path, key))?;
+        parse_version(version, path)
+    } else {
+        let range = text_field(&contents, path, &keys)?.ok_or_else(|| missing_field(path, key))?;
+        parse_version(&contents[range], path)
+    }
+}
+
+/// Stages rewriting the version at the dotted `key` of the file at `path`
+/// to `version`; the version must be there already.
+pub fn stage_field_version(
+    transaction: &mut Transaction,
+    path: &str,
+    key: &str,
+    version: &Version,
+) -> Result<(), Error> {
+    let contents = read_file(path)?;
+    let keys = key.split('.').collect::<Vec<_>>();
+
+    let updated = if path.ends_with(".toml") {
+        let mut document = parse_manifest(&contents, path)?;
+        let current = keys.iter().fold(&document.root, |item, key| &item[*key]);
+        if current.as_str().is_none() {
+            return Err(missing_field(path, key));
+        }
+        let item = keys
+            .iter()
+            .fold(&mut document.root, |item, key| &mut item[*key]);
+        *item = value(version.to_string());
+        document.to_string()
+    } else {
+        let range = text_field(&contents, path, &keys)?.ok_or_else(|| missing_field(path, key))?;
+        let mut updated = contents.clone();
+        updated.replace_range(range, &version.to_string());
+        updated
+    };
+    transaction.stage(path, updated);
+
+    Ok(())
+}
+
+/// The range of the string at the key path of a JSON or YAML file.
+fn text_field(contents: &str, path: &str, keys: &[&str]) -> Result<Option<Range<usize>>, Error> {
+    if path.ends_with(".json") {
+        Ok(json_string_at(contents, keys))
+    } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+        Ok(yaml_scalar_at(contents, keys))
+    } else {
+        Err(Error::new(
+            Code::Adapter,
+            format!(
+                "Version fields must be in TOML, JSON or YAML files, not {}",
+                path
+            ),
+        ))
+    }
+}
+
+fn missing_field(path: &str, key: &str) -> Error {
+    Error::new(
+        Code::Adapter,
+        format!("No version at `{}` in {}", key, path),
+    )
+}
+
+fn parse_manifest(contents: &str, path: &str) -> Result<Document, Error> {
+    cache::parse(path, contents)
+        .map_err(|err| Error::new(Code::ParseManifest, format!("Invalid {}: {}", path, err)))
+}
+
+fn parse_version(version: &str, path: &str) -> Result<Version, Error> {
+    Version::parse(version).map_err(|_| {
+        Error::new(
+            Code::Adapter,
+            format!("Invalid version {} in {}", version, path),
+        )
+    })
+}
+
+fn missing_version(path: &str) -> Error {
+    Error::new(Code::Adapter, format!("No version found in {}", path))
+}
+
+/// The version literal of the first `<spec>.version = '...'` assignment.
+fn podspec_version(contents: &str) -> Option<Range<usize>> {
+    string_assignment(contents, |target| {
+        let mut parts = target.split('.');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(spec), Some("version"), None) => {
+                !spec.is_empty() && spec.chars().all(|c| c.is_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Finds the first line of the form `<target> = "<literal>"` (or with
+/// single quotes) whose target satisfies `is_target`, and returns the byte
+/// range of the literal's contents within `contents`.
+fn string_assignment<F>(contents: &str, is_target: F) -> Option<Range<usize>>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut offset = 0;
+
+    for line in contents.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let equals = match line.find('=') {
+            Some(equals) => equals,
+            None => continue,
+        };
+        if !is_target(line[..equals].trim()) || line[equals + 1..].starts_with('=') {
+            continue;
+        }
+
+        let rest = &line[equals + 1..];
+        let literal = rest.trim_start();
+        let quote = match literal.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => continue,
+        };
+        let literal_start = start + equals + 1 + (rest.len() - literal.len()) + 1;
+        if let Some(length) = literal[1..].find(quote) {
+            return Some(literal_start..literal_start + length);
+        }
+    }
+
+    None
+}
+
+/// The version literal of `version = "..."` in the `[project]` table of a
+/// `pyproject.toml`, or else in its `[tool.poetry]` table.
+fn pyproject_version(contents: &str) -> Option<Range<usize>> {
+    ["project", "tool.poetry"].iter().find_map(|table| {
+        let body = toml_table_body(contents, table)?;
+        string_assignment(&contents[body.clone()], |target| target == "version")
+            .map(|range| body.start + range.start..body.start + range.end)
+    })
+}
+
+/// The byte range of the body of the `[table]` of a TOML document: the
+/// lines after its header up to the next header.
+fn toml_table_body(contents: &str, table: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    let mut start = None;
+
+    for line in contents.split_inclusive('\n') {
+        let header = line.split('#').next().unwrap_or_default().trim();
+        if header.starts_with('[') {
+            if let Some(start) = start {
+                return Some(start..offset);
+            }
+            let name = header.trim_start_matches('[').trim_end_matches(']');
+            if !header.starts_with("[[") && name.trim() == table {
+                start = Some(offset + line.len());
+            }
+        }
+        offset += line.len();
+    }
+
+    start.map(|start| start..contents.len())
+}
+
+/// The version literal of `const Version = "x.y.z"` (also `var`, with an
+/// explicit `string` type, or within a `const (...)` block).
+fn go_version(contents: &str) -> Option<Range<usize>> {
+    string_assignment(contents, |target| {
+        let words = target
+            .split_whitespace()
+            .skip_while(|word| *word == "const" || *word == "var")
+            .collect::<Vec<_>>();
+        words == ["Version"] || words == ["Version", "string"]
+    })
+    .map(|range| without_v_prefix(contents, range))
+}
+
+/// The whole (trimmed) contents of a plain `VERSION` file.
+fn plain_version(contents: &str) -> Option<Range<usize>> {
+    let start = contents.len() - contents.trim_start().len();
+    let end = contents.trim_end().len();
+    if start >= end {
+        return None;
+    }
+
+    Some(without_v_prefix(contents, start..end))
+}
+
+/// Leaves a conventional `v` prefix (`v1.2.3`) out of the version range, so
+/// that it is preserved when the version is rewritten.
+fn without_v_prefix(contents: &str, range: Range<usize>) -> Range<usize> {
+    if contents[range.clone()].starts_with('v') {
+        range.start + 1..range.end
+    } else {
+        range
+    }
+}
+
+/// The contents of the string at the given key path of a JSON document,
+/// e.g. `["package", "version"]`, where numbers index into arrays; `None` if
+/// it is missing, not a string or the document is malformed.
+pub fn json_string_at(contents: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut scanner = JsonScanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.seek(path)?;
+    match scanner.peek()? {
+        b'"' => scanner.string(),
+        _ => None,
+    }
+}
+
+/// The strings of the array at the given key path of a JSON document, e.g.
+/// the `"workspaces"` patterns of a `package.json`; other entries are
+/// skipped.
+pub fn json_strings_at(contents: &str, path: &[&str]) -> Option<Vec<String>> {
+    let mut scanner = JsonScanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.seek(path)?;
+    if scanner.peek()? != b'[' {
+        return None;
+    }
+    scanner.position += 1;
+    let mut strings = vec![];
+    loop {
+        scanner.skip_whitespace();
+        match scanner.peek()? {
+            b']' => return Some(strings),
+            b',' => scanner.position += 1,
+            b'"' => strings.push(contents[scanner.string()?].to_string()),
+            _ => scanner.skip_value()?,
+        }
+    }
+}
+
+/// The value at the given key path of a JSON document as it is written,
+/// e.g. `true` or `"rc"`; `None` if it is missing.
+pub fn json_value_at(contents: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut scanner = JsonScanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.seek(path)?;
+    let start = scanner.position;
+    scanner.skip_value()?;
+
+    Some(start..scanner.position)
+}
+
+/// The keys of the object at the given key path of a JSON document, e.g.
+/// the package names under the `"dependencies"` of a `package.json`.
+pub fn json_keys_at(contents: &str, path: &[&str]) -> Option<Vec<String>> {
+    let mut scanner = JsonScanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.seek(path)?;
+    if scanner.peek()? != b'{' {
+        return None;
+    }
+    scanner.position += 1;
+    let mut keys = vec![];
+    loop {
+        scanner.skip_whitespace();
+        match scanner.peek()? {
+            b'}' => return Some(keys),
+            b',' => scanner.position += 1,
+            b'"' => {
+                keys.push(contents[scanner.string()?].to_string());
+                scanner.skip_whitespace();
+                if scanner.peek()? != b':' {
+                    return None;
+                }
+                scanner.position += 1;
+                scanner.skip_whitespace();
+                scanner.skip_value()?;
+            }
+            _ => return None,
+        }
+    }
+}
+
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    /// Moves to the start of the value at `path` within the value at the
+    /// current position.
+    fn seek(&mut self, path: &[&str]) -> Option<()> {
+        self.skip_whitespace();
+        let (key, rest) = match path.split_first() {
+            None => return Some(()),
+            Some(split) => split,
+        };
+        if self.peek()? == b'[' {
+            return self.seek_element(key.parse().ok()?, rest);
+        }
+        if self.peek()? != b'{' {
+            return None;
+        }
+
+        self.position += 1;
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                b'}' => return None,
+                b',' => self.position += 1,
+                b'"' => {
+                    let name = self.string()?;
+                    self.skip_whitespace();
+                    if self.peek()? != b':' {
+                        return None;
+                    }
+                    self.position += 1;
+                    if &self.bytes[name] == key.as_bytes() {
+                        return self.seek(rest);
+                    }
+                    self.skip_value()?;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Moves to the start of the value at `path` within the element at
+    /// `index` of the array at the current position.
+    fn seek_element(&mut self, index: usize, path: &[&str]) -> Option<()> {
+        self.position += 1;
+        let mut current = 0;
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                b']' => return None,
+                b',' => self.position += 1,
+                _ if current == index => return self.seek(path),
+                _ => {
+                    self.skip_value()?;
+                    current += 1;
+                }
+            }
+        }
+    }
+
+    /// Consumes a string and returns the range of its contents.
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.position + 1;
+        let mut index = start;
+        while index < self.bytes.len() {
+            match self.bytes[index] {
+                b'\\' => index += 2,
+                b'"' => {
+                    self.position = index + 1;
+                    return Some(start..index);
+                }
+                _ => index += 1,
+            }
+        }
+
+        None
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => self.string().map(|_| ()),
+            open @ b'{' | open @ b'[' => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.position += 1;
+                loop {
+                    self.skip_whitespace();
+                    match self.peek()? {
+                        byte if byte == close => {
+                            self.position += 1;
+                            return Some(());
+                        }
+                        b',' | b':' => self.position += 1,
+                        _ => self.skip_value()?,
+                    }
+                }
+            }
+            _ => {
+                while let Some(byte) = self.peek() {
+                    if byte == b',' || byte == b'}' || byte == b']' || byte.is_ascii_whitespace() {
+                        break;
+                    }
+                    self.position += 1;
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+}
+
+/// The scalar at the given key path of a YAML document made of block
+/// mappings, e.g. `["extraMetadata", "version"]`, without its quotes.
+fn yaml_scalar_at(contents: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut parents: Vec<(usize, &str)> = vec![];
+    let mut offset = 0;
+
+    for line in contents.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let text = line.trim_end();
+        let content = text.trim_start();
+        if content.is_empty() || content.starts_with('#') || content.starts_with('-') {
+            continue;
+        }
+        let indent = text.len() - content.len();
+        let colon = match content.find(':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let key = content[..colon]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        let rest = &content[colon + 1..];
+
+        while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+            parents.pop();
+        }
+        let depth = parents.len();
+        let on_path = depth < path.len()
+            && parents
+                .iter()
+                .zip(path)
+                .all(|((_, parent), wanted)| parent == wanted)
+            && key == path[depth];
+
+        let scalar = rest.split(" #").next().unwrap_or_default().trim();
+        if scalar.is_empty() {
+            parents.push((indent, key));
+            continue;
+        }
+        if on_path && depth == path.len() - 1 {
+            let scalar_start = start + indent + colon + 1 + rest.find(scalar)?;
+            let quoted = scalar.len() >= 2
+                && (scalar.starts_with('"') && scalar.ends_with('"')
+                    || scalar.starts_with('\'') && scalar.ends_with('\''));
+            return Some(if quoted {
+                scalar_start + 1..scalar_start + scalar.len() - 1
+            } else {
+                scalar_start..scalar_start + scalar.len()
+            });
+        }
+    }
+
+    None
+}
+
+/// The literal of the `version = "...";` binding nested least deeply in
+/// braces (the first one among equals), skipping comments, strings and
+/// dotted attribute paths such as `meta.version`, so that versions of
+/// dependencies fetched further down are left alone. Versions that are not
+/// plain literals (interpolations, references) are not considered.
+fn nix_version(contents: &str) -> Option<Range<usize>> {
+    let bytes = contents.as_bytes();
+    let mut best: Option<(usize, Range<usize>)> = None;
+    let mut depth = 0usize;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'#' => index = find_from(bytes, index, b"\n").unwrap_or(bytes.len()),
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                index = find_from(bytes, index + 2, b"*/").map_or(bytes.len(), |end| end + 2)
+            }
+            b'"' => index = skip_nix_string(bytes, index + 1),
+            b'\'' if bytes.get(index + 1) == Some(&b'\'') => {
+                index = skip_nix_indented_string(bytes, index + 2)
+            }
+            b'{' => {
+                depth += 1;
+                index += 1;
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                index += 1;
+            }
+            byte if byte.is_ascii_alphabetic() || byte == b'_' => {
+                let start = index;
+                while index < bytes.len() && is_nix_identifier(bytes[index]) {
+                    index += 1;
+                }
+                let dotted =
+                    start > 0 && bytes[start - 1] == b'.' || bytes.get(index) == Some(&b'.');
+                if &contents[start..index] != "version" || dotted {
+                    continue;
+                }
+                if let Some(range) = nix_string_binding(contents, index) {
+                    if best
+                        .as_ref()
+                        .is_none_or(|(best_depth, _)| depth < *best_depth)
+                    {
+                        best = Some((depth, range));
+                    }
+                }
+            }
+            _ => index += 1,
+        }
+    }
+
+    best.map(|(_, range)| range)
+}
+
+/// The literal of `= "...";` following an attribute name ending at `index`.
+fn nix_string_binding(contents: &str, index: usize) -> Option<Range<usize>> {
+    let rest = contents[index..].trim_start().strip_prefix('=')?;
+    if rest.starts_with('=') {
+        return None;
+    }
+    let literal = rest.trim_start().strip_prefix('"')?;
+    let length = literal.find('"')?;
+    if literal[..length].contains('\\') || literal[..length].contains("${") {
+        return None;
+    }
+    if !literal[length + 1..].trim_start().starts_with(';') {
+        return None;
+    }
+
+    let start = contents.len() - literal.len();
+    Some(start..start + length)
+}
+
+fn is_nix_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' || byte == b'\''
+}
+
+fn find_from(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes[from.min(bytes.len())..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| from + position)
+}
+
+/// Skips a `"..."` string whose contents start at `index`, including
+/// escapes and `${...}` interpolations; returns the index after it.
+fn skip_nix_string(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return index + 1,
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                index = skip_interpolation(bytes, index + 2)
+            }
+            _ => index += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Skips a `''...''` string whose contents start at `index`; `'''`, `''$`
+/// and `''\` are escapes within it.
+fn skip_nix_indented_string(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\'' if bytes.get(index + 1) == Some(&b'\'') => match bytes.get(index + 2) {
+                Some(b'\'') | Some(b'$') => index += 3,
+                Some(b'\\') => index += 4,
+                _ => return index + 2,
+            },
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                index = skip_interpolation(bytes, index + 2)
+            }
+            _ => index += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Skips the rest of a `${...}` interpolation starting at `index`.
+fn skip_interpolation(bytes: &[u8], mut index: usize) -> usize {
+    let mut depth = 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' => index = skip_nix_string(bytes, index + 1),
+            b'{' => {
+                depth += 1;
+                index += 1;
+            }
+            b'}' => {
+                depth -= 1;
+                index += 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => index += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// The contents of the string at the given field path of a Zig `.zon`
+/// document, e.g. `["version"]` for the top-level `.version`.
+fn zon_string_at(contents: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut scanner = ZonScanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.find(path)
+}
+
+/// A parser for the subset of Zig expressions `.zon` files consist of:
+/// anonymous struct and tuple literals (`.{ ... }`), strings, multiline
+/// strings, enum literals, numbers and identifiers.
+struct ZonScanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ZonScanner<'a> {
+    /// Looks for `path` within the expression at the current position,
+    /// leaving the position after that expression if it is not found there.
+    fn find(&mut self, path: &[&str]) -> Option<Range<usize>> {
+        self.skip_trivia();
+        let (field, rest) = match path.split_first() {
+            None if self.peek()? == b'"' => return self.string(),
+            None => return None,
+            Some(split) => split,
+        };
+        if !self.bytes[self.position..].starts_with(b".{") {
+            return None;
+        }
+        self.position += 2;
+
+        loop {
+            self.skip_trivia();
+            match self.peek()? {
+                b'}' => return None,
+                b',' => self.position += 1,
+                b'.' => {
+                    self.position += 1;
+                    let name = self.identifier()?;
+                    self.skip_trivia();
+                    if self.peek()? != b'=' {
+                        return None;
+                    }
+                    self.position += 1;
+                    if name == field.as_bytes() {
+                        return self.find(rest);
+                    }
+                    self.skip_expression()?;
+                }
+                _ => self.skip_expression()?,
+            }
+        }
+    }
+
+    /// Consumes an identifier, plain or `@"quoted"`, and returns its name.
+    fn identifier(&mut self) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        if self.peek()? == b'@' {
+            self.position += 1;
+            return self.string().map(|range| &bytes[range]);
+        }
+
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        {
+            self.position += 1;
+        }
+        if self.position == start {
+            return None;
+        }
+
+        Some(&bytes[start..self.position])
+    }
+
+    /// Consumes a string literal and returns the range of its contents.
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.position + 1;
+        let mut index = start;
+        while index < self.bytes.len() {
+            match self.bytes[index] {
+                b'\\' => index += 2,
+                b'"' => {
+                    self.position = index + 1;
+                    return Some(start..index);
+                }
+                b'\n' => return None,
+                _ => index += 1,
+            }
+        }
+
+        None
+    }
+
+    fn skip_expression(&mut self) -> Option<()> {
+        self.skip_trivia();
+        match self.peek()? {
+            b'"' => self.string().map(|_| ()),
+            b'\\' => {
+                // Multiline string: consecutive lines starting with `\\`.
+                while self.bytes[self.position..].starts_with(b"\\\\") {
+                    self.skip_line();
+                    self.skip_whitespace();
+                }
+                Some(())
+            }
+            b'.' if self.bytes.get(self.position + 1) == Some(&b'{') => {
+                self.position += 2;
+                loop {
+                    self.skip_trivia();
+                    match self.peek()? {
+                        b'}' => {
+                            self.position += 1;
+                            return Some(());
+                        }
+                        b',' | b'=' => self.position += 1,
+                        b'.' if self.bytes.get(self.position + 1) != Some(&b'{') => {
+                            self.position += 1;
+                            self.identifier()?;
+                        }
+                        _ => self.skip_expression()?,
+                    }
+                }
+            }
+            b'.' => {
+                self.position += 1;
+                self.identifier().map(|_| ())
+            }
+            _ => {
+                let start = self.position;
+                while self.peek().is_some_and(|byte| {
+                    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' || byte == b'+'
+                }) {
+                    self.position += 1;
+                }
+                if self.position == start {
+                    return None;
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Skips whitespace and `//` comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
+            if self.bytes[self.position..].starts_with(b"//") {
+                self.skip_line();
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek().is_some_and(|byte| byte != b'\n') {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use semver::Version;
+
+    use super::*;
+
+    /// Tests that only the version literal of a podspec is rewritten.
+    #[test]
+    fn test_podspec() {
+        let podspec = "Pod::Spec.new do |s|\n  s.name     = 'MySDK'\n  s.version  = '1.2.3'\n  \
+                       s.source   = { :git => 'x', :tag => s.version.to_s }\nend\n";
+        let format = Format::detect("ios/MySDK.podspec").unwrap();
+
+        assert_eq!(
+            format.read_version(podspec, "MySDK.podspec").unwrap(),
+            Version::parse("1.2.3").unwrap()
+        );
+        assert_eq!(
+            format
+                .write_version(
+                    podspec,
+                    &Version::parse("1.3.0-rc.1").unwrap(),
+                    "MySDK.podspec"
+                )
+                .unwrap(),
+            podspec.replace("'1.2.3'", "'1.3.0-rc.1'")
+        );
+        assert!(format.read_version("s.name = 'x'\n", "x.podspec").is_err());
+    }
+    /// Tests that JSON versions are found by key path, skipping nested
+    /// values and strings that merely contain the key.
+    #[test]
+    fn test_json() {
+        let tauri = "{\n  \"build\": {\"cmd\": [\"a\", {\"version\": \"0.0.1\"}]},\n  \
+                     \"desc\": \"\\\"version\\\"\",\n  \"package\": {\"productName\": \"x\", \"version\": \"1.2.3\"}\n}\n";
+        let updated = Format::Tauri
+            .write_version(tauri, &Version::parse("1.3.0").unwrap(), "tauri.conf.json")
+            .unwrap();
+
+        assert_eq!(updated, tauri.replace("\"1.2.3\"", "\"1.3.0\""));
+        assert_eq!(
+            Format::PackageJson
+                .read_version(
+                    "{\"name\": \"app\", \"version\": \"2.0.0\"}",
+                    "package.json"
+                )
+                .unwrap(),
+            Version::parse("2.0.0").unwrap()
+        );
+        assert!(json_string_at("{\"version\": 1}", &["version"]).is_none());
+        assert_eq!(
+            json_keys_at(
+                "{\"dependencies\": {\"a\": \"^1\", \"@x/b\": {\"v\": [1]}}}",
+                &["dependencies"]
+            ),
+            Some(vec![String::from("a"), String::from("@x/b")])
+        );
+    }
+
+    /// Tests that versions are located by line and column.
+    #[test]
+    fn test_locate() {
+        let contents = "{\n  \"name\": \"é\", \"version\": \"1.2.3\"\n}\n";
+        let ranges = Format::PackageJson.locate(contents);
+
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(&contents[ranges[0].clone()], "1.2.3");
+        assert_eq!(line_column(contents, ranges[0].start), (2, 28));
+        assert!(Format::Cargo
+            .locate("package = { version = \"1.0.0\" }\n")
+            .is_empty());
+    }
+
+    /// Tests that every version field of electron-builder.yml is rewritten.
+    #[test]
+    fn test_electron_builder() {
+        let config = "appId: com.example\nbuildVersion: \"1.2.3\" # build\ndirectories:\n  \
+                      version: 9.9.9\nextraMetadata:\n  main: main.js\n  version: 1.2.3\n";
+        let updated = Format::ElectronBuilder
+            .write_version(
+                config,
+                &Version::parse("1.3.0").unwrap(),
+                "electron-builder.yml",
+            )
+            .unwrap();
+
+        assert_eq!(
+            updated,
+            "appId: com.example\nbuildVersion: \"1.3.0\" # build\ndirectories:\n  \
+             version: 9.9.9\nextraMetadata:\n  main: main.js\n  version: 1.3.0\n"
+        );
+    }
+    /// Tests that only the chart's own version is rewritten, not the
+    /// `appVersion` or the versions of dependencies.
+    #[test]
+    fn test_helm() {
+        let chart = "apiVersion: v2\nname: app\nversion: 1.2.3\nappVersion: \"1.2.3\"\n\
+                     dependencies:\n  - name: redis\n    version: 1.2.3\n";
+
+        assert_eq!(
+            Format::detect("charts/app/Chart.yaml")
+                .unwrap()
+                .write_version(chart, &Version::parse("1.3.0").unwrap(), "Chart.yaml")
+                .unwrap(),
+            "apiVersion: v2\nname: app\nversion: 1.3.0\nappVersion: \"1.2.3\"\n\
+             dependencies:\n  - name: redis\n    version: 1.2.3\n"
+        );
+    }
+    /// Tests that the package's own version binding is rewritten and that
+    /// comments, strings, attribute paths and nested fetchers are skipped.
+    #[test]
+    fn test_nix() {
+        let flake = "{\n  outputs = { self, nixpkgs }: {\n    # version = \"0.0.0\";\n    \
+                     packages.default = buildRustPackage {\n      pname = \"app\";\n      \
+                     version = \"1.2.3\";\n      meta.version = \"9.9.9\";\n      \
+                     doc = ''version = \"8.8.8\";'';\n      \
+                     dep = fetch { version = \"0.1.0\"; };\n    };\n  };\n}\n";
+
+        assert_eq!(
+            Format::detect("flake.nix")
+                .unwrap()
+                .write_version(flake, &Version::parse("1.3.0").unwrap(), "flake.nix")
+                .unwrap(),
+            flake.replace("\"1.2.3\"", "\"1.3.0\"")
+        );
+        assert!(nix_version("{ version = \"${base}.1\"; }").is_none());
+    }
+    /// Tests that only the top-level `.version` of a zon manifest is
+    /// rewritten, not fields of the same name in nested structs, strings
+    /// or comments.
+    #[test]
+    fn test_zon() {
+        let zon = ".{\n    // .version = \"0.0.0\",\n    .name = .app,\n    \
+                   .description =\n        \\\\ .version = \"7.7.7\"\n    ,\n    \
+                   .dependencies = .{\n        .dep = .{ .version = \"0.1.0\", .hash = \"1220ab\" },\n    },\n    \
+                   .@\"version\" = \"1.2.3\",\n    .paths = .{ \"src\", \"build.zig\" },\n}\n";
+
+        assert_eq!(
+            Format::detect("build.zig.zon")
+                .unwrap()
+                .write_version(zon, &Version::parse("1.3.0").unwrap(), "build.zig.zon")
+                .unwrap(),
+            zon.replace("\"1.2.3\"", "\"1.3.0\"")
+        );
+    }
+    /// Tests the Go version constant and the plain VERSION file, including
+    /// keeping a `v` prefix.
+    #[test]
+    fn test_go() {
+        let version_go =
+            "package version\n\nconst (\n\tName    = \"app\"\n\tVersion = \"v1.2.3\"\n)\n";
+        let next = Version::parse("1.3.0").unwrap();
+
+        assert_eq!(
+            Format::detect("internal/version/version.go")
+                .unwrap()
+                .write_version(version_go, &next, "version.go")
+                .unwrap(),
+            version_go.replace("v1.2.3", "v1.3.0")
+        );
+        assert_eq!(
+            Format::Go
+                .read_version("var Version string = \"2.0.0\"\n", "version.go")
+                .unwrap(),
+            Version::parse("2.0.0").unwrap()
+        );
+        assert_eq!(
+            Format::detect("VERSION")
+                .unwrap()
+                .write_version("1.2.3\n", &next, "VERSION")
+                .unwrap(),
+            "1.3.0\n"
+        );
+    }
+    /// Tests that a pyproject.toml is read from [project], or else from
+    /// [tool.poetry], and that other tables' versions are left alone.
+    #[test]
+    fn test_pyproject() {
+        let pyproject = "[build-system]\nrequires = [\"hatchling\"]\n\n[project]\nname = \"app\"\nversion = \"1.2.3\"  # bumped\n\n[tool.bumpver]\nversion = \"0.1.0\"\n";
+        let poetry = "[tool.poetry]\nname = \"app\"\nversion = '2.0.0'\n";
+        let next = Version::parse("1.3.0").unwrap();
+        let format = Format::detect("python/pyproject.toml").unwrap();
+
+        assert_eq!(format, Format::Pyproject);
+        assert_eq!(
+            format
+                .write_version(pyproject, &next, "pyproject.toml")
+                .unwrap(),
+            pyproject.replace("1.2.3", "1.3.0")
+        );
+        assert_eq!(format.package_name(pyproject).as_deref(), Some("app"));
+        assert_eq!(
+            format.read_version(poetry, "pyproject.toml").unwrap(),
+            Version::parse("2.0.0").unwrap()
+        );
+        assert!(format
+            .read_version(
+                "[project]\nname = \"app\"\ndynamic = [\"version\"]\n",
+                "pyproject.toml"
+            )
+            .is_err());
+    }
+    /// Tests that only the described component's version of an SBOM is
+    /// rewritten, not those of the components it lists.
+    #[test]
+    fn test_sbom() {
+        let cyclonedx =
+            "{\n  \"bomFormat\": \"CycloneDX\",\n  \"version\": 1,\n  \"metadata\": {\n    \
+                         \"component\": {\"name\": \"app\", \"version\": \"1.2.3\"}\n  },\n  \
+                         \"components\": [{\"name\": \"dep\", \"version\": \"1.2.3\"}]\n}\n";
+        let spdx = "{\n  \"spdxVersion\": \"SPDX-2.3\",\n  \"packages\": [\n    \
+                    {\"SPDXID\": \"SPDXRef-app\", \"versionInfo\": \"1.2.3\"},\n    \
+                    {\"SPDXID\": \"SPDXRef-dep\", \"versionInfo\": \"1.2.3\"}\n  ]\n}\n";
+        let next = Version::parse("1.3.0").unwrap();
+
+        assert_eq!(
+            Format::detect("sbom/app.cdx.json")
+                .unwrap()
+                .write_version(cyclonedx, &next, "app.cdx.json")
+                .unwrap(),
+            cyclonedx.replacen("\"1.2.3\"", "\"1.3.0\"", 1)
+        );
+        assert_eq!(
+            Format::detect("app.spdx.json")
+                .unwrap()
+                .write_version(spdx, &next, "app.spdx.json")
+                .unwrap(),
+            spdx.replacen("\"1.2.3\"", "\"1.3.0\"", 1)
+        );
+        assert!(json_string_at("{\"a\": [\"x\"]}", &["a", "1"]).is_none());
+    }
+    /// Tests that both root versions of an npm lockfile are rewritten, and
+    /// not those of its dependencies.
+    #[test]
+    fn test_npm_lock() {
+        let lock = "{\n  \"name\": \"app\",\n  \"version\": \"1.2.3\",\n  \"lockfileVersion\": 3,\n  \
+                    \"packages\": {\n    \"\": {\n      \"name\": \"app\",\n      \"version\": \"1.2.3\"\n    },\n    \
+                    \"node_modules/dep\": {\n      \"version\": \"1.2.3\"\n    }\n  }\n}\n";
+
+        assert_eq!(
+            Format::detect("package-lock.json")
+                .unwrap()
+                .write_version(lock, &Version::parse("1.3.0").unwrap(), "package-lock.json")
+                .unwrap(),
+            lock.replacen("\"1.2.3\"", "\"1.3.0\"", 2)
+        );
+    }
+
+    /// Tests that a field is read and rewritten at its key path only,
+    /// next to versions at other paths.
+    #[test]
+    fn test_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let chart = dir.path().join("Chart.yaml");
+        let chart = chart.to_str().unwrap();
+        let schema = dir.path().join("schema.toml");
+        let schema = schema.to_str().unwrap();
+        fs::write(chart, "version: 1.0.0\nappVersion: \"2.3.4\"\n").unwrap();
+        fs::write(
+            schema,
+            "api_version = \"1.0.0\"\n\n[schema]\nversion = \"3.0.0\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_field_version(chart, "appVersion").unwrap(),
+            Version::parse("2.3.4").unwrap()
+        );
+        assert_eq!(
+            read_field_version(schema, "schema.version").unwrap(),
+            Version::parse("3.0.0").unwrap()
+        );
+        assert_eq!(
+            read_field_version(schema, "schema_version")
+                .unwrap_err()
+                .code,
+            Code::Adapter
+        );
+
+        let mut transaction = Transaction::new();
+        stage_field_version(
+            &mut transaction,
+            chart,
+            "appVersion",
+            &Version::parse("2.4.0").unwrap(),
+        )
+        .unwrap();
+        stage_field_version(
+            &mut transaction,
+            schema,
+            "schema.version",
+            &Version::parse("3.1.0").unwrap(),
+        )
+        .unwrap();
+        transaction.commit(|| Ok(())).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(chart).unwrap(),
+            "version: 1.0.0\nappVersion: \"2.4.0\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(schema).unwrap(),
+            "api_version = \"1.0.0\"\n\n[schema]\nversion = \"3.1.0\"\n"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Bump suggestions from changes to the public API, a lightweight
+//! alternative to `cargo semver-checks`: the public items `cargo public-api`
+//! lists for HEAD are compared with those of the last tag, and removed (or
+//! changed) items suggest a MAJOR bump, added ones a MINOR bump. Neither
+//! catches behavioural changes, so this only ever suggests a component.
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+use crate::error::{Code, Error};
+use crate::git;
+use crate::pipeline::Component;
+
+/// The public items removed and added between two `cargo public-api`
+/// listings; a changed item is both.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Diff {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl Diff {
+    pub fn new<S: AsRef<str>>(old: &[S], new: &[S]) -> Diff {
+        let items = |listing: &[S]| {
+            listing
+                .iter()
+                .map(|item| item.as_ref().trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>()
+        };
+        let (old, new) = (items(old), items(new));
+
+        Diff {
+            removed: old
+                .iter()
+                .filter(|item| !new.contains(item))
+                .cloned()
+                .collect(),
+            added: new
+                .iter()
+                .filter(|item| !old.contains(item))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The component the changes suggest bumping; PATCH if the public API
+    /// is unchanged.
+    pub fn component(&self) -> Component {
+        if !self.removed.is_empty() {
+            Component::Major
+        } else if !self.added.is_empty() {
+            Component::Minor
+        } else {
+            Component::Patch
+        }
+    }
+}
+
+/// The public items of the package of the given manifest, one per line, as
+/// listed by `cargo public-api`.
+pub fn public_api(manifest_path: &str) -> Result<Vec<String>, Error> {
+    let mut command = Command::new("cargo");
+    command.args(["public-api", "--manifest-path", manifest_path]);
+    let output = command.output().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Failed to run {:?}: {}", command, err),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(Error::new(
+            Code::Command,
+            format!(
+                "{:?} failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// The public API of the package of the given manifest as of `tag`, listed
+/// from a temporary git worktree checked out at the tag.
+pub fn public_api_at(manifest_path: &str, tag: &str) -> Result<Vec<String>, Error> {
+    let toplevel = git::run(&["rev-parse", "--show-toplevel"])?;
+    let read_error = |path: &str, err| {
+        Error::new(
+            Code::ReadManifest,
+            format!("Could not read {}: {}", path, err),
+        )
+    };
+    let manifest = fs::canonicalize(manifest_path).map_err(|err| read_error(manifest_path, err))?;
+    let toplevel = fs::canonicalize(&toplevel).map_err(|err| read_error(&toplevel, err))?;
+    let relative = manifest.strip_prefix(&toplevel).map_err(|_| {
+        Error::new(
+            Code::Git,
+            format!("{} is outside of the git work tree", manifest_path),
+        )
+    })?;
+
+    let worktree = env::temp_dir().join(format!("semvercli-api-{}", process::id()));
+    let worktree_path = worktree.to_string_lossy().into_owned();
+    git::run(&["worktree", "add", "--detach", worktree_path.as_str(), tag])?;
+    let listing = public_api(&Path::new(&worktree).join(relative).to_string_lossy());
+    git::run(&["worktree", "remove", "--force", worktree_path.as_str()])?;
+
+    listing
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = ["pub fn app::run()", "pub struct app::Config", ""];
+
+        let added = Diff::new(
+            &old[..],
+            &[
+                "pub fn app::run()",
+                "pub struct app::Config",
+                "pub fn app::stop()",
+            ][..],
+        );
+        assert_eq!(added.added, vec!["pub fn app::stop()"]);
+        assert_eq!(added.component(), Component::Minor);
+
+        let changed = Diff::new(
+            &old[..],
+            &["pub fn app::run(bool)", "pub struct app::Config"][..],
+        );
+        assert_eq!(changed.removed, vec!["pub fn app::run()"]);
+        assert_eq!(changed.added, vec!["pub fn app::run(bool)"]);
+        assert_eq!(changed.component(), Component::Major);
+
+        assert_eq!(Diff::new(&old[..], &old[..]).component(), Component::Patch);
+    }
+}
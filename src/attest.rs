@@ -0,0 +1,186 @@
+//! Release provenance: `attest` renders an in-toto statement with a
+//! SLSA provenance predicate naming the released version, the commit it was
+//! built from and the builder, and, given a key, signs it into a DSSE
+//! envelope with `openssl`, for provenance chains built around releases.
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+use semver::Version;
+
+use crate::error::{json_string, Code, Error};
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+pub const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+pub const BUILD_TYPE: &str = "https://github.com/kzvezdarov/semvercli/release/v1";
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// What the statement attests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub name: String,
+    pub version: Version,
+    /// The full hash of the release commit.
+    pub commit: String,
+    /// The URL of the repository, if it has a remote.
+    pub repository: Option<String>,
+    pub builder: String,
+}
+
+impl Provenance {
+    /// Renders the in-toto statement as (compact) JSON.
+    pub fn statement(&self) -> String {
+        let commit = format!("{{\"gitCommit\": {}}}", json_string(&self.commit));
+        let dependencies = match self.repository {
+            Some(ref repository) => format!(
+                "[{{\"uri\": {}, \"digest\": {}}}]",
+                json_string(&format!("git+{}", repository)),
+                commit
+            ),
+            None => String::from("[]"),
+        };
+
+        format!(
+            "{{\"_type\": {}, \"subject\": [{{\"name\": {}, \"digest\": {}}}], \
+             \"predicateType\": {}, \"predicate\": {{\"buildDefinition\": {{\"buildType\": {}, \
+             \"externalParameters\": {{\"version\": {}}}, \"resolvedDependencies\": {}}}, \
+             \"runDetails\": {{\"builder\": {{\"id\": {}}}}}}}}}",
+            json_string(STATEMENT_TYPE),
+            json_string(&format!("{}@{}", self.name, self.version)),
+            commit,
+            json_string(PREDICATE_TYPE),
+            json_string(BUILD_TYPE),
+            json_string(&self.version.to_string()),
+            dependencies,
+            json_string(&self.builder)
+        )
+    }
+}
+
+/// The identity of the CI job running semvercli, from the environment of
+/// GitHub Actions or GitLab CI.
+pub fn detect_builder() -> Option<String> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+    if let (Some(server), Some(repository), Some(run)) = (
+        var("GITHUB_SERVER_URL"),
+        var("GITHUB_REPOSITORY"),
+        var("GITHUB_RUN_ID"),
+    ) {
+        return Some(format!("{}/{}/actions/runs/{}", server, repository, run));
+    }
+    var("CI_JOB_URL")
+}
+
+/// The DSSE pre-authentication encoding of a payload, which is what gets
+/// signed.
+pub fn pae(payload_type: &str, payload: &str) -> Vec<u8> {
+    format!(
+        "DSSEv1 {} {} {} {}",
+        payload_type.len(),
+        payload_type,
+        payload.len(),
+        payload
+    )
+    .into_bytes()
+}
+
+/// Standard base64 with padding.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+            bits | (byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Signs `message` with the Ed25519 private key in the PEM file at `key`.
+/// `openssl` signs Ed25519 in one shot, from a file rather than a pipe.
+fn sign(message: &[u8], key: &str) -> Result<Vec<u8>, Error> {
+    let failed = |err: String| {
+        Error::new(
+            Code::Command,
+            format!("Failed to sign the statement with {}: {}", key, err),
+        )
+    };
+    let path = env::temp_dir().join(format!("semvercli-attest-{}", process::id()));
+    fs::write(&path, message).map_err(|err| failed(err.to_string()))?;
+    let mut command = Command::new("openssl");
+    command.args(["pkeyutl", "-sign", "-rawin", "-inkey", key, "-in"]);
+    command.arg(&path);
+    let output = command.output();
+    fs::remove_file(&path).ok();
+    let output = output.map_err(|err| failed(err.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// The statement signed into a DSSE envelope.
+pub fn envelope(statement: &str, key: &str) -> Result<String, Error> {
+    let signature = sign(&pae(PAYLOAD_TYPE, statement), key)?;
+
+    Ok(format!(
+        "{{\"payloadType\": {}, \"payload\": {}, \"signatures\": [{{\"keyid\": \"\", \"sig\": {}}}]}}",
+        json_string(PAYLOAD_TYPE),
+        json_string(&base64(statement.as_bytes())),
+        json_string(&base64(&signature))
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_statement() {
+        let provenance = Provenance {
+            name: String::from("app"),
+            version: Version::parse("1.4.0").unwrap(),
+            commit: String::from("abc123"),
+            repository: Some(String::from("https://github.com/acme/app")),
+            builder: String::from("https://github.com/acme/app/actions/runs/1"),
+        };
+
+        assert_eq!(
+            provenance.statement(),
+            "{\"_type\": \"https://in-toto.io/Statement/v1\", \
+             \"subject\": [{\"name\": \"app@1.4.0\", \"digest\": {\"gitCommit\": \"abc123\"}}], \
+             \"predicateType\": \"https://slsa.dev/provenance/v1\", \
+             \"predicate\": {\"buildDefinition\": {\
+             \"buildType\": \"https://github.com/kzvezdarov/semvercli/release/v1\", \
+             \"externalParameters\": {\"version\": \"1.4.0\"}, \
+             \"resolvedDependencies\": [{\"uri\": \"git+https://github.com/acme/app\", \
+             \"digest\": {\"gitCommit\": \"abc123\"}}]}, \
+             \"runDetails\": {\"builder\": {\"id\": \"https://github.com/acme/app/actions/runs/1\"}}}}"
+        );
+    }
+
+    #[test]
+    fn test_envelope_encoding() {
+        assert_eq!(
+            pae(PAYLOAD_TYPE, "{}"),
+            b"DSSEv1 28 application/vnd.in-toto+json 2 {}".to_vec()
+        );
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
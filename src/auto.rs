@@ -0,0 +1,557 @@
+//! Automatic bumps: the component to bump is derived from the
+//! [Conventional Commits](https://www.conventionalcommits.org) since the
+//! last tag. Breaking changes bump the MAJOR version, `feat` the MINOR and
+//! `fix`/`perf` the PATCH version; other commits do not warrant a bump.
+//! A commit is breaking if its type is marked with `!` or it has a
+//! `BREAKING CHANGE:` footer, and a revert cancels the reverted commit if
+//! that is since the last tag as well. With the `zero_major` setting, 0.x
+//! versions shift that down by one. Teams with other message styles map
+//! their commits to bumps with `[[commit_convention]]` rules, which take
+//! precedence. Merge commits can be left out and squash merges expanded
+//! into the commits they list.
+use regex::Regex;
+use semver::Version;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::git;
+use crate::pipeline::Component;
+
+/// The most recent tag reachable from HEAD, if there is any.
+pub fn last_tag() -> Option<String> {
+    git::run(&["describe", "--tags", "--abbrev=0"]).ok()
+}
+
+/// A commit of the analyzed history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub hash: String,
+    pub message: String,
+}
+
+/// A `[[commit_convention]]` rule: commits whose message matches `pattern`
+/// warrant bumping `bump` (nothing if `None`) and are listed under
+/// `section` of generated notes (not at all if `None`). The `scope` and
+/// `description` named groups of the pattern give an entry's scope and
+/// text; the subject line is the text otherwise.
+#[derive(Debug, Clone)]
+pub struct Convention {
+    pub pattern: Regex,
+    pub bump: Option<Component>,
+    pub section: Option<String>,
+}
+
+impl PartialEq for Convention {
+    fn eq(&self, other: &Convention) -> bool {
+        self.pattern.as_str() == other.pattern.as_str()
+            && self.bump == other.bump
+            && self.section == other.section
+    }
+}
+
+/// What a commit amounts to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub bump: Option<Component>,
+    /// The changelog section the commit is listed under, if any.
+    pub section: Option<&'a str>,
+    pub scope: Option<&'a str>,
+    pub description: String,
+}
+
+/// The parts of a Conventional Commits subject line, e.g.
+/// `feat(cli)!: drop --old`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conventional<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    /// Whether the commit is marked breaking, by `!` or a footer.
+    pub breaking: bool,
+    pub description: &'a str,
+    /// The description of the breaking change in the `BREAKING CHANGE:`
+    /// footer, if there is one.
+    pub breaking_change: Option<String>,
+}
+
+/// The most recent tag reachable from the parent of `rev`, i.e. the one
+/// before `rev` if that is tagged itself, if there is any.
+pub fn tag_before(rev: &str) -> Option<String> {
+    git::run(&["describe", "--tags", "--abbrev=0", &format!("{}^", rev)]).ok()
+}
+
+/// The commits reachable from `to` but not from `from`, newest first, or
+/// all reachable from `to` without `from`; without merge commits if
+/// `skip_merges`, and only those touching the given git pathspecs if there
+/// are any.
+pub fn commits_between(
+    from: Option<&str>,
+    to: &str,
+    skip_merges: bool,
+    pathspecs: &[String],
+) -> Result<Vec<Commit>, Error> {
+    let range = match from {
+        Some(from) => format!("{}..{}", from, to),
+        None => to.to_string(),
+    };
+    let mut args = vec!["log", "--format=%H%n%B%x00"];
+    if skip_merges {
+        args.push("--no-merges");
+    }
+    args.push(range.as_str());
+    if !pathspecs.is_empty() {
+        args.push("--");
+        args.extend(pathspecs.iter().map(String::as_str));
+    }
+    let log = git::run(&args)?;
+
+    Ok(log
+        .split('\0')
+        .filter_map(|entry| {
+            let (hash, message) = entry.trim().split_once('\n').unwrap_or((entry.trim(), ""));
+            if hash.is_empty() {
+                return None;
+            }
+            Some(Commit {
+                hash: hash.to_string(),
+                message: message.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// The commits in `from..to` (see [`commits_between`]), read as the
+/// `skip_merges` and `expand_squash` settings say.
+pub fn history(
+    config: &Config,
+    from: Option<&str>,
+    to: &str,
+    pathspecs: &[String],
+) -> Result<Vec<Commit>, Error> {
+    let commits = commits_between(from, to, config.skip_merges, pathspecs)?;
+    if !config.expand_squash {
+        return Ok(commits);
+    }
+
+    Ok(commits.iter().flat_map(expand_squash).collect())
+}
+
+/// The commits a squash merge lists in its body, as GitHub writes it: a
+/// `* <subject>` line starting each commit's message, which goes on until
+/// the next one. They keep the squash merge's hash. A commit listing none is
+/// returned as it is.
+pub fn expand_squash(commit: &Commit) -> Vec<Commit> {
+    let mut squashed: Vec<Commit> = vec![];
+    for line in commit.message.lines().skip(1) {
+        match (line.strip_prefix("* "), squashed.last_mut()) {
+            (Some(subject), _) => squashed.push(Commit {
+                hash: commit.hash.clone(),
+                message: subject.to_string(),
+            }),
+            (None, Some(last)) => {
+                last.message.push('\n');
+                last.message.push_str(line);
+            }
+            (None, None) => (),
+        }
+    }
+    if squashed.is_empty() {
+        return vec![commit.clone()];
+    }
+
+    for commit in &mut squashed {
+        commit.message = commit.message.trim().to_string();
+    }
+    squashed
+}
+
+/// The component the commits since the last tag warrant bumping, if any.
+pub fn detect(config: &Config) -> Result<Option<Component>, Error> {
+    let commits = history(config, last_tag().as_deref(), "HEAD", &[])?;
+    let messages = effective(&commits)
+        .into_iter()
+        .map(|commit| commit.message.as_str())
+        .collect::<Vec<_>>();
+
+    Ok(bump_level(&messages, &config.commit_convention))
+}
+
+/// Classifies a commit by the first of the `conventions` matching its
+/// message, or else as a Conventional Commit: breaking changes (described
+/// by their footer, if they have one) go under "Breaking changes", `feat`
+/// under "Added", `perf` under "Changed" and `fix` under "Fixed". `None` if
+/// neither applies.
+pub fn classify<'a>(message: &'a str, conventions: &'a [Convention]) -> Option<Change<'a>> {
+    let subject = message.lines().next().unwrap_or_default();
+    for convention in conventions {
+        if let Some(captures) = convention.pattern.captures(message) {
+            return Some(Change {
+                bump: convention.bump,
+                section: convention.section.as_deref(),
+                scope: captures.name("scope").map(|scope| scope.as_str()),
+                description: captures
+                    .name("description")
+                    .map_or(subject, |description| description.as_str())
+                    .trim()
+                    .to_string(),
+            });
+        }
+    }
+
+    let commit = parse(message)?;
+    let (bump, section) = match commit.kind {
+        _ if commit.breaking => (Some(Component::Major), Some("Breaking changes")),
+        "feat" => (Some(Component::Minor), Some("Added")),
+        "perf" => (Some(Component::Patch), Some("Changed")),
+        "fix" => (Some(Component::Patch), Some("Fixed")),
+        _ => (None, None),
+    };
+
+    Some(Change {
+        bump,
+        section,
+        scope: commit.scope,
+        description: match commit.breaking_change {
+            Some(note) => note.replace('\n', " "),
+            None => commit.description.to_string(),
+        },
+    })
+}
+
+/// Parses a commit message following Conventional Commits; `None` if its
+/// subject does not.
+pub fn parse(message: &str) -> Option<Conventional<'_>> {
+    let subject = message.lines().next().unwrap_or_default();
+    let (prefix, description) = subject.split_once(':')?;
+    let (prefix, marked) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    let breaking_change = footers(message)
+        .into_iter()
+        .find(|(token, _)| *token == "BREAKING CHANGE" || *token == "BREAKING-CHANGE")
+        .map(|(_, value)| value);
+
+    Some(Conventional {
+        kind,
+        scope,
+        breaking: marked || breaking_change.is_some(),
+        description: description.trim(),
+        breaking_change,
+    })
+}
+
+/// The trailers of a commit message: the tokens and values of its last
+/// paragraph, if every line of it is a `Token: value` or `Token #value`
+/// footer or continues one (as `git interpret-trailers` reads them).
+pub fn footers(message: &str) -> Vec<(&str, String)> {
+    let paragraphs = message
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .collect::<Vec<_>>();
+    if paragraphs.len() < 2 {
+        return vec![];
+    }
+    let mut footers: Vec<(&str, String)> = vec![];
+
+    for line in paragraphs[paragraphs.len() - 1].trim().lines() {
+        let footer = line
+            .split_once(": ")
+            .or_else(|| line.split_once(" #"))
+            .filter(|(token, _)| {
+                *token == "BREAKING CHANGE"
+                    || !token.is_empty()
+                        && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+        match (footer, footers.last_mut()) {
+            (Some((token, value)), _) => footers.push((token, value.trim().to_string())),
+            (None, Some((_, value))) => {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+            (None, None) => return vec![],
+        }
+    }
+
+    footers
+}
+
+/// The commit (by hash prefix or subject) a revert reverts: the one of
+/// git's `This reverts commit <hash>.` line or the `Refs:` footer, or else
+/// the one with the quoted subject of `Revert "<subject>"`, or the rest of
+/// a `revert: <subject>` subject.
+fn reverted(message: &str) -> Option<String> {
+    let subject = message.lines().next().unwrap_or_default();
+    let quoted = subject
+        .strip_prefix("Revert \"")
+        .and_then(|rest| rest.strip_suffix('"'));
+    let conventional = parse(message)
+        .filter(|commit| commit.kind == "revert")
+        .map(|commit| commit.description);
+    let subject = quoted.or(conventional)?;
+    let hash = message
+        .lines()
+        .find_map(|line| line.strip_prefix("This reverts commit "))
+        .map(|rest| rest.trim_end_matches('.').to_string())
+        .or_else(|| {
+            let (_, refs) = footers(message)
+                .into_iter()
+                .find(|(token, _)| *token == "Refs")?;
+            Some(refs.split(',').next()?.trim().to_string())
+        })
+        .filter(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+    Some(hash.unwrap_or_else(|| subject.to_string()))
+}
+
+/// The commits (newest first) whose changes remain: reverts and the
+/// commits they revert are left out. A revert of a revert cancels that
+/// one, so the originally reverted commit remains.
+pub fn effective(commits: &[Commit]) -> Vec<&Commit> {
+    let mut cancelled: Vec<String> = vec![];
+    let mut remaining = vec![];
+
+    for commit in commits {
+        let subject = commit.message.lines().next().unwrap_or_default();
+        let position = cancelled
+            .iter()
+            .position(|target| commit.hash.starts_with(target.as_str()) || subject == target);
+        if let Some(position) = position {
+            cancelled.remove(position);
+            continue;
+        }
+        match reverted(&commit.message) {
+            Some(target) => cancelled.push(target),
+            None => remaining.push(commit),
+        }
+    }
+
+    remaining
+}
+
+/// The most significant component any of the commit messages warrants
+/// bumping.
+pub fn bump_level<S: AsRef<str>>(messages: &[S], conventions: &[Convention]) -> Option<Component> {
+    messages
+        .iter()
+        .filter_map(|message| classify(message.as_ref(), conventions)?.bump)
+        .min()
+}
+
+/// The component to bump under the common 0.x convention, where breaking
+/// changes bump the MINOR and features the PATCH version as long as the
+/// MAJOR version is 0; from 1.0.0 on the component is unchanged.
+pub fn zero_ver(component: Component, version: &Version) -> Component {
+    match component {
+        Component::Major if version.major == 0 => Component::Minor,
+        Component::Minor if version.major == 0 => Component::Patch,
+        component => component,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that the most significant change decides and that commits
+    /// which are not features, fixes or breaking changes are ignored.
+    #[test]
+    fn test_bump_level() {
+        assert_eq!(
+            bump_level(&["fix(io): handle EOF", "docs: typo"], &[]),
+            Some(Component::Patch)
+        );
+        assert_eq!(
+            bump_level(&["fix: a", "feat(cli): b", "chore: c"], &[]),
+            Some(Component::Minor)
+        );
+        assert_eq!(
+            bump_level(&["feat!: drop flag", "fix: a"], &[]),
+            Some(Component::Major)
+        );
+        assert_eq!(
+            bump_level(&["fix: a\n\nBREAKING CHANGE: renamed"], &[]),
+            Some(Component::Major)
+        );
+        assert_eq!(
+            bump_level(&["chore: bump deps", "Merge branch x"], &[]),
+            None
+        );
+        assert_eq!(bump_level::<&str>(&[], &[]), None);
+    }
+
+    /// Tests that `BREAKING CHANGE` counts as a footer only, and that the
+    /// footers are only read from a trailing paragraph of footers.
+    #[test]
+    fn test_footers() {
+        let message =
+            "feat: rename\n\nBody.\n\nRefs: #12\nBREAKING CHANGE: `old` is now\n  `new`\n";
+
+        assert_eq!(
+            footers(message),
+            [
+                ("Refs", String::from("#12")),
+                ("BREAKING CHANGE", String::from("`old` is now\n`new`"))
+            ]
+        );
+        assert_eq!(
+            parse(message).unwrap().breaking_change.as_deref(),
+            Some("`old` is now\n`new`")
+        );
+        assert!(footers("fix: a\n\nSee the issue.\nCloses #3").is_empty());
+        assert!(footers("fix: a\nCloses #3").is_empty());
+        assert_eq!(
+            bump_level(&["fix: a\n\nBREAKING CHANGE: x\n\nMore body."], &[]),
+            Some(Component::Patch)
+        );
+    }
+
+    /// Tests that reverts cancel the commits they revert, by hash or
+    /// subject, and that reverting a revert restores the original.
+    #[test]
+    fn test_effective() {
+        let commit = |hash: &str, message: &str| Commit {
+            hash: hash.to_string(),
+            message: message.to_string(),
+        };
+        let hashes = |commits: &[Commit]| {
+            effective(commits)
+                .iter()
+                .map(|commit| commit.hash.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let reverted = [
+            commit(
+                "e5",
+                "Revert \"feat!: drop --old\"\n\nThis reverts commit c3d4.",
+            ),
+            commit("d4", "revert: fix: handle EOF"),
+            commit("c3d4", "feat!: drop --old"),
+            commit("b2", "fix: handle EOF"),
+            commit("a1", "fix: handle EOF"),
+        ];
+        assert_eq!(hashes(&reverted), ["a1"]);
+
+        let restored = [
+            commit("c3", "Revert \"Revert \"feat: add --x\"\""),
+            commit("b2", "Revert \"feat: add --x\""),
+            commit("a1", "feat: add --x"),
+        ];
+        assert_eq!(hashes(&restored), ["a1"]);
+        assert_eq!(
+            hashes(&[commit("a1", "revert: feat: released")]),
+            Vec::<String>::new()
+        );
+    }
+
+    /// Tests that the first matching convention decides, with its named
+    /// groups, and that other commits fall back to Conventional Commits.
+    #[test]
+    fn test_conventions() {
+        let convention = |pattern: &str, bump, section: Option<&str>| Convention {
+            pattern: Regex::new(pattern).unwrap(),
+            bump,
+            section: section.map(String::from),
+        };
+        let conventions = [
+            convention(
+                r"^\[BREAKING\]",
+                Some(Component::Major),
+                Some("Breaking changes"),
+            ),
+            convention(
+                r"^\[(?P<scope>\w+)\] Add (?P<description>.*)",
+                Some(Component::Minor),
+                Some("Added"),
+            ),
+            convention(r"^Merge ", None, None),
+        ];
+
+        assert_eq!(
+            classify("[cli] Add --new flag\n\nBody", &conventions),
+            Some(Change {
+                bump: Some(Component::Minor),
+                section: Some("Added"),
+                scope: Some("cli"),
+                description: String::from("--new flag"),
+            })
+        );
+        assert_eq!(
+            classify("[BREAKING] [cli] Add --new flag", &conventions)
+                .unwrap()
+                .description,
+            "[BREAKING] [cli] Add --new flag"
+        );
+        assert_eq!(
+            bump_level(&["Merge branch 'feat: x'", "fix: y"], &conventions),
+            Some(Component::Patch)
+        );
+        assert_eq!(classify("Tweak things", &conventions), None);
+    }
+
+    /// Tests that a squash merge stands for the commits it lists, with
+    /// their bodies, and that other commits are left alone.
+    #[test]
+    fn test_expand_squash() {
+        let squash = Commit {
+            hash: String::from("1a2b3c4"),
+            message: String::from(
+                "Pagination (#42)\n\n* feat(api): paginate\n\n\
+                 BREAKING CHANGE: lists are paged\n\n* fix: off by one\n\n\
+                 Co-authored-by: A <a@acme.dev>\n",
+            ),
+        };
+        let messages = expand_squash(&squash)
+            .into_iter()
+            .map(|commit| commit.message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "feat(api): paginate\n\nBREAKING CHANGE: lists are paged",
+                "fix: off by one\n\nCo-authored-by: A <a@acme.dev>",
+            ]
+        );
+        assert_eq!(bump_level(&messages, &[]), Some(Component::Major));
+
+        let plain = Commit {
+            hash: String::from("2b3c4d5"),
+            message: String::from("fix: a\n\n- not a squash"),
+        };
+        assert_eq!(expand_squash(&plain), [plain]);
+    }
+
+    #[test]
+    fn test_zero_ver() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert_eq!(
+            zero_ver(Component::Major, &version("0.3.0")),
+            Component::Minor
+        );
+        assert_eq!(
+            zero_ver(Component::Minor, &version("0.3.0")),
+            Component::Patch
+        );
+        assert_eq!(
+            zero_ver(Component::Patch, &version("0.3.0")),
+            Component::Patch
+        );
+        assert_eq!(
+            zero_ver(Component::Major, &version("1.0.0")),
+            Component::Major
+        );
+        assert_eq!(
+            zero_ver(Component::Minor, &version("1.0.0")),
+            Component::Minor
+        );
+    }
+}
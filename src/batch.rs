@@ -0,0 +1,205 @@
+//! `semvercli batch`: runs many commands in one process, read from stdin one
+//! per line (the arguments semvercli would be given, quoted as in a shell),
+//! so orchestration tools avoid a process start and a manifest parse per
+//! command. Blank lines and lines starting with `#` are skipped. Parsed
+//! manifests are cached between commands, and the first failing command
+//! stops the batch with its error. A `--joiner` given to the batch applies
+//! to the commands that do not choose their own.
+use std::io::{BufRead, Write};
+
+use super::{execute, parser};
+use crate::cache;
+use crate::error::{Code, Error};
+
+pub fn run(
+    input: &mut dyn BufRead,
+    stdout: &mut dyn Write,
+    joiner: Option<&str>,
+) -> Result<(), Error> {
+    cache::enable();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| {
+            Error::new(Code::Io, format!("Could not read the batch input: {}", err))
+        })?;
+        let in_line = |err: Error| Error::new(err.code, format!("Line {}: {}", index + 1, err));
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut args =
+            split_args(line).map_err(|err| in_line(Error::new(Code::InvalidArgument, err)))?;
+        if let Some(joiner) = joiner {
+            if !args
+                .iter()
+                .any(|arg| arg == "--joiner" || arg.starts_with("--joiner="))
+            {
+                args.splice(0..0, vec![String::from("--joiner"), joiner.to_string()]);
+            }
+        }
+        args.insert(0, String::from("semvercli"));
+        let matches = parser()
+            .get_matches_from_safe(args)
+            .map_err(|err| in_line(Error::new(Code::InvalidArgument, err.message)))?;
+        match matches.subcommand_name() {
+            Some("batch") => {
+                return Err(in_line(Error::new(
+                    Code::InvalidArgument,
+                    "A batch cannot run another batch",
+                )))
+            }
+            Some("channel")
+                if matches
+                    .subcommand_matches("channel")
+                    .and_then(|channel| channel.subcommand_matches("latest"))
+                    .is_some_and(|latest| latest.is_present("stdin")) =>
+            {
+                return Err(in_line(Error::new(
+                    Code::InvalidArgument,
+                    "channel latest --stdin reads stdin, which is the batch input",
+                )))
+            }
+            Some("canon") => {
+                return Err(in_line(Error::new(
+                    Code::InvalidArgument,
+                    "canon reads stdin, which is the batch input",
+                )))
+            }
+            _ => {}
+        }
+        if matches.value_of("manifest-path") == Some(crate::STDIN_MANIFEST) {
+            return Err(in_line(Error::new(
+                Code::InvalidArgument,
+                "The manifest cannot be read from stdin, which is the batch input",
+            )));
+        }
+        execute(&matches, stdout).map_err(in_line)?;
+    }
+
+    Ok(())
+}
+
+/// Splits a command line into arguments at whitespace, honouring single
+/// quotes, double quotes (in which `\` escapes `"` and `\`) and backslash
+/// escapes outside of quotes.
+fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(current.take()),
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(format!("Unterminated quote in `{}`", line)),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ '"') | Some(c @ '\\') => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(format!("Unterminated quote in `{}`", line)),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(format!("Unterminated quote in `{}`", line)),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| format!("Trailing backslash in `{}`", line))?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::str;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args("do 'bump minor' \"set pre \\\"rc\\\"\" a\\ b ''").unwrap(),
+            ["do", "bump minor", "set pre \"rc\"", "a b", ""]
+        );
+        assert!(split_args("do 'bump minor").is_err());
+    }
+
+    /// Tests that every command sees the changes of the previous ones and
+    /// that a failing command stops the batch.
+    #[test]
+    fn test_run() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let input = format!(
+            "# release candidate\n--manifest-path {0} bump --minor\n\n\
+             --manifest-path {0} read --version\n\
+             --manifest-path {0} do 'set pre rc.1' 'read version'\n\
+             --manifest-path {0} bump --pre '!'\n\
+             --manifest-path {0} bump --major\n",
+            manifest_path
+        );
+        let mut stdout = Vec::new();
+
+        let err = run(&mut input.as_bytes(), &mut stdout, None).unwrap_err();
+
+        assert_eq!(err.code, Code::InvalidArgument);
+        assert!(err.message.starts_with("Line 6: "));
+        assert_eq!(str::from_utf8(&stdout).unwrap(), "1.3.0\n1.3.0-rc.1\n");
+        assert_eq!(
+            fs::read_to_string(manifest_path).unwrap(),
+            "[package]\nversion = \"1.3.0-rc.1\"\n"
+        );
+    }
+
+    /// Tests that the batch's joiner applies unless a command gives its own.
+    #[test]
+    fn test_run_joiner() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let input = format!(
+            "--manifest-path '{0}' read --locate\n\
+             --joiner newline --manifest-path '{0}' read --locate\n",
+            manifest_path
+        );
+        let mut stdout = Vec::new();
+
+        run(&mut input.as_bytes(), &mut stdout, Some("nul")).unwrap();
+
+        let location = format!("{}:2:12: 1.2.3", manifest_path);
+        assert_eq!(
+            str::from_utf8(&stdout).unwrap(),
+            format!("{0}\0{0}\n", location)
+        );
+    }
+}
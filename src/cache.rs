@@ -0,0 +1,90 @@
+//! In-process cache of parsed TOML documents for `batch`, whose commands
+//! often operate on the same manifest over and over. Entries are keyed by
+//! path and only reused while the file's modification time and the hash of
+//! its contents are unchanged, so changes between commands (by semvercli
+//! itself or anything else) are always picked up. The cache is off unless
+//! enabled, since single commands parse every file once anyway.
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+use toml_edit::{Document, TomlError};
+
+struct Entry {
+    modified: Option<SystemTime>,
+    hash: u64,
+    document: Document,
+}
+
+thread_local! {
+    static DOCUMENTS: RefCell<Option<HashMap<String, Entry>>> = const { RefCell::new(None) };
+}
+
+pub fn enable() {
+    DOCUMENTS.with(|documents| *documents.borrow_mut() = Some(HashMap::new()));
+}
+
+/// Parses `contents`, just read from `path`, reusing the document parsed
+/// from the same file before if the file has not changed since.
+pub fn parse(path: &str, contents: &str) -> Result<Document, TomlError> {
+    DOCUMENTS.with(|documents| {
+        let mut documents = documents.borrow_mut();
+        let entries = match documents.as_mut() {
+            Some(entries) => entries,
+            None => return contents.parse::<Document>(),
+        };
+
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some(entry) = entries.get(path) {
+            if entry.modified == modified && entry.hash == hash {
+                return Ok(entry.document.clone());
+            }
+        }
+
+        let document = contents.parse::<Document>()?;
+        entries.insert(
+            path.to_string(),
+            Entry {
+                modified,
+                hash,
+                document: document.clone(),
+            },
+        );
+        Ok(document)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Tests that a changed file is parsed again rather than served from
+    /// the cache.
+    #[test]
+    fn test_invalidation() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let path = tmp_path.to_str().unwrap();
+        let parse_file = || parse(path, &fs::read_to_string(path).unwrap()).unwrap();
+        enable();
+
+        fs::write(path, "[package]\nversion = \"1.0.0\"\n").unwrap();
+        assert_eq!(parse_file()["package"]["version"].as_str(), Some("1.0.0"));
+        assert_eq!(parse_file()["package"]["version"].as_str(), Some("1.0.0"));
+
+        fs::write(path, "[package]\nversion = \"1.0.1\"\n").unwrap();
+        assert_eq!(parse_file()["package"]["version"].as_str(), Some("1.0.1"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Helpers for working with a Keep a Changelog style `CHANGELOG.md`, where
+//! upcoming release notes accumulate under an `Unreleased` heading, and
+//! generating such notes from Conventional Commits.
+use std::fs;
+
+use toml_edit::Document;
+
+use super::read_version;
+use crate::auto::{self, Commit, Convention};
+use crate::error::{Code, Error};
+use crate::git;
+use crate::links::Templates;
+
+/// The order of the sections of generated notes; other sections follow in
+/// the order of the `[[commit_convention]]` rules.
+const SECTIONS: [&str; 4] = ["Breaking changes", "Added", "Changed", "Fixed"];
+
+/// Returns the body of the `Unreleased` section (everything between its
+/// heading and the next heading of the same or a higher level), trimmed;
+/// `None` if there is no such section.
+pub fn unreleased(changelog: &str) -> Option<String> {
+    let mut lines = changelog.lines();
+    let level = lines.by_ref().find_map(|line| {
+        let level = heading_level(line)?;
+        if line[level..].to_lowercase().contains("unreleased") {
+            Some(level)
+        } else {
+            None
+        }
+    })?;
+
+    let body = lines
+        .take_while(|line| heading_level(line).is_none_or(|other| other > level))
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    Some(body.trim().to_string())
+}
+
+/// Returns `changelog` with an empty Unreleased section (with a `Fixed`
+/// subsection) before the first release section, or `None` if it has an
+/// Unreleased section already. An empty changelog gets a title as well.
+pub fn with_unreleased(changelog: &str) -> Option<String> {
+    if unreleased(changelog).is_some() {
+        return None;
+    }
+
+    let scaffold = "## [Unreleased]\n\n### Fixed\n\n";
+    if changelog.trim().is_empty() {
+        return Some(format!("# Changelog\n\n{}", scaffold));
+    }
+    let mut offset = 0;
+    for line in changelog.split_inclusive('\n') {
+        if heading_level(line).is_some_and(|level| level > 1) {
+            break;
+        }
+        offset += line.len();
+    }
+    let (head, releases) = changelog.split_at(offset);
+    let separator = if head.is_empty() || head.ends_with("\n\n") {
+        ""
+    } else if head.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+
+    Some(format!("{}{}{}{}", head, separator, scaffold, releases))
+}
+
+/// Returns `changelog` with the body of its Unreleased section replaced by
+/// `notes`, adding the section as [`with_unreleased`] does if there is none.
+pub fn with_notes(changelog: &str, notes: &str) -> String {
+    let changelog = with_unreleased(changelog).unwrap_or_else(|| changelog.to_string());
+    let (mut start, mut end) = (None, changelog.len());
+    let mut offset = 0;
+    for line in changelog.split_inclusive('\n') {
+        match (start, heading_level(line)) {
+            (None, Some(level)) if line[level..].to_lowercase().contains("unreleased") => {
+                start = Some((offset + line.len(), level));
+            }
+            (Some((_, unreleased)), Some(level)) if level <= unreleased => {
+                end = offset;
+                break;
+            }
+            _ => (),
+        }
+        offset += line.len();
+    }
+    let start = start.map_or(changelog.len(), |(start, _)| start);
+    let notes = notes.trim();
+    let rest = &changelog[end..];
+
+    format!(
+        "{}\n{}{}{}",
+        &changelog[..start],
+        notes,
+        match (notes.is_empty(), rest.is_empty()) {
+            (true, _) => "",
+            (false, true) => "\n",
+            (false, false) => "\n\n",
+        },
+        rest
+    )
+}
+
+/// Release notes for `commits`, one entry per commit with its (linked)
+/// short hash under the section it is classified under (see
+/// [`auto::classify`]); other commits and reverted ones are left out.
+/// Empty if no commit is listed.
+pub fn render(commits: &[Commit], conventions: &[Convention], templates: &Templates) -> String {
+    let entries = auto::effective(commits)
+        .into_iter()
+        .filter_map(|commit| {
+            let change = auto::classify(&commit.message, conventions)?;
+            let scope = change
+                .scope
+                .map(|scope| format!("**{}:** ", scope))
+                .unwrap_or_default();
+            let hash = &commit.hash[..commit.hash.len().min(7)];
+            let entry = format!("- {}{} ({})", scope, change.description, hash);
+
+            Some((change.section?, templates.expand(&entry)))
+        })
+        .collect::<Vec<_>>();
+    let mut sections = SECTIONS.to_vec();
+    for convention in conventions {
+        match convention.section {
+            Some(ref section) if !sections.contains(&section.as_str()) => sections.push(section),
+            _ => (),
+        }
+    }
+
+    sections
+        .into_iter()
+        .filter_map(|title| {
+            let listed = entries
+                .iter()
+                .filter(|(section, _)| *section == title)
+                .map(|(_, entry)| entry.as_str())
+                .collect::<Vec<_>>();
+            if listed.is_empty() {
+                return None;
+            }
+            Some(format!("### {}\n\n{}\n", title, listed.join("\n")))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Enforces the release notes discipline: if the manifest version differs
+/// from the one at the `base` revision, the Unreleased section of the
+/// changelog must be non-empty and changed relative to `base`.
+pub fn check(manifest_path: &str, changelog_path: &str, base: &str) -> Result<(), Error> {
+    let base_manifest = git::show(base, manifest_path)
+        .map_err(|err| {
+            Error::new(
+                Code::Git,
+                format!("Could not read {} at {}: {}", manifest_path, base, err),
+            )
+        })?
+        .parse::<Document>()
+        .map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid {} at {}: {}", manifest_path, base, err),
+            )
+        })?;
+    let manifest = fs::read_to_string(manifest_path)
+        .map_err(|err| {
+            Error::new(
+                Code::ReadManifest,
+                format!("Could not read {}: {}", manifest_path, err),
+            )
+        })?
+        .parse::<Document>()
+        .map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid {}: {}", manifest_path, err),
+            )
+        })?;
+
+    if read_version(&manifest)? == read_version(&base_manifest)? {
+        return Ok(());
+    }
+
+    let current = fs::read_to_string(changelog_path)
+        .ok()
+        .and_then(|contents| unreleased(&contents));
+    let previous = git::show(base, changelog_path)
+        .ok()
+        .and_then(|contents| unreleased(&contents));
+
+    verify_notes(current, previous)
+        .map_err(|err| Error::new(Code::Changelog, format!("{}: {}", changelog_path, err)))
+}
+
+fn verify_notes(current: Option<String>, previous: Option<String>) -> Result<(), String> {
+    match current {
+        None => Err(String::from(
+            "the version is bumped, but there is no Unreleased section",
+        )),
+        Some(ref notes) if notes.is_empty() => Err(String::from(
+            "the version is bumped, but the Unreleased section is empty",
+        )),
+        notes if notes == previous => Err(String::from(
+            "the version is bumped, but the Unreleased section is unchanged from the base branch",
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+/// The number of leading `#` characters of a markdown ATX heading.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    match line[level..].chars().next() {
+        Some(' ') if level > 0 => Some(level),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that only the Unreleased section is returned, including its
+    /// subsections, and that a missing section is reported as such.
+    #[test]
+    fn test_unreleased() {
+        let changelog =
+            "# Changelog\n\n## [Unreleased]\n### Added\n- `plan`\n\n## [1.0.0]\n- Initial\n";
+
+        assert_eq!(
+            unreleased(changelog),
+            Some(String::from("### Added\n- `plan`"))
+        );
+        assert_eq!(unreleased("# Changelog\n## [1.0.0]\n"), None);
+    }
+
+    /// Tests that missing, empty and untouched release notes are rejected.
+    #[test]
+    fn test_verify_notes() {
+        let notes = |notes: &str| Some(String::from(notes));
+
+        assert!(verify_notes(None, None).is_err());
+        assert!(verify_notes(notes(""), None).is_err());
+        assert!(verify_notes(notes("- Fix"), notes("- Fix")).is_err());
+        assert!(verify_notes(notes("- Fix\n- Feature"), notes("- Fix")).is_ok());
+        assert!(verify_notes(notes("- Fix"), None).is_ok());
+    }
+
+    /// Tests that entries are grouped by type, breaking changes first, and
+    /// that other commits are left out.
+    #[test]
+    fn test_render() {
+        let commit = |hash: &str, message: &str| Commit {
+            hash: hash.to_string(),
+            message: message.to_string(),
+        };
+        let commits = [
+            commit("6f7a8b9c0d", "Revert \"feat: add --x\""),
+            commit("1a2b3c4d5e", "fix(io): handle EOF (#12)"),
+            commit("2b3c4d5e6f", "docs: typo"),
+            commit(
+                "3c4d5e6f7a",
+                "feat!: drop --old\n\nBREAKING CHANGE: use\n --new",
+            ),
+            commit("5e6f7a8b9c", "feat: add --x"),
+            commit("4d5e6f7a8b", "feat: add --new"),
+        ];
+        let templates = Templates {
+            issue: Some(String::from("https://github.com/acme/app/issues/{number}")),
+            ..Templates::default()
+        };
+
+        assert_eq!(
+            render(&commits, &[], &templates),
+            "### Breaking changes\n\n- use --new (3c4d5e6)\n\n\
+             ### Added\n\n- add --new (4d5e6f7)\n\n\
+             ### Fixed\n\n- **io:** handle EOF \
+             ([#12](https://github.com/acme/app/issues/12)) (1a2b3c4)\n"
+        );
+        assert_eq!(render(&commits[2..3], &[], &templates), "");
+    }
+
+    /// Tests that the Unreleased section's body is replaced, up to the
+    /// next release, and that one is added where there is none.
+    #[test]
+    fn test_with_notes() {
+        assert_eq!(
+            with_notes(
+                "# Changelog\n\n## [Unreleased]\n### Fixed\n- Old\n\n## [1.0.0]\n- Initial\n",
+                "### Added\n\n- New\n"
+            ),
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- New\n\n## [1.0.0]\n- Initial\n"
+        );
+        assert_eq!(
+            with_notes("", "### Added\n\n- New\n"),
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- New\n"
+        );
+        assert_eq!(
+            with_notes("# Changelog\n\n## [Unreleased]\n- Old\n", ""),
+            "# Changelog\n\n## [Unreleased]\n\n"
+        );
+    }
+
+    /// Tests that the scaffold goes before the first release, and that an
+    /// existing Unreleased section is left alone.
+    #[test]
+    fn test_with_unreleased() {
+        assert_eq!(
+            with_unreleased("# Changelog\n\n## [1.3.2] - 2024-03-01\n\n- Fix\n").unwrap(),
+            "# Changelog\n\n## [Unreleased]\n\n### Fixed\n\n## [1.3.2] - 2024-03-01\n\n- Fix\n"
+        );
+        assert_eq!(
+            with_unreleased("").unwrap(),
+            "# Changelog\n\n## [Unreleased]\n\n### Fixed\n\n"
+        );
+        assert_eq!(with_unreleased("# Changelog\n\n## [Unreleased]\n"), None);
+    }
+}
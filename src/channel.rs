@@ -0,0 +1,128 @@
+//! Release channels. A channel is the set of versions with a given kind of
+//! pre-release: `stable` has none, `nightly` the dated `nightly.<YYYYMMDD>`
+//! ones of `semvercli nightly`, and any other channel, e.g. `beta`, those
+//! labelled with its name and a counter, `1.3.0-beta.2`.
+use semver::{Identifier, Version};
+
+use crate::limits::Limits;
+use crate::nightly;
+use crate::pipeline::Component;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Nightly,
+    Pre(String),
+}
+
+impl Channel {
+    pub fn parse(name: &str) -> Result<Channel, String> {
+        match name {
+            "stable" => Ok(Channel::Stable),
+            "nightly" => Ok(Channel::Nightly),
+            _ if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic() || c == '-') => {
+                Ok(Channel::Pre(name.to_string()))
+            }
+            _ => Err(format!(
+                "Invalid channel {}: channel names are pre-release labels made of letters and -",
+                name
+            )),
+        }
+    }
+
+    fn label(&self) -> Option<&str> {
+        match self {
+            Channel::Stable => None,
+            Channel::Nightly => Some("nightly"),
+            Channel::Pre(label) => Some(label),
+        }
+    }
+
+    /// Whether `version` is on this channel.
+    pub fn contains(&self, version: &Version) -> bool {
+        match (self.label(), version.pre.first()) {
+            (None, None) => true,
+            (Some(label), Some(Identifier::AlphaNumeric(first))) => label == first,
+            _ => false,
+        }
+    }
+
+    /// The version following `version` on this channel: the next counter of
+    /// a version already on it, the release of a pre-release for `stable`,
+    /// and otherwise the first pre-release of the next PATCH version (or of
+    /// the same version, for a pre-release of another channel). Nightlies
+    /// are dated with `date` instead of counted. Fails if a component to
+    /// bump is at its limit.
+    pub fn next(&self, version: &Version, date: u64, limits: &Limits) -> Result<Version, String> {
+        let mut next = version.clone();
+        next.build.clear();
+        let on_channel = self.contains(version);
+
+        match self {
+            Channel::Stable if on_channel => limits.increment(&mut next, Component::Patch)?,
+            Channel::Stable => next.pre.clear(),
+            Channel::Nightly if on_channel => {
+                next.pre = nightly::nightly_version(version, date, limits)?.pre;
+            }
+            Channel::Nightly => next = nightly::nightly_version(version, date, limits)?,
+            Channel::Pre(label) => {
+                let counter = match next.pre.last() {
+                    Some(Identifier::Numeric(counter)) if on_channel => counter + 1,
+                    _ => 1,
+                };
+                if !version.is_prerelease() {
+                    limits.increment(&mut next, Component::Patch)?;
+                }
+                next.pre = vec![
+                    Identifier::AlphaNumeric(label.clone()),
+                    Identifier::Numeric(counter),
+                ];
+            }
+        }
+
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_next() {
+        let next = |channel: &str, version: &str| {
+            Channel::parse(channel)
+                .unwrap()
+                .next(
+                    &Version::parse(version).unwrap(),
+                    20240312,
+                    &Limits::default(),
+                )
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(next("beta", "1.2.3"), "1.2.4-beta.1");
+        assert_eq!(next("beta", "1.3.0-beta.1+ci.7"), "1.3.0-beta.2");
+        assert_eq!(next("beta", "1.3.0-alpha.4"), "1.3.0-beta.1");
+        assert_eq!(next("stable", "1.3.0-beta.2"), "1.3.0");
+        assert_eq!(next("stable", "1.3.0"), "1.3.1");
+        assert_eq!(next("nightly", "1.4.2"), "1.5.0-nightly.20240312");
+        assert_eq!(
+            next("nightly", "1.5.0-nightly.20240311"),
+            "1.5.0-nightly.20240312"
+        );
+        assert!(Channel::parse("beta.1").is_err());
+    }
+
+    #[test]
+    fn test_contains() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert!(Channel::Stable.contains(&version("1.0.0")));
+        assert!(!Channel::Stable.contains(&version("1.0.0-beta.1")));
+        assert!(Channel::Pre(String::from("beta")).contains(&version("1.0.0-beta.1")));
+        assert!(!Channel::Pre(String::from("beta")).contains(&version("1.0.0-betas.1")));
+        assert!(Channel::Nightly.contains(&version("1.1.0-nightly.20240312")));
+    }
+}
@@ -0,0 +1,129 @@
+//! Manifest changes that break dependents: switching the edition, removing
+//! a feature or shrinking what one enables. `bump` compares the manifest
+//! with the one of the last release and warns when such changes go out in
+//! a PATCH release.
+use semver::Version;
+use toml_edit::Document;
+
+/// Descriptions of the breaking changes from the `released` manifest to
+/// the `current` one.
+pub fn breaking_changes(released: &Document, current: &Document) -> Vec<String> {
+    let mut changes = vec![];
+    let edition = |manifest: &Document| {
+        manifest["package"]["edition"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| String::from("2015"))
+    };
+    let (released_edition, edition) = (edition(released), edition(current));
+    if released_edition != edition {
+        changes.push(format!(
+            "the edition changed from {} to {}",
+            released_edition, edition
+        ));
+    }
+
+    let features = |manifest: &Document| {
+        manifest["features"]
+            .as_table()
+            .map(|features| {
+                features
+                    .iter()
+                    .map(|(name, enables)| {
+                        let enables = enables
+                            .as_array()
+                            .map(|enables| {
+                                enables
+                                    .iter()
+                                    .filter_map(|item| item.as_str().map(String::from))
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or_default();
+                        (name.to_string(), enables)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    let current_features = features(current);
+    for (name, enables) in features(released) {
+        let current_enables = match current_features
+            .iter()
+            .find(|(current, _)| *current == name)
+        {
+            Some((_, current_enables)) => current_enables,
+            None => {
+                changes.push(format!("the feature {} was removed", name));
+                continue;
+            }
+        };
+        for item in enables
+            .iter()
+            .filter(|item| !current_enables.contains(item))
+        {
+            changes.push(format!("the feature {} no longer enables {}", name, item));
+        }
+    }
+
+    changes
+}
+
+/// Whether `version` is a PATCH release after `released`.
+pub fn is_patch(released: &Version, version: &Version) -> bool {
+    (version.major, version.minor) == (released.major, released.minor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_breaking_changes() {
+        let released = r#"
+[package]
+name = "app"
+version = "1.2.3"
+
+[features]
+default = ["std", "serde"]
+std = []
+serde = ["dep:serde"]
+legacy = []
+"#
+        .parse::<Document>()
+        .unwrap();
+        let current = r#"
+[package]
+name = "app"
+version = "1.2.3"
+edition = "2021"
+
+[features]
+default = ["std"]
+std = ["alloc"]
+alloc = []
+serde = ["dep:serde"]
+"#
+        .parse::<Document>()
+        .unwrap();
+
+        assert_eq!(
+            breaking_changes(&released, &current),
+            [
+                "the edition changed from 2015 to 2021",
+                "the feature default no longer enables serde",
+                "the feature legacy was removed",
+            ]
+        );
+        assert!(breaking_changes(&released, &released).is_empty());
+        assert!(breaking_changes(&current, &released).len() > 1);
+    }
+
+    #[test]
+    fn test_is_patch() {
+        let version = |text| Version::parse(text).unwrap();
+        assert!(is_patch(&version("1.2.3"), &version("1.2.4")));
+        assert!(!is_patch(&version("1.2.3"), &version("1.3.0")));
+        assert!(!is_patch(&version("0.2.3"), &version("1.0.0")));
+    }
+}
@@ -0,0 +1,1106 @@
+//! Project configuration, read from a `.semvercli.toml` file next to the
+//! manifest or, for projects keeping it in the manifest, from its
+//! `[workspace.metadata.semvercli]` table (with the same keys). Every
+//! setting is optional; no configuration at all means defaults.
+//!
+//! ```toml
+//! # Packages (by name) or paths (relative to this file) whose versions
+//! # automation must never modify, e.g. vendored crates.
+//! protected = ["vendored-openssl", "vendor/"]
+//!
+//! # Forbid wall-clock, git and network inputs, as `--deterministic` does.
+//! deterministic = true
+//!
+//! # Epoch of the project's versions, rendered as `2!1.4.0` and compared
+//! # before the semantic version, after a change of versioning scheme.
+//! epoch = 2
+//!
+//! # While the MAJOR version is 0, let `--auto` bump the MINOR version for
+//! # breaking changes and the PATCH version for features.
+//! zero_major = true
+//!
+//! # How `--auto` and `changelog` read the history: leave merge commits out,
+//! # and read the commits squashed into a squash merge from its body (the
+//! # `* <message>` list GitHub writes) rather than its title.
+//! skip_merges = true
+//! expand_squash = true
+//!
+//! # Other version files (relative to this file) whose version is kept in
+//! # sync with the manifest's on every write, e.g. a CocoaPods podspec.
+//! sync = ["ios/MySDK.podspec"]
+//!
+//! # The version files `bump --workspace` bumps, relative to this file; `*`
+//! # matches within a path segment, `**` any number of segments and entries
+//! # starting with `!` exclude files. A `package.json` declaring an npm
+//! # workspace stands for the packages of that workspace. Without this, the
+//! # packages of the JavaScript workspace next to the manifest are bumped.
+//! packages = ["crates/*/Cargo.toml", "web/package.json", "charts/app/Chart.yaml"]
+//!
+//! # Directory (relative to this file) of WASM plugins providing version
+//! # file formats semvercli does not know; needs the `plugins` feature.
+//! plugins = "semvercli-plugins"
+//!
+//! # How `bump --workspace` versions the workspace packages: "independent"
+//! # (the default) bumps each from its own version, "fixed" moves them all
+//! # to the bumped highest version.
+//! versioning = "fixed"
+//!
+//! # The command a bare `semvercli` runs, with its arguments; a string is
+//! # split on whitespace, a list of strings is taken as it is.
+//! default_command = "read --version"
+//!
+//! # Aliases for command lines, run as `semvercli <alias> [args]`; the
+//! # options given replace the alias's own ones. Subcommands cannot be
+//! # shadowed.
+//! [alias]
+//! rc = "bump --pre rc.1 --open-pr"
+//!
+//! # Named version fields, read and bumped with `--field <name>`: the
+//! # version at the dotted `key` of a TOML, JSON or YAML `file` (relative to
+//! # this file), independent of the manifest's version.
+//! [fields]
+//! app = { file = "charts/app/Chart.yaml", key = "appVersion" }
+//! schema = { file = "schema.toml", key = "schema_version" }
+//!
+//! # Limits of the numeric components (u64::MAX by default), e.g. to fit
+//! # Android's versionCode, and what a bump past one does: "fail" (the
+//! # default), "saturate" (leave the version unchanged) or "rollover" (reset
+//! # the component and bump the next higher one).
+//! [limits]
+//! minor = 99
+//! patch = 99
+//! overflow = "rollover"
+//!
+//! # What a MAJOR, MINOR or PATCH bump resets: the lower components, the
+//! # pre-release and the build metadata (all of them by default, so that
+//! # `--minor` takes 1.2.3-rc.1 to 1.3.0); `--keep-lower`, `--keep-pre` and
+//! # `--keep-build` keep them for a single bump.
+//! [reset]
+//! pre = false
+//!
+//! # What `stamp` embeds the version into: the Go string variables
+//! # `--ldflags` sets (`main.version` by default) and the prefix of the
+//! # variable names `--env-file` writes.
+//! [stamp]
+//! variables = ["main.version", "github.com/acme/app/internal/build.Version"]
+//! env_prefix = "APP_"
+//!
+//! # How `changelog` links commit hashes, issues and pull requests (`#123`)
+//! and GitLab merge requests (`!123`): the forge ("github", "gitlab" or
+//! "gitea", told from the host name by default), the web URL of the
+//! repository (derived from the `origin` remote by default) and templates
+//! overriding the forge's URLs, with `{url}`, `{hash}` and `{number}`.
+//! [links]
+//! forge = "gitea"
+//! url = "https://git.acme.dev/acme/app"
+//! issue = "https://acme.atlassian.net/browse/APP-{number}"
+//!
+//! # Constraints on the pre-release labels `bump --pre` may set, so typos
+//! # never reach a tag: the allowed channels (first identifier), the most
+//! # identifiers a label may have and whether it must end in a number.
+//! [pre_release]
+//! labels = ["alpha", "beta", "rc"]
+//! max_identifiers = 2
+//! numeric_suffix = true
+//!
+//! # Rules mapping the commit messages of a team's own style to what `--auto`
+//! # bumps and the changelog section `changelog` lists them under, ahead of
+//! # Conventional Commits: the first rule whose `pattern` (a regex) matches
+//! # a message decides. Without `bump` a matching commit warrants no bump,
+//! # without `section` it is not listed; the `scope` and `description` named
+//! # groups of the pattern give the entry's scope and text.
+//! [[commit_convention]]
+//! pattern = '^\[(?P<scope>\w+)\] Add (?P<description>.*)'
+//! bump = "minor"
+//! section = "Added"
+//!
+//! # Which versions each branch may produce; `*` in `branch` matches any
+//! # sequence of characters and the first matching rule applies. `stable`
+//! # allows versions without a pre-release, `pre` lists the allowed
+//! # pre-release labels (the first identifier, `"*"` for any). Once any
+//! # rule is configured, branches matching none may not write versions.
+//! [[branch_policy]]
+//! branch = "main"
+//! stable = true
+//!
+//! [[branch_policy]]
+//! branch = "release/*"
+//! pre = ["rc"]
+//! ```
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use semver::{Identifier, Version};
+use toml_edit::{Document, Item, Table};
+
+use crate::adapter::Format;
+use crate::auto::Convention;
+use crate::cache;
+use crate::error::{Code, Error};
+use crate::limits::{Limits, Overflow, Reset};
+use crate::links::{Forge, Links};
+use crate::pipeline::Component;
+use crate::plugin;
+use crate::schema;
+use crate::stamp::Stamp;
+use crate::transaction::DryRun;
+
+pub const CONFIG_FILE: &str = ".semvercli.toml";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    /// Directory the configuration was loaded from; relative paths in the
+    /// configuration are resolved against it.
+    pub root: PathBuf,
+    pub protected: Vec<String>,
+    pub branch_policy: Vec<BranchPolicy>,
+    pub commit_convention: Vec<Convention>,
+    pub pre_release: PreReleasePolicy,
+    pub limits: Limits,
+    pub reset: Reset,
+    pub stamp: Stamp,
+    pub links: Links,
+    pub fields: Vec<Field>,
+    pub sync: Vec<String>,
+    pub packages: Vec<String>,
+    /// Directory of the WASM plugins to load, relative to the root.
+    pub plugins: Option<String>,
+    pub versioning: Versioning,
+    /// Subcommand and arguments run when none are given; empty for none.
+    pub default_command: Vec<String>,
+    /// The `[alias]` names and the command lines they stand for.
+    pub alias: Vec<(String, Vec<String>)>,
+    /// Whether inputs that may differ between runs (the wall clock, git,
+    /// the network) are forbidden; they must be given explicitly instead.
+    pub deterministic: bool,
+    /// Whether `--auto` follows the 0.x convention while MAJOR is 0.
+    pub zero_major: bool,
+    /// Whether the analyzed history leaves merge commits out.
+    pub skip_merges: bool,
+    /// Whether squash merges are analyzed as the commits they list.
+    pub expand_squash: bool,
+    /// Whether bumps refuse to write a version with build metadata.
+    pub forbid_build_metadata: bool,
+    /// Epoch of the project's versions; 0 if there is none.
+    pub epoch: u64,
+    /// Explicit current date (`YYYYMMDD`), from `--now`.
+    pub now: Option<u64>,
+    /// Explicit current git branch, from `--git-branch`.
+    pub git_branch: Option<String>,
+    /// Whether lifecycle events are reported, from `--output events`.
+    pub events: bool,
+    /// Whether output for humans is colored, from `--color`.
+    pub color: bool,
+    /// Whether warnings on stderr are colored, only with `--color always`.
+    pub stderr_color: bool,
+    /// Whether warnings fail the run, from `--deny-warnings`.
+    pub deny_warnings: bool,
+    /// Deprecated settings the configuration uses, reported as warnings
+    /// once the command line is parsed.
+    pub deprecated: Vec<String>,
+    /// Version of a manifest without one, from `--assume-version`.
+    pub assume_version: Option<Version>,
+    /// Format of the manifest, from `--manifest-format` or its name.
+    pub manifest_format: Format,
+    /// How the changes are reported instead of written, from
+    /// `bump --dry-run`; `None` to write them.
+    pub dry_run: Option<DryRun>,
+}
+
+/// How the packages of a workspace are versioned relative to each other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Versioning {
+    /// Every package has its own version.
+    #[default]
+    Independent,
+    /// All packages share one version.
+    Fixed,
+}
+
+/// The versions a branch (or family of branches) may produce.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BranchPolicy {
+    pub branch: String,
+    pub stable: bool,
+    pub pre: Vec<String>,
+}
+
+impl BranchPolicy {
+    fn from_table(table: &Table) -> Result<BranchPolicy, Error> {
+        Ok(BranchPolicy {
+            branch: table["branch"]
+                .as_str()
+                .ok_or_else(|| {
+                    Error::new(
+                        Code::Config,
+                        "Invalid configuration: [[branch_policy]] requires a `branch` pattern",
+                    )
+                })?
+                .to_string(),
+            stable: table["stable"].as_bool().unwrap_or(false),
+            pre: string_list(&table["pre"], "branch_policy.pre")?,
+        })
+    }
+
+    fn allows(&self, version: &Version) -> bool {
+        match version.pre.first() {
+            None => self.stable,
+            Some(label) => {
+                let label = label.to_string();
+                self.pre
+                    .iter()
+                    .any(|allowed| allowed == "*" || *allowed == label)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut allowed = vec![];
+        if self.stable {
+            allowed.push(String::from("stable versions"));
+        }
+        if !self.pre.is_empty() {
+            allowed.push(format!("pre-releases labelled {}", self.pre.join(", ")));
+        }
+
+        if allowed.is_empty() {
+            String::from("no versions")
+        } else {
+            allowed.join(" and ")
+        }
+    }
+}
+
+/// A named version field: the version at a key path of a file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    /// Path of the file, relative to the root.
+    pub file: String,
+    /// Dotted key path of the version in the file.
+    pub key: String,
+}
+
+impl Field {
+    fn from_item(name: &str, item: &Item) -> Result<Field, Error> {
+        let entry = |key: &str| {
+            item[key].as_str().map(String::from).ok_or_else(|| {
+                Error::new(
+                    Code::Config,
+                    format!(
+                        "Invalid configuration: fields.{} requires a `{}` string",
+                        name, key
+                    ),
+                )
+            })
+        };
+
+        Ok(Field {
+            name: name.to_string(),
+            file: entry("file")?,
+            key: entry("key")?,
+        })
+    }
+}
+
+/// Constraints on the pre-release labels set with `bump --pre`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PreReleasePolicy {
+    /// Allowed first identifiers; any if empty.
+    pub labels: Vec<String>,
+    pub max_identifiers: Option<usize>,
+    /// Whether the last identifier must be numeric, as in `rc.1`.
+    pub numeric_suffix: bool,
+}
+
+impl PreReleasePolicy {
+    fn from_item(item: &Item) -> Result<PreReleasePolicy, Error> {
+        let invalid =
+            |what: &str| Error::new(Code::Config, format!("Invalid configuration: {}", what));
+        if item.is_none() {
+            return Ok(PreReleasePolicy::default());
+        }
+        let table = item
+            .as_table()
+            .ok_or_else(|| invalid("[pre_release] must be a table"))?;
+
+        Ok(PreReleasePolicy {
+            labels: string_list(&table["labels"], "pre_release.labels")?,
+            max_identifiers: match table["max_identifiers"].as_integer() {
+                Some(max) if max > 0 => Some(max as usize),
+                None if table["max_identifiers"].is_none() => None,
+                _ => {
+                    return Err(invalid(
+                        "`pre_release.max_identifiers` must be a positive integer",
+                    ))
+                }
+            },
+            numeric_suffix: table["numeric_suffix"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+impl Config {
+    /// Loads the configuration file that sits next to the given manifest,
+    /// falling back to the defaults if there is none.
+    pub fn load(manifest_path: &str) -> Result<Config, Error> {
+        let root = Path::new(manifest_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        let path = root.join(CONFIG_FILE);
+
+        let config = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                let document = cache::parse(&path.to_string_lossy(), &contents).map_err(|err| {
+                    Error::new(Code::Config, format!("Invalid {}: {}", path.display(), err))
+                })?;
+                if manifest_settings(manifest_path).is_some() {
+                    return Err(Error::new(
+                        Code::Config,
+                        format!(
+                            "Settings in both {} and [workspace.metadata.semvercli] of {}; keep one",
+                            path.display(),
+                            manifest_path
+                        ),
+                    ));
+                }
+                let deprecated =
+                    validate(document.as_table(), &contents, &path.to_string_lossy(), &[])?;
+                Config {
+                    deprecated,
+                    ..Config::from_document(root, &document)?
+                }
+            }
+            Err(_) => match manifest_settings(manifest_path) {
+                Some((table, contents)) => {
+                    let deprecated = validate(
+                        &table,
+                        &contents,
+                        manifest_path,
+                        &["workspace", "metadata", "semvercli"],
+                    )?;
+                    Config {
+                        deprecated,
+                        ..Config::from_table(root, &table)?
+                    }
+                }
+                None => Config {
+                    root,
+                    ..Config::default()
+                },
+            },
+        };
+        if let Some(ref plugins) = config.plugins {
+            plugin::load(&config.root.join(plugins))?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_document(root: PathBuf, document: &Document) -> Result<Config, Error> {
+        Config::from_table(root, document.as_table())
+    }
+
+    pub fn from_table(root: PathBuf, settings: &Table) -> Result<Config, Error> {
+        let branch_policy = match settings["branch_policy"].as_array_of_tables() {
+            Some(policies) => policies
+                .iter()
+                .map(BranchPolicy::from_table)
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let commit_convention = match settings["commit_convention"].as_array_of_tables() {
+            Some(conventions) => conventions
+                .iter()
+                .map(convention)
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+
+        Ok(Config {
+            root,
+            protected: string_list(&settings["protected"], "protected")?,
+            branch_policy,
+            commit_convention,
+            pre_release: PreReleasePolicy::from_item(&settings["pre_release"])?,
+            limits: limits(&settings["limits"])?,
+            reset: reset(&settings["reset"])?,
+            stamp: stamp(&settings["stamp"])?,
+            links: links(&settings["links"])?,
+            fields: match settings["fields"].as_table() {
+                Some(fields) => fields
+                    .iter()
+                    .map(|(name, item)| Field::from_item(name, item))
+                    .collect::<Result<_, _>>()?,
+                None if settings["fields"].is_none() => vec![],
+                None => {
+                    return Err(Error::new(
+                        Code::Config,
+                        "Invalid configuration: [fields] must be a table",
+                    ))
+                }
+            },
+            sync: string_list(&settings["sync"], "sync")?,
+            packages: string_list(&settings["packages"], "packages")?,
+            plugins: match settings["plugins"].as_str() {
+                Some(dir) => Some(dir.to_string()),
+                None if settings["plugins"].is_none() => None,
+                None => {
+                    return Err(Error::new(
+                        Code::Config,
+                        "Invalid configuration: `plugins` must be a directory path",
+                    ))
+                }
+            },
+            versioning: match settings["versioning"].as_str() {
+                None if settings["versioning"].is_none() => Versioning::Independent,
+                Some("independent") => Versioning::Independent,
+                Some("fixed") => Versioning::Fixed,
+                _ => {
+                    return Err(Error::new(
+                        Code::Config,
+                        "Invalid configuration: `versioning` must be \"independent\" or \"fixed\"",
+                    ))
+                }
+            },
+            default_command: command(&settings["default_command"], "default_command")?,
+            alias: match settings["alias"].as_table() {
+                Some(aliases) => aliases
+                    .iter()
+                    .map(|(name, item)| match command(item, name)? {
+                        words if words.is_empty() => Err(Error::new(
+                            Code::Config,
+                            format!("Invalid configuration: alias `{}` is empty", name),
+                        )),
+                        words => Ok((name.to_string(), words)),
+                    })
+                    .collect::<Result<_, _>>()?,
+                None if settings["alias"].is_none() => vec![],
+                None => {
+                    return Err(Error::new(
+                        Code::Config,
+                        "Invalid configuration: [alias] must be a table",
+                    ))
+                }
+            },
+            deterministic: settings["deterministic"].as_bool().unwrap_or(false),
+            zero_major: settings["zero_major"]
+                .as_bool()
+                .or_else(|| settings["zero_ver"].as_bool())
+                .unwrap_or(false),
+            skip_merges: settings["skip_merges"].as_bool().unwrap_or(false),
+            expand_squash: settings["expand_squash"].as_bool().unwrap_or(false),
+            forbid_build_metadata: settings["forbid_build_metadata"].as_bool().unwrap_or(false),
+            epoch: match settings["epoch"].as_integer() {
+                Some(epoch) if epoch >= 0 => epoch as u64,
+                None if settings["epoch"].is_none() => 0,
+                _ => {
+                    return Err(Error::new(
+                        Code::Config,
+                        "Invalid configuration: `epoch` must be a non-negative integer",
+                    ))
+                }
+            },
+            ..Config::default()
+        })
+    }
+
+    /// Whether the package with the given name, or the manifest at the given
+    /// path, is on the protected list.
+    pub fn is_protected(&self, name: Option<&str>, manifest_path: &str) -> bool {
+        let path = Path::new(manifest_path);
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+
+        self.protected
+            .iter()
+            .any(|entry| Some(entry.as_str()) == name || relative.starts_with(entry))
+    }
+
+    /// The paths of the files to keep in sync with the manifest.
+    pub fn sync_paths(&self) -> Vec<String> {
+        self.sync
+            .iter()
+            .map(|path| self.root.join(path).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// The configured version field called `name`.
+    pub fn field(&self, name: &str) -> Result<&Field, Error> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| {
+                Error::new(
+                    Code::InvalidArgument,
+                    format!("No version field named `{}` in [fields]", name),
+                )
+            })
+    }
+
+    /// The command line the alias `name` stands for.
+    pub fn alias(&self, name: &str) -> Option<&[String]> {
+        self.alias
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .map(|(_, words)| words.as_slice())
+    }
+
+    /// Refuses `input` (e.g. "the wall clock") in deterministic mode.
+    pub fn forbid_in_deterministic(&self, input: &str) -> Result<(), Error> {
+        if self.deterministic {
+            return Err(Error::new(
+                Code::Nondeterministic,
+                format!("{} may not be used in deterministic mode", input),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the pre-release label `pre` against the `[pre_release]`
+    /// constraints; the error names the violated one.
+    pub fn check_pre_release(&self, pre: &[Identifier]) -> Result<(), String> {
+        let policy = &self.pre_release;
+        let label = pre
+            .iter()
+            .map(Identifier::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        let violation = |what: String| {
+            Err(format!(
+                "Pre-release label `{}` is not allowed: {}",
+                label, what
+            ))
+        };
+
+        let channel = match pre.first() {
+            Some(channel) => channel.to_string(),
+            None => return Ok(()),
+        };
+        if !policy.labels.is_empty() && !policy.labels.contains(&channel) {
+            return violation(format!(
+                "`{}` is not one of {}",
+                channel,
+                policy.labels.join(", ")
+            ));
+        }
+        if let Some(max) = policy.max_identifiers {
+            if pre.len() > max {
+                return violation(format!("it has more than {} identifiers", max));
+            }
+        }
+        if policy.numeric_suffix {
+            if let Some(Identifier::AlphaNumeric(_)) = pre.last() {
+                return violation(String::from("it does not end in a number"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the branch policy allows producing `version` on `branch`;
+    /// the error describes the violated rule.
+    pub fn check_branch_policy(&self, branch: &str, version: &Version) -> Result<(), String> {
+        if self.branch_policy.is_empty() {
+            return Ok(());
+        }
+
+        match self
+            .branch_policy
+            .iter()
+            .find(|policy| glob_match(&policy.branch, branch))
+        {
+            Some(policy) if policy.allows(version) => Ok(()),
+            Some(policy) => Err(format!(
+                "Branch policy violation: branch `{}` may not produce version {} (rule `{}` allows {})",
+                branch,
+                version,
+                policy.branch,
+                policy.describe()
+            )),
+            None => Err(format!(
+                "Branch policy violation: no [[branch_policy]] rule allows producing versions on branch `{}`",
+                branch
+            )),
+        }
+    }
+}
+
+/// Reads an optional list of strings; fails if the item is present but
+/// is anything else.
+fn string_list(item: &Item, key: &str) -> Result<Vec<String>, Error> {
+    if item.is_none() {
+        return Ok(vec![]);
+    }
+
+    let invalid = || {
+        Error::new(
+            Code::Config,
+            format!("Invalid configuration: `{}` must be a list of strings", key),
+        )
+    };
+    item.as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|entry| entry.as_str().map(String::from).ok_or_else(invalid))
+        .collect()
+}
+
+/// A command line given as a string, split on whitespace, or as a list of
+/// its words.
+fn command(item: &Item, key: &str) -> Result<Vec<String>, Error> {
+    match item.as_str() {
+        Some(command) => Ok(command.split_whitespace().map(String::from).collect()),
+        None => string_list(item, key).map_err(|_| {
+            Error::new(
+                Code::Config,
+                format!(
+                    "Invalid configuration: `{}` must be a string or a list of strings",
+                    key
+                ),
+            )
+        }),
+    }
+}
+
+fn limits(item: &Item) -> Result<Limits, Error> {
+    let invalid = |what: &str| Error::new(Code::Config, format!("Invalid configuration: {}", what));
+    if item.is_none() {
+        return Ok(Limits::default());
+    }
+    let table = item
+        .as_table()
+        .ok_or_else(|| invalid("[limits] must be a table"))?;
+    let limit = |key: &str| match table[key].as_integer() {
+        Some(limit) if limit > 0 => Ok(limit as u64),
+        None if table[key].is_none() => Ok(u64::MAX),
+        _ => Err(invalid(&format!(
+            "`limits.{}` must be a positive integer",
+            key
+        ))),
+    };
+
+    Ok(Limits {
+        major: limit("major")?,
+        minor: limit("minor")?,
+        patch: limit("patch")?,
+        overflow: match table["overflow"].as_str() {
+            None if table["overflow"].is_none() => Overflow::Fail,
+            Some("fail") => Overflow::Fail,
+            Some("saturate") => Overflow::Saturate,
+            Some("rollover") => Overflow::Rollover,
+            _ => {
+                return Err(invalid(
+                    "`limits.overflow` must be \"fail\", \"saturate\" or \"rollover\"",
+                ))
+            }
+        },
+    })
+}
+
+fn reset(item: &Item) -> Result<Reset, Error> {
+    if item.is_none() {
+        return Ok(Reset::default());
+    }
+    let table = item.as_table().ok_or_else(|| {
+        Error::new(
+            Code::Config,
+            "Invalid configuration: [reset] must be a table",
+        )
+    })?;
+    let reset = |key: &str| table[key].as_bool().unwrap_or(true);
+
+    Ok(Reset {
+        lower: reset("lower"),
+        pre: reset("pre"),
+        build: reset("build"),
+    })
+}
+
+fn stamp(item: &Item) -> Result<Stamp, Error> {
+    let invalid = |what: &str| Error::new(Code::Config, format!("Invalid configuration: {}", what));
+    if item.is_none() {
+        return Ok(Stamp::default());
+    }
+    let table = item
+        .as_table()
+        .ok_or_else(|| invalid("[stamp] must be a table"))?;
+    let default = Stamp::default();
+
+    Ok(Stamp {
+        variables: if table["variables"].is_none() {
+            default.variables
+        } else {
+            string_list(&table["variables"], "stamp.variables")?
+        },
+        env_prefix: match table["env_prefix"].as_str() {
+            Some(prefix) => prefix.to_string(),
+            None if table["env_prefix"].is_none() => default.env_prefix,
+            None => return Err(invalid("`stamp.env_prefix` must be a string")),
+        },
+    })
+}
+
+fn convention(table: &Table) -> Result<Convention, Error> {
+    let invalid =
+        |what: String| Error::new(Code::Config, format!("Invalid configuration: {}", what));
+    let pattern = table["pattern"].as_str().ok_or_else(|| {
+        invalid(String::from(
+            "[[commit_convention]] requires a `pattern` regex",
+        ))
+    })?;
+
+    Ok(Convention {
+        pattern: Regex::new(pattern)
+            .map_err(|err| invalid(format!("`commit_convention.pattern` {}: {}", pattern, err)))?,
+        bump: match table["bump"].as_str() {
+            Some("major") => Some(Component::Major),
+            Some("minor") => Some(Component::Minor),
+            Some("patch") => Some(Component::Patch),
+            None if table["bump"].is_none() => None,
+            _ => {
+                return Err(invalid(String::from(
+                    "`commit_convention.bump` must be \"major\", \"minor\" or \"patch\"",
+                )))
+            }
+        },
+        section: match table["section"].as_str() {
+            Some(section) => Some(section.to_string()),
+            None if table["section"].is_none() => None,
+            None => {
+                return Err(invalid(String::from(
+                    "`commit_convention.section` must be a string",
+                )))
+            }
+        },
+    })
+}
+
+fn links(item: &Item) -> Result<Links, Error> {
+    let invalid = |what: &str| Error::new(Code::Config, format!("Invalid configuration: {}", what));
+    if item.is_none() {
+        return Ok(Links::default());
+    }
+    let table = item
+        .as_table()
+        .ok_or_else(|| invalid("[links] must be a table"))?;
+    let string = |key: &str| match table[key].as_str() {
+        Some(value) => Ok(Some(value.to_string())),
+        None if table[key].is_none() => Ok(None),
+        None => Err(invalid(&format!("`links.{}` must be a string", key))),
+    };
+
+    Ok(Links {
+        forge: match string("forge")? {
+            Some(forge) => Some(Forge::parse(&forge).ok_or_else(|| {
+                invalid("`links.forge` must be \"github\", \"gitlab\" or \"gitea\"")
+            })?),
+            None => None,
+        },
+        url: string("url")?,
+        commit: string("commit")?,
+        issue: string("issue")?,
+        merge_request: string("merge_request")?,
+    })
+}
+
+/// Matches `text` against a pattern in which `*` stands for any (possibly
+/// empty) sequence of characters.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == text,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            text.starts_with(prefix)
+                && (prefix.len()..=text.len())
+                    .filter(|split| text.is_char_boundary(*split))
+                    .any(|split| glob_match(rest, &text[split..]))
+        }
+    }
+}
+
+/// The `[workspace.metadata.semvercli]` table of the manifest and the
+/// manifest's contents, if the manifest can be read and has one; a broken
+/// manifest is reported by the command reading it, as is one read from
+/// stdin (which can be read once).
+fn manifest_settings(manifest_path: &str) -> Option<(Table, String)> {
+    if manifest_path == crate::STDIN_MANIFEST {
+        return None;
+    }
+    let contents = fs::read_to_string(manifest_path).ok()?;
+    let document = cache::parse(manifest_path, &contents).ok()?;
+    let settings = document["workspace"]["metadata"]["semvercli"].as_table()?;
+
+    Some((settings.clone(), contents))
+}
+
+/// Checks the settings parsed from `contents`, the file at `path`, against
+/// the schema: unknown keys or values of the wrong type fail, all of them
+/// reported at once; the deprecated settings used are returned.
+fn validate(
+    settings: &Table,
+    contents: &str,
+    path: &str,
+    prefix: &[&str],
+) -> Result<Vec<String>, Error> {
+    let report = schema::check(settings, contents, path, prefix);
+    if report.problems.is_empty() {
+        return Ok(report.warnings);
+    }
+
+    Err(Error::new(
+        Code::Config,
+        format!("Invalid configuration: {}", report.problems.join("\n")),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use semver::Version;
+    use toml_edit::Document;
+
+    use super::*;
+
+    /// Tests that packages are matched both by name and by path prefix.
+    #[test]
+    fn test_is_protected() {
+        let document = "protected = [\"vendored\", \"vendor/\"]"
+            .parse::<Document>()
+            .unwrap();
+        let config = Config::from_document(PathBuf::from("repo"), &document).unwrap();
+
+        assert!(config.is_protected(Some("vendored"), "repo/vendored/Cargo.toml"));
+        assert!(config.is_protected(Some("openssl"), "repo/vendor/openssl/Cargo.toml"));
+        assert!(!config.is_protected(Some("app"), "repo/app/Cargo.toml"));
+        assert!(!config.is_protected(Some("app"), "repo/vendor-tools/Cargo.toml"));
+    }
+
+    /// Tests that the first matching rule decides, and that unmatched
+    /// branches are refused once any rule is configured.
+    #[test]
+    fn test_branch_policy() {
+        let document = "[[branch_policy]]\nbranch = \"main\"\nstable = true\n\n\
+                        [[branch_policy]]\nbranch = \"release/*\"\npre = [\"rc\"]\n"
+            .parse::<Document>()
+            .unwrap();
+        let config = Config::from_document(PathBuf::new(), &document).unwrap();
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert!(config
+            .check_branch_policy("main", &version("1.2.0"))
+            .is_ok());
+        assert!(config
+            .check_branch_policy("main", &version("1.2.0-rc.1"))
+            .is_err());
+        assert!(config
+            .check_branch_policy("release/1.2", &version("1.2.0-rc.1"))
+            .is_ok());
+        assert!(config
+            .check_branch_policy("release/1.2", &version("1.2.0-beta.1"))
+            .is_err());
+        assert!(config
+            .check_branch_policy("feature/x", &version("1.2.0"))
+            .is_err());
+        assert!(Config::default()
+            .check_branch_policy("feature/x", &version("1.2.0"))
+            .is_ok());
+    }
+
+    /// Tests that settings are read from the manifest without a
+    /// configuration file, and refused when both exist.
+    #[test]
+    fn test_manifest_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("Cargo.toml");
+        let manifest_path = manifest_path.to_str().unwrap();
+        fs::write(
+            manifest_path,
+            "[workspace]\nmembers = [\"app\"]\n\n[workspace.metadata.semvercli]\n\
+             protected = [\"vendored\"]\nversioning = \"fixed\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(manifest_path).unwrap();
+        assert_eq!(config.root, dir.path());
+        assert_eq!(config.protected, vec!["vendored"]);
+        assert_eq!(config.versioning, Versioning::Fixed);
+
+        fs::write(dir.path().join(CONFIG_FILE), "deterministic = true\n").unwrap();
+        assert_eq!(Config::load(manifest_path).unwrap_err().code, Code::Config);
+    }
+
+    /// Tests that a renamed setting is still read under its former name,
+    /// and reported as deprecated with its position.
+    #[test]
+    fn test_load_deprecated() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("Cargo.toml");
+        let manifest_path = manifest_path.to_str().unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE),
+            "protected = [\"vendored\"]\nzero_ver = true\n",
+        )
+        .unwrap();
+
+        let config = Config::load(manifest_path).unwrap();
+        assert!(config.zero_major);
+        assert_eq!(config.deprecated.len(), 1);
+        assert!(config.deprecated[0]
+            .ends_with(".semvercli.toml:2:1: `zero_ver` is deprecated, use `zero_major` instead"));
+    }
+
+    /// Tests that a configuration that does not match the schema fails to
+    /// load, naming the position of the problem.
+    #[test]
+    fn test_load_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("Cargo.toml");
+        let manifest_path = manifest_path.to_str().unwrap();
+        fs::write(
+            dir.path().join(CONFIG_FILE),
+            "protected = [\"vendored\"]\nzero_major = 1\n",
+        )
+        .unwrap();
+
+        let err = Config::load(manifest_path).unwrap_err();
+        assert_eq!(err.code, Code::Config);
+        assert!(err
+            .message
+            .ends_with(".semvercli.toml:2:1: `zero_major` must be a boolean, not an integer"));
+    }
+
+    /// Tests every pre-release constraint, and that a pre-release label
+    /// may always be cleared.
+    #[test]
+    fn test_pre_release() {
+        let document = "[pre_release]\nlabels = [\"alpha\", \"rc\"]\n\
+                        max_identifiers = 2\nnumeric_suffix = true\n"
+            .parse::<Document>()
+            .unwrap();
+        let config = Config::from_document(PathBuf::new(), &document).unwrap();
+        let check = |version: &str| config.check_pre_release(&Version::parse(version).unwrap().pre);
+
+        assert!(check("1.0.0-rc.1").is_ok());
+        assert!(check("1.0.0").is_ok());
+        assert!(check("1.0.0-aplha.1").is_err());
+        assert!(check("1.0.0-rc.1.2").is_err());
+        assert!(check("1.0.0-rc").is_err());
+        assert!(Config::default()
+            .check_pre_release(&Version::parse("1.0.0-x.y.z").unwrap().pre)
+            .is_ok());
+    }
+
+    /// Tests that [stamp] is read over its defaults, and its types checked.
+    #[test]
+    fn test_stamp() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config = parse("[stamp]\nenv_prefix = \"APP_\"\n").unwrap();
+
+        assert_eq!(config.stamp.variables, ["main.version"]);
+        assert_eq!(config.stamp.env_prefix, "APP_");
+        assert!(parse("[stamp]\nvariables = \"main.version\"\n").is_err());
+    }
+
+    /// Tests that commit conventions are read, and bad patterns refused.
+    #[test]
+    fn test_commit_convention() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config = parse(
+            "[[commit_convention]]\npattern = \"^\\\\[feature\\\\]\"\nbump = \"minor\"\nsection = \"Added\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.commit_convention[0].pattern.as_str(),
+            "^\\[feature\\]"
+        );
+        assert_eq!(config.commit_convention[0].bump, Some(Component::Minor));
+        assert!(parse("[[commit_convention]]\npattern = \"(\"\n").is_err());
+        assert!(parse("[[commit_convention]]\npattern = \"x\"\nbump = \"pre\"\n").is_err());
+    }
+
+    /// Tests that [links] is read, and unknown forges refused.
+    #[test]
+    fn test_links() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config =
+            parse("[links]\nforge = \"gitea\"\nissue = \"https://jira/{number}\"\n").unwrap();
+
+        assert_eq!(config.links.forge, Some(Forge::Gitea));
+        assert_eq!(config.links.issue.as_deref(), Some("https://jira/{number}"));
+        assert_eq!(config.links.url, None);
+        assert!(parse("[links]\nforge = \"bitbucket\"\n").is_err());
+    }
+
+    /// Tests that the default command is read as a string or a list of words.
+    #[test]
+    fn test_default_command() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+
+        assert_eq!(
+            parse("default_command = \"read  --version\"\n")
+                .unwrap()
+                .default_command,
+            ["read", "--version"]
+        );
+        assert_eq!(
+            parse("default_command = [\"changelog\", \"--to\", \"v1 rc\"]\n")
+                .unwrap()
+                .default_command,
+            ["changelog", "--to", "v1 rc"]
+        );
+        assert!(parse("").unwrap().default_command.is_empty());
+        assert!(parse("default_command = 1\n").is_err());
+    }
+
+    /// Tests that aliases are read as strings or lists, and empty ones refused.
+    #[test]
+    fn test_alias() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config =
+            parse("[alias]\nrc = \"bump --pre rc\"\nnext = [\"read\", \"--version\"]\n").unwrap();
+
+        assert_eq!(config.alias("rc").unwrap(), ["bump", "--pre", "rc"]);
+        assert_eq!(config.alias("next").unwrap(), ["read", "--version"]);
+        assert_eq!(config.alias("bump"), None);
+        assert!(parse("[alias]\nrc = \" \"\n").is_err());
+        assert!(parse("alias = \"rc\"\n").is_err());
+    }
+
+    /// Tests that [reset] resets everything it does not turn off.
+    #[test]
+    fn test_reset() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config = parse("[reset]\npre = false\n").unwrap();
+
+        assert!(config.reset.lower);
+        assert!(!config.reset.pre);
+        assert_eq!(Config::default().reset, Reset::default());
+        assert!(parse("reset = true\n").is_err());
+    }
+
+    /// Tests that [limits] is read over its defaults, and bad limits refused.
+    #[test]
+    fn test_limits() {
+        let parse =
+            |toml: &str| Config::from_document(PathBuf::new(), &toml.parse::<Document>().unwrap());
+        let config = parse("[limits]\nminor = 99\noverflow = \"rollover\"\n").unwrap();
+
+        assert_eq!(config.limits.minor, 99);
+        assert_eq!(config.limits.patch, u64::MAX);
+        assert_eq!(config.limits.overflow, Overflow::Rollover);
+        assert_eq!(Config::default().limits, Limits::default());
+        assert!(parse("[limits]\npatch = 0\n").is_err());
+        assert!(parse("[limits]\noverflow = \"wrap\"\n").is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Conversions between semantic versions and the version conventions of
+//! other ecosystems, so that polyglot repositories can derive every
+//! artifact's version from the one in the manifest (and back).
+use semver::{Identifier, Version};
+
+use super::VersionMetadata;
+
+/// Renders a version using Maven's conventions. The pre-release equal to
+/// `snapshot_label` becomes the `-SNAPSHOT` qualifier, other pre-releases
+/// are kept as the qualifier, and build metadata, which Maven has no notion
+/// of, is dropped: `1.5.0-SNAPSHOT+abc` -> `1.5.0-SNAPSHOT`.
+pub fn to_maven(version: &Version, snapshot_label: &str) -> String {
+    let base = format!("{}.{}.{}", version.major, version.minor, version.patch);
+
+    if version.pre.is_empty() {
+        base
+    } else if is_label(&version.pre, snapshot_label) {
+        format!("{}-SNAPSHOT", base)
+    } else {
+        format!(
+            "{}-{}",
+            base,
+            String::from(VersionMetadata(version.pre.clone()))
+        )
+    }
+}
+
+/// Parses a Maven version into a semantic version. Missing components are
+/// padded with zeros (`1.5-SNAPSHOT`), snapshots, including timestamped
+/// ones such as `1.5.0-20240312.101010-1`, map to the `snapshot_label`
+/// pre-release, and any other qualifier is kept as the pre-release.
+pub fn from_maven(maven: &str, snapshot_label: &str) -> Result<Version, String> {
+    let invalid = || format!("Invalid Maven version: {}", maven);
+    let (numbers, qualifier) = match maven.find('-') {
+        Some(dash) => (&maven[..dash], Some(&maven[dash + 1..])),
+        None => (maven, None),
+    };
+
+    let mut components = numbers
+        .split('.')
+        .map(|component| component.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u64>, String>>()?;
+    if components.len() > 3 {
+        return Err(invalid());
+    }
+    components.resize(3, 0);
+
+    let pre = match qualifier {
+        None => String::new(),
+        Some(qualifier)
+            if qualifier.eq_ignore_ascii_case("SNAPSHOT") || is_timestamp(qualifier) =>
+        {
+            snapshot_label.to_string()
+        }
+        Some(qualifier) => qualifier.to_string(),
+    };
+
+    let semver = if pre.is_empty() {
+        format!("{}.{}.{}", components[0], components[1], components[2])
+    } else {
+        format!(
+            "{}.{}.{}-{}",
+            components[0], components[1], components[2], pre
+        )
+    };
+
+    Version::parse(&semver).map_err(|_| invalid())
+}
+
+/// Whether a Maven qualifier is a deployed snapshot's `YYYYMMDD.HHMMSS-N`
+/// timestamp.
+fn is_timestamp(qualifier: &str) -> bool {
+    let digits = |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+    let mut parts = qualifier.splitn(2, '-');
+    let stamp = parts.next().unwrap_or_default();
+    let build = parts.next().unwrap_or_default();
+
+    stamp.is_ascii()
+        && stamp.len() == 15
+        && digits(&stamp[..8], 8)
+        && &stamp[8..9] == "."
+        && digits(&stamp[9..], 6)
+        && !build.is_empty()
+        && build.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Renders a version as a normalized PEP 440 version. Pre-releases must be
+/// made of `alpha`/`a`, `beta`/`b`, `rc`/`c` and `dev` labels, each with an
+/// optional number (`1.2.3-rc.1` -> `1.2.3rc1`, `1.2.3-beta.2.dev.1` ->
+/// `1.2.3b2.dev1`); build metadata becomes the local version label.
+pub fn to_pep440(version: &Version) -> Result<String, String> {
+    let mut pep440 = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    let (mut pre, mut dev) = (None, None);
+
+    for (label, number) in labelled_numbers(&version.pre) {
+        let (slot, rendered) = match label.to_lowercase().as_str() {
+            "a" | "alpha" => (&mut pre, format!("a{}", number)),
+            "b" | "beta" => (&mut pre, format!("b{}", number)),
+            "c" | "rc" | "pre" | "preview" => (&mut pre, format!("rc{}", number)),
+            "dev" => (&mut dev, format!(".dev{}", number)),
+            _ => {
+                return Err(format!(
+                    "Pre-release label `{}` of {} has no PEP 440 equivalent",
+                    label, version
+                ))
+            }
+        };
+        if slot.replace(rendered).is_some() {
+            return Err(format!(
+                "{} has more than one pre-release of the same kind",
+                version
+            ));
+        }
+    }
+
+    pep440.push_str(&pre.unwrap_or_default());
+    pep440.push_str(&dev.unwrap_or_default());
+    if !version.build.is_empty() {
+        pep440.push('+');
+        pep440.push_str(&String::from(VersionMetadata(version.build.clone())));
+    }
+
+    Ok(pep440)
+}
+
+/// Parses a PEP 440 version, normalized or not (`1.2.3rc1`, `1.2.3-RC.1`,
+/// `1.2.dev0`), into a semantic version: the release segment is padded to
+/// three components, pre- and dev-releases become `rc.1`/`dev.0` style
+/// pre-release identifiers and the local label becomes build metadata.
+/// Epochs and post-releases have no semantic version equivalent.
+pub fn from_pep440(pep440: &str) -> Result<Version, String> {
+    let invalid = || format!("Invalid PEP 440 version: {}", pep440);
+    let lowered = pep440.trim().to_lowercase();
+    let lowered = lowered.strip_prefix('v').unwrap_or(&lowered);
+    let (public, local) = match lowered.find('+') {
+        Some(plus) => (&lowered[..plus], Some(&lowered[plus + 1..])),
+        None => (lowered, None),
+    };
+    if public.contains('!') {
+        return Err(format!(
+            "{} has an epoch, which has no semantic version equivalent",
+            pep440
+        ));
+    }
+
+    let release_end = public
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(public.len());
+    let release = public[..release_end].trim_end_matches('.');
+    let mut components = release
+        .split('.')
+        .map(|component| component.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u64>, String>>()?;
+    if components.len() > 3 {
+        return Err(format!("{} has more than three release components", pep440));
+    }
+    components.resize(3, 0);
+
+    let mut pre = vec![];
+    let mut rest = &public[release_end..];
+    while !rest.is_empty() {
+        rest = rest.trim_start_matches(['.', '-', '_']);
+        let label_end = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let label = match &rest[..label_end] {
+            "a" | "alpha" => "a",
+            "b" | "beta" => "b",
+            "c" | "rc" | "pre" | "preview" => "rc",
+            "dev" => "dev",
+            "post" | "rev" | "r" => {
+                return Err(format!(
+                    "{} is a post-release, which has no semantic version equivalent",
+                    pep440
+                ))
+            }
+            _ => return Err(invalid()),
+        };
+        rest = rest[label_end..].trim_start_matches(['.', '-', '_']);
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..number_end].parse::<u64>().unwrap_or(0);
+        rest = &rest[number_end..];
+
+        pre.push(format!("{}.{}", label, number));
+    }
+
+    let mut semver = format!("{}.{}.{}", components[0], components[1], components[2]);
+    if !pre.is_empty() {
+        semver.push('-');
+        semver.push_str(&pre.join("."));
+    }
+    if let Some(local) = local {
+        semver.push('+');
+        semver.push_str(&local.replace(['-', '_'], "."));
+    }
+
+    Version::parse(&semver).map_err(|_| invalid())
+}
+
+/// Renders a version as a Debian version string. The pre-release is joined
+/// with `~`, which Debian sorts before the bare version (`1.2.3-rc.1` ->
+/// `1.2.3~rc.1`, so that `1.2.3~rc.1 << 1.2.3` as in semver), build
+/// metadata is kept after a `+`, and the optional epoch and Debian revision
+/// are added around it: `2:1.2.3~rc.1-1`. Hyphens are only allowed in the
+/// upstream version when there is a revision, as Debian splits on the last one.
+pub fn to_debian(
+    version: &Version,
+    epoch: Option<u64>,
+    revision: Option<&str>,
+) -> Result<String, String> {
+    let mut debian = String::new();
+    if let Some(epoch) = epoch {
+        debian.push_str(&format!("{}:", epoch));
+    }
+
+    let mut upstream = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    if !version.pre.is_empty() {
+        upstream.push('~');
+        upstream.push_str(&String::from(VersionMetadata(version.pre.clone())));
+    }
+    if !version.build.is_empty() {
+        upstream.push('+');
+        upstream.push_str(&String::from(VersionMetadata(version.build.clone())));
+    }
+    debian.push_str(&upstream);
+
+    match revision {
+        Some(revision) => {
+            let valid = !revision.is_empty()
+                && revision
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+.~".contains(c));
+            if !valid {
+                return Err(format!("Invalid Debian revision: {}", revision));
+            }
+            debian.push('-');
+            debian.push_str(revision);
+        }
+        None if upstream.contains('-') => {
+            return Err(format!(
+                "{} contains a hyphen, which Debian only allows together with a revision (--debian-revision)",
+                version
+            ))
+        }
+        None => (),
+    }
+
+    Ok(debian)
+}
+
+/// Sanitizes a version (or any of its components) into a valid OCI image
+/// tag, which may only consist of `[A-Za-z0-9_.-]`, must not start with `.`
+/// or `-` and is at most 128 characters long. The build metadata separator
+/// `+` becomes `_`, per the common convention, and any other invalid
+/// character becomes `-`: `1.2.3-rc.1+build.5` -> `1.2.3-rc.1_build.5`.
+pub fn to_docker_tag(version: &str) -> String {
+    let mut tag = version
+        .chars()
+        .map(|c| match c {
+            '+' => '_',
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' => c,
+            _ => '-',
+        })
+        .take(128)
+        .collect::<String>();
+
+    if tag.starts_with('.') || tag.starts_with('-') {
+        tag.replace_range(..1, "_");
+    }
+
+    tag
+}
+
+/// Splits pre-release identifiers into (label, number) pairs, accepting
+/// both `rc.1` and `rc1`; a label without a number counts as 0.
+fn labelled_numbers(pre: &[Identifier]) -> Vec<(String, u64)> {
+    let mut pairs: Vec<(String, u64)> = vec![];
+
+    for identifier in pre {
+        match identifier {
+            Identifier::Numeric(number) => match pairs.last_mut() {
+                Some(last) if last.1 == 0 => last.1 = *number,
+                _ => pairs.push((String::new(), *number)),
+            },
+            Identifier::AlphaNumeric(alphanumeric) => {
+                let digits = alphanumeric.trim_start_matches(|c: char| !c.is_ascii_digit());
+                let label = &alphanumeric[..alphanumeric.len() - digits.len()];
+                pairs.push((label.to_string(), digits.parse().unwrap_or(0)));
+            }
+        }
+    }
+
+    pairs
+}
+
+fn is_label(pre: &[Identifier], label: &str) -> bool {
+    String::from(VersionMetadata(pre.to_vec())).eq_ignore_ascii_case(label)
+}
+
+#[cfg(test)]
+mod test {
+    use semver::Version;
+
+    use super::*;
+
+    /// Tests that snapshots round-trip and that other qualifiers are kept.
+    #[test]
+    fn test_maven() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert_eq!(
+            to_maven(&version("1.5.0-SNAPSHOT+abc"), "SNAPSHOT"),
+            "1.5.0-SNAPSHOT"
+        );
+        assert_eq!(to_maven(&version("1.5.0-dev"), "dev"), "1.5.0-SNAPSHOT");
+        assert_eq!(to_maven(&version("1.5.0-rc.1"), "dev"), "1.5.0-rc.1");
+        assert_eq!(to_maven(&version("1.5.0"), "dev"), "1.5.0");
+
+        assert_eq!(
+            from_maven("1.5.0-SNAPSHOT", "dev"),
+            Ok(version("1.5.0-dev"))
+        );
+        assert_eq!(
+            from_maven("1.5-SNAPSHOT", "SNAPSHOT"),
+            Ok(version("1.5.0-SNAPSHOT"))
+        );
+        assert_eq!(
+            from_maven("1.5.0-20240312.101010-3", "SNAPSHOT"),
+            Ok(version("1.5.0-SNAPSHOT"))
+        );
+        assert_eq!(
+            from_maven("1.5.0-rc.1", "SNAPSHOT"),
+            Ok(version("1.5.0-rc.1"))
+        );
+        assert!(from_maven("1.5.x", "SNAPSHOT").is_err());
+    }
+
+    /// Tests the PEP 440 mapping in both directions, including the
+    /// alternative spellings PEP 440 allows.
+    #[test]
+    fn test_pep440() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert_eq!(
+            to_pep440(&version("1.2.3-rc.1")),
+            Ok(String::from("1.2.3rc1"))
+        );
+        assert_eq!(
+            to_pep440(&version("1.2.3-beta.2.dev.1+ubuntu.1")),
+            Ok(String::from("1.2.3b2.dev1+ubuntu.1"))
+        );
+        assert_eq!(
+            to_pep440(&version("1.2.3-alpha")),
+            Ok(String::from("1.2.3a0"))
+        );
+        assert!(to_pep440(&version("1.2.3-nightly.1")).is_err());
+
+        assert_eq!(from_pep440("1.2.3rc1"), Ok(version("1.2.3-rc.1")));
+        assert_eq!(from_pep440("1.2.3-RC.1"), Ok(version("1.2.3-rc.1")));
+        assert_eq!(from_pep440("1.2b2.dev1"), Ok(version("1.2.0-b.2.dev.1")));
+        assert_eq!(from_pep440("1.2.3+local-1"), Ok(version("1.2.3+local.1")));
+        assert!(from_pep440("1!1.2.3").is_err());
+        assert!(from_pep440("1.2.3.post1").is_err());
+    }
+
+    /// Tests that tags are sanitized and length limited.
+    #[test]
+    fn test_docker_tag() {
+        assert_eq!(to_docker_tag("1.2.3-rc.1+build.5"), "1.2.3-rc.1_build.5");
+        assert_eq!(to_docker_tag("-rc/1"), "_rc-1");
+        assert_eq!(to_docker_tag(&"1".repeat(200)).len(), 128);
+    }
+
+    /// Tests the Debian rendering of pre-releases, epochs and revisions.
+    #[test]
+    fn test_debian() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert_eq!(
+            to_debian(&version("1.2.3-rc.1"), None, None),
+            Ok(String::from("1.2.3~rc.1"))
+        );
+        assert_eq!(
+            to_debian(&version("1.2.3+git.abc"), Some(2), Some("1ubuntu1")),
+            Ok(String::from("2:1.2.3+git.abc-1ubuntu1"))
+        );
+        assert!(to_debian(&version("1.2.3-rc-1"), None, None).is_err());
+        assert_eq!(
+            to_debian(&version("1.2.3-rc-1"), None, Some("1")),
+            Ok(String::from("1.2.3~rc-1-1"))
+        );
+    }
+}
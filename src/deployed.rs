@@ -0,0 +1,100 @@
+//! Comparing with a deployed service: `check-deployed` fetches the version
+//! a service reports, e.g. on its health endpoint, with `curl` and compares
+//! it to the manifest's (or a given) version, to answer "is prod behind?".
+use std::cmp::Ordering;
+use std::process::Command;
+
+use semver::Version;
+
+use crate::adapter;
+use crate::error::{Code, Error};
+
+/// Fetches the body served at `url`.
+pub fn fetch(url: &str) -> Result<String, Error> {
+    let mut command = Command::new("curl");
+    command.args(["--fail", "--silent", "--show-error", "--location", url]);
+    let output = command.output().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Failed to run {:?}: {}", command, err),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(Error::new(
+            Code::Command,
+            format!(
+                "Could not fetch {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The version in a response body: the string at the `json_path` (e.g.
+/// `.build.version`) of a JSON body, or with the path `.` the whole body,
+/// for endpoints serving the bare version. A leading `v` is allowed.
+pub fn version_at(body: &str, json_path: &str) -> Result<Version, String> {
+    let text = if json_path == "." {
+        body.trim()
+    } else {
+        let path = json_path
+            .trim_start_matches('.')
+            .split('.')
+            .collect::<Vec<_>>();
+        let range = adapter::json_string_at(body, &path)
+            .ok_or_else(|| format!("The response has no string at {}", json_path))?;
+        &body[range]
+    };
+
+    Version::parse(text.trim_start_matches('v'))
+        .map_err(|_| format!("The response reports an invalid version: {}", text))
+}
+
+/// How the deployed version relates to the expected one, for the error;
+/// `None` if they are the same.
+pub fn relation(deployed: &Version, expected: &Version) -> Option<&'static str> {
+    match deployed.cmp(expected) {
+        Ordering::Less => Some("behind"),
+        Ordering::Equal => None,
+        Ordering::Greater => Some("ahead of"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_at() {
+        let body = "{\"status\": \"ok\", \"build\": {\"version\": \"v1.4.0\", \"sha\": \"abc\"}}";
+
+        assert_eq!(
+            version_at(body, ".build.version").unwrap(),
+            Version::parse("1.4.0").unwrap()
+        );
+        assert!(version_at(body, ".status").is_err());
+        assert!(version_at(body, ".version").is_err());
+        assert_eq!(
+            version_at("1.3.0\n", ".").unwrap(),
+            Version::parse("1.3.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_relation() {
+        let version = |text| Version::parse(text).unwrap();
+
+        assert_eq!(
+            relation(&version("1.3.0"), &version("1.4.0")),
+            Some("behind")
+        );
+        assert_eq!(relation(&version("1.4.0"), &version("1.4.0")), None);
+        assert_eq!(
+            relation(&version("1.4.1"), &version("1.4.0")),
+            Some("ahead of")
+        );
+    }
+}
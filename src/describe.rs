@@ -0,0 +1,151 @@
+//! Template variables from `git describe`: `{describe}`,
+//! `{commits-since-tag}`, `{short-sha}` and `{dirty}` can be used in the
+//! BUILD metadata and the tag templates, e.g. `--build
+//! '{commits-since-tag}.g{short-sha}.{dirty}'` for `1.4.0+12.gabc1234.dirty`.
+use crate::config::Config;
+use crate::error::Error;
+use crate::git;
+
+/// The placeholders filled from `git describe`.
+pub const PLACEHOLDERS: [&str; 4] = [
+    "{describe}",
+    "{commits-since-tag}",
+    "{short-sha}",
+    "{dirty}",
+];
+
+/// Where the work tree stands relative to the newest reachable tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Describe {
+    /// The output of `git describe --tags --long --dirty --always`.
+    pub describe: String,
+    pub commits_since_tag: u64,
+    pub short_sha: String,
+    pub dirty: bool,
+}
+
+/// Parses the output of `git describe --tags --long --dirty --always`,
+/// `<tag>-<commits>-g<sha>[-dirty]`, or just `<sha>[-dirty]` when no tag is
+/// reachable, in which case `commits` (the length of the whole history) is
+/// the number of commits since the tag.
+pub fn parse(output: &str, commits: u64) -> Describe {
+    let (rest, dirty) = match output.strip_suffix("-dirty") {
+        Some(rest) => (rest, true),
+        None => (output, false),
+    };
+    let mut parts = rest.rsplitn(3, '-');
+    let tagged = match (parts.next(), parts.next(), parts.next()) {
+        (Some(sha), Some(count), Some(_)) if sha.starts_with('g') => count
+            .parse()
+            .ok()
+            .map(|count| (count, sha.trim_start_matches('g'))),
+        _ => None,
+    };
+    let (commits_since_tag, short_sha) = tagged.unwrap_or((commits, rest));
+
+    Describe {
+        describe: output.to_string(),
+        commits_since_tag,
+        short_sha: short_sha.to_string(),
+        dirty,
+    }
+}
+
+/// Describes the current work tree.
+pub fn current() -> Result<Describe, Error> {
+    let output = git::run(&["describe", "--tags", "--long", "--dirty", "--always"])?;
+    let commits = git::run(&["rev-list", "--count", "HEAD"])?
+        .parse()
+        .unwrap_or(0);
+
+    Ok(parse(&output, commits))
+}
+
+/// Whether `template` uses any of the placeholders.
+pub fn uses_placeholders(template: &str) -> bool {
+    PLACEHOLDERS
+        .iter()
+        .any(|placeholder| template.contains(placeholder))
+}
+
+/// Fills the placeholders of `template`; `{dirty}` is `dirty` for a work
+/// tree with uncommitted changes and empty otherwise.
+pub fn expand(template: &str, describe: &Describe) -> String {
+    template
+        .replace("{describe}", &describe.describe)
+        .replace(
+            "{commits-since-tag}",
+            &describe.commits_since_tag.to_string(),
+        )
+        .replace("{short-sha}", &describe.short_sha)
+        .replace("{dirty}", if describe.dirty { "dirty" } else { "" })
+}
+
+/// Fills the placeholders of a BUILD metadata template, dropping the
+/// identifiers that came out empty, e.g. the `{dirty}` of a clean tree.
+pub fn expand_build(template: &str, describe: &Describe) -> String {
+    expand(template, describe)
+        .split('.')
+        .filter(|identifier| !identifier.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Fills the placeholders of `template` from the current work tree, leaving
+/// templates without any untouched (and git unrun); `build` selects
+/// [`expand_build`].
+pub fn render(template: &str, build: bool, config: &Config) -> Result<String, Error> {
+    if !uses_placeholders(template) {
+        return Ok(template.to_string());
+    }
+    config.forbid_in_deterministic("Git (git describe placeholders)")?;
+    let describe = current()?;
+
+    Ok(if build {
+        expand_build(template, &describe)
+    } else {
+        expand(template, &describe)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("v1.3.0-12-gabc1234-dirty", 40),
+            Describe {
+                describe: String::from("v1.3.0-12-gabc1234-dirty"),
+                commits_since_tag: 12,
+                short_sha: String::from("abc1234"),
+                dirty: true,
+            }
+        );
+        assert_eq!(
+            parse("release-1.3.0-rc.1-0-gabc1234", 40).commits_since_tag,
+            0
+        );
+
+        let untagged = parse("abc1234", 40);
+        assert_eq!(untagged.commits_since_tag, 40);
+        assert_eq!(untagged.short_sha, "abc1234");
+        assert!(!untagged.dirty);
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut describe = parse("v1.3.0-12-gabc1234-dirty", 40);
+        let template = "{commits-since-tag}.g{short-sha}.{dirty}";
+
+        assert_eq!(expand_build(template, &describe), "12.gabc1234.dirty");
+        assert_eq!(
+            expand("v{version}-{describe}", &describe),
+            "v{version}-v1.3.0-12-gabc1234-dirty"
+        );
+        describe.dirty = false;
+        assert_eq!(expand_build(template, &describe), "12.gabc1234");
+        assert!(!uses_placeholders("v{version}"));
+    }
+}
@@ -0,0 +1,176 @@
+//! Compatibility declared by a JavaScript package: `check-engines` verifies
+//! that the `engines.node` and `peerDependencies` ranges of a `package.json`
+//! admit the given Node.js and peer versions. npm ranges are translated to
+//! the requirements used everywhere else: `||` separates alternatives,
+//! whitespace the comparators of one and `a - b` is an inclusive range.
+use semver::{Version, VersionReq};
+
+use crate::adapter;
+use crate::error::{Code, Error};
+
+/// The characters of comparison operators, which npm allows to be separated
+/// from their version by whitespace.
+const OPERATOR: [char; 5] = ['<', '>', '=', '~', '^'];
+
+/// The alternatives of the npm range `range`, any of which admits a version.
+pub fn parse_range(range: &str) -> Result<Vec<VersionReq>, String> {
+    range
+        .split("||")
+        .map(|alternative| {
+            let tokens = alternative.split_whitespace().collect::<Vec<_>>();
+            let comparators = match tokens[..] {
+                [from, "-", to] => vec![format!(">={}", from), format!("<={}", to)],
+                _ => {
+                    let mut comparators: Vec<String> = vec![];
+                    for token in tokens {
+                        match comparators.last_mut() {
+                            Some(operator) if operator.ends_with(OPERATOR) => {
+                                operator.push_str(token)
+                            }
+                            _ => comparators.push(token.to_string()),
+                        }
+                    }
+                    comparators
+                }
+            };
+            let requirement = comparators
+                .iter()
+                .map(|comparator| {
+                    let version = comparator.trim_start_matches(OPERATOR);
+                    let operator = &comparator[..comparator.len() - version.len()];
+                    format!("{}{}", operator, version.trim_start_matches(['v', 'V']))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            VersionReq::parse(&requirement).map_err(|err| format!("{}: {}", range.trim(), err))
+        })
+        .collect()
+}
+
+/// Whether the npm range `range` admits `version`.
+pub fn satisfies(range: &str, version: &Version) -> Result<bool, String> {
+    Ok(parse_range(range)?
+        .iter()
+        .any(|requirement| requirement.matches(version)))
+}
+
+/// Checks the ranges of the `package.json` `contents` at `path` against the
+/// Node.js version `node` and the versions of the named `peers`; a range
+/// that is not declared admits any version.
+pub fn check(
+    contents: &str,
+    path: &str,
+    node: Option<&Version>,
+    peers: &[(&str, Version)],
+) -> Result<(), Error> {
+    let declared = |key_path: &[&str]| {
+        adapter::json_string_at(contents, key_path).map(|range| contents[range].to_string())
+    };
+    let checks = node
+        .map(|node| {
+            (
+                "engines.node".to_string(),
+                declared(&["engines", "node"]),
+                node,
+            )
+        })
+        .into_iter()
+        .chain(peers.iter().map(|(name, version)| {
+            (
+                format!("peerDependencies.{}", name),
+                declared(&["peerDependencies", *name]),
+                version,
+            )
+        }));
+
+    let mut unsatisfied = vec![];
+    for (key, range, version) in checks {
+        let range = match range {
+            Some(range) => range,
+            None => continue,
+        };
+        let satisfied = satisfies(&range, version).map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid range {} in {}: {}", key, path, err),
+            )
+        })?;
+        if !satisfied {
+            unsatisfied.push(format!("{} {:?} does not admit {}", key, range, version));
+        }
+    }
+    if unsatisfied.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        Code::Unsatisfied,
+        format!("{}: {}", path, unsatisfied.join(", ")),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_satisfies() {
+        let cases = [
+            (">=18", "20.11.0", true),
+            (">= 14.17.0 < 17", "16.20.2", true),
+            (">= 14.17.0 < 17", "18.0.0", false),
+            ("^16.8.0 || ^17 || ^18", "17.0.2", true),
+            ("^16.8.0 || ^17 || ^18", "19.0.0", false),
+            ("14.x || >=16", "15.0.0", false),
+            ("1.2.3 - 2.3.4", "2.3.4", true),
+            ("1.2.3 - 2.3.4", "2.3.5", false),
+            ("*", "0.1.0", true),
+            ("v18", "18.1.0", true),
+        ];
+        for (range, version_, satisfied) in &cases {
+            assert_eq!(
+                satisfies(range, &version(version_)),
+                Ok(*satisfied),
+                "{} {}",
+                range,
+                version_
+            );
+        }
+        assert!(satisfies(">= lts", &version("1.0.0")).is_err());
+    }
+
+    /// Tests that every declared range is checked and undeclared ones are
+    /// skipped.
+    #[test]
+    fn test_check() {
+        let package = r#"{
+  "name": "widget",
+  "engines": {"node": ">=18"},
+  "peerDependencies": {"react": "^17 || ^18", "@emotion/react": "^11"}
+}"#;
+        let checked = |node: &str, peers: &[(&str, &str)]| {
+            let peers = peers
+                .iter()
+                .map(|&(name, version_)| (name, version(version_)))
+                .collect::<Vec<_>>();
+            check(package, "package.json", Some(&version(node)), &peers)
+        };
+
+        assert!(checked("20.11.0", &[("react", "18.2.0"), ("vue", "3.0.0")]).is_ok());
+        let err = checked(
+            "16.20.2",
+            &[("react", "18.2.0"), ("@emotion/react", "10.0.0")],
+        )
+        .unwrap_err();
+        assert_eq!(err.code, Code::Unsatisfied);
+        assert!(err
+            .message
+            .contains("engines.node \">=18\" does not admit 16.20.2"));
+        assert!(err.message.contains("peerDependencies.@emotion/react"));
+        assert!(!err.message.contains("peerDependencies.react"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Epoch-prefixed versions, `2!1.4.0`, for projects that migrated between
+//! versioning schemes: the epoch is compared before the semantic version,
+//! so resetting the latter under a new epoch keeps every later version
+//! ordered after the earlier ones. The epoch of the project itself is
+//! stored in the `epoch` setting, since Cargo manifests only accept plain
+//! semantic versions; epoch 0 is the implicit default and never rendered.
+use std::cmp::Ordering;
+use std::fmt;
+
+use semver::Version;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochVersion {
+    pub epoch: u64,
+    pub version: Version,
+}
+
+impl EpochVersion {
+    /// Parses a semantic version with an optional `N!` epoch prefix.
+    pub fn parse(s: &str) -> Result<EpochVersion, String> {
+        let (epoch, version) = match s.find('!') {
+            Some(bang) => (
+                s[..bang]
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid epoch in {}", s))?,
+                &s[bang + 1..],
+            ),
+            None => (0, s),
+        };
+
+        Ok(EpochVersion {
+            epoch,
+            version: Version::parse(version).map_err(|_| format!("Invalid version {}", s))?,
+        })
+    }
+}
+
+impl Ord for EpochVersion {
+    fn cmp(&self, other: &EpochVersion) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| self.version.cmp(&other.version))
+    }
+}
+
+impl PartialOrd for EpochVersion {
+    fn partial_cmp(&self, other: &EpochVersion) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for EpochVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.epoch > 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        write!(f, "{}", self.version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let version = EpochVersion::parse("2!1.4.0-rc.1").unwrap();
+        assert_eq!(version.epoch, 2);
+        assert_eq!(version.version, Version::parse("1.4.0-rc.1").unwrap());
+        assert_eq!(version.to_string(), "2!1.4.0-rc.1");
+        assert_eq!(EpochVersion::parse("1.4.0").unwrap().to_string(), "1.4.0");
+        assert_eq!(EpochVersion::parse("0!1.4.0").unwrap().to_string(), "1.4.0");
+        assert!(EpochVersion::parse("x!1.4.0").is_err());
+        assert!(EpochVersion::parse("2!1.4").is_err());
+    }
+
+    /// Tests that the epoch takes precedence over the semantic version.
+    #[test]
+    fn test_ordering() {
+        let version = |version: &str| EpochVersion::parse(version).unwrap();
+
+        assert!(version("1!0.1.0") > version("9.9.9"));
+        assert!(version("2!0.1.0") > version("1!3.0.0"));
+        assert!(version("1!1.0.0") > version("1!1.0.0-rc.1"));
+        assert_eq!(version("1!1.0.0").cmp(&version("1!1.0.0")), Ordering::Equal);
+    }
+}
@@ -0,0 +1,313 @@
+//! Failure classes. Every error semvercli reports carries one of the codes
+//! below, which map to a stable process exit code and are printed to stderr
+//! as JSON, e.g. `{"code": "E_PARSE_MANIFEST", "exit_code": 3, "message": "..."}`,
+//! so orchestration tools can branch on the cause of a failure. Codes and
+//! exit codes are never renumbered or reused; `semvercli errors` lists them.
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    ReadManifest,
+    ParseManifest,
+    WriteManifest,
+    InvalidVersion,
+    InvalidArgument,
+    Config,
+    Protected,
+    BranchPolicy,
+    Git,
+    Plan,
+    StalePlan,
+    Release,
+    Changelog,
+    Conversion,
+    EmptyComponent,
+    NoChanges,
+    Io,
+    Command,
+    Nondeterministic,
+    Adapter,
+    Workspace,
+    Channel,
+    Overflow,
+    Timeout,
+    Drift,
+    Deployed,
+    Unsatisfied,
+    Msrv,
+    BuildMetadata,
+    StaleManifest,
+    Warning,
+}
+
+impl Code {
+    pub const ALL: [Code; 31] = [
+        Code::ReadManifest,
+        Code::ParseManifest,
+        Code::WriteManifest,
+        Code::InvalidVersion,
+        Code::InvalidArgument,
+        Code::Config,
+        Code::Protected,
+        Code::BranchPolicy,
+        Code::Git,
+        Code::Plan,
+        Code::StalePlan,
+        Code::Release,
+        Code::Changelog,
+        Code::Conversion,
+        Code::EmptyComponent,
+        Code::NoChanges,
+        Code::Io,
+        Code::Command,
+        Code::Nondeterministic,
+        Code::Adapter,
+        Code::Workspace,
+        Code::Channel,
+        Code::Overflow,
+        Code::Timeout,
+        Code::Drift,
+        Code::Deployed,
+        Code::Unsatisfied,
+        Code::Msrv,
+        Code::BuildMetadata,
+        Code::StaleManifest,
+        Code::Warning,
+    ];
+
+    /// The machine readable name, exit code and description of the code.
+    fn info(self) -> (&'static str, i32, &'static str) {
+        match self {
+            Code::ReadManifest => ("E_READ_MANIFEST", 2, "The manifest could not be read."),
+            Code::ParseManifest => ("E_PARSE_MANIFEST", 3, "The manifest is not valid TOML."),
+            Code::WriteManifest => ("E_WRITE_MANIFEST", 4, "The manifest could not be written."),
+            Code::InvalidVersion => (
+                "E_INVALID_VERSION",
+                5,
+                "The manifest has no valid semantic version.",
+            ),
+            Code::InvalidArgument => (
+                "E_INVALID_ARGUMENT",
+                6,
+                "A command line value (version, label, date, ...) is invalid.",
+            ),
+            Code::Config => (
+                "E_CONFIG",
+                7,
+                "The .semvercli.toml configuration is invalid.",
+            ),
+            Code::Protected => (
+                "E_PROTECTED",
+                8,
+                "The package is protected from version changes.",
+            ),
+            Code::BranchPolicy => (
+                "E_BRANCH_POLICY",
+                9,
+                "The branch policy does not allow the version on this branch.",
+            ),
+            Code::Git => ("E_GIT", 10, "A git command failed."),
+            Code::Plan => (
+                "E_PLAN",
+                11,
+                "The plan file could not be read or is invalid, or the idempotency ledger is invalid.",
+            ),
+            Code::StalePlan => (
+                "E_STALE_PLAN",
+                12,
+                "A file changed since the plan was computed.",
+            ),
+            Code::Release => (
+                "E_RELEASE",
+                13,
+                "The release lockfile is missing, invalid or out of date.",
+            ),
+            Code::Changelog => (
+                "E_CHANGELOG",
+                14,
+                "The changelog was not updated for the version bump.",
+            ),
+            Code::Conversion => (
+                "E_CONVERSION",
+                15,
+                "The version cannot be expressed in the requested convention.",
+            ),
+            Code::EmptyComponent => (
+                "E_EMPTY_COMPONENT",
+                16,
+                "The requested version component is empty.",
+            ),
+            Code::NoChanges => (
+                "E_NO_CHANGES",
+                17,
+                "No commit since the last tag warrants a version bump.",
+            ),
+            Code::Io => (
+                "E_IO",
+                18,
+                "A file other than the manifest could not be read or written.",
+            ),
+            Code::Command => ("E_COMMAND", 19, "An external command failed."),
+            Code::Nondeterministic => (
+                "E_NONDETERMINISTIC",
+                20,
+                "A wall-clock, git or network input is needed in deterministic mode.",
+            ),
+            Code::Adapter => (
+                "E_ADAPTER",
+                21,
+                "A version file has an unsupported format or no recognizable version.",
+            ),
+            Code::Workspace => (
+                "E_WORKSPACE",
+                22,
+                "No workspace is declared next to the manifest, or it has no packages.",
+            ),
+            Code::Channel => (
+                "E_CHANNEL",
+                23,
+                "The release channel is invalid or has no released versions.",
+            ),
+            Code::Overflow => (
+                "E_OVERFLOW",
+                24,
+                "A version component would be bumped past its limit.",
+            ),
+            Code::Timeout => (
+                "E_TIMEOUT",
+                25,
+                "The awaited version was not published before the timeout.",
+            ),
+            Code::Drift => (
+                "E_DRIFT",
+                26,
+                "A synced version file disagrees with the manifest.",
+            ),
+            Code::Deployed => (
+                "E_DEPLOYED",
+                27,
+                "The deployed version differs from the expected one.",
+            ),
+            Code::Unsatisfied => (
+                "E_UNSATISFIED",
+                28,
+                "A version does not satisfy the required range.",
+            ),
+            Code::Msrv => (
+                "E_MSRV",
+                29,
+                "The rust-version is invalid or raised without a minor release.",
+            ),
+            Code::BuildMetadata => (
+                "E_BUILD_METADATA",
+                30,
+                "A manifest's version carries build metadata, which is forbidden.",
+            ),
+            Code::StaleManifest => (
+                "E_STALE_MANIFEST",
+                31,
+                "The manifest changed since its expected hash was taken.",
+            ),
+            Code::Warning => (
+                "E_WARNING",
+                32,
+                "A warning was reported and --deny-warnings is given.",
+            ),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().0
+    }
+
+    pub fn exit_code(self) -> i32 {
+        self.info().1
+    }
+
+    pub fn description(self) -> &'static str {
+        self.info().2
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub code: Code,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(code: Code, message: S) -> Error {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The error as the single line JSON object printed to stderr.
+    pub fn to_json(&self) -> String {
+        self.to_json_exiting(self.code.exit_code())
+    }
+
+    /// The error as `to_json` prints it, for a run that exits with
+    /// `exit_code` instead of the code's own.
+    pub fn to_json_exiting(&self, exit_code: i32) -> String {
+        format!(
+            "{{\"code\": {}, \"exit_code\": {}, \"message\": {}}}",
+            json_string(self.code.name()),
+            exit_code,
+            json_string(&self.message)
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Quotes `s` as a JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that names and exit codes are unique, since tools rely on them.
+    #[test]
+    fn test_codes_unique() {
+        for (i, code) in Code::ALL.iter().enumerate() {
+            for other in &Code::ALL[i + 1..] {
+                assert_ne!(code.name(), other.name());
+                assert_ne!(code.exit_code(), other.exit_code());
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let error = Error::new(Code::ParseManifest, "Invalid \"Cargo.toml\"\n");
+
+        assert_eq!(
+            error.to_json(),
+            "{\"code\": \"E_PARSE_MANIFEST\", \"exit_code\": 3, \"message\": \"Invalid \\\"Cargo.toml\\\"\\n\"}"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Lifecycle events for `--output events`: every step of a run (parsing a
+//! version file, computing a new version, writing the files, committing the
+//! writes, creating a tag, skipping an applied bump) is reported as soon as it completes, as one JSON
+//! object per line on stdout, e.g. `{"event": "write", "path": "Cargo.toml"}`,
+//! so wrapping tools can show live progress and tell which step failed.
+//! Regular output becomes `{"event": "output", "value": "..."}` events;
+//! errors are still reported on stderr.
+use std::io::{self, Write};
+
+use semver::Version;
+
+use crate::config::Config;
+use crate::error::json_string;
+
+/// A version file was read and its version parsed.
+pub fn parsed(config: &Config, path: &str, version: &Version) {
+    emit(
+        config,
+        "parse",
+        &[
+            ("path", json_string(path)),
+            ("version", json_string(&version.to_string())),
+        ],
+    );
+}
+
+/// The new version of a file was computed.
+pub fn computed(config: &Config, path: &str, from: &Version, to: &Version) {
+    emit(
+        config,
+        "compute",
+        &[
+            ("path", json_string(path)),
+            ("from", json_string(&from.to_string())),
+            ("to", json_string(&to.to_string())),
+        ],
+    );
+}
+
+/// The given files were written together.
+pub fn written(config: &Config, paths: &[String]) {
+    for path in paths {
+        emit(config, "write", &[("path", json_string(path))]);
+    }
+    emit(config, "commit", &[("files", paths.len().to_string())]);
+}
+
+/// A bump was skipped, as one to `version` was applied under the
+/// idempotency `key` already.
+pub fn skipped(config: &Config, key: &str, version: &Version) {
+    emit(
+        config,
+        "skip",
+        &[
+            ("key", json_string(key)),
+            ("version", json_string(&version.to_string())),
+        ],
+    );
+}
+
+/// A git tag was created.
+pub fn tagged(config: &Config, tag: &str) {
+    emit(config, "tag", &[("tag", json_string(tag))]);
+}
+
+fn emit(config: &Config, event: &str, fields: &[(&str, String)]) {
+    if !config.events {
+        return;
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(stdout, "{}", to_json(event, fields)).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Renders an event; the field values are JSON already.
+fn to_json(event: &str, fields: &[(&str, String)]) -> String {
+    let mut json = format!("{{\"event\": {}", json_string(event));
+    for (name, value) in fields {
+        json.push_str(&format!(", {}: {}", json_string(name), value));
+    }
+    json.push('}');
+
+    json
+}
+
+/// Wraps every line written to it into an `output` event.
+pub struct Lines<'a> {
+    inner: &'a mut dyn Write,
+    line: Vec<u8>,
+}
+
+impl<'a> Lines<'a> {
+    pub fn new(inner: &'a mut dyn Write) -> Lines<'a> {
+        Lines {
+            inner,
+            line: vec![],
+        }
+    }
+}
+
+impl<'a> Write for Lines<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                let value = json_string(&String::from_utf8_lossy(&self.line));
+                writeln!(self.inner, "{}", to_json("output", &[("value", value)]))?;
+                self.line.clear();
+            } else {
+                self.line.push(*byte);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::str;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            to_json(
+                "compute",
+                &[("from", json_string("1.2.3")), ("files", 2.to_string())]
+            ),
+            "{\"event\": \"compute\", \"from\": \"1.2.3\", \"files\": 2}"
+        );
+    }
+
+    /// Tests that output is wrapped line by line, however it is written.
+    #[test]
+    fn test_lines() {
+        let mut stdout = Vec::new();
+        {
+            let mut lines = Lines::new(&mut stdout);
+            write!(lines, "1.2").unwrap();
+            writeln!(lines, ".3\n\"rc\"").unwrap();
+        }
+
+        assert_eq!(
+            str::from_utf8(&stdout).unwrap(),
+            "{\"event\": \"output\", \"value\": \"1.2.3\"}\n\
+             {\"event\": \"output\", \"value\": \"\\\"rc\\\"\"}\n"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Thin wrappers over the `git` command line, used by the subcommands that
+//! need repository state (tags, the version at another revision, etc.).
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{Code, Error};
+
+/// Runs git with the given arguments and returns its trimmed standard
+/// output, or an error holding its standard error if it fails.
+pub fn run(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|err| Error::new(Code::Git, format!("Failed to run git: {}", err)))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(Error::new(
+            Code::Git,
+            format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ))
+    }
+}
+
+/// Runs git as [`run`] does, in the work tree at `dir`.
+pub fn run_in(dir: &Path, args: &[&str]) -> Result<String, Error> {
+    let dir = dir.to_string_lossy();
+    let mut all = vec!["-C", dir.as_ref()];
+    all.extend_from_slice(args);
+
+    run(&all)
+}
+
+/// Reads the contents of `path` (relative to the current directory, or
+/// absolute within the work tree) as of the given revision.
+pub fn show(rev: &str, path: &str) -> Result<String, Error> {
+    let path = Path::new(path);
+    let spec = if path.is_absolute() {
+        let toplevel = run(&["rev-parse", "--show-toplevel"])?;
+        let relative = path.strip_prefix(&toplevel).map_err(|_| {
+            Error::new(
+                Code::Git,
+                format!("{} is outside of the git work tree", path.display()),
+            )
+        })?;
+        format!("{}:{}", rev, relative.display())
+    } else {
+        format!("{}:./{}", rev, path.display())
+    };
+
+    run(&["show", spec.as_str()])
+}
@@ -0,0 +1,82 @@
+//! Hotfix workflow: `hotfix start <base-tag>` cuts a branch from an old
+//! release tag, bumps its PATCH version and adds an Unreleased section to
+//! the changelog for the fix's notes, which is otherwise a manual sequence
+//! of git, bump and editing steps that is easy to get wrong under pressure.
+use std::fs;
+
+use clap::ArgMatches;
+use toml_edit::{value, Document};
+
+use super::{enforce_branch_policy, ensure_unprotected, read_manifest, read_version};
+use crate::adapter;
+use crate::changelog;
+use crate::config::Config;
+use crate::error::{Code, Error};
+use crate::events;
+use crate::git;
+use crate::pipeline::Component;
+use crate::transaction::Transaction;
+
+/// Starts a hotfix of the release tagged `base-tag`. Everything that can
+/// fail is checked before the branch is created.
+pub fn start(manifest_path: &str, matches: &ArgMatches, config: &Config) -> Result<(), Error> {
+    config.forbid_in_deterministic("Git (hotfix start)")?;
+    let tag = matches.value_of("base-tag").unwrap();
+    let base = git::show(tag, manifest_path)?
+        .parse::<Document>()
+        .map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid {} at {}: {}", manifest_path, tag, err),
+            )
+        })?;
+    ensure_unprotected(config, &base, manifest_path)?;
+    let current = read_version(&base)?;
+    events::parsed(config, manifest_path, &current);
+    let mut version = current.clone();
+    config
+        .limits
+        .increment(&mut version, Component::Patch)
+        .map_err(|err| Error::new(Code::Overflow, err))?;
+    events::computed(config, manifest_path, &current, &version);
+
+    let branch = matches
+        .value_of("branch")
+        .unwrap()
+        .replace("{version}", &version.to_string());
+    let mut on_branch = config.clone();
+    on_branch.git_branch = Some(branch.clone());
+    enforce_branch_policy(&on_branch, &version)?;
+
+    git::run(&["checkout", "-b", branch.as_str(), tag])?;
+    let mut manifest = read_manifest(manifest_path)?;
+    manifest["package"]["version"] = value(version.to_string());
+    let changelog_path = match matches.value_of("changelog") {
+        Some(path) => path.to_string(),
+        None => config
+            .root
+            .join("CHANGELOG.md")
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    let mut transaction = Transaction::new();
+    for path in adapter::sync_files(config) {
+        adapter::stage_version(&mut transaction, &path, &version)?;
+    }
+    let contents = fs::read_to_string(&changelog_path).unwrap_or_default();
+    if let Some(scaffold) = changelog::with_unreleased(&contents) {
+        transaction.stage(&changelog_path, scaffold);
+    }
+    transaction.stage(manifest_path, manifest.to_string());
+    let paths = transaction.paths();
+    transaction.commit(|| Ok(())).map_err(|err| {
+        Error::new(
+            err.code,
+            format!("Failed to start hotfix on {}: {}", branch, err),
+        )
+    })?;
+    events::written(config, &paths);
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Idempotency keys for bots and retried CI jobs: a bump or plan given
+//! `--idempotency-key` records the key in a ledger next to the manifest,
+//! written together with the bumped files, so a retry carrying the same key
+//! finds it and succeeds without bumping a second time:
+//!
+//! ```toml
+//! [keys]
+//! "release-run-1234" = "1.3.0"
+//! ```
+use std::fs;
+use std::io;
+
+use semver::Version;
+use toml_edit::{value, Document, Item, Table};
+
+use crate::config::Config;
+use crate::error::{Code, Error};
+use crate::events;
+use crate::transaction::Transaction;
+
+pub const LEDGER_FILE: &str = ".semvercli-keys.toml";
+
+fn ledger_path(config: &Config) -> String {
+    config.root.join(LEDGER_FILE).to_string_lossy().into_owned()
+}
+
+/// The ledger at `path`; empty if there is none yet. A ledger that exists
+/// but cannot be read fails with `E_IO`, and one that is corrupt with
+/// `E_PLAN`, as ignoring either would allow a second bump.
+fn read_ledger(path: &str) -> Result<Document, Error> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse()
+            .map_err(|err| Error::new(Code::Plan, format!("Invalid {}: {}", path, err))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Document::new()),
+        Err(err) => Err(Error::new(
+            Code::Io,
+            format!("Could not read {}: {}", path, err),
+        )),
+    }
+}
+
+/// The version the bump recorded under `key` produced, if one was applied.
+pub fn applied(config: &Config, key: &str) -> Result<Option<Version>, Error> {
+    let path = ledger_path(config);
+    let ledger = read_ledger(&path)?;
+
+    match ledger["keys"][key].as_str() {
+        Some(version) => Version::parse(version).map(Some).map_err(|_| {
+            Error::new(
+                Code::Plan,
+                format!("Invalid {}: `{}` is not a valid version", path, version),
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Whether a bump was applied under `key` already, in which case it is
+/// reported as skipped: as a `skip` event with `--output events`, for bots
+/// to tell a retry from a bump, and as a line on stderr otherwise.
+pub fn skip_applied(config: &Config, key: &str) -> Result<bool, Error> {
+    let version = match applied(config, key)? {
+        Some(version) => version,
+        None => return Ok(false),
+    };
+    if config.events {
+        events::skipped(config, key, &version);
+    } else {
+        eprintln!(
+            "Skipped: a bump to {} was applied under the idempotency key {} already",
+            version, key
+        );
+    }
+
+    Ok(true)
+}
+
+/// Stages recording `key` as having produced `version`.
+pub fn stage(
+    transaction: &mut Transaction,
+    config: &Config,
+    key: &str,
+    version: &Version,
+) -> Result<(), Error> {
+    let path = ledger_path(config);
+    let mut ledger = read_ledger(&path)?;
+    if ledger["keys"].is_none() {
+        ledger["keys"] = Item::Table(Table::new());
+    }
+    ledger["keys"][key] = value(version.to_string());
+    transaction.stage(&path, ledger.to_string().trim_start().to_string());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_ledger() {
+        let tmpdir = tempdir().unwrap();
+        let config = Config {
+            root: tmpdir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let version = Version::parse("1.3.0").unwrap();
+
+        assert_eq!(applied(&config, "run-1").unwrap(), None);
+        assert!(!skip_applied(&config, "run-1").unwrap());
+        let mut transaction = Transaction::new();
+        stage(&mut transaction, &config, "run-1", &version).unwrap();
+        transaction.commit(|| Ok(())).unwrap();
+        let mut transaction = Transaction::new();
+        stage(&mut transaction, &config, "run-2", &version).unwrap();
+        transaction.commit(|| Ok(())).unwrap();
+
+        assert_eq!(applied(&config, "run-1").unwrap(), Some(version));
+        assert!(skip_applied(&config, "run-2").unwrap());
+        assert_eq!(
+            fs::read_to_string(tmpdir.path().join(LEDGER_FILE)).unwrap(),
+            "[keys]\nrun-1 = \"1.3.0\"\nrun-2 = \"1.3.0\"\n"
+        );
+
+        fs::write(tmpdir.path().join(LEDGER_FILE), "[keys]\nrun-1 = \"1.3\"\n").unwrap();
+        let err = applied(&config, "run-1").unwrap_err();
+        assert_eq!(err.code, Code::Plan);
+        assert!(err.message.ends_with("`1.3` is not a valid version"));
+        fs::write(tmpdir.path().join(LEDGER_FILE), "[keys\n").unwrap();
+        assert_eq!(applied(&config, "run-1").unwrap_err().code, Code::Plan);
+        fs::remove_file(tmpdir.path().join(LEDGER_FILE)).unwrap();
+        fs::create_dir(tmpdir.path().join(LEDGER_FILE)).unwrap();
+        assert_eq!(applied(&config, "run-1").unwrap_err().code, Code::Io);
+    }
+}
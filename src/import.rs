@@ -0,0 +1,319 @@
+//! Migration from other release tools: `import-config` translates the
+//! settings of cargo-release (`release.toml`) or semantic-release (a JSON
+//! `.releaserc`) that have an equivalent into a `.semvercli.toml`, and
+//! lists the ones that do not in a comment at its top, for review.
+use toml_edit::{array, table, value, Document, Table};
+
+use crate::adapter;
+use crate::error::{Code, Error};
+
+/// The file each tool reads its settings from, next to the manifest.
+pub const CARGO_RELEASE_FILE: &str = "release.toml";
+pub const SEMANTIC_RELEASE_FILE: &str = ".releaserc";
+
+/// The package semantic-release analyzes commits with.
+const COMMIT_ANALYZER: &str = "@semantic-release/commit-analyzer";
+
+/// The translated configuration and the settings left out of it.
+struct Import {
+    config: Document,
+    skipped: Vec<String>,
+}
+
+impl Import {
+    fn new() -> Import {
+        Import {
+            config: Document::new(),
+            skipped: vec![],
+        }
+    }
+
+    /// Appends a table to the array of tables `key`.
+    fn append(&mut self, key: &str) -> &mut Table {
+        if self.config[key].is_none() {
+            self.config[key] = array();
+        }
+        self.config[key]
+            .as_array_of_tables_mut()
+            .unwrap()
+            .append(Table::new())
+    }
+
+    /// The configuration, headed by a comment naming the source and the
+    /// settings that were not imported.
+    fn render(self, path: &str) -> String {
+        let mut header = format!("# Imported from {} by semvercli import-config.\n", path);
+        if !self.skipped.is_empty() {
+            header.push_str(&format!(
+                "# Not imported, as semvercli has no equivalent: {}\n",
+                self.skipped.join(", ")
+            ));
+        }
+
+        format!("{}\n{}", header, self.config)
+    }
+}
+
+/// Translates the cargo-release settings `contents` of the file at `path`:
+/// `shared-version` becomes fixed versioning and the `allow-branch`
+/// patterns a branch policy allowing any version on them.
+pub fn cargo_release(contents: &str, path: &str) -> Result<String, Error> {
+    let settings = contents
+        .parse::<Document>()
+        .map_err(|err| Error::new(Code::Config, format!("Invalid {}: {}", path, err)))?;
+    let mut import = Import::new();
+
+    for (key, item) in settings.as_table().iter() {
+        match key {
+            "shared-version" if item.as_bool() == Some(false) => {}
+            "shared-version" => import.config["versioning"] = value("fixed"),
+            "allow-branch" => {
+                let invalid = || {
+                    Error::new(
+                        Code::Config,
+                        format!("Invalid {}: `allow-branch` must be a list of strings", path),
+                    )
+                };
+                let branches = item.as_array().ok_or_else(invalid)?;
+                for branch in branches.iter() {
+                    let branch = branch.as_str().ok_or_else(invalid)?;
+                    if branch.starts_with('!') {
+                        import.skipped.push(format!("allow-branch {}", branch));
+                        continue;
+                    }
+                    let policy = import.append("branch_policy");
+                    policy["branch"] = value(branch);
+                    policy["stable"] = value(true);
+                    policy["pre"] = value(vec!["*"].into_iter().collect::<toml_edit::Value>());
+                }
+            }
+            _ if item.is_none() => {}
+            _ => import.skipped.push(key.to_string()),
+        }
+    }
+
+    Ok(import.render(path))
+}
+
+/// Translates the JSON semantic-release settings `contents` of the file at
+/// `path`: the `branches` become a branch policy (pre-release branches
+/// allowing their `prerelease` label), the commit analyzer's
+/// `releaseRules` commit conventions and `repositoryUrl` the links' URL.
+pub fn semantic_release(contents: &str, path: &str) -> Result<String, Error> {
+    let keys = Some(contents)
+        .filter(|contents| contents.trim_start().starts_with('{'))
+        .and_then(|contents| adapter::json_keys_at(contents, &[]))
+        .ok_or_else(|| {
+            Error::new(
+                Code::Config,
+                format!(
+                    "Invalid {}: only JSON semantic-release settings can be imported",
+                    path
+                ),
+            )
+        })?;
+    let string = |path: &[&str]| {
+        adapter::json_string_at(contents, path).map(|range| contents[range].to_string())
+    };
+    let raw = |path: &[&str]| adapter::json_value_at(contents, path).map(|range| &contents[range]);
+    let mut import = Import::new();
+
+    for key in keys {
+        match key.as_str() {
+            "branches" => {
+                let mut index = 0;
+                while raw(&["branches", &index.to_string()]).is_some() {
+                    let at = index.to_string();
+                    index += 1;
+                    let name = match string(&["branches", &at]) {
+                        Some(name) => name,
+                        None => match string(&["branches", &at, "name"]) {
+                            Some(name) => name,
+                            None => continue,
+                        },
+                    };
+                    let label = match raw(&["branches", &at, "prerelease"]) {
+                        None | Some("false") => None,
+                        Some("true") => Some(name.clone()),
+                        Some(_) => string(&["branches", &at, "prerelease"])
+                            .filter(|label| !label.contains("${"))
+                            .or_else(|| Some(String::from("*"))),
+                    };
+                    let policy = import.append("branch_policy");
+                    policy["branch"] = value(name.as_str());
+                    match label {
+                        Some(label) => {
+                            policy["pre"] = value(
+                                vec![label.as_str()]
+                                    .into_iter()
+                                    .collect::<toml_edit::Value>(),
+                            )
+                        }
+                        None => policy["stable"] = value(true),
+                    }
+                }
+            }
+            "repositoryUrl" => match string(&["repositoryUrl"]) {
+                Some(url) if url.starts_with("https://") => {
+                    import.config["links"] = table();
+                    import.config["links"]["url"] = value(url.trim_end_matches(".git"));
+                }
+                _ => import.skipped.push(key),
+            },
+            "plugins" => {
+                let mut index = 0;
+                while raw(&["plugins", &index.to_string()]).is_some() {
+                    let at = index.to_string();
+                    index += 1;
+                    let name = string(&["plugins", &at]).or_else(|| string(&["plugins", &at, "0"]));
+                    if name.as_deref() != Some(COMMIT_ANALYZER) {
+                        import
+                            .skipped
+                            .push(format!("plugin {}", name.unwrap_or_default()));
+                        continue;
+                    }
+                    let mut rule = 0;
+                    while raw(&["plugins", &at, "1", "releaseRules", &rule.to_string()]).is_some() {
+                        let rule_path = ["plugins", &at, "1", "releaseRules", &rule.to_string()];
+                        rule += 1;
+                        let field = |name: &str| {
+                            let mut path = rule_path.to_vec();
+                            path.push(name);
+                            string(&path)
+                        };
+                        let kind = match field("type") {
+                            Some(kind) => kind,
+                            None => {
+                                import
+                                    .skipped
+                                    .push(String::from("releaseRules without a type"));
+                                continue;
+                            }
+                        };
+                        let convention = import.append("commit_convention");
+                        convention["pattern"] =
+                            value(rule_pattern(&kind, field("scope").as_deref()).as_str());
+                        if let Some(release) = field("release") {
+                            convention["bump"] = value(release.as_str());
+                        }
+                        if let Some(section) = section(&kind) {
+                            convention["section"] = value(section);
+                        }
+                    }
+                }
+            }
+            _ => import.skipped.push(key),
+        }
+    }
+
+    Ok(import.render(path))
+}
+
+/// The regex matching the commits of a semantic-release rule: the type,
+/// and the scope if given (in which `*` matches anything).
+fn rule_pattern(kind: &str, scope: Option<&str>) -> String {
+    let scope = match scope {
+        Some(scope) => format!(
+            r"\((?P<scope>{})\)",
+            regex::escape(scope).replace(r"\*", "[^)]*")
+        ),
+        None => String::from(r"(\((?P<scope>[^)]*)\))?"),
+    };
+
+    format!(r"^{}{}!?: (?P<description>.*)", regex::escape(kind), scope)
+}
+
+/// The changelog section Conventional Commits of type `kind` go under.
+fn section(kind: &str) -> Option<&'static str> {
+    match kind {
+        "feat" => Some("Added"),
+        "perf" => Some("Changed"),
+        "fix" => Some("Fixed"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::auto::classify;
+    use crate::config::Config;
+    use crate::pipeline::Component;
+
+    #[test]
+    fn test_cargo_release() {
+        let imported = cargo_release(
+            "shared-version = true\nallow-branch = [\"main\", \"!wip\"]\nsign-tag = true\n",
+            "release.toml",
+        )
+        .unwrap();
+
+        assert!(imported.starts_with(
+            "# Imported from release.toml by semvercli import-config.\n\
+             # Not imported, as semvercli has no equivalent: allow-branch !wip, sign-tag\n"
+        ));
+        let document = imported.parse::<Document>().unwrap();
+        let config = Config::from_document(PathBuf::new(), &document).unwrap();
+        assert_eq!(config.versioning, crate::config::Versioning::Fixed);
+        assert_eq!(config.branch_policy.len(), 1);
+        assert_eq!(config.branch_policy[0].branch, "main");
+        assert_eq!(config.branch_policy[0].pre, ["*"]);
+    }
+
+    /// Tests that branches, release rules and the repository URL are
+    /// translated, and the result is a valid configuration.
+    #[test]
+    fn test_semantic_release() {
+        let releaserc = r#"{
+  "branches": ["main", {"name": "beta", "prerelease": true}, {"name": "next", "prerelease": "rc"}],
+  "repositoryUrl": "https://github.com/acme/app.git",
+  "tagFormat": "v${version}",
+  "plugins": [
+    ["@semantic-release/commit-analyzer", {
+      "preset": "angular",
+      "releaseRules": [{"type": "docs", "scope": "README", "release": "patch"}, {"type": "refactor", "release": "minor"}]
+    }],
+    "@semantic-release/npm"
+  ]
+}"#;
+        let imported = semantic_release(releaserc, ".releaserc").unwrap();
+
+        assert!(imported.contains(
+            "# Not imported, as semvercli has no equivalent: tagFormat, plugin @semantic-release/npm\n"
+        ));
+        let document = imported.parse::<Document>().unwrap();
+        let config = Config::from_document(PathBuf::new(), &document).unwrap();
+        let policies = config
+            .branch_policy
+            .iter()
+            .map(|policy| (policy.branch.as_str(), policy.stable, policy.pre.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            policies,
+            [
+                ("main", true, vec![]),
+                ("beta", false, vec![String::from("beta")]),
+                ("next", false, vec![String::from("rc")]),
+            ]
+        );
+        assert_eq!(
+            config.links.url.as_deref(),
+            Some("https://github.com/acme/app")
+        );
+        let bump = |message: &str| {
+            classify(message, &config.commit_convention).and_then(|change| change.bump)
+        };
+        assert_eq!(bump("docs(README): fix a typo"), Some(Component::Patch));
+        assert_eq!(bump("docs(api): fix a typo"), None);
+        assert_eq!(bump("refactor: split the parser"), Some(Component::Minor));
+
+        assert_eq!(
+            semantic_release("branches: [main]\n", ".releaserc")
+                .unwrap_err()
+                .code,
+            Code::Config
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! How outputs of several values (tags, crate names, located files) are
+//! delimited, chosen with `--joiner`: `newline` prints one per line,
+//! `comma` one line of comma-separated values and `nul` terminates each
+//! with a NUL byte for `xargs -0`, so paths with spaces or newlines survive.
+use std::fmt::Display;
+use std::io::Write;
+
+/// The delimiter of multi-value outputs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Joiner {
+    #[default]
+    Newline,
+    Comma,
+    Nul,
+}
+
+impl Joiner {
+    /// The joiner named by a `--joiner` value.
+    pub fn parse(name: &str) -> Option<Joiner> {
+        match name {
+            "newline" => Some(Joiner::Newline),
+            "comma" => Some(Joiner::Comma),
+            "nul" => Some(Joiner::Nul),
+            _ => None,
+        }
+    }
+
+    /// Writes `values` delimited by the joiner; comma-separated values end
+    /// with a newline, like a single line would.
+    pub fn write<I, T>(self, stdout: &mut dyn Write, values: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: Display,
+    {
+        let mut empty = true;
+        for value in values {
+            match self {
+                Joiner::Newline => writeln!(stdout, "{}", value).unwrap(),
+                Joiner::Comma if empty => write!(stdout, "{}", value).unwrap(),
+                Joiner::Comma => write!(stdout, ",{}", value).unwrap(),
+                Joiner::Nul => write!(stdout, "{}\0", value).unwrap(),
+            }
+            empty = false;
+        }
+        if self == Joiner::Comma && !empty {
+            writeln!(stdout).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str;
+
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let joined = |joiner: Joiner, values: &[&str]| {
+            let mut stdout = Vec::new();
+            joiner.write(&mut stdout, values);
+            String::from_utf8(stdout).unwrap()
+        };
+        let files = ["Cargo.toml", "my crate/Cargo.toml"];
+
+        assert_eq!(
+            joined(Joiner::Newline, &files),
+            "Cargo.toml\nmy crate/Cargo.toml\n"
+        );
+        assert_eq!(
+            joined(Joiner::Comma, &files),
+            "Cargo.toml,my crate/Cargo.toml\n"
+        );
+        assert_eq!(
+            joined(Joiner::Nul, &files),
+            "Cargo.toml\0my crate/Cargo.toml\0"
+        );
+        assert_eq!(joined(Joiner::Comma, &[]), "");
+        assert_eq!(Joiner::parse("nul"), Some(Joiner::Nul));
+        assert_eq!(Joiner::parse("tab"), None);
+    }
+}
@@ -0,0 +1,4360 @@
+//! Command line utility for setting, bumping, and reading Rust pacakge versions.
+//! This is an extremely thin layer over the [semver crate](https://crates.io/crates/semver)
+//! and meant to just serve as a command line glue for tools such as [cargo-make](https://crates.io/crates/cargo-make).
+//!
+//! Tools that would rather not shell out to the binary, e.g. `xtask` binaries or release
+//! bots, can use the library: `Manifest` reads and writes a manifest's version, and
+//! `apply_bump` applies a `VersionOp` as `bump` does. Build scripts that only need the
+//! arithmetic on versions in memory can use the `ops` module.
+//!
+//! ```no_run
+//! use semvercli::{Manifest, VersionOp};
+//!
+//! let mut manifest = Manifest::read("Cargo.toml")?;
+//! let version = manifest.bump(&VersionOp::Pre(String::from("rc.1")))?;
+//! manifest.write("Cargo.toml")?;
+//! println!("Bumped to {}", version);
+//! # Ok::<(), semvercli::Error>(())
+//! ```
+#[macro_use]
+extern crate clap;
+extern crate semver;
+extern crate toml_edit;
+
+#[cfg(test)]
+extern crate tempfile;
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+use semver::{Identifier, VersionReq};
+use toml_edit::{value, Document, Item};
+
+mod adapter;
+mod api;
+mod attest;
+mod auto;
+mod batch;
+mod cache;
+mod changelog;
+mod channel;
+mod compat;
+mod config;
+mod convert;
+mod deployed;
+mod describe;
+mod engines;
+mod epoch;
+mod error;
+mod events;
+mod git;
+mod hotfix;
+mod idempotency;
+mod import;
+mod joiner;
+mod limits;
+mod links;
+mod list;
+mod lock;
+mod manifests;
+mod msrv;
+mod multi;
+mod nightly;
+mod notes;
+pub mod ops;
+mod overview;
+mod patch;
+mod pipeline;
+mod plan;
+mod plugin;
+mod pr;
+mod prune;
+mod publish;
+mod registry;
+mod release;
+mod scan;
+mod schema;
+mod sha256;
+mod stamp;
+mod style;
+mod transaction;
+mod warnings;
+mod watch;
+mod workspace;
+
+use adapter::Format;
+use channel::Channel;
+use config::{Config, Field, CONFIG_FILE};
+use epoch::EpochVersion;
+pub use error::{Code, Error};
+use joiner::Joiner;
+use limits::Reset;
+use pipeline::{Component, Operation};
+use plan::{FileChange, Plan};
+pub use semver::Version;
+use transaction::{DryRun, Transaction};
+
+/// What `read` prints for an empty pre-release or build component, unless
+/// `--raw` is given.
+const EMPTY_COMPONENT: &str = "<none>";
+
+/// `--manifest-path` value that reads the manifest from stdin and writes
+/// the modified manifest to stdout.
+const STDIN_MANIFEST: &str = "-";
+
+fn parser<'a, 'b>() -> App<'a, 'b> {
+    App::new("semvercli")
+        .version(crate_version!())
+        .settings(&[AppSettings::SubcommandRequiredElseHelp])
+        .subcommand(
+            SubCommand::with_name("read")
+                .about("Read and print a specific version component.")
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .help("Print the VERSION set in the given manifest."),
+                )
+                .arg(
+                    Arg::with_name("major")
+                        .long("major")
+                        .help("Print the MAJOR version of this package."),
+                )
+                .arg(
+                    Arg::with_name("minor")
+                        .long("minor")
+                        .help("Print the MINOR version of this package."),
+                )
+                .arg(
+                    Arg::with_name("patch")
+                        .long("patch")
+                        .help("Print the PATCH version of this package."),
+                )
+                .arg(
+                    Arg::with_name("pre")
+                        .long("pre")
+                        .help("Print the PRE-RELEASE version of this package."),
+                )
+                .arg(
+                    Arg::with_name("build")
+                        .long("build")
+                        .help("Print the BUILD version of this package."),
+                )
+                .arg(
+                    Arg::with_name("epoch")
+                        .long("epoch")
+                        .help("Print the EPOCH of this package's versions, 0 if there is none."),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .help("How to render the component; docker-safe yields a valid OCI image tag.")
+                        .takes_value(true)
+                        .possible_values(&["plain", "docker-safe"])
+                        .default_value("plain"),
+                )
+                .arg(
+                    Arg::with_name("raw")
+                        .long("raw")
+                        .help("Print an empty component as the empty string instead of <none>."),
+                )
+                .arg(
+                    Arg::with_name("fail-if-empty")
+                        .long("fail-if-empty")
+                        .help("Exit with E_EMPTY_COMPONENT if the component is empty, printing nothing; other failures keep their own exit codes."),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .long("strict")
+                        .help("Exit with 2 if the component is empty, printing nothing, and with 1 on any other failure.")
+                        .conflicts_with("fail-if-empty"),
+                )
+                .arg(
+                    Arg::with_name("field")
+                        .long("field")
+                        .help("Read the version field of this name configured under [fields] instead.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("Print the component of every package of the workspace (the configured packages, the Cargo workspace the manifest declares, or the npm, yarn or pnpm workspace next to it), after its name."),
+                )
+                .arg(
+                    Arg::with_name("at")
+                        .long("at")
+                        .help("Read the manifest as of this git revision (a tag, branch or commit), without checking it out.")
+                        .takes_value(true)
+                        .conflicts_with_all(&["field", "workspace", "locate"]),
+                )
+                .arg(
+                    Arg::with_name("locate")
+                        .long("locate")
+                        .help("Print the file, line and column of the version in the manifest and every synced file."),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .long("hash")
+                        .help("With --locate, also print the SHA-256 of each file, for bump --expect-hash.")
+                        .requires("locate"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Print every component and the facts derived from them as an aligned table."),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Print the component, or every one with --all, as plain text or as a JSON object of raw values.")
+                        .takes_value(true)
+                        .possible_values(&["plain", "json"])
+                        .default_value("plain"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Same as --format json.")
+                        .conflicts_with_all(&["workspace", "locate"]),
+                )
+                .group(
+                    ArgGroup::with_name("read-args")
+                        .args(&["version", "major", "minor", "patch", "pre", "build", "epoch", "all", "locate"])
+                        .required(true),
+                ),
+        )
+        .subcommand(bump_args(
+            1,
+            SubCommand::with_name("bump")
+                .about("Bump or set a specific version component.")
+                .arg(
+                    Arg::with_name("if-stable")
+                        .long("if-stable")
+                        .help("Only bump if the current version is not a pre-release."),
+                )
+                .arg(
+                    Arg::with_name("if-pre")
+                        .long("if-pre")
+                        .help("Only bump if the current version is a pre-release.")
+                        .conflicts_with("if-stable"),
+                )
+                .arg(
+                    Arg::with_name("skip-if-no-changes")
+                        .long("skip-if-no-changes")
+                        .help("With --auto, do nothing if no commit since the last tag warrants a bump.")
+                        .requires("auto"),
+                )
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("Bump every package of the workspace (the configured packages, the Cargo workspace the manifest declares, or the npm, yarn or pnpm workspace next to it) instead."),
+                )
+                .arg(
+                    Arg::with_name("changed-since")
+                        .long("changed-since")
+                        .help("With --workspace, only bump the packages with files changed since this git ref.")
+                        .takes_value(true)
+                        .requires("workspace"),
+                )
+                .arg(
+                    Arg::with_name("with-dependents")
+                        .long("with-dependents")
+                        .help("With --changed-since, also bump the packages that depend on a changed one.")
+                        .requires("changed-since"),
+                )
+                .arg(
+                    Arg::with_name("cascade")
+                        .long("cascade")
+                        .help("With --workspace, also bump the PATCH version of the packages depending on a bumped one and update their requirements on it.")
+                        .requires("workspace"),
+                )
+                .arg(
+                    Arg::with_name("field")
+                        .long("field")
+                        .help("Bump the version field of this name configured under [fields] instead.")
+                        .takes_value(true)
+                        .conflicts_with("workspace"),
+                )
+                .arg(
+                    Arg::with_name("open-pr")
+                        .long("open-pr")
+                        .help("Commit the bump on a new branch, push it and open a GitHub pull request or GitLab merge request with the changelog as its description.")
+                        .conflicts_with_all(&["workspace", "field"]),
+                )
+                .arg(pr_branch_arg())
+                .arg(idempotency_key_arg().conflicts_with_all(&["workspace", "field"]))
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Print the changes to every file as a unified diff instead of writing them.")
+                        .conflicts_with("open-pr"),
+                )
+                .arg(
+                    Arg::with_name("emit-patch-dir")
+                        .long("emit-patch-dir")
+                        .help("With --dry-run, write one patch per changed file to this directory instead, for review systems.")
+                        .takes_value(true)
+                        .requires("dry-run"),
+                )
+                .arg(
+                    Arg::with_name("commit")
+                        .long("commit")
+                        .help("Stage the written files and commit them once the manifest is written.")
+                        .conflicts_with_all(&["workspace", "field", "open-pr", "dry-run"]),
+                )
+                .arg(
+                    Arg::with_name("message")
+                        .long("message")
+                        .help("With --commit, message of the commit; {version} is replaced with the new version.")
+                        .takes_value(true)
+                        .default_value("chore: release {version}"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .help("Create an annotated git tag at HEAD once the manifest is written (and committed, with --commit).")
+                        .conflicts_with_all(&["workspace", "field", "open-pr", "dry-run"]),
+                )
+                .arg(
+                    Arg::with_name("tag-format")
+                        .long("tag-format")
+                        .help("With --tag, format of the tag; {version} is replaced with the new version, {describe}, {commits-since-tag}, {short-sha} and {dirty} come from git describe.")
+                        .takes_value(true)
+                        .default_value("v{version}"),
+                )
+                .arg(
+                    Arg::with_name("update-dependents")
+                        .long("update-dependents")
+                        .help("Also raise the requirements of the other crates of the Cargo workspace on the bumped crate that its new version no longer satisfies.")
+                        .conflicts_with_all(&["workspace", "field"]),
+                )
+                .arg(
+                    Arg::with_name("expect-hash")
+                        .long("expect-hash")
+                        .help("Refuse to bump unless the manifest's SHA-256 is this one, as read --locate --hash printed it.")
+                        .takes_value(true),
+                ),
+        ))
+        .subcommand(
+            SubCommand::with_name("do")
+                .about("Run a sequence of operations against the version and write the manifest once at the end.")
+                .arg(
+                    Arg::with_name("operations")
+                        .help("Operations to run in order: `bump <major|minor|patch>`, `set <pre|build|version> [VALUE]` or `read <component>`.")
+                        .required(true)
+                        .multiple(true),
+                ),
+        )
+        .subcommand(bump_args(
+            2,
+            SubCommand::with_name("plan")
+                .about("Compute a bump and record it in a plan file without modifying anything.")
+                .arg(
+                    Arg::with_name("plan")
+                        .help("Path to write the plan file to.")
+                        .required_unless("pre"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .help("Record a git TAG to create on apply; {version} is replaced with the new version, {describe}, {commits-since-tag}, {short-sha} and {dirty} come from git describe.")
+                        .takes_value(true),
+                )
+                .arg(idempotency_key_arg().help(
+                    "Record this key in the plan; apply skips the plan if a bump was recorded under the key already.",
+                )),
+        ))
+        .subcommand(
+            SubCommand::with_name("apply")
+                .about("Execute a plan file previously written by `plan`.")
+                .arg(
+                    Arg::with_name("plan")
+                        .help("Path of the plan file to execute.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("notes")
+                        .long("notes")
+                        .help("Attach the new version and the plan's hash to HEAD as a git note."),
+                )
+                .arg(
+                    Arg::with_name("expect-hash")
+                        .long("expect-hash")
+                        .help("Refuse to apply the plan unless the SHA-256 of its manifest is this one.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("release")
+                .about("Drive a multi-step release recorded in a release lockfile.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(bump_args(
+                    1,
+                    SubCommand::with_name("start")
+                        .about("Bump the version and record the release in progress."),
+                ))
+                .subcommand(
+                    SubCommand::with_name("tag")
+                        .about("Create an annotated git tag for the release in progress.")
+                        .arg(
+                            Arg::with_name("tag-format")
+                                .long("tag-format")
+                                .help("Format of the tag; {version} is replaced with the release version, {describe}, {commits-since-tag}, {short-sha} and {dirty} come from git describe.")
+                                .takes_value(true)
+                                .default_value("v{version}"),
+                        )
+                        .arg(
+                            Arg::with_name("notes")
+                                .long("notes")
+                                .help("Attach the version and changelog to the tagged commit as a git note."),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("publish")
+                        .about("Run `cargo publish` for every package of the release in progress.")
+                        .arg(
+                            Arg::with_name("dry-run")
+                                .long("dry-run")
+                                .help("Pass --dry-run to `cargo publish`."),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("finalize")
+                        .about("Conclude the release in progress and remove its lockfile."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check-changelog")
+                .about("Fail if the version is bumped without updating the changelog's Unreleased section.")
+                .arg(
+                    Arg::with_name("base")
+                        .long("base")
+                        .help("Revision to compare the version and changelog against.")
+                        .takes_value(true)
+                        .default_value("main"),
+                )
+                .arg(
+                    Arg::with_name("changelog")
+                        .long("changelog")
+                        .help("Path to the changelog [default: CHANGELOG.md next to the manifest]")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("api-diff")
+                .about("Suggest the component to bump from the public API changes since the last tag, listed by cargo public-api.")
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .help("Tag to compare with [default: the last tag]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("old")
+                        .long("old")
+                        .help("File with the old public API listing, instead of listing it at the tag.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .long("new")
+                        .help("File with the new public API listing, instead of listing it at HEAD.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("explain")
+                        .long("explain")
+                        .help("Also print the removed (-) and added (+) public items."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("channel")
+                .about("Query release channels.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(
+                    SubCommand::with_name("latest")
+                        .about("Print the newest released version on a channel.")
+                        .arg(
+                            Arg::with_name("channel")
+                                .help("Channel: stable, nightly or a pre-release label like beta.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("tag-format")
+                                .long("tag-format")
+                                .help("Format of the release tags; {version} is replaced with the version.")
+                                .takes_value(true)
+                                .default_value("v{version}"),
+                        )
+                        .arg(
+                            Arg::with_name("stdin")
+                                .long("stdin")
+                                .help("Read the released versions from stdin, one per line (e.g. from a registry), instead of the tags."),
+                        ),
+                ),
+        )
+        .subcommand(wait_args(
+            SubCommand::with_name("wait-published")
+                .about("Wait until the registry lists the manifest's version, e.g. after cargo publish."),
+        ))
+        .subcommand(wait_args(
+            SubCommand::with_name("publish-order")
+                .about("Print the Cargo workspace crates to publish, each after the crates it depends on.")
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .help("Only the crates whose version changed since this git ref.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("changed-since")
+                        .long("changed-since")
+                        .help("Only the crates with files changed since this git ref.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("with-dependents")
+                        .long("with-dependents")
+                        .help("With --changed-since, also the crates that depend on a changed one.")
+                        .requires("changed-since"),
+                )
+                .arg(
+                    Arg::with_name("publish")
+                        .long("publish")
+                        .help("Also cargo publish them in that order, waiting for each to reach the index."),
+                )
+                .arg(
+                    Arg::with_name("retries")
+                        .long("retries")
+                        .help("How often to retry a failed cargo publish.")
+                        .takes_value(true)
+                        .default_value("3"),
+                ),
+        ))
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Watch the manifest and synced files and report synced versions that drift from the manifest.")
+                .arg(
+                    Arg::with_name("fix")
+                        .long("fix")
+                        .help("Rewrite drifted files to the manifest's version instead of only reporting them."),
+                )
+                .arg(
+                    Arg::with_name("once")
+                        .long("once")
+                        .help("Check once and exit, failing if a file drifted and --fix is not given."),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .help("Seconds between checks for changed files.")
+                        .takes_value(true)
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("multi")
+                .about("Version the git repositories listed in a repositories file together.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .help("Path to the repositories file.")
+                        .takes_value(true)
+                        .default_value(multi::REPOS_FILE),
+                )
+                .subcommand(bump_args(
+                    1,
+                    SubCommand::with_name("bump")
+                        .about("Clone or fetch every repository and bump its manifest.")
+                        .arg(
+                            Arg::with_name("commit")
+                                .long("commit")
+                                .help("Commit the bump in every repository."),
+                        )
+                        .arg(
+                            Arg::with_name("open-pr")
+                                .long("open-pr")
+                                .help("Commit the bump in every repository on a new branch, push it and open a pull request.")
+                                .conflicts_with("commit"),
+                        )
+                        .arg(pr_branch_arg()),
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("check-deployed")
+                .about("Compare the version a deployed service reports with the manifest's.")
+                .arg(
+                    Arg::with_name("url")
+                        .long("url")
+                        .help("URL the service reports its version at, e.g. its health endpoint.")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("json-path")
+                        .long("json-path")
+                        .help("Path of the version in the JSON response, e.g. .build.version; `.` for a response that is just the version.")
+                        .takes_value(true)
+                        .default_value(".version"),
+                )
+                .arg(
+                    Arg::with_name("expect")
+                        .long("expect")
+                        .help("Compare with this version instead of the manifest's.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check-engines")
+                .about("Check that the engines.node and peerDependencies ranges of a package.json admit the given versions.")
+                .arg(
+                    Arg::with_name("node")
+                        .long("node")
+                        .help("Node.js version that engines.node must admit.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("peer")
+                        .long("peer")
+                        .help("Peer dependency version that its peerDependencies range must admit, as name@version.")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("package-json")
+                        .long("package-json")
+                        .help("Path to the package.json [default: package.json next to the manifest]")
+                        .takes_value(true),
+                )
+                .group(
+                    ArgGroup::with_name("versions")
+                        .args(&["node", "peer"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Print how version a orders against version b by precedence: -1, 0 or 1.")
+                .arg(
+                    Arg::with_name("a")
+                        .help("Version on the left of the comparison.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("b")
+                        .help("Version on the right of the comparison.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Print the ordering as -1/0/1 (number) or lt/eq/gt (word).")
+                        .takes_value(true)
+                        .possible_values(&["number", "word"])
+                        .default_value("number"),
+                )
+                .arg(
+                    Arg::with_name("expect")
+                        .long("expect")
+                        .help("Fail with E_UNSATISFIED unless a relates to b as given.")
+                        .takes_value(true)
+                        .possible_values(&["lt", "le", "eq", "ne", "ge", "gt"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check that the manifest's version satisfies a requirement, failing with E_UNSATISFIED otherwise.")
+                .arg(
+                    Arg::with_name("requirement")
+                        .long("requirement")
+                        .help("Requirement the version must satisfy, in Cargo's syntax, e.g. \"^1.2\" or \">= 1.4, < 2\".")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .help("Check this version instead of the manifest's.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff-manifests")
+                .about("Print the packages whose versions differ between two manifests and the members of their workspaces.")
+                .arg(
+                    Arg::with_name("a")
+                        .help("Manifest on the left, as a path or as ref:path to read it at a git revision.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("b")
+                        .help("Manifest on the right, as a path or as ref:path to read it at a git revision.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Print the differences as `name a -> b` lines (plain) or as a JSON array of {name, a, b} objects (json).")
+                        .takes_value(true)
+                        .possible_values(&["plain", "json"])
+                        .default_value("plain"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("lint").about(
+            "Check that the manifest's version has no build metadata, which Cargo ignores; for CI.",
+        ))
+        .subcommand(
+            SubCommand::with_name("attest")
+                .about("Print an in-toto provenance statement for the release of the manifest's version at HEAD.")
+                .arg(
+                    Arg::with_name("builder-id")
+                        .long("builder-id")
+                        .help("Identity of the builder [default: the GitHub Actions or GitLab CI job running semvercli]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("key")
+                        .long("key")
+                        .help("Ed25519 private key (PEM) to sign the statement into a DSSE envelope with.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stamp")
+                .about("Print linker flags or write an env file carrying the version, to embed it into built artifacts.")
+                .arg(
+                    Arg::with_name("ldflags")
+                        .long("ldflags")
+                        .help("Print linker flags setting the [stamp] variables to the version."),
+                )
+                .arg(
+                    Arg::with_name("env-file")
+                        .long("env-file")
+                        .help("Write the version and its components to this env file.")
+                        .takes_value(true),
+                )
+                .group(
+                    ArgGroup::with_name("stamp-args")
+                        .args(&["ldflags", "env-file"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("changelog")
+                .about("Print release notes generated from the Conventional Commits since the last tag (or of a range of revisions), with references linked to the forge.")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .help("Revision (exclusive) the notes start at, e.g. v1.2.0 [default: the last tag before --to]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .help("Revision the notes end at, e.g. v1.4.0 [default: HEAD]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("Instead write the notes of each workspace package, from the commits touching it, into the Unreleased section of its CHANGELOG.md."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("notes")
+                .about("Read the release metadata attached as git notes.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Print the release metadata of the commit a tag points to.")
+                        .arg(
+                            Arg::with_name("tag")
+                                .help("Release tag.")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("hotfix")
+                .about("Fix an old release on a branch of its own.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(
+                    SubCommand::with_name("start")
+                        .about("Branch off the release tag, bump the PATCH version and add an Unreleased changelog section.")
+                        .arg(
+                            Arg::with_name("base-tag")
+                                .help("Tag of the release to fix.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("branch")
+                                .long("branch")
+                                .help("Name of the hotfix branch; {version} is replaced with the hotfix version.")
+                                .takes_value(true)
+                                .default_value("hotfix/{version}"),
+                        )
+                        .arg(
+                            Arg::with_name("changelog")
+                                .long("changelog")
+                                .help("Path to the changelog [default: CHANGELOG.md next to the manifest]")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("prune-prereleases")
+                .about("List the pre-release tags older than the newest ones kept, and optionally delete them.")
+                .arg(
+                    Arg::with_name("keep")
+                        .long("keep")
+                        .help("Number of the newest pre-release tags to keep.")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("tag-format")
+                        .long("tag-format")
+                        .help("Format of the package's tags; {version} is replaced with the version.")
+                        .takes_value(true)
+                        .default_value("v{version}"),
+                )
+                .arg(
+                    Arg::with_name("delete-tags")
+                        .long("delete-tags")
+                        .help("Delete the listed tags."),
+                )
+                .arg(
+                    Arg::with_name("remote")
+                        .long("remote")
+                        .help("With --delete-tags, delete them from this remote as well.")
+                        .takes_value(true)
+                        .requires("delete-tags"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("lock")
+                .about("Inspect the dependency versions resolved in the Cargo.lock.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(
+                    SubCommand::with_name("report")
+                        .about("Print the locked versions of the given packages, and optionally check them against a requirement.")
+                        .arg(
+                            Arg::with_name("package")
+                                .help("Names of the packages to report.")
+                                .required(true)
+                                .multiple(true),
+                        )
+                        .arg(
+                            Arg::with_name("req")
+                                .long("req")
+                                .help("Fail unless every locked version satisfies this requirement, e.g. \">= 3.0\".")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("lockfile")
+                                .long("lockfile")
+                                .help("Path to the lockfile [default: the Cargo.lock next to the manifest or its workspace]")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("msrv")
+                .about("Manage the minimum supported Rust version, the package's rust-version.")
+                .settings(&[AppSettings::SubcommandRequiredElseHelp])
+                .subcommand(SubCommand::with_name("read").about("Print the rust-version."))
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Set the rust-version; raising it requires at least a minor release.")
+                        .arg(
+                            Arg::with_name("rust-version")
+                                .help("The new rust-version, MAJOR.MINOR or MAJOR.MINOR.PATCH.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("released")
+                                .long("released")
+                                .help("Version of the last release [default: the manifest's version at the last tag]")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("bump")
+                        .about("Bump the rust-version; this requires at least a minor release.")
+                        .arg(
+                            Arg::with_name("component")
+                                .help("Component of the rust-version to bump.")
+                                .possible_values(&["major", "minor", "patch"])
+                                .default_value("minor"),
+                        )
+                        .arg(
+                            Arg::with_name("released")
+                                .long("released")
+                                .help("Version of the last release [default: the manifest's version at the last tag]")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rollback")
+                .about("Set the version back to a previous release, e.g. on a hotfix branch cut from it.")
+                .arg(
+                    Arg::with_name("to-tag")
+                        .long("to-tag")
+                        .help("Release tag whose manifest version to go back to.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .help("Released version to go back to; its release tag must exist.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("tag-format")
+                        .long("tag-format")
+                        .help("Format of the release tags; {version} is replaced with the version.")
+                        .takes_value(true)
+                        .default_value("v{version}"),
+                )
+                .group(
+                    ArgGroup::with_name("target")
+                        .args(&["to-tag", "to"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("nightly")
+                .about("Print the nightly version: the next MINOR version with a nightly.YYYYMMDD pre-release.")
+                .arg(
+                    Arg::with_name("date")
+                        .long("date")
+                        .help("Date of the nightly as YYYYMMDD or YYYY-MM-DD [default: today (UTC)]")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("write")
+                        .long("write")
+                        .help("Also write the nightly version to the manifest."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Convert a version to or from another ecosystem's versioning convention.")
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .help("Convert the given VERSION (or the manifest's) to this convention.")
+                        .takes_value(true)
+                        .possible_values(&["maven", "pep440", "debian"]),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .help("Convert the given VERSION from this convention to a semantic version.")
+                        .takes_value(true)
+                        .possible_values(&["maven", "pep440"])
+                        .requires("version"),
+                )
+                .arg(
+                    Arg::with_name("snapshot-label")
+                        .long("snapshot-label")
+                        .help("Pre-release that corresponds to a Maven -SNAPSHOT.")
+                        .takes_value(true)
+                        .default_value("SNAPSHOT"),
+                )
+                .arg(
+                    Arg::with_name("epoch")
+                        .long("epoch")
+                        .help("Epoch to prefix a Debian or PEP 440 version with, instead of the version's own.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("debian-revision")
+                        .long("debian-revision")
+                        .help("Debian revision to append to a Debian version.")
+                        .takes_value(true),
+                )
+                .arg(Arg::with_name("version").help("Version to convert."))
+                .group(
+                    ArgGroup::with_name("direction")
+                        .args(&["to", "from"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-config")
+                .about("Print the settings of another release tool translated into a .semvercli.toml.")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .help("The tool the settings are for.")
+                        .takes_value(true)
+                        .possible_values(&["cargo-release", "semantic-release"])
+                        .required(true),
+                )
+                .arg(Arg::with_name("file").help(
+                    "The tool's settings file; release.toml or .releaserc next to the manifest by default.",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("canon")
+                .about("Print the versions read from stdin, one per line, sorted and deduplicated.")
+                .arg(
+                    Arg::with_name("build")
+                        .long("build")
+                        .help("Whether versions differing only in BUILD metadata are duplicates (ignore), distinct (keep) or stripped of it (strip).")
+                        .takes_value(true)
+                        .possible_values(&["ignore", "keep", "strip"])
+                        .default_value("ignore"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("batch").about(
+            "Run commands read from stdin, one per line, reusing parsed manifests between them.",
+        ))
+        .subcommand(
+            SubCommand::with_name("errors")
+                .about("List the error codes and exit codes semvercli fails with."),
+        )
+        .arg(
+            Arg::with_name("manifest-path")
+                .long("manifest-path")
+                .help("Path to Cargo.toml, or - to read it from stdin and write it to stdout")
+                .takes_value(true)
+                .default_value("Cargo.toml"),
+        )
+        .arg(
+            Arg::with_name("manifest-format")
+                .long("manifest-format")
+                .help("Format of the manifest; auto reads a file named package.json as json, one named pyproject.toml as pyproject, one named VERSION or version.txt as plain and anything else as cargo. json, pyproject and plain default --manifest-path to package.json, pyproject.toml and VERSION.")
+                .takes_value(true)
+                .possible_values(&["auto", "cargo", "json", "pyproject", "plain"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("deterministic")
+                .long("deterministic")
+                .help("Forbid wall-clock, git and network inputs; they must be given explicitly."),
+        )
+        .arg(
+            Arg::with_name("now")
+                .long("now")
+                .help("Current date as YYYYMMDD or YYYY-MM-DD, instead of the wall clock.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("git-branch")
+                .long("git-branch")
+                .help("Current git branch, instead of asking git.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("assume-version")
+                .long("assume-version")
+                .help(
+                    "Version of a manifest without one; its new version is printed instead of written.",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .help("How to report progress; events prints every lifecycle step as a JSON line, matrix the packages bump --workspace changed as a JSON array.")
+                .takes_value(true)
+                .possible_values(&["plain", "events", "matrix"])
+                .default_value("plain"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .help("When to color output; auto colors a terminal unless NO_COLOR is set.")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("deny-warnings")
+                .long("deny-warnings")
+                .help("Fail with E_WARNING on the first warning instead of reporting it on stderr."),
+        )
+        .arg(
+            Arg::with_name("joiner")
+                .long("joiner")
+                .help("How to delimit outputs of several values; nul terminates each with a NUL byte, for xargs -0.")
+                .takes_value(true)
+                .possible_values(&["newline", "comma", "nul"])
+                .default_value("newline"),
+        )
+}
+
+/// Adds the registry polling arguments shared by the subcommands that
+/// wait for a published version (`wait-published` and `publish-order`).
+fn wait_args<'a, 'b>(subcommand: App<'a, 'b>) -> App<'a, 'b> {
+    subcommand
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("Seconds to wait for a version to be published before giving up.")
+                .takes_value(true)
+                .default_value("300"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .help("Seconds between polls of the registry.")
+                .takes_value(true)
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("index")
+                .long("index")
+                .help("URL of the sparse registry index.")
+                .takes_value(true)
+                .default_value(registry::CRATES_IO_INDEX),
+        )
+}
+
+/// The number argument `name` of the matches; fails if it is not a
+/// non-negative integer, or does not fit into `T`.
+fn number_arg<T: TryFrom<u64>>(matches: &ArgMatches, name: &str) -> Result<T, Error> {
+    let given = matches.value_of(name).unwrap();
+
+    given
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| {
+            Error::new(
+                Code::InvalidArgument,
+                format!("Invalid {} given: {}", name, given),
+            )
+        })
+}
+
+fn idempotency_key_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("idempotency-key")
+        .long("idempotency-key")
+        .help("Record the bump under this key, and skip it if a bump was recorded under the key already.")
+        .takes_value(true)
+}
+
+fn pr_branch_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("pr-branch")
+        .long("pr-branch")
+        .help("With --open-pr, the branch to push the bump to; {version} is replaced with the new version.")
+        .takes_value(true)
+        .default_value(pr::BRANCH)
+}
+
+/// The arguments `bump_args` adds: name, whether it takes a value (which
+/// `--pre` may leave out) and help. `multi bump` forwards exactly these.
+pub(crate) const BUMP_ARGS: [(&str, bool, &str); 13] = [
+    ("version", true, "Set the full VERSION"),
+    ("major", false, "Bump the MAJOR version."),
+    ("minor", false, "Bump the MINOR version."),
+    ("patch", false, "Bump the PATCH version."),
+    ("pre", true, "Set the PRE-RELEASE version; without one, increment its trailing number (rc.1 to rc.2, rc to rc.1)."),
+    ("build", true, "Set the BUILD metadata; {describe}, {commits-since-tag}, {short-sha} and {dirty} are filled from git describe."),
+    ("build-number", false, "Increment the trailing numeric BUILD identifier, or append one starting at 1."),
+    ("channel", true, "Set the next version on this release CHANNEL (stable, nightly or a pre-release label like beta)."),
+    ("auto", false, "Bump the component warranted by the conventional commits since the last tag."),
+    ("strip-build-on-bump", false, "Drop the BUILD metadata from the new version, which Cargo ignores."),
+    ("keep-lower", false, "With --major or --minor, keep the lower components instead of resetting them to 0."),
+    ("keep-pre", false, "With --major, --minor or --patch, keep the PRE-RELEASE version instead of dropping it."),
+    ("keep-build", false, "With --major, --minor or --patch, keep the BUILD metadata instead of dropping it."),
+];
+
+/// The arguments of `BUMP_ARGS` that choose the component, one of which
+/// is required.
+const BUMP_COMPONENT_ARGS: [&str; 9] = [
+    "version",
+    "major",
+    "minor",
+    "patch",
+    "pre",
+    "build",
+    "build-number",
+    "channel",
+    "auto",
+];
+
+/// Adds the component selection arguments shared by every subcommand
+/// that computes a new version (`bump` and `plan`); `--pre` takes up to
+/// `pre_values` values, which lets `plan` sort out its file (see
+/// `plan_target`).
+fn bump_args<'a, 'b>(pre_values: u64, subcommand: App<'a, 'b>) -> App<'a, 'b> {
+    BUMP_ARGS
+        .iter()
+        .fold(subcommand, |subcommand, &(name, takes_value, help)| {
+            let arg = Arg::with_name(name)
+                .long(name)
+                .help(help)
+                .takes_value(takes_value);
+            subcommand.arg(match name {
+                "pre" => arg.min_values(0).max_values(pre_values),
+                "strip-build-on-bump" => arg.conflicts_with_all(&["build", "build-number"]),
+                "keep-build" => arg.conflicts_with("strip-build-on-bump"),
+                _ => arg,
+            })
+        })
+        .group(
+            ArgGroup::with_name("bump-args")
+                .args(&BUMP_COMPONENT_ARGS)
+                .required(true),
+        )
+}
+
+/// semver::Version does not implement converting
+/// its version metadata labels (pre-release and build information)
+/// into string, so in order to make rendering those labels
+/// consistent that's implemented here via a newtype
+///
+/// Newtype that wraps semver::Version's pre and build
+/// properties in order to allow implementing string conversion
+/// on them
+struct VersionMetadata(Vec<Identifier>);
+
+/// String conversion for semver::Version's pre and build
+/// properties.
+impl From<VersionMetadata> for String {
+    /// The semver spec states that pre-release and build
+    /// information consists of a sequence of alphanumeric
+    /// identifiers joined by the `.` character.
+    fn from(meta: VersionMetadata) -> String {
+        meta.iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+}
+
+/// Conversion from a semver metadata label to a Vec<semver::Identifier> for use
+/// in the properties of semver::Version.
+impl TryFrom<&str> for VersionMetadata {
+    type Error = &'static str;
+
+    /// The semver spec states that pre-release and build labels have the same
+    /// spec, except for the way they are joined to the main version - pre-release
+    /// is joined by a `-` and build by `+`. This is used to get around the fact that
+    /// semver does not currently (2019-06-12) provide a way to parse just a metadata
+    /// label - the label is formatted into some junk version, into the pre-release position,
+    /// the whole thing is parsed, and finally the label itself is returned.
+    fn try_from(meta: &str) -> Result<VersionMetadata, Self::Error> {
+        Version::parse(&format!("0.0.0-{}", meta))
+            .map(|version| VersionMetadata(version.pre))
+            .map_err(|_| "Invalid version metadata label")
+    }
+}
+
+impl Deref for VersionMetadata {
+    type Target = Vec<Identifier>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Reads the contents of the manifest at `path`, or of stdin for
+/// `STDIN_MANIFEST`.
+fn read_manifest_contents(path: &str) -> Result<String, Error> {
+    let contents = if path == STDIN_MANIFEST {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents).map(|_| contents)
+    } else {
+        fs::read_to_string(path)
+    };
+
+    contents.map_err(|err| {
+        Error::new(
+            Code::ReadManifest,
+            format!("Could not read {}: {}", path, err),
+        )
+    })
+}
+
+fn read_manifest(path: &str) -> Result<Document, Error> {
+    let contents = read_manifest_contents(path)?;
+
+    cache::parse(path, &contents)
+        .map_err(|err| Error::new(Code::ParseManifest, format!("Invalid {}: {}", path, err)))
+}
+
+/// Reads just the package version of the manifest at `path`, scanning for
+/// it directly where that is unambiguous and parsing the manifest otherwise.
+fn read_manifest_version(path: &str, config: &Config) -> Result<Version, Error> {
+    let contents = read_manifest_contents(path)?;
+    if config.manifest_format != Format::Cargo {
+        return config.manifest_format.read_version(&contents, path);
+    }
+
+    match scan::package_version(&contents).and_then(|version| Version::parse(version).ok()) {
+        Some(version) => Ok(version),
+        None => current_version(
+            &cache::parse(path, &contents).map_err(|err| {
+                Error::new(Code::ParseManifest, format!("Invalid {}: {}", path, err))
+            })?,
+            config,
+        ),
+    }
+}
+
+/// Reads the version of the manifest at `path` as of the git revision
+/// `rev`, from the object database rather than the work tree.
+fn manifest_version_at(rev: &str, path: &str, config: &Config) -> Result<Version, Error> {
+    if path == STDIN_MANIFEST {
+        return Err(Error::new(
+            Code::InvalidArgument,
+            "A manifest read from stdin has no git history",
+        ));
+    }
+    let contents = git::show(rev, path)?;
+    if config.manifest_format != Format::Cargo {
+        return config
+            .manifest_format
+            .read_version(&contents, &format!("{} at {}", path, rev));
+    }
+    let manifest = contents.parse::<Document>().map_err(|err| {
+        Error::new(
+            Code::ParseManifest,
+            format!("Invalid {} at {}: {}", path, rev, err),
+        )
+    })?;
+
+    current_version(&manifest, config)
+}
+
+fn write_manifest(manifest: Document, path: &str) -> Result<(), Error> {
+    fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(manifest.to_string().as_bytes()))
+        .map_err(|err| {
+            Error::new(
+                Code::WriteManifest,
+                format!("Failed to write updated manifest to {}: {}", path, err),
+            )
+        })
+}
+
+/// A Cargo manifest, edited in place so that its formatting and comments
+/// are kept.
+#[derive(Debug, Clone)]
+pub struct Manifest(Document);
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    pub fn read(path: &str) -> Result<Manifest, Error> {
+        read_manifest(path).map(Manifest)
+    }
+
+    /// Parses a manifest's contents.
+    pub fn parse(contents: &str) -> Result<Manifest, Error> {
+        contents
+            .parse::<Document>()
+            .map(Manifest)
+            .map_err(|err| Error::new(Code::ParseManifest, format!("Invalid manifest: {}", err)))
+    }
+
+    /// The package version.
+    pub fn version(&self) -> Result<Version, Error> {
+        read_version(&self.0)
+    }
+
+    pub fn set_version(&mut self, version: &Version) {
+        self.0["package"]["version"] = value(version.to_string());
+    }
+
+    /// Applies `op` to the package version, returning the new version.
+    pub fn bump(&mut self, op: &VersionOp) -> Result<Version, Error> {
+        let version = apply_bump(&self.version()?, op)?;
+        self.set_version(&version);
+
+        Ok(version)
+    }
+
+    /// Writes the manifest to `path`.
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        write_manifest(self.0.clone(), path)
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Writes the manifest together with every configured sync target, which
+/// are brought to the manifest's version first; the manifest is written
+/// last, so that a failure leaves none of the files changed. A manifest
+/// read from stdin is written to `stdout` instead, and nothing else is.
+fn write_synced(
+    manifest: Document,
+    manifest_path: &str,
+    config: &Config,
+    stdout: &mut dyn Write,
+) -> Result<(), Error> {
+    write_synced_with(
+        manifest,
+        manifest_path,
+        config,
+        Transaction::new(),
+        None,
+        None,
+        stdout,
+    )
+}
+
+/// Writes the manifest as [`write_synced`] does, in a transaction with the
+/// files already staged in `transaction`, then commits the written files
+/// with `message` and creates `tag`, if given.
+fn write_synced_with(
+    manifest: Document,
+    manifest_path: &str,
+    config: &Config,
+    mut transaction: Transaction,
+    message: Option<&str>,
+    tag: Option<&str>,
+    stdout: &mut dyn Write,
+) -> Result<(), Error> {
+    if manifest_path == STDIN_MANIFEST {
+        write!(stdout, "{}", manifest).unwrap();
+        return Ok(());
+    }
+
+    let version = read_version(&manifest)?;
+    for path in adapter::sync_files(config) {
+        adapter::stage_version(&mut transaction, &path, &version)?;
+    }
+
+    if let Some(ref dry_run) = config.dry_run {
+        transaction.stage(manifest_path, manifest.to_string());
+        return transaction.preview(dry_run, &config.root, stdout);
+    }
+    let mut paths = transaction.paths();
+    paths.push(manifest_path.to_string());
+    let original = adapter::read_file(manifest_path)?;
+    transaction.commit(|| {
+        write_manifest(manifest, manifest_path)?;
+        commit_and_tag(&paths, message, tag, &version).inspect_err(|_| {
+            let _ = fs::write(manifest_path, &original);
+        })
+    })?;
+    events::written(config, &paths);
+    if let Some(tag) = tag {
+        events::tagged(config, tag);
+    }
+
+    Ok(())
+}
+
+/// The message of the commit `bump --commit` makes for the new `version`,
+/// from `--message`; `None` without `--commit`.
+fn bump_commit_message(
+    matches: &ArgMatches,
+    version: &Version,
+    config: &Config,
+) -> Result<Option<String>, Error> {
+    if !matches.is_present("commit") {
+        return Ok(None);
+    }
+    config.forbid_in_deterministic("Git (bump --commit)")?;
+
+    Ok(Some(
+        matches
+            .value_of("message")
+            .unwrap()
+            .replace("{version}", &version.to_string()),
+    ))
+}
+
+/// Commits the written `paths` with `message`, if given, and then creates
+/// `tag`, if given, at the new HEAD. If the tag cannot be created the
+/// commit is undone again, so that the caller can restore the files.
+fn commit_and_tag(
+    paths: &[String],
+    message: Option<&str>,
+    tag: Option<&str>,
+    version: &Version,
+) -> Result<(), Error> {
+    if let Some(message) = message {
+        commit_files(paths, message)?;
+    }
+    if let Some(tag) = tag {
+        create_tag(tag, version).inspect_err(|_| {
+            if message.is_some() {
+                uncommit_files(paths);
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Stages `paths` and commits exactly them with `message`; other changes
+/// staged in the index are left out of the commit.
+fn commit_files(paths: &[String], message: &str) -> Result<(), Error> {
+    let mut add = vec!["add", "--"];
+    add.extend(paths.iter().map(String::as_str));
+    let mut commit = vec!["commit", "--quiet", "--message", message, "--"];
+    commit.extend(paths.iter().map(String::as_str));
+
+    git::run(&add)
+        .and_then(|_| git::run(&commit))
+        .map(|_| ())
+        .map_err(|err| Error::new(Code::Git, format!("Failed to commit the bump: {}", err)))
+}
+
+/// Best-effort undo of [`commit_files`]: drops the commit and unstages
+/// `paths` again.
+fn uncommit_files(paths: &[String]) {
+    let mut reset = vec!["reset", "--quiet", "HEAD", "--"];
+    reset.extend(paths.iter().map(String::as_str));
+    if git::run(&["reset", "--quiet", "--soft", "HEAD~1"]).is_ok() {
+        let _ = git::run(&reset);
+    }
+}
+
+/// The tag `bump --tag` creates for the new `version`, from `--tag-format`;
+/// `None` without `--tag`.
+fn bump_tag(
+    matches: &ArgMatches,
+    version: &Version,
+    config: &Config,
+) -> Result<Option<String>, Error> {
+    if !matches.is_present("tag") {
+        return Ok(None);
+    }
+    config.forbid_in_deterministic("Git (bump --tag)")?;
+
+    Ok(Some(
+        describe::render(matches.value_of("tag-format").unwrap(), false, config)?
+            .replace("{version}", &version.to_string()),
+    ))
+}
+
+/// Creates the annotated tag `tag` for the release of `version` at HEAD.
+fn create_tag(tag: &str, version: &Version) -> Result<(), Error> {
+    git::run(&[
+        "tag",
+        "--annotate",
+        tag,
+        "--message",
+        &format!("Release {}", version),
+    ])
+    .map(|_| ())
+    .map_err(|err| {
+        Error::new(
+            Code::Git,
+            format!("Failed to create git tag {}: {}", tag, err),
+        )
+    })
+}
+
+/// Builds a TOML string item holding exactly `s`. `toml_edit::value` guesses
+/// the delimiters by first trying to parse its input as a string literal,
+/// which interprets escapes in (or panics on) arbitrary text such as paths
+/// or changelog snippets, so the literal is quoted explicitly here; a JSON
+/// string is a valid TOML basic string.
+fn string_value(s: &str) -> Item {
+    value(error::json_string(s).as_str())
+}
+
+/// Reads the package version string of the given manifest document
+/// and parses it into a semver::Version.
+fn read_version(manifest: &Document) -> Result<Version, Error> {
+    let version_str = manifest["package"]["version"]
+        .as_str()
+        .ok_or_else(|| Error::new(Code::InvalidVersion, "The manifest has no package version"))?;
+
+    Version::parse(version_str).map_err(|_| {
+        Error::new(
+            Code::InvalidVersion,
+            format!("Invalid package version: {} in Cargo.toml", version_str),
+        )
+    })
+}
+
+/// Reads the package version of the manifest like `read_version`, falling
+/// back to `--assume-version` if the manifest has no version at all.
+fn current_version(manifest: &Document, config: &Config) -> Result<Version, Error> {
+    match config.assume_version {
+        Some(ref version) if is_versionless(manifest, config) => Ok(version.clone()),
+        _ => read_version(manifest),
+    }
+}
+
+/// Whether the manifest has no version and `--assume-version` stands in for
+/// it, in which case the manifest is never written.
+fn is_versionless(manifest: &Document, config: &Config) -> bool {
+    config.assume_version.is_some() && manifest["package"]["version"].is_none()
+}
+
+/// Refuses to go on if the package of the given manifest is on the
+/// configured protected list.
+fn ensure_unprotected(
+    config: &Config,
+    manifest: &Document,
+    manifest_path: &str,
+) -> Result<(), Error> {
+    if config.is_protected(manifest["package"]["name"].as_str(), manifest_path) {
+        return Err(Error::new(
+            Code::Protected,
+            format!(
+                "{} is protected from version changes in {}",
+                manifest_path, CONFIG_FILE
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Refuses to go on if the configured branch policy does not allow
+/// producing `version` on the currently checked out branch.
+fn enforce_branch_policy(config: &Config, version: &Version) -> Result<(), Error> {
+    if config.branch_policy.is_empty() {
+        return Ok(());
+    }
+
+    let branch = match config.git_branch {
+        Some(ref branch) => branch.clone(),
+        None => {
+            config.forbid_in_deterministic("The current git branch (pass --git-branch)")?;
+            git::run(&["rev-parse", "--abbrev-ref", "HEAD"]).map_err(|err| {
+                Error::new(
+                    Code::Git,
+                    format!("Could not determine the current branch: {}", err),
+                )
+            })?
+        }
+    };
+    config
+        .check_branch_policy(&branch, version)
+        .map_err(|err| Error::new(Code::BranchPolicy, err))
+}
+
+/// Warns when `version` is a PATCH release after the last tag although the
+/// manifest changed since in ways that break dependents. The check needs
+/// git, so it is skipped in deterministic mode and when there is no tag.
+fn warn_breaking_patch(
+    manifest: &Document,
+    manifest_path: &str,
+    version: &Version,
+    config: &Config,
+) -> Result<(), Error> {
+    if config.deterministic || manifest_path == STDIN_MANIFEST {
+        return Ok(());
+    }
+    let released = auto::last_tag()
+        .and_then(|tag| git::show(&tag, manifest_path).ok())
+        .and_then(|released| released.parse::<Document>().ok());
+    let released = match released {
+        Some(released) => released,
+        None => return Ok(()),
+    };
+    match read_version(&released) {
+        Ok(released_version) if compat::is_patch(&released_version, version) => {}
+        _ => return Ok(()),
+    }
+    for change in compat::breaking_changes(&released, manifest) {
+        warnings::warn(
+            config,
+            warnings::Kind::BreakingPatch,
+            format!(
+                "{} is a PATCH release, but {} since the last release",
+                version, change
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Fails if the component is empty and `--fail-if-empty` or `--strict` is
+/// given.
+fn check_empty(component: &str, matches: &ArgMatches) -> Result<(), Error> {
+    if component.is_empty() && (matches.is_present("fail-if-empty") || matches.is_present("strict"))
+    {
+        return Err(Error::new(
+            Code::EmptyComponent,
+            "The requested version component is empty",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Renders a version component as `read` prints it: `EMPTY_COMPONENT` for
+/// an empty one unless `--raw`, or failing with `--fail-if-empty` or
+/// `--strict`.
+fn render_component(component: String, matches: &ArgMatches) -> Result<String, Error> {
+    check_empty(&component, matches)?;
+
+    Ok(match matches.value_of("output").unwrap() {
+        _ if component.is_empty() && !matches.is_present("raw") => String::from(EMPTY_COMPONENT),
+        "docker-safe" => convert::to_docker_tag(&component),
+        _ => component,
+    })
+}
+
+/// Reads the version component chosen from the command line and
+/// prints it to screen.
+fn read(version: Version, matches: &ArgMatches) -> String {
+    if matches.is_present("major") {
+        version.major.to_string()
+    } else if matches.is_present("minor") {
+        version.minor.to_string()
+    } else if matches.is_present("patch") {
+        version.patch.to_string()
+    } else if matches.is_present("pre") {
+        String::from(VersionMetadata(version.pre))
+    } else if matches.is_present("build") {
+        String::from(VersionMetadata(version.build))
+    } else if matches.is_present("version") {
+        version.to_string()
+    } else {
+        panic!("Unreachable - at least one argument to bump must be specified.");
+    }
+}
+
+/// A change to a version, as `bump` makes it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionOp {
+    /// Increment the MAJOR version; the lower components, pre-release and
+    /// build metadata are reset according to the `reset` setting.
+    Major,
+    /// Increment the MINOR version; PATCH, pre-release and build metadata
+    /// are reset according to the `reset` setting.
+    Minor,
+    /// Increment the PATCH version; pre-release and build metadata are
+    /// reset according to the `reset` setting.
+    Patch,
+    /// Set the pre-release label, e.g. `rc.1`.
+    Pre(String),
+    /// Increment the trailing numeric pre-release identifier, or append one.
+    PreNumber,
+    /// Set the build metadata; `{describe}`, `{commits-since-tag}`,
+    /// `{short-sha}` and `{dirty}` are filled from `git describe`.
+    Build(String),
+    /// Increment the trailing numeric build identifier, or append one.
+    BuildNumber,
+    /// Move to the next version on a release channel, e.g. `nightly`.
+    Channel(String),
+    /// Set the whole version.
+    Version(Version),
+    /// Bump the component the conventional commits since the last tag
+    /// warrant.
+    Auto,
+}
+
+impl VersionOp {
+    /// The operation chosen on the `bump` command line.
+    fn from_matches(matches: &ArgMatches) -> Result<VersionOp, Error> {
+        Ok(if matches.is_present("major") {
+            VersionOp::Major
+        } else if matches.is_present("minor") {
+            VersionOp::Minor
+        } else if matches.is_present("patch") {
+            VersionOp::Patch
+        } else if matches.is_present("pre") {
+            match matches.value_of("pre") {
+                Some(pre) => VersionOp::Pre(pre.to_string()),
+                None => VersionOp::PreNumber,
+            }
+        } else if let Some(build) = matches.value_of("build") {
+            VersionOp::Build(build.to_string())
+        } else if matches.is_present("build-number") {
+            VersionOp::BuildNumber
+        } else if let Some(channel) = matches.value_of("channel") {
+            VersionOp::Channel(channel.to_string())
+        } else if let Some(new_version_str) = matches.value_of("version") {
+            VersionOp::Version(Version::parse(new_version_str).map_err(|_| {
+                Error::new(
+                    Code::InvalidArgument,
+                    format!("Invalid new version given: {}", new_version_str),
+                )
+            })?)
+        } else if matches.is_present("auto") {
+            VersionOp::Auto
+        } else {
+            panic!("Unreachable - at least one argument to bump must be specified.");
+        })
+    }
+}
+
+/// Applies `op` to `version` as `bump` would without a configuration: with
+/// no limits on the components or constraints on pre-release labels.
+pub fn apply_bump(version: &Version, op: &VersionOp) -> Result<Version, Error> {
+    apply_op(version, op, &Config::default())
+}
+
+/// Computes the version that results from applying the component chosen
+/// on the command line to the given version; fails if an incorrect
+/// pre-release/build/version string is passed in the argument matches, or
+/// if the result keeps build metadata `forbid_build_metadata` forbids, and
+/// warns about build metadata otherwise; assumes that it will always be
+/// called with a component to bump.
+fn bumped_version(
+    version: &Version,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<Version, Error> {
+    bumped_by(version, &VersionOp::from_matches(matches)?, matches, config)
+}
+
+/// Computes the version `op` results in, under the options of `matches`,
+/// as `bumped_version` does.
+fn bumped_by(
+    version: &Version,
+    op: &VersionOp,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<Version, Error> {
+    let mut bumped = apply_op(version, op, config)?;
+    let component = match op {
+        VersionOp::Major => Some(Component::Major),
+        VersionOp::Minor => Some(Component::Minor),
+        VersionOp::Patch => Some(Component::Patch),
+        _ => None,
+    };
+    if let Some(component) = component {
+        let reset = Reset {
+            lower: config.reset.lower && !matches.is_present("keep-lower"),
+            pre: config.reset.pre && !matches.is_present("keep-pre"),
+            build: config.reset.build && !matches.is_present("keep-build"),
+        };
+        reset.keep(version, &mut bumped, component);
+    }
+    if matches.is_present("strip-build-on-bump") {
+        bumped.build.clear();
+    }
+    if config.forbid_build_metadata && !bumped.build.is_empty() {
+        return Err(Error::new(
+            Code::BuildMetadata,
+            format!(
+                "{} carries build metadata, which forbid_build_metadata in {} forbids; pass --strip-build-on-bump to drop it",
+                bumped, CONFIG_FILE
+            ),
+        ));
+    }
+    if !bumped.build.is_empty() {
+        warnings::warn(
+            config,
+            warnings::Kind::BuildMetadata,
+            format!(
+                "{} carries build metadata, which version precedence ignores",
+                bumped
+            ),
+        )?;
+    }
+
+    Ok(bumped)
+}
+
+/// The plan file and the operation `plan` was given. `--pre` takes an
+/// optional label, so the parser hands it the file that follows it as
+/// well: with no file left over, its last value is the file, and a lone
+/// one means the pre-release number is incremented.
+fn plan_target<'a>(matches: &'a ArgMatches) -> Result<(&'a str, VersionOp), Error> {
+    let pre: Vec<&str> = matches
+        .values_of("pre")
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    match (matches.value_of("plan"), pre.as_slice()) {
+        (Some(_), [_, _]) => Err(Error::new(
+            Code::InvalidArgument,
+            "--pre takes at most one PRE-RELEASE version",
+        )),
+        (Some(plan), _) => Ok((plan, VersionOp::from_matches(matches)?)),
+        (None, [plan]) => Ok((plan, VersionOp::PreNumber)),
+        (None, [pre, plan]) => Ok((plan, VersionOp::Pre(pre.to_string()))),
+        _ => Err(Error::new(
+            Code::InvalidArgument,
+            "plan needs the path to write the plan file to",
+        )),
+    }
+}
+
+/// Applies `op` to `version` under the limits and policies of `config`.
+fn apply_op(version: &Version, op: &VersionOp, config: &Config) -> Result<Version, Error> {
+    let mut version = version.clone();
+    let invalid = |what: &str, given: &str| {
+        Error::new(
+            Code::InvalidArgument,
+            format!("Invalid {} given: {}", what, given),
+        )
+    };
+
+    let overflow = |err| Error::new(Code::Overflow, err);
+
+    match op {
+        VersionOp::Major => config
+            .limits
+            .increment(&mut version, Component::Major)
+            .map_err(overflow)?,
+        VersionOp::Minor => config
+            .limits
+            .increment(&mut version, Component::Minor)
+            .map_err(overflow)?,
+        VersionOp::Patch => config
+            .limits
+            .increment(&mut version, Component::Patch)
+            .map_err(overflow)?,
+        VersionOp::Pre(pre) => {
+            version.pre = VersionMetadata::try_from(pre.as_str())
+                .map_err(|_| invalid("pre-release", pre))?
+                .0;
+            config
+                .check_pre_release(&version.pre)
+                .map_err(|err| Error::new(Code::InvalidArgument, err))?;
+        }
+        VersionOp::PreNumber => {
+            if version.pre.is_empty() {
+                return Err(Error::new(
+                    Code::InvalidVersion,
+                    format!(
+                        "{} is not a pre-release, so --pre has no number to increment and needs the label to set",
+                        version
+                    ),
+                ));
+            }
+            version.pre = next_number(&version.pre).ok_or_else(|| {
+                Error::new(
+                    Code::InvalidVersion,
+                    format!(
+                        "The pre-release number of {} cannot be incremented",
+                        version
+                    ),
+                )
+            })?;
+            config
+                .check_pre_release(&version.pre)
+                .map_err(|err| Error::new(Code::InvalidArgument, err))?;
+        }
+        VersionOp::Build(build) => {
+            let build = describe::render(build, true, config)?;
+            version.build = VersionMetadata::try_from(build.as_str())
+                .map_err(|_| invalid("build metadata", &build))?
+                .0;
+        }
+        VersionOp::BuildNumber => {
+            version.build = next_number(&version.build).ok_or_else(|| {
+                Error::new(
+                    Code::InvalidVersion,
+                    format!("The build number of {} cannot be incremented", version),
+                )
+            })?;
+        }
+        VersionOp::Channel(channel) => {
+            let channel = Channel::parse(channel).map_err(|err| Error::new(Code::Channel, err))?;
+            let date = match config.now {
+                Some(now) => now,
+                None if channel == Channel::Nightly => {
+                    config.forbid_in_deterministic("The wall clock (pass --now)")?;
+                    nightly::today()
+                }
+                None => 0,
+            };
+            version = channel
+                .next(&version, date, &config.limits)
+                .map_err(overflow)?;
+        }
+        VersionOp::Version(new_version) => version.clone_from(new_version),
+        VersionOp::Auto => {
+            config.forbid_in_deterministic("The git history (--auto)")?;
+            let component = auto::detect(config)?.map(|component| {
+                if config.zero_major {
+                    auto::zero_ver(component, &version)
+                } else {
+                    component
+                }
+            });
+            match component {
+                Some(component) => config
+                    .limits
+                    .increment(&mut version, component)
+                    .map_err(overflow)?,
+                None => {
+                    return Err(Error::new(
+                        Code::NoChanges,
+                        "No commits since the last tag warrant a version bump",
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(version)
+}
+
+/// The pre-release or build identifiers with the trailing numeric one,
+/// treated as a counter, incremented (`build.41` to `build.42`, `rc.1` to
+/// `rc.2`), or with a counter starting at 1 appended if there is none;
+/// `None` if the counter overflows.
+fn next_number(identifiers: &[Identifier]) -> Option<Vec<Identifier>> {
+    let mut identifiers = identifiers.to_vec();
+    match identifiers.last_mut() {
+        Some(Identifier::Numeric(number)) => *number = number.checked_add(1)?,
+        _ => identifiers.push(Identifier::Numeric(1)),
+    }
+
+    Some(identifiers)
+}
+
+/// Whether the `--if-stable`/`--if-pre` condition given on the command
+/// line, if any, holds for the current version.
+fn condition_holds(version: &Version, matches: &ArgMatches) -> bool {
+    if matches.is_present("if-stable") {
+        !version.is_prerelease()
+    } else if matches.is_present("if-pre") {
+        version.is_prerelease()
+    } else {
+        true
+    }
+}
+
+/// Bumps the package version string of the provided manifest
+/// according to the component chosen on the command line.
+fn bump(
+    manifest: &mut Document,
+    manifest_path: &str,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<(), Error> {
+    let current = current_version(manifest, config)?;
+    let version = bumped_version(&current, matches, config)?;
+    events::computed(config, manifest_path, &current, &version);
+    enforce_branch_policy(config, &version)?;
+
+    manifest["package"]["version"] = value(version.to_string());
+
+    Ok(())
+}
+
+/// The newest released version on the channel given on the command line,
+/// from the release tags or the versions listed on stdin.
+fn latest_on_channel(matches: &ArgMatches, config: &Config) -> Result<EpochVersion, Error> {
+    let name = matches.value_of("channel").unwrap();
+    let channel = Channel::parse(name).map_err(|err| Error::new(Code::Channel, err))?;
+    let versions = if matches.is_present("stdin") {
+        io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::new(Code::Io, format!("Could not read stdin: {}", err)))?
+    } else {
+        config.forbid_in_deterministic("Git (channel latest, pass --stdin)")?;
+        let tag_format = matches.value_of("tag-format").unwrap();
+        let (prefix, suffix) = tag_format.split_at(tag_format.find("{version}").unwrap_or(0));
+        let suffix = suffix.trim_start_matches("{version}");
+        git::run(&["tag", "--list"])?
+            .lines()
+            .filter_map(|tag| tag.strip_prefix(prefix)?.strip_suffix(suffix))
+            .map(String::from)
+            .collect()
+    };
+
+    list::canonicalize(&versions, list::Build::Ignore)
+        .into_iter()
+        .rfind(|version| channel.contains(&version.version))
+        .ok_or_else(|| {
+            Error::new(
+                Code::Channel,
+                format!("No version was released on {}", name),
+            )
+        })
+}
+
+/// The previously released version `rollback` goes back to: the manifest
+/// version at the given tag, or the given version if its release tag
+/// exists. It must be older than the current version.
+fn released_version(
+    manifest_path: &str,
+    matches: &ArgMatches,
+    current: &Version,
+    config: &Config,
+) -> Result<Version, Error> {
+    let version = match matches.value_of("to") {
+        Some(to) => Version::parse(to).map_err(|_| {
+            Error::new(
+                Code::InvalidArgument,
+                format!("Invalid version given: {}", to),
+            )
+        })?,
+        None => {
+            config.forbid_in_deterministic("Git (rollback --to-tag)")?;
+            let tag = matches.value_of("to-tag").unwrap();
+            let manifest = git::show(tag, manifest_path)?
+                .parse::<Document>()
+                .map_err(|err| {
+                    Error::new(
+                        Code::ParseManifest,
+                        format!("Invalid {} at {}: {}", manifest_path, tag, err),
+                    )
+                })?;
+            read_version(&manifest)?
+        }
+    };
+    if version >= *current {
+        return Err(Error::new(
+            Code::InvalidArgument,
+            format!(
+                "Cannot roll back to {}, which is not older than {}",
+                version, current
+            ),
+        ));
+    }
+
+    if matches.is_present("to") {
+        config.forbid_in_deterministic("Git (rollback --to)")?;
+        let tag = matches
+            .value_of("tag-format")
+            .unwrap()
+            .replace("{version}", &version.to_string());
+        if git::run(&["tag", "--list", tag.as_str()])?.is_empty() {
+            return Err(Error::new(
+                Code::InvalidArgument,
+                format!("{} was never released: there is no tag {}", version, tag),
+            ));
+        }
+    }
+
+    Ok(version)
+}
+
+/// Bumps the configured version field `field` according to the component
+/// chosen on the command line, leaving the manifest and sync targets alone.
+fn bump_field(
+    field: &Field,
+    matches: &ArgMatches,
+    config: &Config,
+    stdout: &mut dyn Write,
+) -> Result<(), Error> {
+    let path = config.root.join(&field.file).to_string_lossy().into_owned();
+    let current = adapter::read_field_version(&path, &field.key)?;
+    events::parsed(config, &path, &current);
+    if !condition_holds(&current, matches) {
+        return Ok(());
+    }
+    if config.is_protected(None, &path) {
+        return Err(Error::new(
+            Code::Protected,
+            format!(
+                "{} is protected from version changes in {}",
+                path, CONFIG_FILE
+            ),
+        ));
+    }
+
+    let version = bumped_version(&current, matches, config)?;
+    events::computed(config, &path, &current, &version);
+    enforce_branch_policy(config, &version)?;
+    let mut transaction = Transaction::new();
+    adapter::stage_field_version(&mut transaction, &path, &field.key, &version)?;
+    if let Some(ref dry_run) = config.dry_run {
+        return transaction.preview(dry_run, &config.root, stdout);
+    }
+    transaction.commit(|| Ok(()))?;
+    events::written(config, &[path]);
+
+    Ok(())
+}
+
+/// Fails unless the SHA-256 of the file at `path` is `expected`, so that a
+/// file changed since it was inspected is not overwritten.
+fn expect_hash(path: &str, expected: &str) -> Result<(), Error> {
+    let actual = sha256::file_hex(path)?;
+    if !sha256::matches(expected, &actual) {
+        return Err(Error::new(
+            Code::StaleManifest,
+            format!(
+                "{} changed since it was inspected: its SHA-256 is {}, not {}",
+                path, actual, expected
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bumps a manifest that is not a Cargo one, e.g. a `package.json`, as
+/// `bump` does a Cargo manifest: together with the synced files and the
+/// idempotency key, if any, in one transaction.
+fn bump_version_file(
+    path: &str,
+    key: Option<&str>,
+    matches: &ArgMatches,
+    config: &Config,
+    stdout: &mut dyn Write,
+) -> Result<(), Error> {
+    let format = config.manifest_format;
+    let contents = adapter::read_file(path)?;
+    let current = format.read_version(&contents, path)?;
+    events::parsed(config, path, &current);
+    if !condition_holds(&current, matches) {
+        return Ok(());
+    }
+    if config.is_protected(format.package_name(&contents).as_deref(), path) {
+        return Err(Error::new(
+            Code::Protected,
+            format!(
+                "{} is protected from version changes in {}",
+                path, CONFIG_FILE
+            ),
+        ));
+    }
+
+    let version = bumped_version(&current, matches, config)?;
+    events::computed(config, path, &current, &version);
+    enforce_branch_policy(config, &version)?;
+    let mut transaction = Transaction::new();
+    if let Some(key) = key {
+        idempotency::stage(&mut transaction, config, key, &version)?;
+    }
+    for sync in adapter::sync_files(config) {
+        adapter::stage_version(&mut transaction, &sync, &version)?;
+    }
+    transaction.stage(path, format.write_version(&contents, &version, path)?);
+    if let Some(ref dry_run) = config.dry_run {
+        return transaction.preview(dry_run, &config.root, stdout);
+    }
+    let message = bump_commit_message(matches, &version, config)?;
+    let tag = bump_tag(matches, &version, config)?;
+    let paths = transaction.paths();
+    transaction.commit(|| commit_and_tag(&paths, message.as_deref(), tag.as_deref(), &version))?;
+    events::written(config, &paths);
+    if let Some(ref tag) = tag {
+        events::tagged(config, tag);
+    }
+
+    Ok(())
+}
+
+/// Main entrypoint, which executes the chosen subcommand with the
+/// provided arguments. It takes in an output explicitly in order to
+/// simplify testing.
+fn execute(matches: &ArgMatches, stdout: &mut dyn Write) -> Result<(), Error> {
+    let manifest_format = match matches.value_of("manifest-format") {
+        Some("json") => Format::PackageJson,
+        Some("pyproject") => Format::Pyproject,
+        Some("plain") => Format::Plain,
+        Some("cargo") => Format::Cargo,
+        _ => Format::detect_manifest(matches.value_of("manifest-path").unwrap()),
+    };
+    let manifest_path = match manifest_format.manifest_name() {
+        Some(name) if matches.occurrences_of("manifest-path") == 0 => name,
+        _ => matches.value_of("manifest-path").unwrap(),
+    };
+    let joiner = Joiner::parse(matches.value_of("joiner").unwrap()).unwrap();
+
+    if let ("errors", Some(_)) = matches.subcommand() {
+        for code in Code::ALL.iter() {
+            writeln!(
+                stdout,
+                "{:<3} {:<20} {}",
+                code.exit_code(),
+                code.name(),
+                code.description()
+            )
+            .unwrap();
+        }
+        return Ok(());
+    }
+    if let ("canon", Some(canon_matches)) = matches.subcommand() {
+        let lines = io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::new(Code::Io, format!("Could not read stdin: {}", err)))?;
+        let build = match canon_matches.value_of("build").unwrap() {
+            "keep" => list::Build::Keep,
+            "strip" => list::Build::Strip,
+            _ => list::Build::Ignore,
+        };
+        joiner.write(stdout, list::canonicalize(&lines, build));
+        return Ok(());
+    }
+    if let ("batch", Some(_)) = matches.subcommand() {
+        let inherited = Some(matches.value_of("joiner").unwrap())
+            .filter(|_| matches.occurrences_of("joiner") > 0);
+        return batch::run(&mut io::stdin().lock(), stdout, inherited);
+    }
+
+    if manifest_path == STDIN_MANIFEST {
+        match matches.subcommand_name() {
+            Some("bump") | Some("read") | Some("do") | Some("nightly") | Some("convert") => {}
+            Some(subcommand) => {
+                return Err(Error::new(
+                    Code::InvalidArgument,
+                    format!("{} cannot read the manifest from stdin", subcommand),
+                ))
+            }
+            None => {}
+        }
+    }
+    if manifest_format != Format::Cargo {
+        match matches.subcommand_name() {
+            Some("bump") | Some("read") | Some("check") if manifest_path != STDIN_MANIFEST => {}
+            Some(subcommand) => {
+                return Err(Error::new(
+                    Code::InvalidArgument,
+                    format!(
+                        "{} cannot read a {} manifest{}",
+                        subcommand,
+                        manifest_format.manifest_name().unwrap(),
+                        if manifest_path == STDIN_MANIFEST {
+                            " from stdin"
+                        } else {
+                            ""
+                        }
+                    ),
+                ))
+            }
+            None => {}
+        }
+    }
+    let mut config = Config::load(manifest_path)?;
+    config.manifest_format = manifest_format;
+    config.deterministic |= matches.is_present("deterministic");
+    config.git_branch = matches.value_of("git-branch").map(String::from);
+    if let Some(now) = matches.value_of("now") {
+        config.now =
+            Some(nightly::parse_date(now).map_err(|err| Error::new(Code::InvalidArgument, err))?);
+    }
+    config.events = matches.value_of("output") == Some("events");
+    let matrix = matches.value_of("output") == Some("matrix");
+    if config.events && joiner != Joiner::Newline {
+        return Err(Error::new(
+            Code::InvalidArgument,
+            "--joiner cannot be combined with --output events, which reports one value per event",
+        ));
+    }
+    if matrix
+        && !matches
+            .subcommand_matches("bump")
+            .is_some_and(|bump_matches| bump_matches.is_present("workspace"))
+    {
+        return Err(Error::new(
+            Code::InvalidArgument,
+            "--output matrix only applies to bump --workspace",
+        ));
+    }
+    config.color = !config.events
+        && style::enabled(
+            matches.value_of("color").unwrap(),
+            io::stdout().is_terminal(),
+            style::no_color(),
+        );
+    config.stderr_color = matches.value_of("color") == Some("always");
+    config.deny_warnings = matches.is_present("deny-warnings");
+    for setting in &config.deprecated {
+        warnings::warn(&config, warnings::Kind::Deprecated, setting.as_str())?;
+    }
+    if let Some(version) = matches.value_of("assume-version") {
+        config.assume_version = Some(Version::parse(version).map_err(|_| {
+            Error::new(
+                Code::InvalidArgument,
+                format!("Invalid version given: {}", version),
+            )
+        })?);
+    }
+    let mut lines;
+    let stdout: &mut dyn Write = if config.events {
+        lines = events::Lines::new(stdout);
+        &mut lines
+    } else {
+        stdout
+    };
+
+    match matches.subcommand() {
+        ("bump", Some(bump_matches)) => {
+            let git_step = ["commit", "tag"]
+                .iter()
+                .find(|arg| bump_matches.is_present(arg));
+            if let (Some(arg), true) = (git_step, manifest_path == STDIN_MANIFEST) {
+                return Err(Error::new(
+                    Code::InvalidArgument,
+                    format!("--{} needs a manifest file, not stdin", arg),
+                ));
+            }
+            if let Some(expected) = bump_matches.value_of("expect-hash") {
+                if manifest_path == STDIN_MANIFEST {
+                    return Err(Error::new(
+                        Code::InvalidArgument,
+                        "--expect-hash needs a manifest file, not stdin",
+                    ));
+                }
+                expect_hash(manifest_path, expected)?;
+            }
+            if bump_matches.is_present("dry-run") {
+                config.dry_run = Some(match bump_matches.value_of("emit-patch-dir") {
+                    Some(dir) => DryRun::PatchDir(PathBuf::from(dir)),
+                    None => DryRun::Stdout,
+                });
+            }
+            if bump_matches.is_present("skip-if-no-changes") {
+                config.forbid_in_deterministic("The git history (--skip-if-no-changes)")?;
+                if auto::detect(&config)?.is_none() {
+                    return Ok(());
+                }
+            }
+            if bump_matches.is_present("workspace") {
+                let (changes, skipped) = workspace::bump(&config, bump_matches, stdout)?;
+                for path in skipped {
+                    warnings::warn(
+                        &config,
+                        warnings::Kind::Protected,
+                        format!("Skipped {}: protected in {}", path, CONFIG_FILE),
+                    )?;
+                }
+                if matrix {
+                    writeln!(stdout, "{}", workspace::matrix(&changes)).unwrap();
+                }
+                return Ok(());
+            }
+            if let Some(name) = bump_matches.value_of("field") {
+                return bump_field(config.field(name)?, bump_matches, &config, stdout);
+            }
+            let key = bump_matches.value_of("idempotency-key");
+            if let Some(key) = key {
+                if idempotency::skip_applied(&config, key)? {
+                    return Ok(());
+                }
+            }
+            if config.manifest_format != Format::Cargo {
+                let unsupported = ["open-pr", "update-dependents"]
+                    .iter()
+                    .find(|arg| bump_matches.is_present(arg));
+                if let Some(arg) = unsupported {
+                    return Err(Error::new(
+                        Code::InvalidArgument,
+                        format!("--{} needs a Cargo manifest", arg),
+                    ));
+                }
+                return bump_version_file(manifest_path, key, bump_matches, &config, stdout);
+            }
+            let mut manifest = read_manifest(manifest_path)?;
+            let current = current_version(&manifest, &config)?;
+            events::parsed(&config, manifest_path, &current);
+            if !condition_holds(&current, bump_matches) {
+                if manifest_path == STDIN_MANIFEST {
+                    write!(stdout, "{}", manifest).unwrap();
+                }
+                return Ok(());
+            }
+            ensure_unprotected(&config, &manifest, manifest_path)?;
+            let versionless = is_versionless(&manifest, &config);
+            bump(&mut manifest, manifest_path, bump_matches, &config)?;
+            let version = read_version(&manifest)?;
+            warn_breaking_patch(&manifest, manifest_path, &version, &config)?;
+            let pull_request = if bump_matches.is_present("open-pr") {
+                config.forbid_in_deterministic("Git (bump --open-pr)")?;
+                let dir = if config.root.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    config.root.as_path()
+                };
+                Some(pr::PullRequest::prepare(
+                    dir,
+                    &version,
+                    bump_matches.value_of("pr-branch").unwrap(),
+                    &config.root.join("CHANGELOG.md"),
+                )?)
+            } else {
+                None
+            };
+            let message = bump_commit_message(bump_matches, &version, &config)?;
+            let tag = bump_tag(bump_matches, &version, &config)?;
+            if versionless {
+                if message.is_some() {
+                    return Err(Error::new(
+                        Code::InvalidArgument,
+                        "--commit needs a manifest with a version to write",
+                    ));
+                }
+                writeln!(stdout, "{}", version).unwrap();
+                if let Some(ref tag) = tag {
+                    create_tag(tag, &version)?;
+                    events::tagged(&config, tag);
+                }
+            } else {
+                let mut transaction = Transaction::new();
+                if let Some(key) = key {
+                    idempotency::stage(&mut transaction, &config, key, &version)?;
+                }
+                if bump_matches.is_present("update-dependents") {
+                    let name = manifest["package"]["name"].as_str().ok_or_else(|| {
+                        Error::new(
+                            Code::InvalidArgument,
+                            "--update-dependents needs a package name in the manifest",
+                        )
+                    })?;
+                    if manifest_path == STDIN_MANIFEST {
+                        return Err(Error::new(
+                            Code::InvalidArgument,
+                            "--update-dependents needs a manifest file, not stdin",
+                        ));
+                    }
+                    workspace::update_dependents(&mut transaction, manifest_path, name, &version)?;
+                }
+                write_synced_with(
+                    manifest,
+                    manifest_path,
+                    &config,
+                    transaction,
+                    message.as_deref(),
+                    tag.as_deref(),
+                    stdout,
+                )?;
+            }
+            if let Some(pull_request) = pull_request {
+                writeln!(stdout, "{}", pull_request.open()?).unwrap();
+            }
+        }
+        ("read", Some(read_matches)) => {
+            let json =
+                read_matches.value_of("format") == Some("json") || read_matches.is_present("json");
+            if json && (read_matches.is_present("locate") || read_matches.is_present("workspace")) {
+                return Err(Error::new(
+                    Code::InvalidArgument,
+                    "--format json cannot be combined with --locate or --workspace",
+                ));
+            }
+            if read_matches.is_present("locate") {
+                if read_matches.is_present("field") {
+                    return Err(Error::new(
+                        Code::InvalidArgument,
+                        "--locate cannot be combined with --field",
+                    ));
+                }
+                let mut locations = vec![];
+                let mut files = vec![(manifest_path.to_string(), config.manifest_format)];
+                for path in adapter::sync_files(&config) {
+                    let format = Format::detect(&path)?;
+                    files.push((path, format));
+                }
+                for (path, format) in files {
+                    let contents = if path == manifest_path {
+                        read_manifest_contents(&path)?
+                    } else {
+                        adapter::read_file(&path)?
+                    };
+                    let ranges = format.locate(&contents);
+                    if ranges.is_empty() {
+                        return Err(Error::new(
+                            Code::Adapter,
+                            format!("Could not locate the version in {}", path),
+                        ));
+                    }
+                    let hash = if read_matches.is_present("hash") {
+                        format!(" sha256:{}", sha256::hex(contents.as_bytes()))
+                    } else {
+                        String::new()
+                    };
+                    for range in ranges {
+                        let (line, column) = adapter::line_column(&contents, range.start);
+                        locations.push(format!(
+                            "{}:{}:{}: {}{}",
+                            path, line, column, &contents[range], hash
+                        ));
+                    }
+                }
+                joiner.write(stdout, locations);
+                return Ok(());
+            }
+            if read_matches.is_present("workspace") {
+                let unsupported = ["field", "locate", "all", "epoch"]
+                    .iter()
+                    .find(|arg| read_matches.is_present(arg));
+                if let Some(arg) = unsupported {
+                    return Err(Error::new(
+                        Code::InvalidArgument,
+                        format!("--workspace cannot be combined with --{}", arg),
+                    ));
+                }
+                let mut components = vec![];
+                for (name, path, version) in workspace::versions(&config)? {
+                    events::parsed(&config, &path, &version);
+                    let component = if read_matches.is_present("version") {
+                        version.to_string()
+                    } else {
+                        read(version, read_matches)
+                    };
+                    components.push(format!(
+                        "{} {}",
+                        name,
+                        render_component(component, read_matches)?
+                    ));
+                }
+                joiner.write(stdout, components);
+                return Ok(());
+            }
+            let (path, version) = match read_matches.value_of("field") {
+                Some(name) => {
+                    let field = config.field(name)?;
+                    let path = config.root.join(&field.file).to_string_lossy().into_owned();
+                    let version = adapter::read_field_version(&path, &field.key)?;
+                    (path, version)
+                }
+                None => match read_matches.value_of("at") {
+                    Some(rev) => {
+                        config.forbid_in_deterministic("Git (read --at)")?;
+                        (
+                            format!("{}:{}", rev, manifest_path),
+                            manifest_version_at(rev, manifest_path, &config)?,
+                        )
+                    }
+                    None => (
+                        manifest_path.to_string(),
+                        read_manifest_version(manifest_path, &config)?,
+                    ),
+                },
+            };
+            events::parsed(&config, &path, &version);
+            let epoch = if read_matches.is_present("field") {
+                0
+            } else {
+                config.epoch
+            };
+            if read_matches.is_present("all") || json {
+                let mut rows = overview::rows(&EpochVersion { epoch, version });
+                if !read_matches.is_present("all") {
+                    rows.retain(|(name, _)| read_matches.is_present(name));
+                    for (_, value) in &rows {
+                        if let overview::Value::Text(text) = value {
+                            check_empty(text, read_matches)?;
+                        }
+                    }
+                }
+                if json {
+                    write!(stdout, "{}", overview::json(&rows)).unwrap();
+                } else {
+                    write!(stdout, "{}", overview::table(&rows)).unwrap();
+                }
+                return Ok(());
+            }
+            let component = if read_matches.is_present("epoch") {
+                epoch.to_string()
+            } else if read_matches.is_present("version") {
+                EpochVersion { epoch, version }.to_string()
+            } else {
+                read(version, read_matches)
+            };
+            writeln!(stdout, "{}", render_component(component, read_matches)?).unwrap();
+        }
+        ("do", Some(do_matches)) => {
+            let operations = do_matches
+                .values_of("operations")
+                .unwrap()
+                .map(Operation::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| Error::new(Code::InvalidArgument, err))?;
+            let mut manifest = read_manifest(manifest_path)?;
+            let mutates = operations.iter().any(Operation::mutates);
+            if mutates {
+                ensure_unprotected(&config, &manifest, manifest_path)?;
+            }
+
+            let current = current_version(&manifest, &config)?;
+            events::parsed(&config, manifest_path, &current);
+            let version = pipeline::run(&operations, &current, &config.limits, stdout)
+                .map_err(|err| Error::new(Code::Overflow, err))?;
+            if mutates && !is_versionless(&manifest, &config) {
+                events::computed(&config, manifest_path, &current, &version);
+                enforce_branch_policy(&config, &version)?;
+                manifest["package"]["version"] = value(version.to_string());
+                write_synced(manifest, manifest_path, &config, stdout)?;
+            }
+        }
+        ("plan", Some(plan_matches)) => {
+            let manifest = read_manifest(manifest_path)?;
+            ensure_unprotected(&config, &manifest, manifest_path)?;
+            let old = read_version(&manifest)?;
+            events::parsed(&config, manifest_path, &old);
+            let (plan_path, op) = plan_target(plan_matches)?;
+            let new = bumped_by(&old, &op, plan_matches, &config)?;
+            let tags =
+                match plan_matches.value_of("tag") {
+                    Some(tag) => vec![describe::render(tag, false, &config)?
+                        .replace("{version}", &new.to_string())],
+                    None => vec![],
+                };
+            let mut files = vec![FileChange {
+                path: plan::relative(&config, manifest_path),
+                old,
+                new: new.clone(),
+                hash: Some(sha256::file_hex(manifest_path)?),
+            }];
+            for path in adapter::sync_files(&config) {
+                let old = adapter::read_file_version(&path)?;
+                events::parsed(&config, &path, &old);
+                files.push(FileChange {
+                    old,
+                    new: new.clone(),
+                    hash: Some(sha256::file_hex(&path)?),
+                    path: plan::relative(&config, &path),
+                });
+            }
+            for change in &files {
+                events::computed(&config, &change.resolve(&config), &change.old, &change.new);
+            }
+            let plan = Plan {
+                files,
+                tags,
+                key: plan_matches.value_of("idempotency-key").map(String::from),
+            };
+            plan::write_plan(&plan, plan_path)?;
+            events::written(&config, &[plan_path.to_string()]);
+        }
+        ("apply", Some(apply_matches)) => {
+            let plan_path = apply_matches.value_of("plan").unwrap();
+            let plan = plan::read_plan(plan_path)?;
+            if let (Some(expected), Some(change)) =
+                (apply_matches.value_of("expect-hash"), plan.files.first())
+            {
+                expect_hash(&change.resolve(&config), expected)?;
+            }
+            if apply_matches.is_present("notes") {
+                config.forbid_in_deterministic("Git (apply --notes)")?;
+            }
+            plan::apply(&plan, &config)?;
+            if let (true, Some(change)) = (apply_matches.is_present("notes"), plan.files.first()) {
+                notes::attach(
+                    "HEAD",
+                    &notes::Metadata {
+                        version: change.new.clone(),
+                        changelog: None,
+                        plan: Some(notes::hash_file(plan_path)?),
+                    },
+                )?;
+            }
+        }
+        ("release", Some(release_matches)) => match release_matches.subcommand() {
+            ("start", Some(start_matches)) => {
+                release::start(manifest_path, start_matches, &config)?
+            }
+            ("tag", Some(tag_matches)) => {
+                config.forbid_in_deterministic("Git (release tag)")?;
+                release::tag(
+                    manifest_path,
+                    tag_matches.value_of("tag-format").unwrap(),
+                    tag_matches.is_present("notes"),
+                    &config,
+                )?
+            }
+            ("publish", Some(publish_matches)) => {
+                config.forbid_in_deterministic("The network (release publish)")?;
+                release::publish(manifest_path, publish_matches.is_present("dry-run"))?
+            }
+            ("finalize", Some(_)) => release::finalize(manifest_path)?,
+            (_, _) => panic!("Unreachable - a release subcommand must be specified."),
+        },
+        ("check-changelog", Some(check_matches)) => {
+            let changelog_path = match check_matches.value_of("changelog") {
+                Some(path) => PathBuf::from(path),
+                None => config.root.join("CHANGELOG.md"),
+            };
+            let base = check_matches.value_of("base").unwrap();
+            config.forbid_in_deterministic("The git history (check-changelog)")?;
+            changelog::check(manifest_path, &changelog_path.to_string_lossy(), base)?;
+        }
+        ("api-diff", Some(api_matches)) => {
+            let listing = |arg: &str| -> Result<Option<Vec<String>>, Error> {
+                match api_matches.value_of(arg) {
+                    Some(path) => Ok(Some(
+                        adapter::read_file(path)?
+                            .lines()
+                            .map(String::from)
+                            .collect(),
+                    )),
+                    None => Ok(None),
+                }
+            };
+            let old = match listing("old")? {
+                Some(old) => old,
+                None => {
+                    config.forbid_in_deterministic("Git (api-diff, pass --old)")?;
+                    let tag = match api_matches.value_of("tag") {
+                        Some(tag) => tag.to_string(),
+                        None => auto::last_tag().ok_or_else(|| {
+                            Error::new(Code::Git, "There is no tag to compare the public API with")
+                        })?,
+                    };
+                    api::public_api_at(manifest_path, &tag)?
+                }
+            };
+            let new = match listing("new")? {
+                Some(new) => new,
+                None => api::public_api(manifest_path)?,
+            };
+            let diff = api::Diff::new(&old, &new);
+            let mut component = diff.component();
+            if config.zero_major {
+                component =
+                    auto::zero_ver(component, &read_manifest_version(manifest_path, &config)?);
+            }
+
+            let component = component.to_string();
+            writeln!(
+                stdout,
+                "{}",
+                style::paint(config.color, style::BOLD, &component)
+            )
+            .unwrap();
+            if api_matches.is_present("explain") {
+                for item in &diff.removed {
+                    let line = format!("- {}", item);
+                    writeln!(stdout, "{}", style::paint(config.color, style::RED, &line)).unwrap();
+                }
+                for item in &diff.added {
+                    let line = format!("+ {}", item);
+                    writeln!(
+                        stdout,
+                        "{}",
+                        style::paint(config.color, style::GREEN, &line)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        ("channel", Some(channel_matches)) => match channel_matches.subcommand() {
+            ("latest", Some(latest_matches)) => {
+                let version = latest_on_channel(latest_matches, &config)?;
+                writeln!(stdout, "{}", version).unwrap();
+            }
+            (_, _) => panic!("Unreachable - a channel subcommand must be specified."),
+        },
+        ("wait-published", Some(wait_matches)) => {
+            config.forbid_in_deterministic("The network (wait-published)")?;
+            let manifest = read_manifest(manifest_path)?;
+            let name = manifest["package"]["name"].as_str().ok_or_else(|| {
+                Error::new(
+                    Code::ParseManifest,
+                    format!("{} has no package name", manifest_path),
+                )
+            })?;
+            let version = current_version(&manifest, &config)?;
+            events::parsed(&config, manifest_path, &version);
+            registry::wait(
+                wait_matches.value_of("index").unwrap(),
+                name,
+                &version,
+                Duration::from_secs(number_arg(wait_matches, "timeout")?),
+                Duration::from_secs(number_arg(wait_matches, "interval")?),
+            )?;
+        }
+        ("publish-order", Some(order_matches)) => {
+            let mut crates = workspace::cargo_crates(manifest_path)?;
+            if let Some(since) = order_matches.value_of("changed-since") {
+                config.forbid_in_deterministic("Git (publish-order --changed-since)")?;
+                let dirs = crates
+                    .iter()
+                    .map(|krate| {
+                        Path::new(&krate.path)
+                            .parent()
+                            .unwrap_or_else(|| Path::new(""))
+                    })
+                    .collect::<Vec<_>>();
+                let mut selected = workspace::changed_since(since, &dirs)?;
+                if order_matches.is_present("with-dependents") {
+                    selected = workspace::with_dependents(
+                        selected,
+                        &crates
+                            .iter()
+                            .map(|krate| krate.name.as_str())
+                            .collect::<Vec<_>>(),
+                        &crates
+                            .iter()
+                            .map(|krate| krate.dependencies.clone())
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                let mut selected = selected.into_iter();
+                crates.retain(|_| selected.next().unwrap());
+            }
+            crates.retain(|krate| krate.publish);
+            if let Some(since) = order_matches.value_of("since") {
+                config.forbid_in_deterministic("Git (publish-order --since)")?;
+                let mut changed = vec![];
+                for krate in crates {
+                    let old = git::show(since, &krate.path)
+                        .ok()
+                        .and_then(|contents| scan::package_version(&contents).map(String::from));
+                    if old != Some(krate.version.to_string()) {
+                        changed.push(krate);
+                    }
+                }
+                crates = changed;
+            }
+
+            let ordered = publish::order(&crates)?;
+            joiner.write(stdout, ordered.iter().map(|krate| &krate.name));
+            if order_matches.is_present("publish") {
+                config.forbid_in_deterministic("The network (publish-order --publish)")?;
+                publish::publish_all(
+                    &ordered,
+                    order_matches.value_of("index").unwrap(),
+                    number_arg(order_matches, "retries")?,
+                    Duration::from_secs(number_arg(order_matches, "timeout")?),
+                    Duration::from_secs(number_arg(order_matches, "interval")?),
+                )?;
+            }
+        }
+        ("watch", Some(watch_matches)) => {
+            let fix = watch_matches.is_present("fix");
+            if !watch_matches.is_present("once") {
+                let interval = Duration::from_secs(number_arg(watch_matches, "interval")?);
+                watch::run(manifest_path, &config, fix, interval, stdout);
+            }
+            let drifted = watch::check(manifest_path, &config, fix, stdout)?;
+            if drifted > 0 && !fix {
+                return Err(Error::new(
+                    Code::Drift,
+                    format!(
+                        "{} synced version file(s) drifted from the manifest",
+                        drifted
+                    ),
+                ));
+            }
+        }
+        ("multi", Some(multi_matches)) => match multi_matches.subcommand() {
+            ("bump", Some(bump_matches)) => {
+                config.forbid_in_deterministic("Git (multi bump)")?;
+                let repos = multi::Repos::read(multi_matches.value_of("file").unwrap())?;
+                let pr_branch = if bump_matches.is_present("open-pr") {
+                    bump_matches.value_of("pr-branch")
+                } else {
+                    None
+                };
+                let bumped = multi::bump(
+                    &repos,
+                    &multi::bump_args(bump_matches),
+                    bump_matches.is_present("commit"),
+                    pr_branch,
+                )?;
+                let summary =
+                    bumped
+                        .into_iter()
+                        .map(|(name, version, pull_request)| match pull_request {
+                            Some(url) => format!("{} {} {}", name, version, url),
+                            None => format!("{} {}", name, version),
+                        });
+                joiner.write(stdout, summary);
+            }
+            (_, _) => panic!("Unreachable - a multi subcommand must be specified."),
+        },
+        ("check-engines", Some(engines_matches)) => {
+            let parse = |version: &str| {
+                Version::parse(version.trim_start_matches('v')).map_err(|err| {
+                    Error::new(
+                        Code::InvalidVersion,
+                        format!("Invalid version {}: {}", version, err),
+                    )
+                })
+            };
+            let node = engines_matches.value_of("node").map(parse).transpose()?;
+            let peers = engines_matches
+                .values_of("peer")
+                .unwrap_or_default()
+                .map(|peer| match peer.rsplit_once('@') {
+                    Some((name, version)) if !name.is_empty() => Ok((name, parse(version)?)),
+                    _ => Err(Error::new(
+                        Code::InvalidArgument,
+                        format!("Invalid peer {}, expected name@version.", peer),
+                    )),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let path = match engines_matches.value_of("package-json") {
+                Some(path) => PathBuf::from(path),
+                None => config.root.join("package.json"),
+            };
+            let path = path.to_string_lossy();
+            engines::check(&adapter::read_file(&path)?, &path, node.as_ref(), &peers)?;
+        }
+        ("compare", Some(compare_matches)) => {
+            let parse = |name: &str| ops::parse(compare_matches.value_of(name).unwrap());
+            let (a, b) = (parse("a")?, parse("b")?);
+            let ordering = ops::compare(&a, &b);
+            let (number, word) = match ordering {
+                Ordering::Less => ("-1", "lt"),
+                Ordering::Equal => ("0", "eq"),
+                Ordering::Greater => ("1", "gt"),
+            };
+            match compare_matches.value_of("format") {
+                Some("word") => writeln!(stdout, "{}", word).unwrap(),
+                _ => writeln!(stdout, "{}", number).unwrap(),
+            }
+            if let Some(expect) = compare_matches.value_of("expect") {
+                let holds = match expect {
+                    "lt" => ordering == Ordering::Less,
+                    "le" => ordering != Ordering::Greater,
+                    "eq" => ordering == Ordering::Equal,
+                    "ne" => ordering != Ordering::Equal,
+                    "ge" => ordering != Ordering::Less,
+                    _ => ordering == Ordering::Greater,
+                };
+                if !holds {
+                    return Err(Error::new(
+                        Code::Unsatisfied,
+                        format!("{} is {} {}, not {}", a, word, b, expect),
+                    ));
+                }
+            }
+        }
+        ("check-deployed", Some(deployed_matches)) => {
+            config.forbid_in_deterministic("The network (check-deployed)")?;
+            let expected = match deployed_matches.value_of("expect") {
+                Some(version) => Version::parse(version).map_err(|_| {
+                    Error::new(
+                        Code::InvalidArgument,
+                        format!("Invalid version given: {}", version),
+                    )
+                })?,
+                None => read_manifest_version(manifest_path, &config)?,
+            };
+            let url = deployed_matches.value_of("url").unwrap();
+            let body = deployed::fetch(url)?;
+            let deployed =
+                deployed::version_at(&body, deployed_matches.value_of("json-path").unwrap())
+                    .map_err(|err| Error::new(Code::InvalidVersion, format!("{}: {}", url, err)))?;
+            if let Some(relation) = deployed::relation(&deployed, &expected) {
+                return Err(Error::new(
+                    Code::Deployed,
+                    format!(
+                        "The deployed version {} is {} {}",
+                        deployed, relation, expected
+                    ),
+                ));
+            }
+            writeln!(stdout, "{} is deployed", deployed).unwrap();
+        }
+        ("check", Some(check_matches)) => {
+            let requirement = check_matches.value_of("requirement").unwrap();
+            let requirement = VersionReq::parse(requirement).map_err(|err| {
+                Error::new(
+                    Code::InvalidArgument,
+                    format!("Invalid requirement {}: {}", requirement, err),
+                )
+            })?;
+            let version = match check_matches.value_of("version") {
+                Some(version) => Version::parse(version).map_err(|_| {
+                    Error::new(
+                        Code::InvalidArgument,
+                        format!("Invalid version given: {}", version),
+                    )
+                })?,
+                None => read_manifest_version(manifest_path, &config)?,
+            };
+            if !requirement.matches(&version) {
+                return Err(Error::new(
+                    Code::Unsatisfied,
+                    format!("{} does not satisfy {}", version, requirement),
+                ));
+            }
+            writeln!(stdout, "{} satisfies {}", version, requirement).unwrap();
+        }
+        ("diff-manifests", Some(diff_matches)) => {
+            let a = manifests::Side::parse(diff_matches.value_of("a").unwrap());
+            let b = manifests::Side::parse(diff_matches.value_of("b").unwrap());
+            if a.rev.is_some() || b.rev.is_some() {
+                config.forbid_in_deterministic("Git (diff-manifests ref:path)")?;
+            }
+            let differences = manifests::differences(&a.packages()?, &b.packages()?);
+            match diff_matches.value_of("format") {
+                Some("json") => writeln!(stdout, "{}", manifests::json(&differences)).unwrap(),
+                _ => joiner.write(stdout, manifests::lines(&differences)),
+            }
+        }
+        ("lint", Some(_)) => {
+            let version = read_manifest_version(manifest_path, &config)?;
+            if !version.build.is_empty() {
+                let mut stripped = version.clone();
+                stripped.build.clear();
+                return Err(Error::new(
+                    Code::BuildMetadata,
+                    format!(
+                        "{}: the version {} carries build metadata, which Cargo ignores; use {}",
+                        manifest_path, version, stripped
+                    ),
+                ));
+            }
+        }
+        ("attest", Some(attest_matches)) => {
+            config.forbid_in_deterministic("Git (attest)")?;
+            let builder = match attest_matches.value_of("builder-id") {
+                Some(builder) => builder.to_string(),
+                None => attest::detect_builder().ok_or_else(|| {
+                    Error::new(
+                        Code::InvalidArgument,
+                        "Not running in a known CI system; pass --builder-id",
+                    )
+                })?,
+            };
+            let manifest = read_manifest(manifest_path)?;
+            let provenance = attest::Provenance {
+                name: manifest["package"]["name"]
+                    .as_str()
+                    .unwrap_or(manifest_path)
+                    .to_string(),
+                version: read_manifest_version(manifest_path, &config)?,
+                commit: git::run(&["rev-parse", "HEAD"])?,
+                repository: git::run(&["remote", "get-url", "origin"]).ok(),
+                builder,
+            };
+            let statement = provenance.statement();
+            match attest_matches.value_of("key") {
+                Some(key) => writeln!(stdout, "{}", attest::envelope(&statement, key)?).unwrap(),
+                None => writeln!(stdout, "{}", statement).unwrap(),
+            }
+        }
+        ("stamp", Some(stamp_matches)) => {
+            let version = read_manifest_version(manifest_path, &config)?;
+            match stamp_matches.value_of("env-file") {
+                Some(path) => {
+                    config.stamp.write_env_file(path, &version)?;
+                    events::written(&config, &[path.to_string()]);
+                }
+                None => writeln!(stdout, "{}", config.stamp.ldflags(&version)).unwrap(),
+            }
+        }
+        ("changelog", Some(changelog_matches)) => {
+            config.forbid_in_deterministic("The git history (changelog)")?;
+            let remote = git::run(&["remote", "get-url", "origin"]).ok();
+            let templates = config.links.templates(remote.as_deref());
+            let to = changelog_matches.value_of("to");
+            let from = match changelog_matches.value_of("from") {
+                Some(from) => Some(from.to_string()),
+                None => match to {
+                    Some(to) => auto::tag_before(to),
+                    None => auto::last_tag(),
+                },
+            };
+            let (from, to) = (from.as_deref(), to.unwrap_or("HEAD"));
+            if changelog_matches.is_present("workspace") {
+                let mut transaction = Transaction::new();
+                let mut written = vec![];
+                for (path, pathspecs) in workspace::changelogs(&config)? {
+                    let commits = auto::history(&config, from, to, &pathspecs)?;
+                    let notes = changelog::render(&commits, &config.commit_convention, &templates);
+                    let contents = fs::read_to_string(&path).unwrap_or_default();
+                    transaction.stage(&path, changelog::with_notes(&contents, &notes));
+                    written.push(path);
+                }
+                transaction.commit(|| Ok(()))?;
+                events::written(&config, &written);
+                return Ok(());
+            }
+            let commits = auto::history(&config, from, to, &[])?;
+            write!(
+                stdout,
+                "{}",
+                changelog::render(&commits, &config.commit_convention, &templates)
+            )
+            .unwrap();
+        }
+        ("notes", Some(notes_matches)) => match notes_matches.subcommand() {
+            ("show", Some(show_matches)) => {
+                config.forbid_in_deterministic("Git (notes show)")?;
+                let note = notes::show(show_matches.value_of("tag").unwrap())?;
+                writeln!(stdout, "{}", note).unwrap();
+            }
+            (_, _) => panic!("Unreachable - a notes subcommand must be specified."),
+        },
+        ("hotfix", Some(hotfix_matches)) => match hotfix_matches.subcommand() {
+            ("start", Some(start_matches)) => hotfix::start(manifest_path, start_matches, &config)?,
+            (_, _) => panic!("Unreachable - a hotfix subcommand must be specified."),
+        },
+        ("prune-prereleases", Some(prune_matches)) => {
+            config.forbid_in_deterministic("Git (prune-prereleases)")?;
+            let keep: usize = number_arg(prune_matches, "keep")?;
+            let tags = git::run(&["tag", "--list"])?;
+            let tags = tags.lines().collect::<Vec<_>>();
+            let stale = prune::stale(&tags, prune_matches.value_of("tag-format").unwrap(), keep);
+            if prune_matches.is_present("delete-tags") {
+                prune::delete(&stale, prune_matches.value_of("remote"))?;
+            }
+            joiner.write(stdout, stale);
+        }
+        ("lock", Some(lock_matches)) => match lock_matches.subcommand() {
+            ("report", Some(report_matches)) => {
+                let requirement = report_matches
+                    .value_of("req")
+                    .map(|requirement| {
+                        VersionReq::parse(requirement).map_err(|err| {
+                            Error::new(
+                                Code::InvalidArgument,
+                                format!("Invalid requirement {}: {}", requirement, err),
+                            )
+                        })
+                    })
+                    .transpose()?;
+                let path = match report_matches.value_of("lockfile") {
+                    Some(path) => PathBuf::from(path),
+                    None => lock::find(Path::new(manifest_path)).ok_or_else(|| {
+                        Error::new(
+                            Code::Io,
+                            format!("No Cargo.lock found for {}.", manifest_path),
+                        )
+                    })?,
+                };
+                let names = report_matches
+                    .values_of("package")
+                    .unwrap()
+                    .collect::<Vec<_>>();
+                let report = lock::report(&lock::read(&path)?, &names)?;
+                joiner.write(
+                    stdout,
+                    report
+                        .iter()
+                        .map(|(name, version)| format!("{} {}", name, version)),
+                );
+                if let Some(requirement) = requirement {
+                    lock::check(&report, &requirement)?;
+                }
+            }
+            (_, _) => panic!("Unreachable - a lock subcommand must be specified."),
+        },
+        ("msrv", Some(msrv_matches)) => {
+            let mut manifest = read_manifest(manifest_path)?;
+            let current = msrv::Msrv::read(&manifest)?;
+            let (msrv, matches) = match msrv_matches.subcommand() {
+                ("read", Some(_)) => {
+                    let current = current.ok_or_else(|| {
+                        Error::new(Code::Msrv, "The manifest has no package rust-version")
+                    })?;
+                    writeln!(stdout, "{}", current).unwrap();
+                    return Ok(());
+                }
+                ("set", Some(set_matches)) => (
+                    msrv::Msrv::parse(set_matches.value_of("rust-version").unwrap())?,
+                    set_matches,
+                ),
+                ("bump", Some(bump_matches)) => {
+                    let current = current.ok_or_else(|| {
+                        Error::new(
+                            Code::Msrv,
+                            "The manifest has no package rust-version to bump",
+                        )
+                    })?;
+                    let component = match bump_matches.value_of("component").unwrap() {
+                        "major" => Component::Major,
+                        "minor" => Component::Minor,
+                        _ => Component::Patch,
+                    };
+                    (current.bump(component), bump_matches)
+                }
+                (_, _) => panic!("Unreachable - a msrv subcommand must be specified."),
+            };
+            ensure_unprotected(&config, &manifest, manifest_path)?;
+            let released = match matches.value_of("released") {
+                Some(released) => {
+                    let released = Version::parse(released).map_err(|_| {
+                        Error::new(
+                            Code::InvalidArgument,
+                            format!("Invalid version given: {}", released),
+                        )
+                    })?;
+                    Some((released, current))
+                }
+                None => {
+                    config.forbid_in_deterministic("Git (msrv, pass --released)")?;
+                    match auto::last_tag() {
+                        Some(tag) => {
+                            let released = git::show(&tag, manifest_path)?
+                                .parse::<Document>()
+                                .map_err(|err| {
+                                    Error::new(
+                                        Code::ParseManifest,
+                                        format!("Invalid {} at {}: {}", manifest_path, tag, err),
+                                    )
+                                })?;
+                            Some((read_version(&released)?, msrv::Msrv::read(&released)?))
+                        }
+                        None => None,
+                    }
+                }
+            };
+            if let Some((released, released_msrv)) = released {
+                msrv::check_policy(msrv, &read_version(&manifest)?, &released, released_msrv)?;
+            }
+            manifest["package"]["rust-version"] = value(msrv.to_string());
+            write_synced(manifest, manifest_path, &config, stdout)?;
+        }
+        ("rollback", Some(rollback_matches)) => {
+            let mut manifest = read_manifest(manifest_path)?;
+            let current = read_version(&manifest)?;
+            events::parsed(&config, manifest_path, &current);
+            let version = released_version(manifest_path, rollback_matches, &current, &config)?;
+            events::computed(&config, manifest_path, &current, &version);
+            ensure_unprotected(&config, &manifest, manifest_path)?;
+            enforce_branch_policy(&config, &version)?;
+            manifest["package"]["version"] = value(version.to_string());
+            write_synced(manifest, manifest_path, &config, stdout)?;
+        }
+        ("nightly", Some(nightly_matches)) => {
+            let date = match nightly_matches.value_of("date") {
+                Some(date) => nightly::parse_date(date)
+                    .map_err(|err| Error::new(Code::InvalidArgument, err))?,
+                None => match config.now {
+                    Some(now) => now,
+                    None => {
+                        config.forbid_in_deterministic("The wall clock (pass --date or --now)")?;
+                        nightly::today()
+                    }
+                },
+            };
+            let mut manifest = read_manifest(manifest_path)?;
+            let current = current_version(&manifest, &config)?;
+            events::parsed(&config, manifest_path, &current);
+            let version = nightly::nightly_version(&current, date, &config.limits)
+                .map_err(|err| Error::new(Code::Overflow, err))?;
+            events::computed(&config, manifest_path, &current, &version);
+
+            if nightly_matches.is_present("write") && !is_versionless(&manifest, &config) {
+                ensure_unprotected(&config, &manifest, manifest_path)?;
+                enforce_branch_policy(&config, &version)?;
+                manifest["package"]["version"] = value(version.to_string());
+                write_synced(manifest, manifest_path, &config, stdout)?;
+                if manifest_path == STDIN_MANIFEST {
+                    return Ok(());
+                }
+            }
+            writeln!(stdout, "{}", version).unwrap();
+        }
+        ("import-config", Some(import_matches)) => {
+            let from = import_matches.value_of("from").unwrap();
+            let path = match import_matches.value_of("file") {
+                Some(file) => PathBuf::from(file),
+                None if from == "cargo-release" => config.root.join(import::CARGO_RELEASE_FILE),
+                None => config.root.join(import::SEMANTIC_RELEASE_FILE),
+            };
+            let path = path.to_string_lossy();
+            let contents = adapter::read_file(&path)?;
+            let imported = match from {
+                "cargo-release" => import::cargo_release(&contents, &path)?,
+                _ => import::semantic_release(&contents, &path)?,
+            };
+            write!(stdout, "{}", imported).unwrap();
+        }
+        ("convert", Some(convert_matches)) => {
+            let snapshot_label = convert_matches.value_of("snapshot-label").unwrap();
+            let converted = if let Some(from) = convert_matches.value_of("from") {
+                let foreign = convert_matches.value_of("version").unwrap();
+                match from {
+                    "maven" => convert::from_maven(foreign, snapshot_label),
+                    "pep440" => convert::from_pep440(foreign),
+                    _ => panic!("Unreachable - unsupported convention {}.", from),
+                }
+                .map_err(|err| Error::new(Code::Conversion, err))?
+                .to_string()
+            } else {
+                let EpochVersion { epoch, version } = match convert_matches.value_of("version") {
+                    Some(version) => EpochVersion::parse(version).map_err(|_| {
+                        Error::new(
+                            Code::InvalidArgument,
+                            format!("Invalid version given: {}", version),
+                        )
+                    })?,
+                    None => EpochVersion {
+                        epoch: config.epoch,
+                        version: read_manifest_version(manifest_path, &config)?,
+                    },
+                };
+                let epoch = match convert_matches.value_of("epoch") {
+                    Some(epoch) => Some(epoch.parse::<u64>().map_err(|_| {
+                        Error::new(
+                            Code::InvalidArgument,
+                            format!("Invalid epoch given: {}", epoch),
+                        )
+                    })?),
+                    None if epoch > 0 => Some(epoch),
+                    None => None,
+                };
+                match convert_matches.value_of("to").unwrap() {
+                    "maven" if epoch.is_some() => {
+                        Err(String::from("Maven versions cannot have an epoch"))
+                    }
+                    "maven" => Ok(convert::to_maven(&version, snapshot_label)),
+                    "pep440" => convert::to_pep440(&version).map(|pep440| match epoch {
+                        Some(epoch) => format!("{}!{}", epoch, pep440),
+                        None => pep440,
+                    }),
+                    "debian" => convert::to_debian(
+                        &version,
+                        epoch,
+                        convert_matches.value_of("debian-revision"),
+                    ),
+                    to => panic!("Unreachable - unsupported convention {}.", to),
+                }
+                .map_err(|err| Error::new(Code::Conversion, err))?
+            };
+            writeln!(stdout, "{}", converted).unwrap();
+        }
+        (_, _) => panic!("Unreachable - at least one subcommand must be specified."),
+    };
+
+    Ok(())
+}
+
+/// The command line with the configured `default_command` appended when it
+/// names no subcommand, and with an `[alias]` it names in place of a
+/// subcommand replaced by its command line. Anything else, including
+/// command lines clap rejects, is left as it is for clap to handle.
+fn expand_args(args: Vec<String>) -> Result<Vec<String>, Error> {
+    let lenient = || parser().unset_setting(AppSettings::SubcommandRequiredElseHelp);
+    // Tells options taking a value from flags by whether a subcommand after
+    // them is taken for (or rejected as) their value.
+    let mut position = 1;
+    while position < args.len() && args[position].starts_with('-') {
+        let probe = args[..=position]
+            .iter()
+            .map(String::as_str)
+            .chain(Some("errors"));
+        position += match lenient().get_matches_from_safe(probe) {
+            Ok(ref matches) if matches.subcommand_name().is_some() => 1,
+            _ => 2,
+        };
+    }
+    let manifest_path = match lenient().get_matches_from_safe(&args[..position.min(args.len())]) {
+        Ok(matches) => matches.value_of("manifest-path").unwrap().to_string(),
+        Err(_) => return Ok(args),
+    };
+    let config = Config::load(&manifest_path)?;
+    let mut args = args;
+    if position >= args.len() {
+        args.extend(config.default_command.iter().cloned());
+    }
+    let alias = match args.get(position).and_then(|name| config.alias(name)) {
+        Some(alias) => alias,
+        None => return Ok(args),
+    };
+    match lenient().get_matches_from_safe(&args[..=position]) {
+        Err(ref err)
+            if err.kind == clap::ErrorKind::UnknownArgument
+                || err.kind == clap::ErrorKind::UnrecognizedSubcommand => {}
+        _ => return Ok(args),
+    }
+    let given = args.split_off(position + 1);
+    args.pop();
+    args.extend(merge_alias(alias, &given));
+
+    Ok(args)
+}
+
+/// The command line of an alias followed by the arguments `given` with it,
+/// without the alias's options that are given again (and their values).
+fn merge_alias(alias: &[String], given: &[String]) -> Vec<String> {
+    let name = |word: &str| -> Option<String> {
+        if !word.starts_with("--") {
+            return None;
+        }
+        Some(word.split('=').next().unwrap().to_string())
+    };
+    let overridden = given
+        .iter()
+        .filter_map(|word| name(word))
+        .collect::<Vec<_>>();
+    let mut merged = vec![];
+    let mut words = alias.iter().peekable();
+    while let Some(word) = words.next() {
+        match name(word) {
+            Some(option) if overridden.contains(&option) => {
+                if !word.contains('=') && words.peek().is_some_and(|next| !next.starts_with('-')) {
+                    words.next();
+                }
+            }
+            _ => merged.push(word.clone()),
+        }
+    }
+    merged.extend(given.iter().cloned());
+
+    merged
+}
+
+/// The line `err` is reported with: its JSON record, or a colored
+/// `error[CODE]: message` line only if `color` (a `--color` value) is
+/// `always`, so tools reading stderr never get anything but JSON unasked.
+fn error_line(err: &Error, exit_code: i32, color: &str) -> String {
+    if color == "always" {
+        let prefix = format!("error[{}]:", err.code.name());
+        format!("{} {}", style::paint(true, style::RED, &prefix), err)
+    } else {
+        err.to_json_exiting(exit_code)
+    }
+}
+
+/// The exit code a run that failed with `err` ends with: the code's own,
+/// except under `read --strict`, whose contract is 2 for an empty component
+/// and 1 for any other failure.
+fn exit_code(err: &Error, matches: &ArgMatches) -> i32 {
+    match matches.subcommand_matches("read") {
+        Some(read_matches) if read_matches.is_present("strict") => match err.code {
+            Code::EmptyComponent => 2,
+            _ => 1,
+        },
+        _ => err.code.exit_code(),
+    }
+}
+
+/// Reports `err` on stderr and exits with `exit_code`.
+fn fail(err: Error, exit_code: i32, color: &str) -> ! {
+    eprintln!("{}", error_line(&err, exit_code, color));
+    process::exit(exit_code);
+}
+
+/// Runs the command line interface on the process's arguments; failures
+/// are reported on stderr as JSON, or as a colored `error[CODE]: message`
+/// line with `--color always`, and mapped to their stable exit code. The
+/// `semvercli` binary is just this.
+pub fn run() {
+    let args = expand_args(env::args().collect()).unwrap_or_else(|err| {
+        let exit_code = err.code.exit_code();
+        fail(err, exit_code, "auto")
+    });
+    let matches = parser().get_matches_from(args);
+
+    if let Err(err) = execute(&matches, &mut io::stdout()) {
+        let exit_code = exit_code(&err, &matches);
+        fail(err, exit_code, matches.value_of("color").unwrap());
+    }
+}
+
+
+/// Property tests to validate read/bump behavior;
+/// these are probably a massiver overkill given the simplicity of the implementation above,
+/// but it's a useful demonstration/exercise in using proptest.
+///
+/// The current tests only ensure that the correct operation is performed given valid input for
+/// the corresponding subcommand and work almost end to end - both start with a valid command line
+/// input and assert on the final file/stream outputs (i.e. the `bump` makes use of tempdir, and `read`
+/// just passes in a vector for stdout). It would be nice to eventually lift and combine those and demonstrate
+/// verifying the full command from start to finish against more fuzzed inputs.
+#[cfg(test)]
+mod test {
+    use proptest::option::of;
+    use proptest::prelude::*;
+    use toml_edit::{Document, Item, Table, value};
+    use semver::{Version, Identifier};
+    use tempfile::tempdir;
+
+    use std::convert::TryFrom;
+    use std::fs::File;
+    use std::str;
+
+    use super::*;
+
+    /// Enum of operations that can be performed
+    /// by each subcommand; generating the CLI
+    /// convenience
+    #[derive(Debug, Clone)]
+    enum Op {
+        Major,
+        Minor,
+        Patch,
+        Pre(String),
+        Build(String),
+        Version(String)
+    }
+
+    /// Runs the command line `args` on the manifest at `manifest`, returning
+    /// what was printed on stdout.
+    fn run_on(manifest: &Path, args: &[&str]) -> Result<String, Error> {
+        let mut cli_args = vec!["semvercli", "--manifest-path", manifest.to_str().unwrap()];
+        cli_args.extend_from_slice(args);
+        let mut stdout = Vec::new();
+        execute(&parser().get_matches_from(cli_args), &mut stdout).map(|_| String::from_utf8(stdout).unwrap())
+    }
+
+    prop_compose! {
+        /// Metadata generation strategy that outputs semver parsed metadata labels. Both the
+        /// build and prerelease labels conform to the same format, so only one strategy is needed.
+        // Proptest doesn't seem to support the character classes from the regex crate, such as
+        // the [[:alphanum:]] class
+        fn metadata_strat()(label in r"[a-zA-Z0-9]+(\.[a-zA-Z0-9]+)*") -> Vec<Identifier> {
+            dbg!(label.clone());
+            VersionMetadata::try_from(label.as_str()).unwrap().0
+        }
+    }
+
+    prop_compose! {
+        /// Semantic version genration strategy; the only interesting thing here is making use
+        /// of `proptest::option::of` to encode that build and prerelease labels are not always
+        /// present.
+        fn version_strat()(major in any::<u64>(),
+                     minor in any::<u64>(),
+                     patch in any::<u64>(),
+                     pre in of(metadata_strat()),
+                     build in of(metadata_strat())) -> Version {
+            Version {
+                major, minor, patch,
+                pre: pre.unwrap_or(vec![]),
+                build: build.unwrap_or(vec![])
+            }
+        }
+    }
+
+    prop_compose! {
+        /// Manifest generation strategy; currently it will always be valid, in the
+        /// sense of being valid TOML (since the output is a `toml_edit::Document`) and
+        /// containing a `[package]` table which has a `version` key that maps to a
+        /// valid semantic version string.
+        fn manifest_strat()(version in version_strat()) -> Document {
+            let mut manifest = Document::new();
+            manifest["package"] = Item::Table(Table::new());
+            manifest["package"]["version"] = value(version.to_string());
+
+            return manifest
+        }
+    }
+
+    /// Simple operation generation strategy that is shared by tests to both `bump`
+    /// and `read`; in the case of `bump` the values for metadata labels and version
+    /// are simply ignored.
+    fn op_strat() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            Just(Op::Major),
+            Just(Op::Minor),
+            Just(Op::Patch),
+            metadata_strat()
+                .prop_map(|p| Op::Pre(String::from(VersionMetadata(p)))),
+            metadata_strat()
+                .prop_map(|b| Op::Build(String::from(VersionMetadata(b)))),
+            version_strat()
+                .prop_map(|v| Op::Version(v.to_string()))
+        ]
+    }
+
+    proptest! {
+        /// Tests that given valid inputs to the bump subcommand and a valid manifest file
+        /// the manifest file is updated with the correct version string.
+        /// The test works by essentially reimplementing the logic of `bump` as minimalistically as possible -
+        /// it just matches each op to an effect, and using a temp. file to validate the file manipulation performed
+        /// by the subcomand.
+        #[test]
+        fn test_bump(manifest in manifest_strat(), op in op_strat()) {
+            let tmpdir = tempdir().unwrap();
+            let tmp_path = tmpdir.path().join("Cargo.toml");
+            let manifest_path = tmp_path.to_str().unwrap();
+            File::create(tmp_path.clone()).unwrap();
+
+            let old_version = read_version(&manifest).unwrap();
+
+            let mut cli_args = vec!["version-bump",
+                                    "--manifest-path",
+                                    manifest_path,
+                                    "bump"];
+
+            cli_args.extend_from_slice(
+                match op {
+                    Op::Major => vec!["--major"],
+                    Op::Minor => vec!["--minor"],
+                    Op::Patch => vec!["--patch"],
+                    Op::Pre(ref pre) => vec!["--pre", pre.as_str()],
+                    Op::Build(ref build) => vec!["--build", build.as_str()],
+                    Op::Version(ref version) => vec!["--version", version.as_str()],
+                }.as_slice());
+
+            write_manifest(manifest, manifest_path).unwrap();
+
+            let matches = parser().get_matches_from(cli_args.as_slice());
+            let mut stdout = Vec::new();
+
+            execute(&matches, &mut stdout).unwrap();
+
+            let bumped_manifest = read_manifest(manifest_path).unwrap();
+            let bumped_version = read_version(&bumped_manifest).unwrap();
+
+
+            match op {
+                Op::Major => assert_eq!(old_version.major + 1, bumped_version.major),
+                Op::Minor => assert_eq!(old_version.minor + 1, bumped_version.minor),
+                Op::Patch => assert_eq!(old_version.patch + 1, bumped_version.patch),
+                Op::Pre(pre) => assert_eq!(pre,
+                                           String::from(VersionMetadata(bumped_version.pre))),
+                Op::Build(build) => assert_eq!(build,
+                                               String::from(VersionMetadata(bumped_version.build))),
+                Op::Version(version) => assert_eq!(version, bumped_version.to_string()),
+            };
+        }
+
+        /// Tests that given valid inputs to read the correct version component is written
+        /// to `stdout`. It does so by reimplementing the minimum amount of logic from `read` to
+        /// parse the component out of the input version and compare to what was written to `stdout`.
+        #[test]
+        fn test_read(manifest in manifest_strat(), op in op_strat()) {
+            let tmpdir = tempdir().unwrap();
+            let tmp_path = tmpdir.path().join("Cargo.toml");
+            let manifest_path = tmp_path.to_str().unwrap();
+            File::create(tmp_path.clone()).unwrap();
+
+            let version = read_version(&manifest).unwrap();
+
+            let mut cli_args = vec!["version-bump",
+                                    "--manifest-path",
+                                    manifest_path,
+                                    "read"];
+
+            cli_args.extend_from_slice(
+                match op {
+                    Op::Major => &["--major"],
+                    Op::Minor => &["--minor"],
+                    Op::Patch => &["--patch"],
+                    Op::Pre(_) => &["--pre"],
+                    Op::Build(_) => &["--build"],
+                    Op::Version(_) => &["--version"]
+                });
+
+            write_manifest(manifest, manifest_path).unwrap();
+
+            let matches = parser().get_matches_from(cli_args.as_slice());
+            let mut stdout = Vec::new();
+
+            execute(&matches, &mut stdout).unwrap();
+
+            let expected = match op {
+                Op::Major => format!("{}\n", version.major),
+                Op::Minor => format!("{}\n", version.minor),
+                Op::Patch => format!("{}\n", version.patch),
+                Op::Pre(_) if version.pre.is_empty() => format!("{}\n", EMPTY_COMPONENT),
+                Op::Pre(_) => format!("{}\n",
+                                      String::from(VersionMetadata(version.pre))),
+                Op::Build(_) if version.build.is_empty() => format!("{}\n", EMPTY_COMPONENT),
+                Op::Build(_) => format!("{}\n",
+                                        String::from(VersionMetadata(version.build))),
+                Op::Version(_) => format!("{}\n",
+                                          version)
+            };
+
+            assert_eq!(str::from_utf8(&stdout).unwrap(), expected.as_str());
+        }
+    }
+
+    /// Tests that conditional bumps are no-ops when their condition does not hold.
+    #[test]
+    fn test_conditional_bump() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3-rc.1\"\n").unwrap();
+
+        run_on(&manifest, &["bump", "--patch", "--if-stable"]).unwrap();
+        run_on(&manifest, &["bump", "--pre", "rc.2", "--if-pre"]).unwrap();
+
+        assert_eq!(run_on(&manifest, &["read", "--version"]).unwrap(), "1.2.3-rc.2\n");
+    }
+
+    /// Tests that empty components are printed as a placeholder, as the empty
+    /// string with `--raw`, and are an error with `--fail-if-empty` or
+    /// `--strict` that other failures can be told apart from, by their own
+    /// exit codes or by `--strict`'s 2 and 1.
+    #[test]
+    fn test_read_empty() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let read = |args: &[&str]| run_on(&manifest, &[&["read", "--pre"], args].concat());
+
+        assert_eq!(read(&[]).unwrap(), "<none>\n");
+        assert_eq!(read(&["--raw"]).unwrap(), "\n");
+        assert_eq!(read(&["--fail-if-empty"]).unwrap_err().code, Code::EmptyComponent);
+        assert_eq!(read(&["--strict"]).unwrap_err().code, Code::EmptyComponent);
+        fs::write(&manifest, "[package]\nversion = \"1.2.3-rc.1\"\n").unwrap();
+        assert_eq!(read(&["--strict"]).unwrap(), "rc.1\n");
+        fs::write(&manifest, "[package]\nversion = \"1.2\"\n").unwrap();
+        assert_eq!(read(&["--strict"]).unwrap_err().code, Code::InvalidVersion);
+
+        let exit_code = |args: &[&str], code: Code| {
+            let matches = parser().get_matches_from([&["semvercli", "read", "--pre"], args].concat());
+            super::exit_code(&Error::new(code, "failed"), &matches)
+        };
+        assert_eq!(exit_code(&["--strict"], Code::EmptyComponent), 2);
+        assert_eq!(exit_code(&["--strict"], Code::ReadManifest), 1);
+        assert_eq!(exit_code(&["--strict"], Code::InvalidVersion), 1);
+        assert_eq!(exit_code(&["--fail-if-empty"], Code::EmptyComponent), 16);
+        assert_eq!(exit_code(&["--fail-if-empty"], Code::ReadManifest), 2);
+    }
+
+    /// Tests that `read --format json` prints the chosen component, or all
+    /// of them, as a JSON object of raw values.
+    #[test]
+    fn test_read_json() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3-rc.1+abc\"\n").unwrap();
+        let read = |args: &[&str]| run_on(&manifest, &[&["read", "--format", "json"], args].concat());
+
+        assert_eq!(read(&["--major"]).unwrap(), "{\"major\": 1}\n");
+        assert_eq!(read(&["--pre"]).unwrap(), "{\"pre\": \"rc.1\"}\n");
+        assert_eq!(read(&["--version"]).unwrap(), "{\"version\": \"1.2.3-rc.1+abc\"}\n");
+        assert!(read(&["--all"])
+            .unwrap()
+            .contains("\"major\": 1, \"minor\": 2, \"patch\": 3, \"pre\": \"rc.1\", \"build\": \"abc\""));
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        assert_eq!(read(&["--build"]).unwrap(), "{\"build\": \"\"}\n");
+        assert_eq!(read(&["--build", "--strict"]).unwrap_err().code, Code::EmptyComponent);
+        assert_eq!(read(&["--locate"]).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests that read and bump work on a package.json manifest, detected
+    /// by name or chosen with --manifest-format.
+    #[test]
+    fn test_package_json_manifest() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("package.json");
+        fs::write(&manifest, "{\n  \"name\": \"app\",\n  \"version\": \"1.2.3\"\n}\n").unwrap();
+
+        assert_eq!(run_on(&manifest, &["read", "--version"]).unwrap(), "1.2.3\n");
+        run_on(&manifest, &["bump", "--minor"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.3.0\"\n}\n"
+        );
+        assert!(run_on(&manifest, &["read", "--all", "--format", "json"]).unwrap().contains("\"minor\": 3"));
+        assert_eq!(run_on(&manifest, &["stamp", "--ldflags"]).unwrap_err().code, Code::InvalidArgument);
+
+        let renamed = tmpdir.path().join("manifest.json");
+        fs::rename(&manifest, &renamed).unwrap();
+        run_on(&renamed, &["--manifest-format", "json", "read", "--minor"]).unwrap();
+        assert_eq!(
+            run_on(&renamed, &["--manifest-format", "auto", "read", "--minor"]).unwrap_err().code,
+            Code::ParseManifest
+        );
+    }
+
+    /// Tests that read and bump work on a pyproject.toml manifest, detected
+    /// by name.
+    #[test]
+    fn test_pyproject_manifest() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("pyproject.toml");
+        fs::write(&manifest, "[tool.poetry]\nname = \"app\"\nversion = \"0.4.1\"\n").unwrap();
+
+        assert_eq!(run_on(&manifest, &["read", "--version"]).unwrap(), "0.4.1\n");
+        run_on(&manifest, &["bump", "--patch"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "[tool.poetry]\nname = \"app\"\nversion = \"0.4.2\"\n"
+        );
+        assert_eq!(run_on(&manifest, &["lint"]).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests that bump --expect-hash takes the hash read --locate --hash
+    /// prints, and refuses to write once the manifest changed.
+    #[test]
+    fn test_expect_hash() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+
+        let location = run_on(&manifest, &["read", "--locate", "--hash"]).unwrap();
+        let hash = location.trim().rsplit(' ').next().unwrap().to_string();
+        assert_eq!(location, format!("{}:2:12: 1.2.3 {}\n", manifest.display(), hash));
+        assert_eq!(hash, format!("sha256:{}", sha256::file_hex(manifest.to_str().unwrap()).unwrap()));
+        run_on(&manifest, &["bump", "--minor", "--expect-hash", &hash]).unwrap();
+        assert_eq!(
+            run_on(&manifest, &["bump", "--minor", "--expect-hash", &hash]).unwrap_err().code,
+            Code::StaleManifest
+        );
+        assert_eq!(run_on(&manifest, &["read", "--version"]).unwrap(), "1.3.0\n");
+    }
+
+    /// Tests that read and bump work on a plain version file, detected by
+    /// name or chosen with --manifest-format plain.
+    #[test]
+    fn test_plain_manifest() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("version.txt");
+        fs::write(&manifest, "v1.2.3\n").unwrap();
+
+        assert_eq!(run_on(&manifest, &["read", "--version"]).unwrap(), "1.2.3\n");
+        run_on(&manifest, &["bump", "--major"]).unwrap();
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "v2.0.0\n");
+
+        let renamed = tmpdir.path().join("RELEASE");
+        fs::rename(&manifest, &renamed).unwrap();
+        run_on(&renamed, &["--manifest-format", "plain", "bump", "--pre", "rc.1"]).unwrap();
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "v2.0.0-rc.1\n");
+    }
+
+    /// Tests that check matches the manifest's version, or the given one,
+    /// against the requirement.
+    #[test]
+    fn test_check() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.4.2\"\n").unwrap();
+        let check = |args: &[&str]| run_on(&manifest, &[&["check"], args].concat());
+
+        assert_eq!(
+            check(&["--requirement", "^1.2"]).unwrap(),
+            "1.4.2 satisfies ^1.2\n"
+        );
+        assert_eq!(
+            check(&["--requirement", ">= 1.5"]).unwrap_err().code,
+            Code::Unsatisfied
+        );
+        assert_eq!(
+            check(&["--requirement", "^1.2", "--version", "2.0.0"])
+                .unwrap_err()
+                .code,
+            Code::Unsatisfied
+        );
+        assert_eq!(
+            check(&["--requirement", "^1.2", "--version", "1.2.0"]).unwrap(),
+            "1.2.0 satisfies ^1.2\n"
+        );
+        assert_eq!(
+            check(&["--requirement", "^one"]).unwrap_err().code,
+            Code::InvalidArgument
+        );
+    }
+
+    /// Tests that component bumps reset the lower components, pre-release
+    /// and build metadata unless [reset] or the --keep-* flags keep them.
+    #[test]
+    fn test_bump_keep() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let bump = |args: &[&str]| {
+            fs::write(&manifest, "[package]\nversion = \"1.2.3-rc.1+abc\"\n").unwrap();
+            run_on(&manifest, &[&["bump"], args].concat()).unwrap();
+            read_manifest_version(manifest.to_str().unwrap(), &Config::default()).unwrap().to_string()
+        };
+
+        assert_eq!(bump(&["--minor"]), "1.3.0");
+        assert_eq!(bump(&["--minor", "--keep-pre"]), "1.3.0-rc.1");
+        assert_eq!(bump(&["--major", "--keep-lower", "--keep-build"]), "2.2.3+abc");
+        assert_eq!(bump(&["--pre", "rc.2", "--keep-lower"]), "1.2.3-rc.2+abc");
+
+        fs::write(tmpdir.path().join(CONFIG_FILE), "[reset]\npre = false\n").unwrap();
+        assert_eq!(bump(&["--patch"]), "1.2.4-rc.1");
+    }
+
+    /// Tests that lint and forbid_build_metadata keep build metadata out of
+    /// the manifest, and that --strip-build-on-bump drops it.
+    #[test]
+    fn test_build_metadata() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3+abc\"\n").unwrap();
+        let run = |args: &[&str]| run_on(&manifest, args);
+
+        assert_eq!(run(&["lint"]).unwrap_err().code, Code::BuildMetadata);
+        run(&["bump", "--pre", "rc.1"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "[package]\nversion = \"1.2.3-rc.1+abc\"\n"
+        );
+        fs::write(
+            tmpdir.path().join(CONFIG_FILE),
+            "forbid_build_metadata = true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            run(&["bump", "--pre", "rc.2"]).unwrap_err().code,
+            Code::BuildMetadata
+        );
+        run(&["bump", "--pre", "rc.2", "--strip-build-on-bump"]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "[package]\nversion = \"1.2.3-rc.2\"\n"
+        );
+        run(&["lint"]).unwrap();
+    }
+
+    /// Tests that --deny-warnings turns a warning into a failure before
+    /// anything is written, while without it the bump goes ahead.
+    #[test]
+    fn test_deny_warnings() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3+abc\"\n").unwrap();
+
+        let err = run_on(&manifest, &["--deny-warnings", "bump", "--pre", "rc.1"]).unwrap_err();
+        assert_eq!(err.code, Code::Warning);
+        assert!(err.message.contains("W_BUILD_METADATA"));
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "[package]\nversion = \"1.2.3+abc\"\n");
+        run_on(&manifest, &["--deny-warnings", "bump", "--pre", "rc.1", "--strip-build-on-bump"]).unwrap();
+        run_on(&manifest, &["bump", "--build", "def"]).unwrap();
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "[package]\nversion = \"1.2.3-rc.1+def\"\n");
+    }
+
+    /// Tests that bump --commit is refused before anything is written when
+    /// there is nothing to commit or git may not be used.
+    #[test]
+    fn test_bump_commit_refused() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let run = |args: &[&str]| run_on(&manifest, args).unwrap_err().code;
+
+        fs::write(&manifest, "[package]\nname = \"app\"\n").unwrap();
+        assert_eq!(run(&["--assume-version", "1.0.0", "bump", "--minor", "--commit"]), Code::InvalidArgument);
+        fs::write(&manifest, "[package]\nversion = \"1.0.0\"\n").unwrap();
+        assert_eq!(run(&["--deterministic", "bump", "--minor", "--commit"]), Code::Nondeterministic);
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "[package]\nversion = \"1.0.0\"\n");
+    }
+
+    /// Tests that compare orders by precedence, ignoring build metadata,
+    /// and fails unless the expected relation holds.
+    #[test]
+    fn test_compare() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let compare = |args: &[&str]| run_on(&manifest, &[&["compare"], args].concat());
+
+        assert_eq!(compare(&["1.2.3", "1.10.0"]).unwrap(), "-1\n");
+        assert_eq!(compare(&["v1.2.3", "1.2.3+build.5"]).unwrap(), "0\n");
+        assert_eq!(compare(&["1.2.3", "1.2.3-rc.1"]).unwrap(), "1\n");
+        assert_eq!(
+            compare(&["--format", "word", "1.2.3-rc.2", "1.2.3-rc.10"]).unwrap(),
+            "lt\n"
+        );
+        assert_eq!(compare(&["--expect", "ge", "2.0.0", "1.9.9"]).unwrap(), "1\n");
+        assert_eq!(
+            compare(&["--expect", "gt", "1.2.3", "1.2.3"]).unwrap_err().code,
+            Code::Unsatisfied
+        );
+        assert_eq!(
+            compare(&["1.2", "1.2.3"]).unwrap_err().code,
+            Code::InvalidVersion
+        );
+    }
+
+    /// Tests that deterministic mode refuses the wall clock but accepts an
+    /// explicit `--now`.
+    #[test]
+    fn test_deterministic() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let nightly = |args: &[&str]| run_on(&manifest, &[&["--deterministic"], args, &["nightly"]].concat());
+
+        assert_eq!(nightly(&[]).unwrap_err().code, Code::Nondeterministic);
+        assert_eq!(nightly(&["--now", "2024-03-12"]).unwrap(), "1.3.0-nightly.20240312\n");
+    }
+
+    /// Tests that sync targets are bumped together with the manifest.
+    #[test]
+    fn test_bump_syncs() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let podspec_path = tmpdir.path().join("MySDK.podspec");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        fs::write(tmpdir.path().join(CONFIG_FILE), "sync = [\"MySDK.podspec\"]\n").unwrap();
+        fs::write(&podspec_path, "Pod::Spec.new do |s|\n  s.version = '1.2.3'\nend\n").unwrap();
+
+        run_on(&manifest, &["bump", "--minor"]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&podspec_path).unwrap(),
+            "Pod::Spec.new do |s|\n  s.version = '1.3.0'\nend\n"
+        );
+    }
+
+    /// Tests that versionless manifests are read and bumped with
+    /// `--assume-version`, and never written.
+    #[test]
+    fn test_assume_version() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nname = \"tool\"\npublish = false\n").unwrap();
+        let run = |args: &[&str]| run_on(&manifest, args);
+
+        assert_eq!(run(&["read", "--version"]).unwrap_err().code, Code::InvalidVersion);
+        assert_eq!(run(&["--assume-version", "0.0.0", "read", "--version"]).unwrap(), "0.0.0\n");
+        assert_eq!(run(&["--assume-version", "0.0.0", "bump", "--minor"]).unwrap(), "0.1.0\n");
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "[package]\nname = \"tool\"\npublish = false\n"
+        );
+        assert_eq!(run(&["--assume-version", "x", "read", "--version"]).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests that the trailing numeric build identifier is incremented, or
+    /// appended if missing.
+    #[test]
+    fn test_build_number() {
+        let next = |version: &str| {
+            let build = Version::parse(version).unwrap().build;
+            next_number(&build).map(|build| String::from(VersionMetadata(build)))
+        };
+
+        assert_eq!(next("1.0.0+build.41"), Some(String::from("build.42")));
+        assert_eq!(next("1.0.0+build"), Some(String::from("build.1")));
+        assert_eq!(next("1.0.0"), Some(String::from("1")));
+        assert_eq!(next("1.0.0+7.sha"), Some(String::from("7.sha.1")));
+        assert_eq!(next("1.0.0+18446744073709551615"), None);
+    }
+
+    /// Tests that `--pre` without a value increments the trailing number of
+    /// the pre-release, that a stable version has none to increment, and
+    /// that `plan` still finds its file after a lone `--pre`.
+    #[test]
+    fn test_pre_number() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let bump = |version: &str, args: &[&str]| {
+            fs::write(&manifest, format!("[package]\nversion = \"{}\"\n", version)).unwrap();
+            run_on(&manifest, &[&["bump"], args].concat())
+                .map(|_| read_version(&read_manifest(manifest.to_str().unwrap()).unwrap()).unwrap().to_string())
+        };
+
+        assert_eq!(bump("1.2.0-rc.1", &["--pre"]).unwrap(), "1.2.0-rc.2");
+        assert_eq!(bump("1.2.0-rc", &["--pre"]).unwrap(), "1.2.0-rc.1");
+        assert_eq!(bump("1.2.0-beta.2.exp", &["--pre", "--if-pre"]).unwrap(), "1.2.0-beta.2.exp.1");
+        assert_eq!(bump("1.2.0-rc.1", &["--pre", "beta.1"]).unwrap(), "1.2.0-beta.1");
+        assert_eq!(bump("1.2.0", &["--pre"]).unwrap_err().code, Code::InvalidVersion);
+
+        let plan = tmpdir.path().join("release.plan");
+        let plan_path = plan.to_str().unwrap();
+        fs::write(&manifest, "[package]\nversion = \"1.2.0-rc.1\"\n").unwrap();
+        run_on(&manifest, &["plan", "--pre", plan_path]).unwrap();
+        assert_eq!(plan::read_plan(plan_path).unwrap().files[0].new.to_string(), "1.2.0-rc.2");
+        run_on(&manifest, &["plan", "--pre", "beta.1", plan_path]).unwrap();
+        assert_eq!(plan::read_plan(plan_path).unwrap().files[0].new.to_string(), "1.2.0-beta.1");
+        run_on(&manifest, &["plan", plan_path, "--pre"]).unwrap();
+        assert_eq!(plan::read_plan(plan_path).unwrap().files[0].new.to_string(), "1.2.0-rc.2");
+    }
+
+    /// Tests that a manifest read from stdin is written to stdout, and that
+    /// commands which need the manifest on disk refuse it.
+    #[test]
+    fn test_stdin_manifest() {
+        let manifest = "[package]\nname = \"app\" # kept\nversion = \"1.2.3\"\n"
+            .parse::<Document>()
+            .unwrap();
+        let mut stdout = Vec::new();
+        write_synced(manifest, STDIN_MANIFEST, &Config::default(), &mut stdout).unwrap();
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            "[package]\nname = \"app\" # kept\nversion = \"1.2.3\"\n"
+        );
+
+        let matches = parser().get_matches_from(vec!["semvercli", "--manifest-path", "-", "plan", "plan.toml", "--minor"]);
+        assert_eq!(execute(&matches, &mut Vec::new()).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests that configured version fields are read and bumped without
+    /// touching the manifest.
+    #[test]
+    fn test_fields() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        fs::write(
+            tmpdir.path().join(CONFIG_FILE),
+            "[fields]\napp = { file = \"Chart.yaml\", key = \"appVersion\" }\n",
+        )
+        .unwrap();
+        fs::write(tmpdir.path().join("Chart.yaml"), "version: 0.1.0\nappVersion: 2.0.0\n").unwrap();
+        let run = |args: &[&str]| run_on(&manifest, args);
+
+        run(&["bump", "--field", "app", "--minor"]).unwrap();
+        assert_eq!(run(&["read", "--field", "app", "--version"]).unwrap(), "2.1.0\n");
+        assert_eq!(run(&["read", "--version"]).unwrap(), "1.2.3\n");
+        assert_eq!(
+            fs::read_to_string(tmpdir.path().join("Chart.yaml")).unwrap(),
+            "version: 0.1.0\nappVersion: 2.1.0\n"
+        );
+        assert_eq!(run(&["read", "--field", "web", "--version"]).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests that rollbacks only go back to older versions.
+    #[test]
+    fn test_rollback_newer() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.4.0\"\n").unwrap();
+
+        for to in ["1.4.0", "2.0.0"].iter() {
+            assert_eq!(run_on(&manifest, &["rollback", "--to", to]).unwrap_err().code, Code::InvalidArgument);
+        }
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "[package]\nversion = \"1.4.0\"\n");
+    }
+
+    /// Tests that `bump --channel` moves the version along the channel.
+    #[test]
+    fn test_bump_channel() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let bump = |channel: &str| {
+            run_on(&manifest, &["bump", "--channel", channel]).unwrap();
+            run_on(&manifest, &["read", "--version"]).unwrap().trim_end().to_string()
+        };
+
+        assert_eq!(bump("beta"), "1.2.4-beta.1");
+        assert_eq!(bump("beta"), "1.2.4-beta.2");
+        assert_eq!(bump("rc"), "1.2.4-rc.1");
+        assert_eq!(bump("stable"), "1.2.4");
+    }
+
+    /// Tests that `--color` styles the api-diff explanation only when asked,
+    /// and that errors stay JSON unless `--color always` is given.
+    #[test]
+    fn test_color() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let old = tmpdir.path().join("old.txt");
+        let new = tmpdir.path().join("new.txt");
+        fs::write(&old, "pub fn a()\n").unwrap();
+        fs::write(&new, "pub fn b()\n").unwrap();
+        let run = |color: &str| {
+            let args = ["--color", color, "api-diff", "--old", old.to_str().unwrap(), "--new", new.to_str().unwrap(), "--explain"];
+            run_on(&manifest, &args).unwrap()
+        };
+
+        assert_eq!(run("never"), "major\n- pub fn a()\n+ pub fn b()\n");
+        assert_eq!(
+            run("always"),
+            "\x1b[1mmajor\x1b[0m\n\x1b[31m- pub fn a()\x1b[0m\n\x1b[32m+ pub fn b()\x1b[0m\n"
+        );
+
+        let err = Error::new(Code::InvalidArgument, "bad");
+        assert_eq!(error_line(&err, 1, "auto"), err.to_json_exiting(1));
+        assert_eq!(error_line(&err, 1, "always"), "\x1b[31merror[E_INVALID_ARGUMENT]:\x1b[0m bad");
+    }
+
+    /// Tests that `watch --once` reports drifted synced files and fixes them.
+    #[test]
+    fn test_watch_once() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let package_path = tmpdir.path().join("package.json");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        fs::write(tmpdir.path().join(CONFIG_FILE), "sync = [\"package.json\"]\n").unwrap();
+        fs::write(&package_path, "{\"version\": \"1.2.0\"}\n").unwrap();
+
+        assert_eq!(run_on(&manifest, &["watch", "--once"]).unwrap_err().code, Code::Drift);
+        assert!(run_on(&manifest, &["watch", "--once", "--fix"]).unwrap().contains("is at 1.2.0, but"));
+        assert_eq!(fs::read_to_string(&package_path).unwrap(), "{\"version\": \"1.2.3\"}\n");
+        assert_eq!(run_on(&manifest, &["watch", "--once"]).unwrap(), "");
+    }
+
+    /// Tests that `bump --dry-run` previews every file it would write,
+    /// as one diff or one patch per file, and writes none of them.
+    #[test]
+    fn test_bump_dry_run() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        let patch_dir = tmpdir.path().join("patches");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        fs::write(tmpdir.path().join(CONFIG_FILE), "sync = [\"package.json\"]\n").unwrap();
+        fs::write(tmpdir.path().join("package.json"), "{\"version\": \"1.2.3\"}\n").unwrap();
+        let bump = |args: &[&str]| run_on(&manifest, &[&["bump", "--minor", "--dry-run"], args].concat());
+        let package_patch = "--- a/package.json\n+++ b/package.json\n@@ -1 +1 @@\n-{\"version\": \"1.2.3\"}\n+{\"version\": \"1.3.0\"}\n";
+        let manifest_patch = "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,2 +1,2 @@\n [package]\n-version = \"1.2.3\"\n+version = \"1.3.0\"\n";
+
+        assert_eq!(bump(&[]).unwrap(), format!("{}{}", package_patch, manifest_patch));
+        let emitted = bump(&["--emit-patch-dir", patch_dir.to_str().unwrap()]).unwrap();
+        let first = patch_dir.join("0001-package.json.patch");
+        let second = patch_dir.join("0002-Cargo.toml.patch");
+        assert_eq!(emitted, format!("{}\n{}\n", first.display(), second.display()));
+        assert_eq!(fs::read_to_string(first).unwrap(), package_patch);
+        assert_eq!(fs::read_to_string(second).unwrap(), manifest_patch);
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), "[package]\nversion = \"1.2.3\"\n");
+    }
+
+    /// Tests that `stamp` prints the linker flags and writes the env file.
+    #[test]
+    fn test_stamp() {
+        let tmpdir = tempdir().unwrap();
+        let manifest = tmpdir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nversion = \"1.2.3-rc.1\"\n").unwrap();
+        let env_path = tmpdir.path().join("version.env");
+
+        assert_eq!(run_on(&manifest, &["stamp", "--ldflags"]).unwrap(), "-X main.version=1.2.3-rc.1\n");
+        run_on(&manifest, &["stamp", "--env-file", env_path.to_str().unwrap()]).unwrap();
+        assert!(fs::read_to_string(&env_path).unwrap().starts_with("VERSION=1.2.3-rc.1\nVERSION_MAJOR=1\n"));
+    }
+
+    /// Tests that a bare command line runs the configured default command,
+    /// and that explicit subcommands and other command lines are kept.
+    #[test]
+    fn test_default_command() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let expand = |args: &[&str]| {
+            let mut cli_args = vec!["semvercli", "--manifest-path", manifest_path];
+            cli_args.extend(args);
+            expand_args(cli_args.iter().map(|arg| arg.to_string()).collect()).unwrap()
+        };
+
+        assert_eq!(expand(&[])[3..].join(" "), "");
+        fs::write(tmpdir.path().join(CONFIG_FILE), "default_command = \"read --version\"\n").unwrap();
+        assert_eq!(expand(&[])[3..].join(" "), "read --version");
+        assert_eq!(expand(&["read", "--major"])[3..].join(" "), "read --major");
+        assert_eq!(expand(&["--help"])[3..].join(" "), "--help");
+        let mut stdout = Vec::new();
+        execute(&parser().get_matches_from(expand(&[])), &mut stdout).unwrap();
+        assert_eq!(String::from_utf8(stdout).unwrap(), "1.2.3\n");
+    }
+
+    /// Tests that aliases are expanded in place of a subcommand, with the
+    /// options given replacing the alias's own, and cannot shadow one.
+    #[test]
+    fn test_alias() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        fs::write(tmpdir.path().join(CONFIG_FILE), "default_command = \"v\"\n\n[alias]\nrc = \"bump --pre rc.1 --open-pr\"\nv = \"read --version\"\nread = \"bump --major\"\n").unwrap();
+        let expand = |args: &[&str]| {
+            let mut cli_args = vec!["semvercli", "--color", "never", "--manifest-path", manifest_path];
+            cli_args.extend(args);
+            expand_args(cli_args.iter().map(|arg| arg.to_string()).collect()).unwrap()[5..].join(" ")
+        };
+
+        assert_eq!(expand(&["rc"]), "bump --pre rc.1 --open-pr");
+        assert_eq!(expand(&["rc", "--pre", "beta.1"]), "bump --open-pr --pre beta.1");
+        assert_eq!(expand(&["rc", "--pre=beta.1", "--open-pr"]), "bump --pre=beta.1 --open-pr");
+        assert_eq!(expand(&[]), "read --version");
+        assert_eq!(expand(&["read", "--major"]), "read --major");
+        assert_eq!(expand(&["bump", "rc"]), "bump rc");
+    }
+
+    /// Tests that a number argument too large for its type is refused
+    /// rather than truncated.
+    #[test]
+    fn test_number_arg() {
+        let matches = parser().get_matches_from(vec!["semvercli", "publish-order", "--retries", "4294967296"]);
+        let order_matches = matches.subcommand_matches("publish-order").unwrap();
+        assert_eq!(number_arg::<u64>(order_matches, "retries").unwrap(), 4294967296);
+        assert_eq!(number_arg::<u32>(order_matches, "retries").unwrap_err().code, Code::InvalidArgument);
+    }
+
+    /// Tests the library API: bumping a parsed manifest keeps its layout.
+    #[test]
+    fn test_manifest_bump() {
+        let mut manifest =
+            Manifest::parse("[package]\nname = \"app\" # the app\nversion = \"1.2.3\"\n")
+                .unwrap();
+        assert_eq!(manifest.bump(&VersionOp::Minor).unwrap().to_string(), "1.3.0");
+        let pre = VersionOp::Pre(String::from("rc.1"));
+        assert_eq!(manifest.bump(&pre).unwrap().to_string(), "1.3.0-rc.1");
+        assert_eq!(
+            manifest.to_string(),
+            "[package]\nname = \"app\" # the app\nversion = \"1.3.0-rc.1\"\n"
+        );
+
+        let version = Version::parse("1.2.3").unwrap();
+        let err = apply_bump(&version, &VersionOp::Pre(String::from("rc!"))).unwrap_err();
+        assert_eq!(err.code, Code::InvalidArgument);
+    }
+}
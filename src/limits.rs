@@ -0,0 +1,240 @@
+//! Limits of the numeric version components. Besides `u64::MAX`, business
+//! rules may cap components, e.g. to fit Android's `versionCode`; what a
+//! bump past a limit does is configured rather than left to wrap around:
+//! it fails (the default), saturates, or rolls over into the next higher
+//! component. What a bump resets below the bumped component is configured
+//! too, as teams disagree on whether `--minor` should drop `-rc.1`.
+use semver::Version;
+
+use crate::pipeline::Component;
+
+/// What happens when a bump would take a component past its limit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The bump fails.
+    #[default]
+    Fail,
+    /// The component stays at its limit and the version is unchanged.
+    Saturate,
+    /// The component resets to 0 and the next higher one is bumped; MAJOR
+    /// has none, so it fails.
+    Rollover,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub overflow: Overflow,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            major: u64::MAX,
+            minor: u64::MAX,
+            patch: u64::MAX,
+            overflow: Overflow::Fail,
+        }
+    }
+}
+
+/// What bumping a MAJOR, MINOR or PATCH component resets, from `[reset]`
+/// and the `--keep-*` flags; everything by default, as semver's
+/// `increment_*` do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reset {
+    /// Whether the lower numeric components are reset to 0.
+    pub lower: bool,
+    /// Whether the pre-release is dropped.
+    pub pre: bool,
+    /// Whether the build metadata is dropped.
+    pub build: bool,
+}
+
+impl Default for Reset {
+    fn default() -> Reset {
+        Reset {
+            lower: true,
+            pre: true,
+            build: true,
+        }
+    }
+}
+
+impl Reset {
+    /// Restores on `bumped`, the result of incrementing `component` of
+    /// `version`, what is not to be reset. The lower components are kept
+    /// only if the bump did not roll over into a higher component.
+    pub fn keep(&self, version: &Version, bumped: &mut Version, component: Component) {
+        if !self.lower {
+            match component {
+                Component::Major if bumped.major != version.major => {
+                    bumped.minor = version.minor;
+                    bumped.patch = version.patch;
+                }
+                Component::Minor
+                    if bumped.major == version.major && bumped.minor != version.minor =>
+                {
+                    bumped.patch = version.patch;
+                }
+                _ => {}
+            }
+        }
+        if !self.pre {
+            bumped.pre = version.pre.clone();
+        }
+        if !self.build {
+            bumped.build = version.build.clone();
+        }
+    }
+}
+
+impl Limits {
+    /// Bumps the MAJOR, MINOR or PATCH `component` of `version` as semver's
+    /// `increment_*` do, resetting the lower components and dropping the
+    /// pre-release and build metadata, within the limits.
+    pub fn increment(&self, version: &mut Version, component: Component) -> Result<(), String> {
+        let (value, limit) = match component {
+            Component::Major => (version.major, self.major),
+            Component::Minor => (version.minor, self.minor),
+            Component::Patch => (version.patch, self.patch),
+            _ => panic!("Unreachable - {} is not a numeric component.", component),
+        };
+        if value < limit {
+            match component {
+                Component::Major => version.increment_major(),
+                Component::Minor => version.increment_minor(),
+                _ => version.increment_patch(),
+            }
+            return Ok(());
+        }
+
+        match (self.overflow, component) {
+            (Overflow::Saturate, _) => Ok(()),
+            (Overflow::Rollover, Component::Patch) => self.increment(version, Component::Minor),
+            (Overflow::Rollover, Component::Minor) => self.increment(version, Component::Major),
+            _ => Err(format!(
+                "Cannot bump the {} version of {} past its limit of {}",
+                component.to_string().to_uppercase(),
+                version,
+                limit
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_increment() {
+        let bump = |limits: &Limits, version: &str, component| {
+            let mut version = Version::parse(version).unwrap();
+            limits
+                .increment(&mut version, component)
+                .map(|_| version.to_string())
+        };
+        let capped = |overflow| Limits {
+            minor: 99,
+            patch: 99,
+            overflow,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            bump(&capped(Overflow::Fail), "1.2.3-rc.1", Component::Patch).unwrap(),
+            "1.2.4"
+        );
+        assert!(bump(&capped(Overflow::Fail), "1.2.99", Component::Patch).is_err());
+        assert!(bump(
+            &Limits::default(),
+            "1.2.18446744073709551615",
+            Component::Patch
+        )
+        .is_err());
+        assert_eq!(
+            bump(&capped(Overflow::Saturate), "1.2.99", Component::Patch).unwrap(),
+            "1.2.99"
+        );
+        assert_eq!(
+            bump(&capped(Overflow::Rollover), "1.2.99", Component::Patch).unwrap(),
+            "1.3.0"
+        );
+        assert_eq!(
+            bump(&capped(Overflow::Rollover), "1.99.99", Component::Patch).unwrap(),
+            "2.0.0"
+        );
+        assert!(bump(
+            &Limits {
+                overflow: Overflow::Rollover,
+                ..Limits::default()
+            },
+            "18446744073709551615.0.0",
+            Component::Major
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_reset() {
+        let bump = |reset: Reset, limits: &Limits, version: &str, component| {
+            let version = Version::parse(version).unwrap();
+            let mut bumped = version.clone();
+            limits.increment(&mut bumped, component).unwrap();
+            reset.keep(&version, &mut bumped, component);
+            bumped.to_string()
+        };
+        let keep_all = Reset {
+            lower: false,
+            pre: false,
+            build: false,
+        };
+
+        assert_eq!(
+            bump(
+                Reset::default(),
+                &Limits::default(),
+                "1.2.3-rc.1+abc",
+                Component::Minor
+            ),
+            "1.3.0"
+        );
+        assert_eq!(
+            bump(
+                keep_all,
+                &Limits::default(),
+                "1.2.3-rc.1+abc",
+                Component::Minor
+            ),
+            "1.3.3-rc.1+abc"
+        );
+        assert_eq!(
+            bump(keep_all, &Limits::default(), "1.2.3", Component::Major),
+            "2.2.3"
+        );
+        assert_eq!(
+            bump(
+                Reset {
+                    pre: false,
+                    ..Reset::default()
+                },
+                &Limits::default(),
+                "1.2.3-rc.1+abc",
+                Component::Patch
+            ),
+            "1.2.4-rc.1"
+        );
+        let rollover = Limits {
+            minor: 99,
+            overflow: Overflow::Rollover,
+            ..Limits::default()
+        };
+        assert_eq!(
+            bump(keep_all, &rollover, "1.99.3", Component::Minor),
+            "2.0.0"
+        );
+    }
+}
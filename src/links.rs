@@ -0,0 +1,251 @@
+//! Links in generated release notes: commit hashes, issue and pull request
+//! numbers (`#123`) and GitLab merge requests (`!123`) become links to the
+//! forge hosting the repository, following its URL scheme unless the
+//! `[links]` templates override it.
+use crate::pr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl Forge {
+    pub fn parse(name: &str) -> Option<Forge> {
+        match name {
+            "github" => Some(Forge::GitHub),
+            "gitlab" => Some(Forge::GitLab),
+            "gitea" => Some(Forge::Gitea),
+            _ => None,
+        }
+    }
+
+    /// The forge a host name suggests, if any.
+    fn detect(host: &str) -> Option<Forge> {
+        [
+            ("github", Forge::GitHub),
+            ("gitlab", Forge::GitLab),
+            ("gitea", Forge::Gitea),
+        ]
+        .iter()
+        .find(|(name, _)| host.contains(name))
+        .map(|&(_, forge)| forge)
+    }
+
+    /// The forge's commit, issue and merge request URL templates. GitHub
+    /// and Gitea number issues and pull requests together and redirect
+    /// between them, so `#123` links either.
+    fn templates(self) -> [Option<&'static str>; 3] {
+        match self {
+            Forge::GitHub => [
+                Some("{url}/commit/{hash}"),
+                Some("{url}/issues/{number}"),
+                None,
+            ],
+            Forge::GitLab => [
+                Some("{url}/-/commit/{hash}"),
+                Some("{url}/-/issues/{number}"),
+                Some("{url}/-/merge_requests/{number}"),
+            ],
+            Forge::Gitea => [
+                Some("{url}/commit/{hash}"),
+                Some("{url}/issues/{number}"),
+                None,
+            ],
+        }
+    }
+}
+
+/// The `[links]` settings.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Links {
+    /// The forge; told from the repository's host name unless given.
+    pub forge: Option<Forge>,
+    /// The web URL of the repository, e.g. `https://github.com/acme/app`;
+    /// derived from the `origin` remote unless given.
+    pub url: Option<String>,
+    /// Templates overriding the forge's URLs of commits (`{hash}`), issues
+    /// and merge requests (`{number}`); `{url}` is the repository's URL.
+    pub commit: Option<String>,
+    pub issue: Option<String>,
+    pub merge_request: Option<String>,
+}
+
+/// The URLs references are linked to; a reference without one is left as
+/// it is.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Templates {
+    pub commit: Option<String>,
+    pub issue: Option<String>,
+    pub merge_request: Option<String>,
+}
+
+impl Links {
+    /// The templates for the repository whose `origin` remote has the URL
+    /// `remote`, if it has one.
+    pub fn templates(&self, remote: Option<&str>) -> Templates {
+        let url = self.url.clone().or_else(|| {
+            let (host, project) = pr::split_url(remote?).ok()?;
+            Some(format!("https://{}/{}", host, project))
+        });
+        let url = url.as_deref().map(|url| url.trim_end_matches('/'));
+        let forge = self.forge.or_else(|| {
+            let host = url?.split("://").last()?.split('/').next()?;
+            Forge::detect(host)
+        });
+        let defaults = forge.map(Forge::templates).unwrap_or_default();
+        let template = |configured: &Option<String>, default: Option<&str>| {
+            let template = configured.as_deref().or(default)?;
+            match url {
+                Some(url) => Some(template.replace("{url}", url)),
+                None if template.contains("{url}") => None,
+                None => Some(template.to_string()),
+            }
+        };
+
+        Templates {
+            commit: template(&self.commit, defaults[0]),
+            issue: template(&self.issue, defaults[1]),
+            merge_request: template(&self.merge_request, defaults[2]),
+        }
+    }
+}
+
+impl Templates {
+    /// Links the references in a line of markdown. References in code
+    /// spans, in links already and within words are left alone; a commit
+    /// hash has 7 to 40 hexadecimal digits, at least one of them a number,
+    /// so that words like `defaced` are not taken for one.
+    pub fn expand(&self, text: &str) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut in_code = false;
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            let boundary =
+                !expanded.ends_with(|c: char| c.is_alphanumeric() || "[/-_.".contains(c));
+            if c == '`' {
+                in_code = !in_code;
+            }
+            let link = if in_code || !boundary {
+                None
+            } else {
+                self.reference(rest)
+            };
+            match link {
+                Some((length, link)) => {
+                    expanded.push_str(&link);
+                    rest = &rest[length..];
+                }
+                None => {
+                    expanded.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+
+        expanded
+    }
+
+    /// The link of the reference `text` starts with and the reference's
+    /// length, if it starts with one.
+    fn reference(&self, text: &str) -> Option<(usize, String)> {
+        let word = text
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .map_or(text, |end| &text[..end]);
+        let (template, number) = match text.chars().next()? {
+            '#' => (
+                self.issue.as_ref()?,
+                text[1..].split(|c: char| !c.is_ascii_digit()).next()?,
+            ),
+            '!' => (
+                self.merge_request.as_ref()?,
+                text[1..].split(|c: char| !c.is_ascii_digit()).next()?,
+            ),
+            _ => {
+                let hash = (7..=40).contains(&word.len())
+                    && word
+                        .chars()
+                        .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+                    && word.chars().any(|c| c.is_ascii_digit());
+                if !hash {
+                    return None;
+                }
+                let template = self.commit.as_ref()?;
+                return Some((
+                    word.len(),
+                    format!("[{}]({})", word, template.replace("{hash}", word)),
+                ));
+            }
+        };
+        let after = text[1 + number.len()..].chars().next();
+        if number.is_empty() || after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        let reference = &text[..1 + number.len()];
+
+        Some((
+            reference.len(),
+            format!("[{}]({})", reference, template.replace("{number}", number)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_templates() {
+        let github = Links::default().templates(Some("git@github.com:acme/app.git"));
+        assert_eq!(
+            github.commit.as_deref(),
+            Some("https://github.com/acme/app/commit/{hash}")
+        );
+        assert_eq!(github.merge_request, None);
+
+        let gitea = Links {
+            forge: Some(Forge::Gitea),
+            issue: Some(String::from("https://jira.acme.dev/browse/APP-{number}")),
+            ..Links::default()
+        }
+        .templates(Some("https://git.acme.dev/acme/app.git"));
+        assert_eq!(
+            gitea,
+            Templates {
+                commit: Some(String::from("https://git.acme.dev/acme/app/commit/{hash}")),
+                issue: Some(String::from("https://jira.acme.dev/browse/APP-{number}")),
+                merge_request: None,
+            }
+        );
+
+        assert_eq!(
+            Links::default().templates(Some("https://git.acme.dev/acme/app")),
+            Templates::default()
+        );
+        assert_eq!(Links::default().templates(None), Templates::default());
+    }
+
+    /// Tests that hashes, issues and merge requests are linked, but not
+    /// within words, code spans or existing links.
+    #[test]
+    fn test_expand() {
+        let gitlab = Links::default().templates(Some("https://gitlab.com/acme/app"));
+
+        assert_eq!(
+            gitlab.expand("- Fix EOF (#12, !34) (1a2b3c4)"),
+            "- Fix EOF ([#12](https://gitlab.com/acme/app/-/issues/12), \
+             [!34](https://gitlab.com/acme/app/-/merge_requests/34)) \
+             ([1a2b3c4](https://gitlab.com/acme/app/-/commit/1a2b3c4))"
+        );
+        for text in &[
+            "Defaced facade",
+            "`#12` and `1a2b3c4`",
+            "[#12](https://x/12)",
+            "C#12, #12a, 1a2b3c4d_x and !",
+        ] {
+            assert_eq!(gitlab.expand(text), *text);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Tools for lists of versions read from stdin, one per line, such as the
+//! output of `git tag`: the preprocessing every "pick a version from the
+//! tags" script otherwise reimplements.
+use crate::epoch::EpochVersion;
+
+/// How versions that differ only in their build metadata are treated,
+/// since build metadata does not take part in version precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Build {
+    /// They are duplicates; the first one listed is kept.
+    Ignore,
+    /// They are distinct, and ordered by their build metadata.
+    Keep,
+    /// Build metadata is removed from every version.
+    Strip,
+}
+
+/// The versions on the given lines, without a leading `v`/`V`, sorted by
+/// precedence (epochs first) and without duplicates. Blank lines and lines that are not
+/// versions (e.g. other tags) are skipped.
+pub fn canonicalize<S: AsRef<str>>(lines: &[S], build: Build) -> Vec<EpochVersion> {
+    let mut versions = lines
+        .iter()
+        .filter_map(|line| {
+            let line = line.as_ref().trim();
+            EpochVersion::parse(line.trim_start_matches(['v', 'V'])).ok()
+        })
+        .map(|mut version| {
+            if build == Build::Strip {
+                version.version.build.clear();
+            }
+            version
+        })
+        .collect::<Vec<_>>();
+
+    if build == Build::Keep {
+        versions.sort_by(|a, b| a.cmp(b).then_with(|| a.version.build.cmp(&b.version.build)));
+        versions.dedup_by(|a, b| a == b && a.version.build == b.version.build);
+    } else {
+        versions.sort();
+        versions.dedup();
+    }
+
+    versions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let lines = [
+            "v1.2.0",
+            "latest",
+            "1.10.0",
+            "",
+            "1.2.0+linux",
+            "V1.2.0-rc.1",
+            "1.2.0+abc",
+            "1.10.0",
+            "1!0.1.0",
+        ];
+        let canon = |build| {
+            canonicalize(&lines, build)
+                .iter()
+                .map(EpochVersion::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            canon(Build::Ignore),
+            ["1.2.0-rc.1", "1.2.0", "1.10.0", "1!0.1.0"]
+        );
+        assert_eq!(
+            canon(Build::Keep),
+            [
+                "1.2.0-rc.1",
+                "1.2.0",
+                "1.2.0+abc",
+                "1.2.0+linux",
+                "1.10.0",
+                "1!0.1.0"
+            ]
+        );
+        assert_eq!(
+            canon(Build::Strip),
+            ["1.2.0-rc.1", "1.2.0", "1.10.0", "1!0.1.0"]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Resolved dependency versions: `lock report` reads the `Cargo.lock` of the
+//! package and reports the versions its dependencies were resolved to, and
+//! with `--req` checks them against a requirement, so that deployment gates
+//! can assert e.g. "we're shipping openssl >= 3.0" with the same CLI.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+use toml_edit::Document;
+
+use crate::error::{Code, Error};
+
+/// The `Cargo.lock` of the package at `manifest_path`: next to it or, for a
+/// workspace member, next to the workspace's manifest in a parent directory.
+pub fn find(manifest_path: &Path) -> Option<PathBuf> {
+    let directory = manifest_path
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    directory
+        .canonicalize()
+        .ok()?
+        .ancestors()
+        .map(|directory| directory.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+/// Reads the lockfile at `path`.
+pub fn read(path: &Path) -> Result<Document, Error> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        Error::new(
+            Code::Io,
+            format!("Failed to read {}: {}", path.display(), err),
+        )
+    })?;
+
+    contents.parse::<Document>().map_err(|err| {
+        Error::new(
+            Code::ParseManifest,
+            format!("Invalid {}: {}", path.display(), err),
+        )
+    })
+}
+
+/// The versions the package `name` is resolved to in the lockfile, lowest
+/// first; a package is locked at several versions when dependents require
+/// incompatible ones.
+pub fn resolved(lock: &Document, name: &str) -> Vec<Version> {
+    let mut versions = lock["package"]
+        .as_array_of_tables()
+        .map(|packages| {
+            packages
+                .iter()
+                .filter(|package| package["name"].as_str() == Some(name))
+                .filter_map(|package| Version::parse(package["version"].as_str()?).ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    versions.sort();
+
+    versions
+}
+
+/// The resolved versions of the packages `names`, in the order given;
+/// a package missing from the lockfile fails the report.
+pub fn report<'a>(lock: &Document, names: &[&'a str]) -> Result<Vec<(&'a str, Version)>, Error> {
+    let mut report = vec![];
+    for &name in names {
+        let versions = resolved(lock, name);
+        if versions.is_empty() {
+            return Err(Error::new(
+                Code::Unsatisfied,
+                format!("{} is not in the lockfile.", name),
+            ));
+        }
+        report.extend(versions.into_iter().map(|version| (name, version)));
+    }
+
+    Ok(report)
+}
+
+/// Fails if any of the reported versions does not satisfy `requirement`.
+pub fn check(report: &[(&str, Version)], requirement: &VersionReq) -> Result<(), Error> {
+    let unsatisfied = report
+        .iter()
+        .filter(|(_, version)| !requirement.matches(version))
+        .map(|(name, version)| format!("{} {}", name, version))
+        .collect::<Vec<_>>();
+    if unsatisfied.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        Code::Unsatisfied,
+        format!(
+            "Locked versions do not satisfy {}: {}",
+            requirement,
+            unsatisfied.join(", ")
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "openssl"
+version = "0.10.55"
+
+[[package]]
+name = "libc"
+version = "0.2.147"
+
+[[package]]
+name = "openssl"
+version = "0.9.24"
+"#;
+
+    /// Tests that every locked version of a package is reported, and that
+    /// the requirement must hold for all of them.
+    #[test]
+    fn test_report() {
+        let lock = LOCK.parse::<Document>().unwrap();
+        let report = report(&lock, &["openssl", "libc"]).unwrap();
+        assert_eq!(
+            report,
+            [
+                ("openssl", Version::parse("0.9.24").unwrap()),
+                ("openssl", Version::parse("0.10.55").unwrap()),
+                ("libc", Version::parse("0.2.147").unwrap()),
+            ]
+        );
+
+        assert!(check(&report[1..], &VersionReq::parse(">= 0.2").unwrap()).is_ok());
+        let err = check(&report, &VersionReq::parse(">= 0.10").unwrap()).unwrap_err();
+        assert_eq!(err.code, Code::Unsatisfied);
+        assert!(err.message.contains("openssl 0.9.24"));
+        assert!(!err.message.contains("0.10.55"));
+
+        let err = super::report(&lock, &["serde"]).unwrap_err();
+        assert_eq!(err.code, Code::Unsatisfied);
+    }
+}
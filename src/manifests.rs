@@ -0,0 +1,347 @@
+//! Version differences between two Cargo manifests, for `diff-manifests`:
+//! each side is a manifest in the work tree or, given as `ref:path`, at a
+//! git revision, together with the members of the workspace it declares,
+//! so two releases can be audited package by package.
+use std::path::Path;
+
+use semver::Version;
+use toml_edit::Document;
+
+use crate::adapter;
+use crate::config::glob_match;
+use crate::error::{json_string, Code, Error};
+use crate::git;
+use crate::workspace;
+
+/// One side of the comparison: the root manifest at `path`, read from the
+/// work tree or from the git revision `rev`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Side {
+    pub rev: Option<String>,
+    pub path: String,
+}
+
+impl Side {
+    /// Parses a `path` or `ref:path` spec; a spec naming an existing file
+    /// is a path even if it contains a colon.
+    pub fn parse(spec: &str) -> Side {
+        match spec.split_once(':') {
+            Some((rev, path)) if !rev.is_empty() && !Path::new(spec).is_file() => Side {
+                rev: Some(rev.to_string()),
+                path: path.to_string(),
+            },
+            _ => Side {
+                rev: None,
+                path: spec.to_string(),
+            },
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Document, Error> {
+        let contents = match self.rev {
+            Some(ref rev) => git::show(rev, path)?,
+            None => adapter::read_file(path)?,
+        };
+
+        contents.parse::<Document>().map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid {}: {}", self.describe(path), err),
+            )
+        })
+    }
+
+    fn describe(&self, path: &str) -> String {
+        match self.rev {
+            Some(ref rev) => format!("{}:{}", rev, path),
+            None => path.to_string(),
+        }
+    }
+
+    /// The paths of the workspace members' manifests at the revision: the
+    /// `Cargo.toml` files of the revision's tree below the root whose
+    /// directory matches a `members` pattern and no `exclude` one.
+    fn revision_members(&self, rev: &str, root_manifest: &Document) -> Result<Vec<String>, Error> {
+        let root = Path::new(&self.path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let patterns = |key: &str| {
+            root_manifest["workspace"][key]
+                .as_array()
+                .map(|array| {
+                    array
+                        .iter()
+                        .filter_map(|pattern| pattern.as_str().map(String::from))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        let (members, excluded) = (patterns("members"), patterns("exclude"));
+        let matches_any = |patterns: &[String], dir: &str| {
+            patterns
+                .iter()
+                .any(|pattern| path_match(&segments(pattern), &dir.split('/').collect::<Vec<_>>()))
+        };
+
+        let mut paths = git::run_in(root, &["ls-tree", "-r", "--name-only", rev])?
+            .lines()
+            .filter_map(|file| file.strip_suffix("/Cargo.toml"))
+            .filter(|dir| matches_any(&members, dir) && !matches_any(&excluded, dir))
+            .map(|dir| {
+                root.join(dir)
+                    .join("Cargo.toml")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    /// The name and version of every package of the side: the root package,
+    /// if any, and the members of its workspace, in path order. Versions
+    /// inherited with `version.workspace = true` are resolved.
+    pub fn packages(&self) -> Result<Vec<(String, Version)>, Error> {
+        let root_manifest = self.read(&self.path)?;
+        let paths = match (&self.rev, root_manifest["workspace"].is_none()) {
+            (_, true) => vec![self.path.clone()],
+            (None, false) => workspace::cargo_paths(&self.path, &root_manifest)?,
+            (Some(rev), false) => {
+                let mut paths = self.revision_members(rev, &root_manifest)?;
+                if !root_manifest["package"].is_none() {
+                    paths.insert(0, self.path.clone());
+                }
+                paths
+            }
+        };
+        let inherited = root_manifest["workspace"]["package"]["version"].as_str();
+
+        paths
+            .iter()
+            .map(|path| {
+                let manifest = if *path == self.path {
+                    root_manifest.clone()
+                } else {
+                    self.read(path)?
+                };
+                let package = &manifest["package"];
+                let version = match package["version"].as_str() {
+                    _ if package["version"]["workspace"].as_bool() == Some(true) => inherited,
+                    version => version,
+                };
+                let version = version
+                    .and_then(|version| Version::parse(version).ok())
+                    .ok_or_else(|| {
+                        Error::new(
+                            Code::InvalidVersion,
+                            format!("{} has no valid package version", self.describe(path)),
+                        )
+                    })?;
+                let name = match package["name"].as_str() {
+                    Some(name) => name.to_string(),
+                    None => path.clone(),
+                };
+                Ok((name, version))
+            })
+            .collect()
+    }
+}
+
+/// The segments of a workspace member pattern.
+fn segments(pattern: &str) -> Vec<&str> {
+    pattern
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Whether the path segments match the pattern segments, in which `*`
+/// matches within a segment and a `**` segment any number of them.
+fn path_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| path_match(rest, &path[skip..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| glob_match(segment, name) && path_match(rest, path)),
+    }
+}
+
+/// A package whose version differs between the sides; `None` on the side
+/// that does not have it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub name: String,
+    pub a: Option<Version>,
+    pub b: Option<Version>,
+}
+
+/// The packages whose versions differ between `a` and `b`: those of `a` in
+/// order, then the ones only `b` has.
+pub fn differences(a: &[(String, Version)], b: &[(String, Version)]) -> Vec<Difference> {
+    let find = |packages: &[(String, Version)], name: &str| {
+        packages
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, version)| version.clone())
+    };
+    let only_b = b
+        .iter()
+        .filter(|(name, _)| find(a, name).is_none())
+        .map(|(name, version)| Difference {
+            name: name.clone(),
+            a: None,
+            b: Some(version.clone()),
+        });
+
+    a.iter()
+        .map(|(name, version)| Difference {
+            name: name.clone(),
+            a: Some(version.clone()),
+            b: find(b, name),
+        })
+        .filter(|difference| {
+            difference.a.as_ref().map(Version::to_string)
+                != difference.b.as_ref().map(Version::to_string)
+        })
+        .chain(only_b)
+        .collect()
+}
+
+/// The differences as `name a -> b` lines, `<none>` standing for a side
+/// without the package.
+pub fn lines(differences: &[Difference]) -> Vec<String> {
+    let side = |version: &Option<Version>| match version {
+        Some(version) => version.to_string(),
+        None => String::from(crate::EMPTY_COMPONENT),
+    };
+
+    differences
+        .iter()
+        .map(|difference| {
+            format!(
+                "{} {} -> {}",
+                difference.name,
+                side(&difference.a),
+                side(&difference.b)
+            )
+        })
+        .collect()
+}
+
+/// The differences as a single line JSON array of `{name, a, b}` objects,
+/// `null` standing for a side without the package.
+pub fn json(differences: &[Difference]) -> String {
+    let side = |version: &Option<Version>| match version {
+        Some(version) => json_string(&version.to_string()),
+        None => String::from("null"),
+    };
+    let objects = differences
+        .iter()
+        .map(|difference| {
+            format!(
+                "{{\"name\": {}, \"a\": {}, \"b\": {}}}",
+                json_string(&difference.name),
+                side(&difference.a),
+                side(&difference.b)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("[{}]", objects.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Tests that workspace members are found and only changed, added and
+    /// removed packages are reported.
+    #[test]
+    fn test_differences() {
+        let tmpdir = tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = tmpdir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write(
+            "a/Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"1.0.0\"\n",
+        );
+        write(
+            "a/crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion = { workspace = true }\n",
+        );
+        write(
+            "a/crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"0.4.0\"\n",
+        );
+        write(
+            "a/crates/old/Cargo.toml",
+            "[package]\nname = \"old\"\nversion = \"0.1.0\"\n",
+        );
+        write(
+            "b/Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"2.0.0\"\n\n[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"1.1.0\"\n",
+        );
+        write(
+            "b/crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion = { workspace = true }\n",
+        );
+        write(
+            "b/crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"0.4.0\"\n",
+        );
+        let packages = |side: &str| {
+            Side::parse(
+                tmpdir
+                    .path()
+                    .join(side)
+                    .join("Cargo.toml")
+                    .to_str()
+                    .unwrap(),
+            )
+            .packages()
+            .unwrap()
+        };
+
+        let differences = differences(&packages("a"), &packages("b"));
+        assert_eq!(
+            lines(&differences),
+            [
+                "core 1.0.0 -> 1.1.0",
+                "old 0.1.0 -> <none>",
+                "app <none> -> 2.0.0"
+            ]
+        );
+        assert_eq!(
+            json(&differences[1..2]),
+            "[{\"name\": \"old\", \"a\": \"0.1.0\", \"b\": null}]"
+        );
+    }
+
+    #[test]
+    fn test_side() {
+        assert_eq!(
+            Side::parse("v1.2.0:crates/Cargo.toml"),
+            Side {
+                rev: Some(String::from("v1.2.0")),
+                path: String::from("crates/Cargo.toml")
+            }
+        );
+        assert_eq!(Side::parse("Cargo.toml").rev, None);
+        assert!(path_match(&segments("crates/*"), &["crates", "core"]));
+        assert!(path_match(&segments("**/sys"), &["a", "b", "sys"]));
+        assert!(!path_match(
+            &segments("crates/*"),
+            &["crates", "core", "sub"]
+        ));
+    }
+}
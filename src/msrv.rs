@@ -0,0 +1,177 @@
+//! The minimum supported Rust version: `msrv read|set|bump` manage the
+//! `package.rust-version` of the manifest, a partial version such as `1.70`.
+//! Raising it drops support for toolchains users may be on, so it is only
+//! allowed when the package's version is at least a minor release ahead of
+//! the last release.
+use std::fmt;
+
+use semver::Version;
+use toml_edit::Document;
+
+use crate::error::{Code, Error};
+use crate::pipeline::Component;
+
+/// A `rust-version`: MAJOR.MINOR with an optional PATCH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Msrv {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: Option<u64>,
+}
+
+impl Msrv {
+    pub fn parse(text: &str) -> Result<Msrv, Error> {
+        let invalid = || {
+            Error::new(
+                Code::Msrv,
+                format!(
+                    "Invalid rust-version {}: expected MAJOR.MINOR or MAJOR.MINOR.PATCH",
+                    text
+                ),
+            )
+        };
+        let components = text
+            .split('.')
+            .map(|component| match component.parse::<u64>() {
+                Ok(number) if !component.starts_with('+') => Ok(number),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        match components[..] {
+            [major, minor] => Ok(Msrv {
+                major,
+                minor,
+                patch: None,
+            }),
+            [major, minor, patch] => Ok(Msrv {
+                major,
+                minor,
+                patch: Some(patch),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The `rust-version` of the manifest, if it declares one.
+    pub fn read(manifest: &Document) -> Result<Option<Msrv>, Error> {
+        let item = &manifest["package"]["rust-version"];
+        if item.is_none() {
+            return Ok(None);
+        }
+        match item.as_str() {
+            Some(text) => Msrv::parse(text).map(Some),
+            None => Err(Error::new(
+                Code::Msrv,
+                "The rust-version is not a string, e.g. inherited from the workspace",
+            )),
+        }
+    }
+
+    /// The version with `component` incremented and the lower ones reset,
+    /// keeping whether it has a PATCH unless that is what is bumped.
+    pub fn bump(self, component: Component) -> Msrv {
+        match component {
+            Component::Major => Msrv {
+                major: self.major + 1,
+                minor: 0,
+                patch: self.patch.map(|_| 0),
+            },
+            Component::Minor => Msrv {
+                minor: self.minor + 1,
+                patch: self.patch.map(|_| 0),
+                ..self
+            },
+            _ => Msrv {
+                patch: Some(self.patch.unwrap_or(0) + 1),
+                ..self
+            },
+        }
+    }
+
+    /// The version's precedence; a missing PATCH is 0.
+    fn precedence(self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+}
+
+impl fmt::Display for Msrv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        match self.patch {
+            Some(patch) => write!(f, ".{}", patch),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Checks that changing the `rust-version` to `msrv` is allowed for the
+/// package at `version`, given the version and `rust-version` of its last
+/// release: raising it (or declaring one where the release had none)
+/// requires at least a minor release.
+pub fn check_policy(
+    msrv: Msrv,
+    version: &Version,
+    released: &Version,
+    released_msrv: Option<Msrv>,
+) -> Result<(), Error> {
+    let raised =
+        released_msrv.is_none_or(|released_msrv| msrv.precedence() > released_msrv.precedence());
+    if !raised || (version.major, version.minor) > (released.major, released.minor) {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        Code::Msrv,
+        format!(
+            "Raising the rust-version to {} requires at least a minor release, but {} is not one after {}",
+            msrv, version, released
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msrv(text: &str) -> Msrv {
+        Msrv::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(msrv("1.70").to_string(), "1.70");
+        assert_eq!(msrv("1.70.1").to_string(), "1.70.1");
+        for text in &["1", "1.70.0.1", "1.70.0-nightly", "1.x", "1.+7", ""] {
+            assert_eq!(Msrv::parse(text).unwrap_err().code, Code::Msrv, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_bump() {
+        assert_eq!(msrv("1.70").bump(Component::Minor), msrv("1.71"));
+        assert_eq!(msrv("1.70.2").bump(Component::Minor), msrv("1.71.0"));
+        assert_eq!(msrv("1.70").bump(Component::Patch), msrv("1.70.1"));
+        assert_eq!(msrv("1.70.1").bump(Component::Major), msrv("2.0.0"));
+    }
+
+    /// Tests that only raising the rust-version needs a minor release.
+    #[test]
+    fn test_check_policy() {
+        let released = Version::parse("1.4.2").unwrap();
+        let check = |to: &str, version: &str, released_msrv: Option<&str>| {
+            let version = Version::parse(version).unwrap();
+            check_policy(msrv(to), &version, &released, released_msrv.map(msrv))
+        };
+
+        assert!(check("1.70.0", "1.4.3", Some("1.70")).is_ok());
+        assert!(check("1.65", "1.4.3", Some("1.70")).is_ok());
+        assert!(check("1.74", "1.5.0", Some("1.70")).is_ok());
+        assert!(check("1.74", "2.0.0-rc.1", None).is_ok());
+        assert_eq!(
+            check("1.74", "1.4.3", Some("1.70")).unwrap_err().code,
+            Code::Msrv
+        );
+        assert!(check("1.74", "1.4.3", None).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Versioning a family of repositories together: a `repos.toml` lists the
+//! git repositories and the manifest of each, and `multi bump` clones (or
+//! fetches) every one into a work directory and bumps it there, e.g.:
+//!
+//! ```toml
+//! # Where the repositories are checked out, relative to this file.
+//! workdir = ".semvercli-repos"
+//!
+//! [[repos]]
+//! url = "git@github.com:acme/api.git"
+//!
+//! [[repos]]
+//! name = "web"
+//! url = "git@github.com:acme/web-frontend.git"
+//! manifest = "package.json"
+//! branch = "develop"
+//! ```
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ArgMatches;
+use toml_edit::{Document, Table};
+
+use crate::error::{Code, Error};
+use crate::git;
+use crate::plan;
+use crate::pr;
+use crate::BUMP_ARGS;
+
+pub const REPOS_FILE: &str = "repos.toml";
+
+/// The plan of the bump, written into each checkout while it is applied.
+const PLAN_FILE: &str = ".semvercli-multi.plan.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repo {
+    /// Name of the checkout directory; the last segment of the URL without
+    /// `.git` unless given.
+    pub name: String,
+    pub url: String,
+    /// The branch to bump; the remote's default branch unless given.
+    pub branch: Option<String>,
+    /// Path of the manifest within the repository.
+    pub manifest: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repos {
+    pub workdir: PathBuf,
+    pub repos: Vec<Repo>,
+}
+
+impl Repo {
+    fn from_table(table: &Table) -> Result<Repo, String> {
+        let string = |key: &str| match table[key].as_str() {
+            Some(value) => Ok(Some(value.to_string())),
+            None if table[key].is_none() => Ok(None),
+            None => Err(format!("`{}` must be a string", key)),
+        };
+        let url = string("url")?.ok_or("every repository needs a `url`")?;
+        let name = match string("name")? {
+            Some(name) => name,
+            None => url
+                .trim_end_matches('/')
+                .rsplit(['/', ':'])
+                .next()
+                .unwrap()
+                .trim_end_matches(".git")
+                .to_string(),
+        };
+
+        Ok(Repo {
+            name,
+            url,
+            branch: string("branch")?,
+            manifest: string("manifest")?.unwrap_or_else(|| String::from("Cargo.toml")),
+        })
+    }
+}
+
+impl Repos {
+    /// Parses a repositories file; `root` is the directory it is in.
+    pub fn from_document(root: &Path, document: &Document) -> Result<Repos, String> {
+        let workdir = match document["workdir"].as_str() {
+            Some(workdir) => workdir,
+            None if document["workdir"].is_none() => ".semvercli-repos",
+            None => return Err(String::from("`workdir` must be a string")),
+        };
+        let repos = document["repos"]
+            .as_array_of_tables()
+            .ok_or("the repositories must be listed as [[repos]]")?
+            .iter()
+            .map(Repo::from_table)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (index, repo) in repos.iter().enumerate() {
+            if repos[..index].iter().any(|other| other.name == repo.name) {
+                return Err(format!(
+                    "two repositories are named {}; set `name` on one",
+                    repo.name
+                ));
+            }
+        }
+
+        Ok(Repos {
+            workdir: root.join(workdir),
+            repos,
+        })
+    }
+
+    pub fn read(path: &str) -> Result<Repos, Error> {
+        let invalid = |err: String| Error::new(Code::Config, format!("Invalid {}: {}", path, err));
+        let document = fs::read_to_string(path)
+            .map_err(|err| invalid(err.to_string()))?
+            .parse::<Document>()
+            .map_err(|err| invalid(err.to_string()))?;
+        let root = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+        Repos::from_document(root, &document).map_err(invalid)
+    }
+}
+
+/// Clones the repository into `dir`, or brings an earlier clone up to date
+/// with its remote.
+pub fn checkout(repo: &Repo, dir: &Path) -> Result<(), Error> {
+    if !dir.exists() {
+        let dir = dir.to_string_lossy();
+        let mut args = vec!["clone", "--quiet"];
+        if let Some(ref branch) = repo.branch {
+            args.extend_from_slice(&["--branch", branch.as_str()]);
+        }
+        args.extend_from_slice(&[repo.url.as_str(), dir.as_ref()]);
+        return git::run(&args).map(|_| ());
+    }
+
+    git::run_in(dir, &["fetch", "--quiet", "origin"])?;
+    if let Some(ref branch) = repo.branch {
+        git::run_in(dir, &["checkout", "--quiet", branch.as_str()])?;
+    }
+    git::run_in(dir, &["merge", "--quiet", "--ff-only"]).map(|_| ())
+}
+
+/// The command line arguments of the bump given to `multi bump`.
+pub fn bump_args(matches: &ArgMatches) -> Vec<String> {
+    let mut args = vec![];
+    for &(name, takes_value, _) in BUMP_ARGS.iter() {
+        if !matches.is_present(name) {
+            continue;
+        }
+        args.push(format!("--{}", name));
+        if takes_value {
+            args.extend(matches.value_of(name).map(String::from));
+        }
+    }
+
+    args
+}
+
+/// Runs semvercli itself in the checkout `dir`, so the repository's own
+/// configuration, git history and branch apply.
+pub fn semvercli(dir: &Path, args: &[String]) -> Result<String, Error> {
+    let exe = env::current_exe().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Could not locate the semvercli executable: {}", err),
+        )
+    })?;
+    let mut command = Command::new(exe);
+    command.current_dir(dir).args(args);
+    let output = command.output().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Failed to run {:?}: {}", command, err),
+        )
+    })?;
+    if !output.status.success() {
+        return Err(Error::new(
+            Code::Command,
+            format!(
+                "semvercli {} failed in {}: {}",
+                args.join(" "),
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Checks out every repository and applies the bump given by `args` to its
+/// manifest, committing it with `commit`, or opening a pull request from a
+/// branch named after `pr_branch` if given; returns the repositories' names,
+/// new versions and pull requests.
+pub fn bump(
+    repos: &Repos,
+    args: &[String],
+    commit: bool,
+    pr_branch: Option<&str>,
+) -> Result<Vec<(String, String, Option<String>)>, Error> {
+    let mut bumped = vec![];
+
+    for repo in &repos.repos {
+        let dir = repos.workdir.join(&repo.name);
+        checkout(repo, &dir)?;
+        let manifest = [String::from("--manifest-path"), repo.manifest.clone()];
+        // Planned first, so that nothing is written when the pull request
+        // cannot be prepared.
+        let plan = [String::from(PLAN_FILE)];
+        semvercli(
+            &dir,
+            &[&manifest[..], &[String::from("plan")], &plan[..], args].concat(),
+        )?;
+        let plan_path = dir.join(PLAN_FILE).to_string_lossy().into_owned();
+        let version = plan::read_plan(&plan_path)?.files[0].new.clone();
+        let pull_request = pr_branch
+            .map(|branch| {
+                let changelog = dir.join(&repo.manifest).with_file_name("CHANGELOG.md");
+                pr::PullRequest::prepare(&dir, &version, branch, &changelog)
+            })
+            .transpose()
+            .and_then(|pull_request| {
+                semvercli(&dir, &[&[String::from("apply")], &plan[..]].concat())?;
+                Ok(pull_request)
+            });
+        fs::remove_file(&plan_path).ok();
+        let url = pull_request?.map(pr::PullRequest::open).transpose()?;
+        if commit {
+            git::run_in(
+                &dir,
+                &[
+                    "commit",
+                    "--quiet",
+                    "--all",
+                    "--message",
+                    &format!("Bump version to {}", version),
+                ],
+            )?;
+        }
+        bumped.push((repo.name.clone(), version.to_string(), url));
+    }
+
+    Ok(bumped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_repos() {
+        let repos = "workdir = \"checkouts\"\n\
+                     [[repos]]\nurl = \"git@github.com:acme/api.git\"\n\
+                     [[repos]]\nname = \"web\"\nurl = \"https://github.com/acme/web-frontend\"\n\
+                     manifest = \"package.json\"\nbranch = \"develop\"\n";
+        let repos = Repos::from_document(Path::new("ops"), &repos.parse().unwrap()).unwrap();
+
+        assert_eq!(repos.workdir, Path::new("ops/checkouts"));
+        assert_eq!(
+            repos.repos[0],
+            Repo {
+                name: String::from("api"),
+                url: String::from("git@github.com:acme/api.git"),
+                branch: None,
+                manifest: String::from("Cargo.toml"),
+            }
+        );
+        assert_eq!(repos.repos[1].name, "web");
+        assert_eq!(repos.repos[1].branch.as_deref(), Some("develop"));
+
+        let twice = "[[repos]]\nurl = \"a/api.git\"\n[[repos]]\nurl = \"b/api\"\n";
+        assert!(Repos::from_document(Path::new(""), &twice.parse().unwrap()).is_err());
+        assert!(Repos::from_document(Path::new(""), &"[[repos]]\n".parse().unwrap()).is_err());
+    }
+
+    /// Tests that every bump argument, including every one of the
+    /// `bump-args` group, is forwarded with its value.
+    #[test]
+    fn test_bump_args() {
+        for name in crate::BUMP_COMPONENT_ARGS.iter() {
+            assert!(BUMP_ARGS.iter().any(|arg| arg.0 == *name), "{}", name);
+        }
+
+        for &(name, takes_value, _) in BUMP_ARGS.iter() {
+            let flag = format!("--{}", name);
+            let mut cli_args = vec!["semvercli", "multi", "bump", flag.as_str()];
+            if takes_value {
+                cli_args.push("1.0.0");
+            }
+            if !crate::BUMP_COMPONENT_ARGS.contains(&name) {
+                cli_args.push("--patch");
+            }
+            let matches = crate::parser().get_matches_from(cli_args);
+            let bump_matches = matches
+                .subcommand_matches("multi")
+                .and_then(|multi| multi.subcommand_matches("bump"))
+                .unwrap();
+
+            let forwarded = bump_args(bump_matches);
+            let at = forwarded.iter().position(|arg| *arg == flag).unwrap();
+            if takes_value {
+                assert_eq!(forwarded[at + 1], "1.0.0");
+            }
+        }
+    }
+}
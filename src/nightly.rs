@@ -0,0 +1,107 @@
+//! Nightly versions: the next minor version with a `nightly.<YYYYMMDD>`
+//! pre-release, e.g. `1.5.0-nightly.20240312` for a package at `1.4.2`.
+//! The date is an explicit input so the same inputs always produce the same
+//! version; it only defaults to the current UTC date.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use semver::{Identifier, Version};
+
+use crate::limits::Limits;
+use crate::pipeline::Component;
+
+/// The nightly version following `version` for the given `YYYYMMDD` date;
+/// fails if MINOR is at its limit.
+pub fn nightly_version(version: &Version, date: u64, limits: &Limits) -> Result<Version, String> {
+    let mut nightly = version.clone();
+    limits.increment(&mut nightly, Component::Minor)?;
+    nightly.pre = vec![
+        Identifier::AlphaNumeric(String::from("nightly")),
+        Identifier::Numeric(date),
+    ];
+
+    Ok(nightly)
+}
+
+/// Parses a date given as `YYYYMMDD` or `YYYY-MM-DD` into its `YYYYMMDD`
+/// numeric form.
+pub fn parse_date(date: &str) -> Result<u64, String> {
+    let digits = date.replace('-', "");
+    let valid = digits.len() == 8
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && (date.len() == 8 || date.len() == 10 && &date[4..5] == "-" && &date[7..8] == "-");
+    if !valid {
+        return Err(format!(
+            "Invalid date {}, expected YYYYMMDD or YYYY-MM-DD",
+            date
+        ));
+    }
+
+    let month = digits[4..6].parse::<u64>().unwrap();
+    let day = digits[6..8].parse::<u64>().unwrap();
+    if month == 0 || month > 12 || day == 0 || day > 31 {
+        return Err(format!("Invalid date {}", date));
+    }
+
+    Ok(digits.parse().unwrap())
+}
+
+/// Today's UTC date in `YYYYMMDD` form.
+pub fn today() -> u64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is set before 1970")
+        .as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+
+    (year as u64) * 10_000 + month * 100 + day
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) proleptic
+/// Gregorian date, after Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use semver::Version;
+
+    use super::*;
+
+    /// Tests that nightlies are based on the next minor version and that
+    /// pre-release and build information of the current version is dropped.
+    #[test]
+    fn test_nightly_version() {
+        let version = Version::parse("1.4.2-rc.1+abc").unwrap();
+
+        assert_eq!(
+            nightly_version(
+                &version,
+                parse_date("2024-03-12").unwrap(),
+                &Limits::default()
+            )
+            .unwrap()
+            .to_string(),
+            "1.5.0-nightly.20240312"
+        );
+        assert!(parse_date("2024-3-12").is_err());
+        assert!(parse_date("20241312").is_err());
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_794), (2024, 3, 12));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+}
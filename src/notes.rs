@@ -0,0 +1,107 @@
+//! Release metadata kept as git notes: the version, changelog and plan of a
+//! release are attached to the release commit under `refs/notes/semvercli`
+//! rather than to extra files, and travel with the repository when the
+//! notes ref is pushed. `notes show` reads them back from a tag.
+use semver::Version;
+use toml_edit::{value, Document};
+
+use super::string_value;
+use crate::error::{Code, Error};
+use crate::git;
+
+/// The notes ref the metadata is stored under.
+pub const NOTES_REF: &str = "semvercli";
+
+/// The metadata of a release.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub version: Version,
+    pub changelog: Option<String>,
+    /// The git object hash of the plan file the release was applied from.
+    pub plan: Option<String>,
+}
+
+impl Metadata {
+    /// Renders the metadata as the TOML note, e.g.:
+    ///
+    /// ```toml
+    /// version = "1.3.0"
+    /// plan = "5b1d3e0c..."
+    /// ```
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::new();
+        document["version"] = value(self.version.to_string());
+        if let Some(ref plan) = self.plan {
+            document["plan"] = string_value(plan);
+        }
+        if let Some(ref changelog) = self.changelog {
+            document["changelog"] = string_value(changelog);
+        }
+
+        document
+    }
+}
+
+/// Attaches the metadata to the commit `rev`, replacing an earlier note.
+pub fn attach(rev: &str, metadata: &Metadata) -> Result<(), Error> {
+    let note = metadata.to_document().to_string();
+
+    git::run(&[
+        "notes",
+        "--ref",
+        NOTES_REF,
+        "add",
+        "--force",
+        "--message",
+        note.as_str(),
+        rev,
+    ])
+    .map(|_| ())
+    .map_err(|err| {
+        Error::new(
+            Code::Git,
+            format!("Failed to attach the release notes to {}: {}", rev, err),
+        )
+    })
+}
+
+/// The metadata note of the commit the tag points to.
+pub fn show(tag: &str) -> Result<String, Error> {
+    git::run(&[
+        "notes",
+        "--ref",
+        NOTES_REF,
+        "show",
+        &format!("{}^{{commit}}", tag),
+    ])
+    .map_err(|_| {
+        Error::new(
+            Code::Git,
+            format!("{} has no release notes in refs/notes/{}", tag, NOTES_REF),
+        )
+    })
+}
+
+/// The git object hash of the file at `path`, as `git hash-object` computes it.
+pub fn hash_file(path: &str) -> Result<String, Error> {
+    git::run(&["hash-object", path])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_document() {
+        let metadata = Metadata {
+            version: Version::parse("1.3.0").unwrap(),
+            changelog: Some(String::from("- Added x")),
+            plan: Some(String::from("5b1d3e0c")),
+        };
+
+        assert_eq!(
+            metadata.to_document().to_string(),
+            "version = \"1.3.0\"\nplan = \"5b1d3e0c\"\nchangelog = \"- Added x\"\n"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Version arithmetic as `bump` does it, on versions in memory: no files,
+//! configuration or command line are involved, so build scripts and
+//! `xtask` crates can share the rules instead of reimplementing them.
+//!
+//! ```
+//! use semvercli::ops;
+//!
+//! let version = ops::parse("v1.2.3")?;
+//! let rc = ops::set_pre(&ops::bump_minor(&version)?, "rc.1")?;
+//! assert_eq!(rc.to_string(), "1.3.0-rc.1");
+//! assert_eq!(ops::finalize(&rc).to_string(), "1.3.0");
+//! assert!(ops::compare(&rc, &ops::finalize(&rc)).is_lt());
+//! # Ok::<(), semvercli::Error>(())
+//! ```
+use std::cmp::Ordering;
+
+use semver::Version;
+
+use crate::error::{Code, Error};
+use crate::{apply_bump, VersionOp};
+
+/// Parses a version, allowing a leading `v` as in tag names.
+pub fn parse(version: &str) -> Result<Version, Error> {
+    Version::parse(version.trim_start_matches('v')).map_err(|err| {
+        Error::new(
+            Code::InvalidVersion,
+            format!("Invalid version {}: {}", version, err),
+        )
+    })
+}
+
+/// Increments MAJOR, resetting MINOR and PATCH and dropping the
+/// pre-release and build metadata; fails past `u64::MAX`.
+pub fn bump_major(version: &Version) -> Result<Version, Error> {
+    apply_bump(version, &VersionOp::Major)
+}
+
+/// Increments MINOR, resetting PATCH and dropping the pre-release and
+/// build metadata; fails past `u64::MAX`.
+pub fn bump_minor(version: &Version) -> Result<Version, Error> {
+    apply_bump(version, &VersionOp::Minor)
+}
+
+/// Increments PATCH, dropping the pre-release and build metadata; fails
+/// past `u64::MAX`.
+pub fn bump_patch(version: &Version) -> Result<Version, Error> {
+    apply_bump(version, &VersionOp::Patch)
+}
+
+/// Sets the pre-release label, e.g. `rc.1`; fails if it is not a valid
+/// one.
+pub fn set_pre(version: &Version, pre: &str) -> Result<Version, Error> {
+    apply_bump(version, &VersionOp::Pre(pre.to_string()))
+}
+
+/// The release a version leads up to: the version without its pre-release
+/// and build metadata.
+pub fn finalize(version: &Version) -> Version {
+    let mut version = version.clone();
+    version.pre.clear();
+    version.build.clear();
+
+    version
+}
+
+/// Orders two versions by semver precedence, which ignores build metadata.
+pub fn compare(a: &Version, b: &Version) -> Ordering {
+    a.cmp(b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ops() {
+        let version = parse("1.2.3-rc.1+abc").unwrap();
+
+        assert_eq!(bump_major(&version).unwrap().to_string(), "2.0.0");
+        assert_eq!(bump_minor(&version).unwrap().to_string(), "1.3.0");
+        assert_eq!(bump_patch(&version).unwrap().to_string(), "1.2.4");
+        assert_eq!(
+            set_pre(&version, "beta.2").unwrap().to_string(),
+            "1.2.3-beta.2+abc"
+        );
+        assert_eq!(
+            set_pre(&version, "rc 1").unwrap_err().code,
+            Code::InvalidArgument
+        );
+        assert_eq!(finalize(&version).to_string(), "1.2.3");
+        assert_eq!(
+            compare(&version, &parse("v1.2.3-rc.1").unwrap()),
+            Ordering::Equal
+        );
+        assert_eq!(parse("1.2").unwrap_err().code, Code::InvalidVersion);
+        assert!(bump_patch(&parse("1.2.18446744073709551615").unwrap()).is_err());
+    }
+}
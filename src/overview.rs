@@ -0,0 +1,119 @@
+//! The overview `read --all` prints: every component of a version plus the
+//! facts derived from it, for a quick look at a checkout.
+use std::fmt;
+
+use crate::epoch::EpochVersion;
+use crate::error::json_string;
+use crate::VersionMetadata;
+
+/// A value in the overview.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(u64),
+    Flag(bool),
+    Text(String),
+}
+
+impl Value {
+    fn to_json(&self) -> String {
+        match self {
+            Value::Number(number) => number.to_string(),
+            Value::Flag(flag) => flag.to_string(),
+            Value::Text(text) => json_string(text),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(text) if text.is_empty() => write!(f, "{}", crate::EMPTY_COMPONENT),
+            Value::Text(text) => write!(f, "{}", text),
+            value => write!(f, "{}", value.to_json()),
+        }
+    }
+}
+
+/// The named rows of the overview of `version`. The requirement is the
+/// caret requirement Cargo would write for a dependency on exactly it.
+pub fn rows(version: &EpochVersion) -> Vec<(&'static str, Value)> {
+    let mut requirement = version.version.clone();
+    requirement.build.clear();
+
+    vec![
+        ("version", Value::Text(version.to_string())),
+        ("epoch", Value::Number(version.epoch)),
+        ("major", Value::Number(version.version.major)),
+        ("minor", Value::Number(version.version.minor)),
+        ("patch", Value::Number(version.version.patch)),
+        (
+            "pre",
+            Value::Text(String::from(VersionMetadata(version.version.pre.clone()))),
+        ),
+        (
+            "build",
+            Value::Text(String::from(VersionMetadata(version.version.build.clone()))),
+        ),
+        (
+            "is_prerelease",
+            Value::Flag(version.version.is_prerelease()),
+        ),
+        ("has_build", Value::Flag(!version.version.build.is_empty())),
+        ("requirement", Value::Text(format!("^{}", requirement))),
+    ]
+}
+
+/// The rows as lines of two aligned columns.
+pub fn table(rows: &[(&str, Value)]) -> String {
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    rows.iter()
+        .map(|(name, value)| format!("{:width$}  {}\n", name, value, width = width))
+        .collect()
+}
+
+/// The rows as a single line JSON object.
+pub fn json(rows: &[(&str, Value)]) -> String {
+    let fields = rows
+        .iter()
+        .map(|(name, value)| format!("{}: {}", json_string(name), value.to_json()))
+        .collect::<Vec<_>>();
+
+    format!("{{{}}}\n", fields.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let rows = rows(&EpochVersion::parse("1.2.3-rc.1+abc").unwrap());
+
+        assert_eq!(
+            table(&rows),
+            "version        1.2.3-rc.1+abc\n\
+             epoch          0\n\
+             major          1\n\
+             minor          2\n\
+             patch          3\n\
+             pre            rc.1\n\
+             build          abc\n\
+             is_prerelease  true\n\
+             has_build      true\n\
+             requirement    ^1.2.3-rc.1\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let rows = rows(&EpochVersion::parse("2!1.0.0").unwrap());
+
+        assert_eq!(
+            json(&rows),
+            "{\"version\": \"2!1.0.0\", \"epoch\": 2, \"major\": 1, \"minor\": 0, \"patch\": 0, \
+             \"pre\": \"\", \"build\": \"\", \"is_prerelease\": false, \"has_build\": false, \
+             \"requirement\": \"^1.0.0\"}\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Unified diffs of staged file changes, for `bump --dry-run`. The edit
+//! script is computed with Myers' algorithm, whose cost grows with the
+//! number of differences rather than the size of the files, as a version
+//! bump changes a few lines of possibly large files (lockfiles).
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+/// The unified diff turning `old` (`None` for a file that does not exist
+/// yet) into `new`, with `path` in the headers as git writes them; empty
+/// if the contents are the same.
+pub fn unified(path: &str, old: Option<&str>, new: &str) -> String {
+    let old_lines = old
+        .unwrap_or_default()
+        .split_inclusive('\n')
+        .collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let script = edits(&old_lines, &new_lines);
+    if old.is_some() && script.iter().all(|edit| matches!(edit, Edit::Keep(_))) {
+        return String::new();
+    }
+
+    let mut diff = match old {
+        Some(_) => format!("--- a/{}\n+++ b/{}\n", path, path),
+        None => format!("--- /dev/null\n+++ b/{}\n", path),
+    };
+    for (start, end) in hunks(&script) {
+        let before = |index: usize, keep: fn(&Edit) -> bool| {
+            script[..index].iter().filter(|edit| keep(edit)).count()
+        };
+        let is_old = |edit: &Edit| !matches!(edit, Edit::Add(_));
+        let is_new = |edit: &Edit| !matches!(edit, Edit::Remove(_));
+        let (old_start, new_start) = (before(start, is_old), before(start, is_new));
+        let (old_count, new_count) = (
+            before(end, is_old) - old_start,
+            before(end, is_new) - new_start,
+        );
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+        for edit in &script[start..end] {
+            let (marker, line) = match edit {
+                Edit::Keep(line) => (' ', line),
+                Edit::Remove(line) => ('-', line),
+                Edit::Add(line) => ('+', line),
+            };
+            diff.push(marker);
+            diff.push_str(line);
+            if !line.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    diff
+}
+
+/// A hunk header range: the first line (after the `skipped` ones) and the
+/// line count, which is left out when it is 1.
+fn range(skipped: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", skipped),
+        1 => (skipped + 1).to_string(),
+        _ => format!("{},{}", skipped + 1, count),
+    }
+}
+
+/// The spans of the edit script shown as hunks: every change with its
+/// context, merging changes whose contexts touch.
+fn hunks(script: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (index, edit) in script.iter().enumerate() {
+        if let Edit::Keep(_) = edit {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + 1 + CONTEXT).min(script.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+/// The shortest edit script turning `old` into `new`.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let at = |k: isize| (k + offset) as usize;
+    // The furthest x reached on each diagonal k = x - y, as of the start
+    // of every round d.
+    let mut furthest = vec![0; 2 * offset as usize + 1];
+    let mut trace = vec![];
+    let down = |furthest: &[isize], d: isize, k: isize| {
+        k == -d || (k != d && furthest[at(k - 1)] < furthest[at(k + 1)])
+    };
+
+    'search: for d in 0..=n + m {
+        trace.push(furthest.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if down(&furthest, d, k) {
+                furthest[at(k + 1)]
+            } else {
+                furthest[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut script = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if down(furthest, d, k) { k + 1 } else { k - 1 };
+        let previous_x = furthest[at(previous_k)];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            script.push(Edit::Keep(old[x as usize]));
+        }
+        if d > 0 {
+            if x == previous_x {
+                y -= 1;
+                script.push(Edit::Add(new[y as usize]));
+            } else {
+                x -= 1;
+                script.push(Edit::Remove(old[x as usize]));
+            }
+        }
+    }
+    script.reverse();
+
+    script
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unified() {
+        let old = "[package]\nname = \"app\"\nversion = \"1.2.3\"\nedition = \"2018\"\n\n[dependencies]\nserde = \"1\"\nregex = \"1\"\n";
+        let new = old.replace("1.2.3", "1.3.0");
+
+        assert_eq!(
+            unified("Cargo.toml", Some(old), &new),
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,6 +1,6 @@\n [package]\n name = \"app\"\n-version = \"1.2.3\"\n+version = \"1.3.0\"\n edition = \"2018\"\n \n [dependencies]\n"
+        );
+        assert_eq!(unified("Cargo.toml", Some(old), old), "");
+        assert_eq!(
+            unified("VERSION", Some("1.2.3"), "1.3.0"),
+            "--- a/VERSION\n+++ b/VERSION\n@@ -1 +1 @@\n-1.2.3\n\\ No newline at end of file\n+1.3.0\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified(".semvercli-keys.toml", None, "release-42 = \"1.3.0\"\n"),
+            "--- /dev/null\n+++ b/.semvercli-keys.toml\n@@ -0,0 +1 @@\n+release-42 = \"1.3.0\"\n"
+        );
+    }
+
+    /// Tests that distant changes get hunks of their own.
+    #[test]
+    fn test_unified_hunks() {
+        let old = (1..=20)
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        let new = (1..=20)
+            .map(|line| match line {
+                2 => String::from("two\n"),
+                19 => String::from("nineteen\n"),
+                _ => format!("{}\n", line),
+            })
+            .collect::<String>();
+
+        assert_eq!(
+            unified("lines", Some(&old), &new),
+            "--- a/lines\n+++ b/lines\n@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -16,5 +16,5 @@\n 16\n 17\n 18\n-19\n+nineteen\n 20\n"
+        );
+    }
+}
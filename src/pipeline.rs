@@ -0,0 +1,212 @@
+//! Operation pipelines for `semvercli do`: a sequence of steps such as
+//! `"bump minor" "set pre rc.1" "read version"` that runs in order against a
+//! single in-memory version, so the manifest is parsed once and written at
+//! most once, after every step has succeeded.
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+
+use semver::{Identifier, Version};
+
+use super::VersionMetadata;
+use crate::limits::Limits;
+
+/// A version component; MAJOR, MINOR and PATCH order by significance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Component {
+    Major,
+    Minor,
+    Patch,
+    Pre,
+    Build,
+    Version,
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Component::Major => "major",
+            Component::Minor => "minor",
+            Component::Patch => "patch",
+            Component::Pre => "pre",
+            Component::Build => "build",
+            Component::Version => "version",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A single step of a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Increments the MAJOR, MINOR or PATCH version.
+    Bump(Component),
+    /// Sets the PRE-RELEASE, BUILD or full VERSION; an empty label clears
+    /// the pre-release or build metadata.
+    Set(Component, String),
+    /// Prints a component of the version as of this step.
+    Read(Component),
+}
+
+impl Operation {
+    /// Parses an operation written as `bump <major|minor|patch>`,
+    /// `set <pre|build|version> [VALUE]` or `read <component>`.
+    pub fn parse(operation: &str) -> Result<Operation, String> {
+        let words = operation.split_whitespace().collect::<Vec<_>>();
+        let parsed = match words.as_slice() {
+            ["bump", component] => match component_named(component)? {
+                component @ Component::Major
+                | component @ Component::Minor
+                | component @ Component::Patch => Operation::Bump(component),
+                _ => return Err(format!("Cannot bump {}, only major, minor or patch", component)),
+            },
+            ["set", component] | ["set", component, _] => {
+                let label = words.get(2).copied().unwrap_or_default();
+                match component_named(component)? {
+                    component @ Component::Pre | component @ Component::Build
+                        if label.is_empty() || is_label(label) =>
+                    {
+                        Operation::Set(component, label.to_string())
+                    }
+                    Component::Version if Version::parse(label).is_ok() => {
+                        Operation::Set(Component::Version, label.to_string())
+                    }
+                    Component::Pre | Component::Build | Component::Version => {
+                        return Err(format!("Invalid {} given: {}", component, label))
+                    }
+                    _ => return Err(format!("Cannot set {}, only pre, build or version", component)),
+                }
+            }
+            ["read", component] => Operation::Read(component_named(component)?),
+            _ => {
+                return Err(format!(
+                    "Invalid operation `{}`, expected `bump <component>`, `set <component> <value>` or `read <component>`",
+                    operation
+                ))
+            }
+        };
+
+        Ok(parsed)
+    }
+
+    /// Whether the operation changes the version.
+    pub fn mutates(&self) -> bool {
+        !matches!(self, Operation::Read(_))
+    }
+}
+
+/// Runs the operations in order, starting from `version`, writes the output
+/// of every `read` step to `stdout` and returns the resulting version; fails
+/// if a bump goes past the component's limit.
+pub fn run(
+    operations: &[Operation],
+    version: &Version,
+    limits: &Limits,
+    stdout: &mut dyn Write,
+) -> Result<Version, String> {
+    let mut version = version.clone();
+
+    for operation in operations {
+        match operation {
+            Operation::Bump(component) => limits.increment(&mut version, *component)?,
+            Operation::Set(Component::Pre, pre) => version.pre = metadata(pre),
+            Operation::Set(Component::Build, build) => version.build = metadata(build),
+            Operation::Set(Component::Version, new) => version = Version::parse(new).unwrap(),
+            Operation::Read(component) => {
+                writeln!(stdout, "{}", read(&version, *component)).unwrap()
+            }
+            _ => panic!("Unreachable - unsupported operation {:?}.", operation),
+        }
+    }
+
+    Ok(version)
+}
+
+fn read(version: &Version, component: Component) -> String {
+    match component {
+        Component::Major => version.major.to_string(),
+        Component::Minor => version.minor.to_string(),
+        Component::Patch => version.patch.to_string(),
+        Component::Pre => String::from(VersionMetadata(version.pre.clone())),
+        Component::Build => String::from(VersionMetadata(version.build.clone())),
+        Component::Version => version.to_string(),
+    }
+}
+
+fn component_named(name: &str) -> Result<Component, String> {
+    match name {
+        "major" => Ok(Component::Major),
+        "minor" => Ok(Component::Minor),
+        "patch" => Ok(Component::Patch),
+        "pre" => Ok(Component::Pre),
+        "build" => Ok(Component::Build),
+        "version" => Ok(Component::Version),
+        _ => Err(format!("Unknown version component `{}`", name)),
+    }
+}
+
+/// Whether `label` is a valid pre-release or build label.
+fn is_label(label: &str) -> bool {
+    Version::parse(&format!("0.0.0-{}", label)).is_ok()
+}
+
+fn metadata(label: &str) -> Vec<Identifier> {
+    if label.is_empty() {
+        vec![]
+    } else {
+        VersionMetadata::try_from(label).unwrap().0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str;
+
+    use semver::Version;
+
+    use super::*;
+
+    /// Tests that every step sees the result of the previous ones and that
+    /// reads print the intermediate versions.
+    #[test]
+    fn test_run() {
+        let operations = [
+            "bump minor",
+            "set pre rc.1",
+            "read version",
+            "set pre",
+            "read pre",
+        ]
+        .iter()
+        .map(|operation| Operation::parse(operation).unwrap())
+        .collect::<Vec<_>>();
+        let mut stdout = Vec::new();
+
+        let version = run(
+            &operations,
+            &Version::parse("1.2.3+abc").unwrap(),
+            &Limits::default(),
+            &mut stdout,
+        )
+        .unwrap();
+
+        assert_eq!(version, Version::parse("1.3.0").unwrap());
+        assert_eq!(str::from_utf8(&stdout).unwrap(), "1.3.0-rc.1\n\n");
+    }
+
+    /// Tests that malformed operations are rejected up front.
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Operation::parse("set build x.1"),
+            Ok(Operation::Set(Component::Build, String::from("x.1")))
+        );
+        assert!(Operation::parse("bump pre").is_err());
+        assert!(Operation::parse("set major 2").is_err());
+        assert!(Operation::parse("set version 1.2").is_err());
+        assert!(Operation::parse("set pre rc!1").is_err());
+        assert!(Operation::parse("read").is_err());
+        assert!(Operation::parse("frobnicate minor").is_err());
+    }
+}
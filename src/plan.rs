@@ -0,0 +1,453 @@
+//! Two-phase plan/apply workflow: `plan` computes every mutation a bump
+//! would perform and records it in a TOML plan file, which `apply` later
+//! executes verbatim. Splitting computation from mutation allows putting an
+//! approval gate (code review, a manual CI step) in between the two.
+use std::fs;
+use std::path::Path;
+
+use semver::Version;
+use toml_edit::{value, ArrayOfTables, Document, Item, Table, Value};
+
+use super::{enforce_branch_policy, string_value};
+use crate::adapter::{self, Format};
+use crate::config::Config;
+use crate::error::{Code, Error};
+use crate::events;
+use crate::git;
+use crate::idempotency;
+use crate::sha256;
+use crate::transaction::Transaction;
+use crate::warnings;
+
+/// A single manifest whose version is changed by a plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    /// The path of the file relative to the configuration's root, so that
+    /// the plan applies the same from any directory.
+    pub path: String,
+    pub old: Version,
+    pub new: Version,
+    /// The SHA-256 of the file when the plan was computed; applying the
+    /// plan refuses to write a file that changed since.
+    pub hash: Option<String>,
+}
+
+impl FileChange {
+    /// The path of the file, resolved against the configuration's root.
+    pub fn resolve(&self, config: &Config) -> String {
+        if Path::new(&self.path).is_absolute() {
+            return self.path.clone();
+        }
+
+        config.root.join(&self.path).to_string_lossy().into_owned()
+    }
+}
+
+/// `path` relative to the configuration's root, as a plan records it;
+/// unchanged if it is outside of the root.
+pub fn relative(config: &Config, path: &str) -> String {
+    match Path::new(path).strip_prefix(&config.root) {
+        Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Every mutation applying a plan performs: the manifests to rewrite
+/// and the git tags to create afterwards, in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Plan {
+    pub files: Vec<FileChange>,
+    pub tags: Vec<String>,
+    /// The idempotency key the plan is applied under, if any.
+    pub key: Option<String>,
+}
+
+impl Plan {
+    /// Renders the plan into its on-disk TOML form, e.g.:
+    ///
+    /// ```toml
+    /// tags = ["v1.3.0"]
+    /// idempotency_key = "release-run-1234"
+    ///
+    /// [[files]]
+    /// path = "Cargo.toml"
+    /// old = "1.2.3"
+    /// new = "1.3.0"
+    /// hash = "3f1c...e07a"
+    /// ```
+    pub fn to_document(&self) -> Document {
+        let mut files = ArrayOfTables::new();
+
+        for change in &self.files {
+            let mut table = Table::new();
+            table["path"] = string_value(&change.path);
+            table["old"] = value(change.old.to_string());
+            table["new"] = value(change.new.to_string());
+            if let Some(ref hash) = change.hash {
+                table["hash"] = value(hash.as_str());
+            }
+            files.append(table);
+        }
+
+        let mut document = Document::new();
+        document["tags"] = value(self.tags.iter().map(|tag| tag.as_str()).collect::<Value>());
+        if let Some(ref key) = self.key {
+            document["idempotency_key"] = string_value(key);
+        }
+        document["files"] = Item::ArrayOfTables(files);
+
+        document
+    }
+
+    /// Parses a plan back from its TOML form; fails on plan files that
+    /// were not produced by `to_document`.
+    pub fn from_document(document: &Document) -> Result<Plan, Error> {
+        let files = match document["files"].as_array_of_tables() {
+            Some(files) => files.iter().map(file_change).collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let tags = match document["tags"].as_array() {
+            Some(tags) => tags
+                .iter()
+                .map(|tag| {
+                    tag.as_str()
+                        .map(String::from)
+                        .ok_or_else(|| Error::new(Code::Plan, "Invalid plan: tags must be strings"))
+                })
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+
+        let key = match document["idempotency_key"].as_str() {
+            Some(key) => Some(key.to_string()),
+            None if document["idempotency_key"].is_none() => None,
+            None => {
+                return Err(Error::new(
+                    Code::Plan,
+                    "Invalid plan: idempotency_key must be a string",
+                ))
+            }
+        };
+
+        Ok(Plan { files, tags, key })
+    }
+}
+
+fn file_change(table: &Table) -> Result<FileChange, Error> {
+    let field = |key: &str| {
+        table[key].as_str().ok_or_else(|| {
+            Error::new(
+                Code::Plan,
+                format!("Invalid plan: missing `{}` in [[files]]", key),
+            )
+        })
+    };
+    let version = |key: &str| {
+        Version::parse(field(key)?).map_err(|_| {
+            Error::new(
+                Code::Plan,
+                format!("Invalid plan: `{}` is not a valid version", key),
+            )
+        })
+    };
+
+    Ok(FileChange {
+        path: field("path")?.to_string(),
+        old: version("old")?,
+        new: version("new")?,
+        hash: match table["hash"].as_str() {
+            Some(hash) => Some(hash.to_string()),
+            None if table["hash"].is_none() => None,
+            None => {
+                return Err(Error::new(
+                    Code::Plan,
+                    "Invalid plan: `hash` in [[files]] must be a string",
+                ))
+            }
+        },
+    })
+}
+
+pub fn write_plan(plan: &Plan, path: &str) -> Result<(), Error> {
+    fs::write(path, plan.to_document().to_string()).map_err(|err| {
+        Error::new(
+            Code::Io,
+            format!("Failed to write plan file {}: {}", path, err),
+        )
+    })
+}
+
+pub fn read_plan(path: &str) -> Result<Plan, Error> {
+    let document = fs::read_to_string(path)
+        .map_err(|err| {
+            Error::new(
+                Code::Plan,
+                format!("Could not read plan file {}: {}", path, err),
+            )
+        })?
+        .parse::<Document>()
+        .map_err(|err| Error::new(Code::Plan, format!("Invalid plan file {}: {}", path, err)))?;
+
+    Plan::from_document(&document)
+}
+
+/// Executes the plan exactly as recorded. Every manifest is checked to
+/// still be at the version the plan was computed from, and to still have
+/// the recorded hash, before anything is written, so a stale plan is
+/// refused instead of silently overriding changes made since. Manifests
+/// of protected packages are skipped and reported rather than modified.
+/// All remaining manifests are written in a single transaction with tag
+/// creation as its final step; if any of it fails, every file is rolled
+/// back and already created tags are deleted again. A plan whose
+/// idempotency key was applied already is skipped with a report.
+pub fn apply(plan: &Plan, config: &Config) -> Result<(), Error> {
+    if let Some(ref key) = plan.key {
+        if idempotency::skip_applied(config, key)? {
+            return Ok(());
+        }
+    }
+    if !plan.tags.is_empty() {
+        config.forbid_in_deterministic("Git (plan tags)")?;
+    }
+    let mut transaction = Transaction::new();
+
+    for change in &plan.files {
+        let path = change.resolve(config);
+        let format = Format::detect(&path)?;
+        let contents = adapter::read_file(&path)?;
+        if let Some(ref hash) = change.hash {
+            if !sha256::matches(hash, &sha256::hex(contents.as_bytes())) {
+                return Err(Error::new(
+                    Code::StalePlan,
+                    format!("Stale plan: {} changed since the plan was computed", path),
+                ));
+            }
+        }
+        if config.is_protected(format.package_name(&contents).as_deref(), &path) {
+            warnings::warn(
+                config,
+                warnings::Kind::Protected,
+                format!(
+                    "Skipped {}: the package is protected from version changes",
+                    path
+                ),
+            )?;
+            continue;
+        }
+        let current = format.read_version(&contents, &path)?;
+        events::parsed(config, &path, &current);
+        if current != change.old {
+            return Err(Error::new(
+                Code::StalePlan,
+                format!(
+                    "Stale plan: {} is at version {}, but the plan expects {}",
+                    path, current, change.old
+                ),
+            ));
+        }
+        events::computed(config, &path, &current, &change.new);
+        enforce_branch_policy(config, &change.new)?;
+        transaction.stage(&path, format.write_version(&contents, &change.new, &path)?);
+    }
+
+    if let (Some(key), Some(change)) = (&plan.key, plan.files.first()) {
+        idempotency::stage(&mut transaction, config, key, &change.new)?;
+    }
+    let paths = transaction.paths();
+    transaction
+        .commit(|| create_tags(&plan.tags))
+        .map_err(|err| {
+            Error::new(
+                err.code,
+                format!(
+                    "Failed to apply plan, all changes were rolled back: {}",
+                    err
+                ),
+            )
+        })?;
+    events::written(config, &paths);
+    for tag in &plan.tags {
+        events::tagged(config, tag);
+    }
+
+    Ok(())
+}
+
+/// Creates the given tags in order; if one fails, the ones created
+/// before it are deleted so the repository is left as it was.
+fn create_tags(tags: &[String]) -> Result<(), Error> {
+    for (index, tag) in tags.iter().enumerate() {
+        if let Err(err) = git::run(&["tag", tag.as_str()]) {
+            for created in &tags[..index] {
+                let _ = git::run(&["tag", "--delete", created.as_str()]);
+            }
+            return Err(Error::new(
+                Code::Git,
+                format!("Failed to create git tag {}: {}", tag, err),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use semver::Version;
+    use tempfile::tempdir;
+    use toml_edit::Document;
+
+    use super::*;
+    use crate::{read_manifest, read_version};
+
+    fn plan_for(path: &str) -> Plan {
+        Plan {
+            files: vec![FileChange {
+                path: path.to_string(),
+                old: Version::parse("1.2.3").unwrap(),
+                new: Version::parse("1.3.0-rc.1").unwrap(),
+                hash: None,
+            }],
+            tags: vec![String::from("v1.3.0-rc.1")],
+            key: None,
+        }
+    }
+
+    /// Tests that a plan survives being written out and read back unchanged.
+    #[test]
+    fn test_roundtrip() {
+        let mut plan = plan_for("Cargo.toml");
+        plan.key = Some(String::from("release-run-1234"));
+        plan.files[0].hash = Some(String::from("3f1c"));
+        let document = plan.to_document().to_string().parse::<Document>().unwrap();
+
+        assert_eq!(plan, Plan::from_document(&document).unwrap());
+    }
+
+    /// Tests that applying a plan rewrites the manifest, and that a second
+    /// application of the same plan is refused as stale.
+    #[test]
+    fn test_apply() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+
+        let mut plan = plan_for(manifest_path);
+        plan.tags.clear();
+        apply(&plan, &Config::default()).unwrap();
+
+        assert_eq!(
+            read_version(&read_manifest(manifest_path).unwrap()).unwrap(),
+            Version::parse("1.3.0-rc.1").unwrap()
+        );
+        assert_eq!(
+            apply(&plan, &Config::default()).unwrap_err().code,
+            Code::StalePlan
+        );
+    }
+
+    /// Tests that a plan is refused as stale when a file changed since its
+    /// hash was recorded, even if its version did not.
+    #[test]
+    fn test_apply_hash() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+
+        let mut plan = plan_for(manifest_path);
+        plan.tags.clear();
+        plan.files[0].hash = Some(sha256::file_hex(manifest_path).unwrap());
+        fs::write(
+            manifest_path,
+            "[package]\nversion = \"1.2.3\"\nedition = \"2018\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            apply(&plan, &Config::default()).unwrap_err().code,
+            Code::StalePlan
+        );
+    }
+
+    /// Tests that a plan applied under an idempotency key is skipped when
+    /// applied again under the same key.
+    #[test]
+    fn test_apply_idempotent() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+
+        let mut plan = plan_for(manifest_path);
+        plan.tags.clear();
+        plan.key = Some(String::from("release-run-1234"));
+        let config = Config {
+            root: tmpdir.path().to_path_buf(),
+            ..Config::default()
+        };
+        apply(&plan, &config).unwrap();
+        apply(&plan, &config).unwrap();
+
+        assert_eq!(
+            read_version(&read_manifest(manifest_path).unwrap()).unwrap(),
+            Version::parse("1.3.0-rc.1").unwrap()
+        );
+    }
+
+    /// Tests that manifests of protected packages are left untouched.
+    #[test]
+    fn test_apply_skips_protected() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(
+            manifest_path,
+            "[package]\nname = \"vendored\"\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+
+        let mut plan = plan_for(manifest_path);
+        plan.tags.clear();
+        let config = Config {
+            protected: vec![String::from("vendored")],
+            ..Config::default()
+        };
+        apply(&plan, &config).unwrap();
+
+        assert_eq!(
+            read_version(&read_manifest(manifest_path).unwrap()).unwrap(),
+            Version::parse("1.2.3").unwrap()
+        );
+    }
+
+    /// Tests that a plan records paths relative to the configuration's
+    /// root, and applies from another directory against the root.
+    #[test]
+    fn test_apply_relative() {
+        let tmpdir = tempdir().unwrap();
+        fs::create_dir(tmpdir.path().join("app")).unwrap();
+        let tmp_path = tmpdir.path().join("app").join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        let plan_path = tmpdir.path().join("plan.toml");
+        let plan_path = plan_path.to_str().unwrap();
+        fs::write(manifest_path, "[package]\nversion = \"1.2.3\"\n").unwrap();
+        let run = |args: &[&str]| {
+            let mut cli_args = vec!["semvercli", "--manifest-path", manifest_path];
+            cli_args.extend_from_slice(args);
+            crate::execute(&crate::parser().get_matches_from(cli_args), &mut Vec::new())
+        };
+
+        run(&["plan", plan_path, "--minor"]).unwrap();
+        assert_eq!(read_plan(plan_path).unwrap().files[0].path, "Cargo.toml");
+        run(&["apply", plan_path]).unwrap();
+
+        assert_eq!(
+            read_version(&read_manifest(manifest_path).unwrap()).unwrap(),
+            Version::parse("1.3.0").unwrap()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! WASM plugins: version file formats semvercli does not know itself,
+//! provided by third parties as WebAssembly components of the `adapter`
+//! world in `wit/adapter.wit` (`detect`, `read` and `write`). Every `*.wasm`
+//! file in the directory configured as `plugins` is loaded, and a file no
+//! built-in format claims is handed to the first plugin (by file name) whose
+//! `detect` accepts its path. Plugins run sandboxed: they see nothing but the
+//! paths and contents passed to them. Loading plugins requires building
+//! semvercli with the `plugins` feature.
+use std::path::Path;
+
+use semver::Version;
+
+use crate::error::{Code, Error};
+
+#[cfg(feature = "plugins")]
+mod runtime {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use wasmtime::component::{Component, Linker};
+    use wasmtime::{Engine, Store};
+
+    use crate::error::{Code, Error};
+
+    wasmtime::component::bindgen!({ world: "adapter", path: "wit/adapter.wit" });
+
+    struct Plugin {
+        path: PathBuf,
+        engine: Engine,
+        component: Component,
+    }
+
+    /// The plugins of the current run; a format's plugin index points here.
+    static PLUGINS: Mutex<Vec<Plugin>> = Mutex::new(Vec::new());
+
+    pub fn load(dir: &Path) -> Result<(), Error> {
+        let mut paths = fs::read_dir(dir)
+            .map_err(|err| {
+                Error::new(
+                    Code::Adapter,
+                    format!("Could not read plugins from {}: {}", dir.display(), err),
+                )
+            })?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "wasm")
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let engine = Engine::default();
+        let plugins = paths
+            .into_iter()
+            .map(|path| {
+                let component = Component::from_file(&engine, &path).map_err(|err| {
+                    Error::new(
+                        Code::Adapter,
+                        format!("Invalid plugin {}: {}", path.display(), err),
+                    )
+                })?;
+                Ok(Plugin {
+                    path,
+                    engine: engine.clone(),
+                    component,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        *PLUGINS.lock().unwrap() = plugins;
+
+        Ok(())
+    }
+
+    /// Runs `call` against a fresh instance of the plugin.
+    pub fn call<T, F>(index: usize, call: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Adapter, &mut Store<()>) -> wasmtime::Result<T>,
+    {
+        let plugins = PLUGINS.lock().unwrap();
+        let plugin = &plugins[index];
+        let mut store = Store::new(&plugin.engine, ());
+
+        Adapter::instantiate(&mut store, &plugin.component, &Linker::new(&plugin.engine))
+            .and_then(|adapter| call(&adapter, &mut store))
+            .map_err(|err| {
+                Error::new(
+                    Code::Adapter,
+                    format!("Plugin {} failed: {}", plugin.path.display(), err),
+                )
+            })
+    }
+
+    pub fn count() -> usize {
+        PLUGINS.lock().unwrap().len()
+    }
+}
+
+/// Loads the plugins in `dir`, replacing any loaded before.
+#[cfg(feature = "plugins")]
+pub fn load(dir: &Path) -> Result<(), Error> {
+    runtime::load(dir)
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn load(dir: &Path) -> Result<(), Error> {
+    Err(Error::new(
+        Code::Config,
+        format!(
+            "Plugins are configured ({}), but semvercli was built without the `plugins` feature",
+            dir.display()
+        ),
+    ))
+}
+
+/// The index of the first plugin that handles the file at `path`.
+#[cfg(feature = "plugins")]
+pub fn detect(path: &str) -> Result<Option<usize>, Error> {
+    for index in 0..runtime::count() {
+        if runtime::call(index, |adapter, store| adapter.call_detect(store, path))? {
+            return Ok(Some(index));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn detect(_path: &str) -> Result<Option<usize>, Error> {
+    Ok(None)
+}
+
+#[cfg(feature = "plugins")]
+pub fn read_version(plugin: usize, contents: &str, path: &str) -> Result<Version, Error> {
+    let version = runtime::call(plugin, |adapter, store| adapter.call_read(store, contents))?
+        .map_err(|err| Error::new(Code::Adapter, format!("{}: {}", path, err)))?;
+
+    Version::parse(&version).map_err(|_| {
+        Error::new(
+            Code::InvalidVersion,
+            format!("Invalid version {} in {}", version, path),
+        )
+    })
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn read_version(_plugin: usize, _contents: &str, _path: &str) -> Result<Version, Error> {
+    panic!("Unreachable - no plugins are loaded without the plugins feature.");
+}
+
+#[cfg(feature = "plugins")]
+pub fn write_version(
+    plugin: usize,
+    contents: &str,
+    version: &Version,
+    path: &str,
+) -> Result<String, Error> {
+    runtime::call(plugin, |adapter, store| {
+        adapter.call_write(store, contents, &version.to_string())
+    })?
+    .map_err(|err| Error::new(Code::Adapter, format!("{}: {}", path, err)))
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn write_version(
+    _plugin: usize,
+    _contents: &str,
+    _version: &Version,
+    _path: &str,
+) -> Result<String, Error> {
+    panic!("Unreachable - no plugins are loaded without the plugins feature.");
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Tests that a plugins directory is refused without plugin support,
+    /// and that files that are no components are refused with it.
+    #[test]
+    fn test_load() {
+        let tmpdir = tempdir().unwrap();
+        fs::write(tmpdir.path().join("format.wasm"), "not a component").unwrap();
+        fs::write(tmpdir.path().join("README.md"), "ignored").unwrap();
+
+        let expected = if cfg!(feature = "plugins") {
+            Code::Adapter
+        } else {
+            Code::Config
+        };
+        assert_eq!(load(tmpdir.path()).unwrap_err().code, expected);
+        assert_eq!(detect("app.custom").unwrap(), None);
+    }
+}
@@ -0,0 +1,83 @@
+//! Pre-release retention: `prune-prereleases` lists the pre-release tags of
+//! a package beyond the newest `--keep` ones, and with `--delete-tags`
+//! deletes them, so repositories cutting nightly or rc tags keep their tag
+//! namespace manageable. Release tags are never touched.
+use semver::Version;
+
+use crate::error::Error;
+use crate::git;
+
+/// The pre-release tags among `tags` (with the format `tag_format`, e.g.
+/// `v{version}`) older than the newest `keep` ones, oldest first.
+pub fn stale<S: AsRef<str>>(tags: &[S], tag_format: &str, keep: usize) -> Vec<String> {
+    let (prefix, suffix) = tag_format.split_at(tag_format.find("{version}").unwrap_or(0));
+    let suffix = suffix.trim_start_matches("{version}");
+    let mut pre_releases = tags
+        .iter()
+        .filter_map(|tag| {
+            let tag = tag.as_ref();
+            let version = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            let version = Version::parse(version).ok()?;
+            if version.pre.is_empty() {
+                return None;
+            }
+            Some((version, tag))
+        })
+        .collect::<Vec<_>>();
+    pre_releases.sort();
+    let stale = pre_releases.len().saturating_sub(keep);
+
+    pre_releases
+        .into_iter()
+        .take(stale)
+        .map(|(_, tag)| tag.to_string())
+        .collect()
+}
+
+/// Deletes the tags, from `remote` first if given, so that a failed push
+/// leaves the local tags to retry with.
+pub fn delete(tags: &[String], remote: Option<&str>) -> Result<(), Error> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    if let Some(remote) = remote {
+        let refs = tags
+            .iter()
+            .map(|tag| format!("refs/tags/{}", tag))
+            .collect::<Vec<_>>();
+        let mut args = vec!["push", "--quiet", "--delete", remote];
+        args.extend(refs.iter().map(String::as_str));
+        git::run(&args)?;
+    }
+    let mut args = vec!["tag", "--delete"];
+    args.extend(tags.iter().map(String::as_str));
+
+    git::run(&args).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that only pre-releases of the tag format are pruned, by
+    /// precedence rather than by name, and that the newest are kept.
+    #[test]
+    fn test_stale() {
+        let tags = [
+            "v1.0.0",
+            "v1.1.0-nightly.10",
+            "v1.1.0-nightly.9",
+            "v1.1.0-rc.1",
+            "v1.0.0-rc.1",
+            "api-v1.1.0-nightly.1",
+            "v1.1.0",
+        ];
+
+        assert_eq!(
+            stale(&tags, "v{version}", 2),
+            ["v1.0.0-rc.1", "v1.1.0-nightly.9"]
+        );
+        assert_eq!(stale(&tags, "api-v{version}", 0), ["api-v1.1.0-nightly.1"]);
+        assert!(stale(&tags, "v{version}", 5).is_empty());
+    }
+}
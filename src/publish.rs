@@ -0,0 +1,136 @@
+//! Publishing a Cargo workspace: every crate has to be published after the
+//! workspace crates it depends on, and only once the registry index lists
+//! them, or `cargo publish` fails to resolve the dependency.
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{Code, Error};
+use crate::registry;
+use crate::workspace::Crate;
+
+/// The crates in an order in which each comes after the crates it depends
+/// on, alphabetical among those that are ready at the same time.
+/// Dependencies on crates that are not in `crates` are ignored; a cycle is
+/// an error.
+pub fn order(crates: &[Crate]) -> Result<Vec<&Crate>, Error> {
+    let mut pending = crates.iter().collect::<Vec<_>>();
+    pending.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut ordered: Vec<&Crate> = vec![];
+
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|candidate| {
+            candidate.dependencies.iter().all(|dependency| {
+                ordered.iter().any(|done| done.name == *dependency)
+                    || !pending.iter().any(|other| other.name == *dependency)
+            })
+        });
+        match ready {
+            Some(index) => ordered.push(pending.remove(index)),
+            None => {
+                return Err(Error::new(
+                    Code::Workspace,
+                    format!(
+                        "The workspace crates {} depend on each other in a cycle",
+                        pending
+                            .iter()
+                            .map(|krate| krate.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(ordered)
+}
+
+/// Publishes a crate with `cargo publish`, retrying a failed publish up to
+/// `retries` more times, `interval` apart.
+pub fn publish(krate: &Crate, retries: u32, interval: Duration) -> Result<(), Error> {
+    let mut command = Command::new("cargo");
+    command.args(["publish", "--manifest-path", krate.path.as_str()]);
+
+    let mut attempt = 0;
+    loop {
+        let output = command.output().map_err(|err| {
+            Error::new(
+                Code::Command,
+                format!("Failed to run {:?}: {}", command, err),
+            )
+        })?;
+        if output.status.success() {
+            return Ok(());
+        }
+        if attempt == retries {
+            return Err(Error::new(
+                Code::Command,
+                format!(
+                    "{:?} failed after {} attempts: {}",
+                    command,
+                    attempt + 1,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        attempt += 1;
+        thread::sleep(interval);
+    }
+}
+
+/// Publishes the crates in order, waiting after each until the index
+/// lists it.
+pub fn publish_all(
+    crates: &[&Crate],
+    index: &str,
+    retries: u32,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<(), Error> {
+    for krate in crates {
+        publish(krate, retries, interval)?;
+        registry::wait(index, &krate.name, &krate.version, timeout, interval)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use semver::Version;
+
+    use super::*;
+
+    fn krate(name: &str, dependencies: &[&str]) -> Crate {
+        Crate {
+            name: name.to_string(),
+            path: format!("{}/Cargo.toml", name),
+            version: Version::parse("1.0.0").unwrap(),
+            publish: true,
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        let crates = vec![
+            krate("app", &["core", "macros"]),
+            krate("macros", &["core"]),
+            krate("core", &["serde"]),
+            krate("cli", &["app"]),
+        ];
+
+        assert_eq!(
+            order(&crates)
+                .unwrap()
+                .iter()
+                .map(|krate| krate.name.as_str())
+                .collect::<Vec<_>>(),
+            ["core", "macros", "app", "cli"]
+        );
+
+        let cycle = vec![krate("a", &["b"]), krate("b", &["a"]), krate("c", &[])];
+        assert_eq!(order(&cycle).unwrap_err().code, Code::Workspace);
+    }
+}
@@ -0,0 +1,114 @@
+//! Waiting for a version to be published: the package's entry in a sparse
+//! Cargo registry index (crates.io's by default) is fetched with `curl`
+//! until it lists the version, so jobs that depend on the crate right after
+//! `cargo publish` can gate on it instead of sleeping.
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use semver::Version;
+
+use crate::error::{Code, Error};
+
+/// The sparse index of crates.io.
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io";
+
+/// The path of a package's entry in a sparse index, which shards packages
+/// by the first characters of their lowercased name.
+pub fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Whether an index entry (one JSON object per published version) lists
+/// `version`.
+pub fn lists_version(entry: &str, version: &Version) -> bool {
+    let needle = format!("\"vers\":\"{}\"", version);
+
+    entry
+        .lines()
+        .any(|line| line.replace(' ', "").contains(&needle))
+}
+
+/// Fetches the index entry of `name`; `None` while the registry does not
+/// have it (yet) or cannot be reached.
+fn fetch(index: &str, name: &str) -> Result<Option<String>, Error> {
+    let url = format!("{}/{}", index.trim_end_matches('/'), index_path(name));
+    let mut command = Command::new("curl");
+    command.args(["--fail", "--silent", "--location", url.as_str()]);
+    let output = command.output().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Failed to run {:?}: {}", command, err),
+        )
+    })?;
+
+    Ok(if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    })
+}
+
+/// Polls the index every `interval` until the entry of `name` lists
+/// `version`; fails once `timeout` has passed without it.
+pub fn wait(
+    index: &str,
+    name: &str,
+    version: &Version,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<(), Error> {
+    let start = Instant::now();
+
+    loop {
+        if let Some(entry) = fetch(index, name)? {
+            if lists_version(&entry, version) {
+                return Ok(());
+            }
+        }
+        if start.elapsed() + interval > timeout {
+            return Err(Error::new(
+                Code::Timeout,
+                format!(
+                    "{} {} was not published within {} seconds",
+                    name,
+                    version,
+                    timeout.as_secs()
+                ),
+            ));
+        }
+        thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("io"), "2/io");
+        assert_eq!(index_path("syn"), "3/s/syn");
+        assert_eq!(index_path("Semver"), "se/mv/semver");
+    }
+
+    #[test]
+    fn test_lists_version() {
+        let entry = "{\"name\":\"semvercli\",\"vers\":\"0.1.0\",\"deps\":[]}\n\
+                     {\"name\":\"semvercli\",\"vers\":\"0.2.0-rc.1\",\"deps\":[]}\n";
+
+        assert!(lists_version(entry, &Version::parse("0.2.0-rc.1").unwrap()));
+        assert!(!lists_version(entry, &Version::parse("0.2.0").unwrap()));
+        assert!(!lists_version(
+            entry,
+            &Version::parse("0.1.0-rc.1").unwrap()
+        ));
+    }
+}
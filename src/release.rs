@@ -0,0 +1,341 @@
+//! Pending-release lockfile. A release that spans several CI jobs records
+//! its target version, the packages it includes and a snapshot of the
+//! changelog in `.semvercli-release.toml` next to the manifest when it is
+//! started; the later steps (`tag`, `publish`, `finalize`) only consume that
+//! file instead of recomputing anything, so every job acts on the same release.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ArgMatches;
+use semver::Version;
+use toml_edit::{value, ArrayOfTables, Document, Item, Table};
+
+use super::{
+    bumped_version, enforce_branch_policy, ensure_unprotected, read_manifest, read_version,
+    string_value,
+};
+use crate::adapter;
+use crate::changelog;
+use crate::config::Config;
+use crate::describe;
+use crate::error::{Code, Error};
+use crate::events;
+use crate::git;
+use crate::notes;
+use crate::transaction::Transaction;
+
+pub const LOCKFILE: &str = ".semvercli-release.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub path: String,
+}
+
+/// The in-flight release, as recorded in the lockfile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub version: Version,
+    pub packages: Vec<Package>,
+    pub changelog: Option<String>,
+}
+
+impl Release {
+    pub fn to_document(&self) -> Document {
+        let mut packages = ArrayOfTables::new();
+
+        for package in &self.packages {
+            let mut table = Table::new();
+            table["name"] = string_value(&package.name);
+            table["path"] = string_value(&package.path);
+            packages.append(table);
+        }
+
+        let mut document = Document::new();
+        document["version"] = value(self.version.to_string());
+        if let Some(ref changelog) = self.changelog {
+            document["changelog"] = string_value(changelog);
+        }
+        document["packages"] = Item::ArrayOfTables(packages);
+
+        document
+    }
+
+    /// Parses a release back from the lockfile; fails on lockfiles
+    /// that were not produced by `to_document`.
+    pub fn from_document(document: &Document) -> Result<Release, Error> {
+        let version = document["version"]
+            .as_str()
+            .and_then(|version| Version::parse(version).ok())
+            .ok_or_else(|| {
+                Error::new(
+                    Code::Release,
+                    "Invalid release lockfile: missing or invalid version",
+                )
+            })?;
+        let packages = match document["packages"].as_array_of_tables() {
+            Some(packages) => packages
+                .iter()
+                .map(|table| {
+                    Ok(Package {
+                        name: table["name"].as_str().unwrap_or_default().to_string(),
+                        path: table["path"]
+                            .as_str()
+                            .ok_or_else(|| {
+                                Error::new(
+                                    Code::Release,
+                                    "Invalid release lockfile: package without a path",
+                                )
+                            })?
+                            .to_string(),
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            None => vec![],
+        };
+        let changelog = document["changelog"].as_str().map(String::from);
+
+        Ok(Release {
+            version,
+            packages,
+            changelog,
+        })
+    }
+}
+
+/// The lockfile lives next to the manifest the release was started from.
+pub fn lockfile_path(manifest_path: &str) -> PathBuf {
+    Path::new(manifest_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(LOCKFILE)
+}
+
+pub fn read_release(manifest_path: &str) -> Result<Release, Error> {
+    let document = fs::read_to_string(lockfile_path(manifest_path))
+        .map_err(|_| {
+            Error::new(
+                Code::Release,
+                "No release in progress - run `semvercli release start` first",
+            )
+        })?
+        .parse::<Document>()
+        .map_err(|err| Error::new(Code::Release, format!("Invalid release lockfile: {}", err)))?;
+
+    Release::from_document(&document)
+}
+
+/// Starts a release: bumps the manifest (and its sync targets) as chosen on
+/// the command line and records the release in the lockfile, all in a
+/// single transaction.
+pub fn start(manifest_path: &str, matches: &ArgMatches, config: &Config) -> Result<(), Error> {
+    let lockfile = lockfile_path(manifest_path);
+    if lockfile.exists() {
+        return Err(Error::new(
+            Code::Release,
+            format!(
+                "A release is already in progress, see {}",
+                lockfile.display()
+            ),
+        ));
+    }
+
+    let mut manifest = read_manifest(manifest_path)?;
+    ensure_unprotected(config, &manifest, manifest_path)?;
+    let current = read_version(&manifest)?;
+    events::parsed(config, manifest_path, &current);
+    let version = bumped_version(&current, matches, config)?;
+    events::computed(config, manifest_path, &current, &version);
+    enforce_branch_policy(config, &version)?;
+    manifest["package"]["version"] = value(version.to_string());
+
+    let changelog_path = lockfile.with_file_name("CHANGELOG.md");
+    let release = Release {
+        version,
+        packages: vec![Package {
+            name: manifest["package"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            path: manifest_path.to_string(),
+        }],
+        changelog: fs::read_to_string(changelog_path)
+            .ok()
+            .and_then(|contents| changelog::unreleased(&contents)),
+    };
+
+    let mut transaction = Transaction::new();
+    for path in adapter::sync_files(config) {
+        adapter::stage_version(&mut transaction, &path, &release.version)?;
+    }
+    transaction.stage(manifest_path, manifest.to_string());
+    transaction.stage(
+        lockfile.to_str().unwrap(),
+        release.to_document().to_string(),
+    );
+    let paths = transaction.paths();
+    transaction
+        .commit(|| Ok(()))
+        .map_err(|err| Error::new(err.code, format!("Failed to start release: {}", err)))?;
+    events::written(config, &paths);
+
+    Ok(())
+}
+
+/// Creates an annotated tag for the release, using the changelog snapshot
+/// as the tag message; with `notes`, also attaches the release metadata to
+/// the tagged commit as a git note.
+pub fn tag(
+    manifest_path: &str,
+    tag_format: &str,
+    notes: bool,
+    config: &Config,
+) -> Result<(), Error> {
+    let release = read_release(manifest_path)?;
+    let tag = describe::render(tag_format, false, config)?
+        .replace("{version}", &release.version.to_string());
+    let message = match release.changelog {
+        Some(ref changelog) if !changelog.is_empty() => {
+            format!("Release {}\n\n{}", release.version, changelog)
+        }
+        _ => format!("Release {}", release.version),
+    };
+
+    git::run(&[
+        "tag",
+        "--annotate",
+        tag.as_str(),
+        "--message",
+        message.as_str(),
+    ])
+    .map_err(|err| {
+        Error::new(
+            Code::Git,
+            format!("Failed to create git tag {}: {}", tag, err),
+        )
+    })?;
+    events::tagged(config, &tag);
+    if notes {
+        notes::attach(
+            "HEAD",
+            &notes::Metadata {
+                version: release.version.clone(),
+                changelog: release.changelog.clone(),
+                plan: None,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Publishes every package of the release, in the recorded order.
+pub fn publish(manifest_path: &str, dry_run: bool) -> Result<(), Error> {
+    for package in read_release(manifest_path)?.packages {
+        let mut command = Command::new("cargo");
+        command.args(["publish", "--manifest-path", package.path.as_str()]);
+        if dry_run {
+            command.arg("--dry-run");
+        }
+        run(&mut command)?;
+    }
+
+    Ok(())
+}
+
+/// Concludes the release by removing the lockfile, after checking that
+/// every package still carries the release version.
+pub fn finalize(manifest_path: &str) -> Result<(), Error> {
+    let release = read_release(manifest_path)?;
+
+    for package in &release.packages {
+        let current = read_version(&read_manifest(&package.path)?)?;
+        if current != release.version {
+            return Err(Error::new(
+                Code::Release,
+                format!(
+                    "{} is at version {}, but the release in progress is {}",
+                    package.path, current, release.version
+                ),
+            ));
+        }
+    }
+
+    fs::remove_file(lockfile_path(manifest_path)).map_err(|err| {
+        Error::new(
+            Code::Io,
+            format!("Failed to remove release lockfile: {}", err),
+        )
+    })
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let status = command.status().map_err(|err| {
+        Error::new(
+            Code::Command,
+            format!("Failed to run {:?}: {}", command, err),
+        )
+    })?;
+    if !status.success() {
+        return Err(Error::new(
+            Code::Command,
+            format!("{:?} failed with {}", command, status),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use semver::Version;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::parser;
+
+    /// Tests that starting a release bumps the manifest and snapshots the
+    /// changelog into the lockfile, and that finalizing removes it again.
+    #[test]
+    fn test_start_finalize() {
+        let tmpdir = tempdir().unwrap();
+        let tmp_path = tmpdir.path().join("Cargo.toml");
+        let manifest_path = tmp_path.to_str().unwrap();
+        fs::write(
+            manifest_path,
+            "[package]\nname = \"demo\"\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+        fs::write(
+            tmpdir.path().join("CHANGELOG.md"),
+            "## Unreleased\n- \"quoted\" \\ change\n\n## 1.2.3\n",
+        )
+        .unwrap();
+
+        let matches = parser().get_matches_from(["semvercli", "bump", "--minor"]);
+        start(
+            manifest_path,
+            matches.subcommand_matches("bump").unwrap(),
+            &Config::default(),
+        )
+        .unwrap();
+
+        let release = read_release(manifest_path).unwrap();
+        assert_eq!(release.version, Version::parse("1.3.0").unwrap());
+        assert_eq!(release.packages[0].name, "demo");
+        assert_eq!(
+            release.changelog,
+            Some(String::from("- \"quoted\" \\ change"))
+        );
+        assert_eq!(
+            read_version(&read_manifest(manifest_path).unwrap()).unwrap(),
+            release.version
+        );
+
+        finalize(manifest_path).unwrap();
+        assert!(!lockfile_path(manifest_path).exists());
+    }
+}
@@ -0,0 +1,177 @@
+//! Fast path for reading the package version: a line scanner that finds the
+//! `version` key of the `[package]` table without building a full
+//! `toml_edit::Document`, which is slow for very large (e.g. generated)
+//! manifests. It only handles the unambiguous common layout and gives up on
+//! anything else - quoted or dotted keys, multi-line strings or arrays, a
+//! `package` defined outside of a `[package]` header, duplicates - so that
+//! the caller falls back to the full parser, which has the final say.
+use std::ops::Range;
+
+/// The version string of the `[package]` table, or `None` if the manifest
+/// cannot be read unambiguously without parsing it.
+pub fn package_version(contents: &str) -> Option<&str> {
+    let mut in_package = false;
+    let mut seen_package = false;
+    let mut version = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.contains("\"\"\"") || line.contains("'''") {
+            return None;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let header = table_header(line)?;
+            if in_package {
+                return version;
+            }
+            if header == "package" {
+                if seen_package {
+                    return None;
+                }
+                in_package = true;
+                seen_package = true;
+            }
+            continue;
+        }
+
+        let equals = line.find('=')?;
+        let key = line[..equals].trim();
+        let value = line[equals + 1..].trim();
+        if !is_bare_key(key.split('.').next().unwrap_or_default()) || !is_balanced(value) {
+            return None;
+        }
+        if !seen_package && key.split('.').next() == Some("package") {
+            return None;
+        }
+        if in_package && key.split('.').next() == Some("version") {
+            if key != "version" || version.is_some() {
+                return None;
+            }
+            version = Some(string_value(value)?);
+        }
+    }
+
+    if in_package {
+        version
+    } else {
+        None
+    }
+}
+
+/// The byte range of the version string of the `[package]` table within
+/// `contents`, under the same conditions as `package_version`.
+pub fn package_version_range(contents: &str) -> Option<Range<usize>> {
+    let version = package_version(contents)?;
+    let start = version.as_ptr() as usize - contents.as_ptr() as usize;
+
+    Some(start..start + version.len())
+}
+
+/// The name of a `[table]` or `[[array]]` header made of bare keys.
+fn table_header(line: &str) -> Option<&str> {
+    let (open, close) = if line.starts_with("[[") {
+        ("[[", "]]")
+    } else {
+        ("[", "]")
+    };
+    let end = line.find(close)?;
+    let rest = line[end + close.len()..].trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return None;
+    }
+
+    let name = line[open.len()..end].trim();
+    if name.split('.').all(|part| is_bare_key(part.trim())) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-')
+}
+
+/// Whether an inline array or table value is closed on its own line; other
+/// values are trivially so.
+fn is_balanced(value: &str) -> bool {
+    let count = |byte: u8| value.bytes().filter(|b| *b == byte).count();
+
+    count(b'[') == count(b']') && count(b'{') == count(b'}')
+}
+
+/// The contents of a basic string without escapes or of a literal string,
+/// followed by nothing but an optional comment.
+fn string_value(value: &str) -> Option<&str> {
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let end = value[1..].find(quote)? + 1;
+    let contents = &value[1..end];
+    let rest = value[end + 1..].trim();
+    if contents.contains('\\') && quote == '"' || !rest.is_empty() && !rest.starts_with('#') {
+        return None;
+    }
+
+    Some(contents)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_package_version() {
+        assert_eq!(
+            package_version(
+                "# comment\n[package]\nname = \"app\"\nversion = \"1.2.3\" # bumped\n\n\
+                 [dependencies]\nsemver = { version = \"0.9\" }\n"
+            ),
+            Some("1.2.3")
+        );
+        assert_eq!(
+            package_version(
+                "[workspace]\nmembers = [\"a\"]\n\n[package]\nversion = '1.0.0-rc.1'\n"
+            ),
+            Some("1.0.0-rc.1")
+        );
+        assert_eq!(
+            package_version(
+                "[package.metadata]\nversion = \"9.9.9\"\n[package]\nversion = \"1.0.0\"\n"
+            ),
+            Some("1.0.0")
+        );
+        assert_eq!(
+            package_version_range("[package]\nversion = \"1.2.3\"\n"),
+            Some(21..26)
+        );
+    }
+
+    /// Tests that every layout the scanner cannot read with certainty is
+    /// left to the full parser.
+    #[test]
+    fn test_ambiguous() {
+        let ambiguous = [
+            "package = { version = \"1.0.0\" }\n",
+            "package.version = \"1.0.0\"\n",
+            "[\"package\"]\nversion = \"1.0.0\"\n",
+            "[package]\nversion.workspace = true\n",
+            "[package]\n\"version\" = \"1.0.0\"\n",
+            "[package]\nversion = \"1.0.\\u0030\"\n",
+            "[package]\nversion = \"1.0.0\"\nversion = \"2.0.0\"\n",
+            "[package]\ndescription = \"\"\"\n[package]\n\"\"\"\nversion = \"1.0.0\"\n",
+            "[package]\nauthors = [\n  \"a\",\n]\nversion = \"1.0.0\"\n",
+            "[package]\nname = \"app\"\n",
+            "[dependencies]\nversion = \"1.0.0\"\n",
+        ];
+
+        for manifest in ambiguous.iter() {
+            assert_eq!(package_version(manifest), None, "{}", manifest);
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! The shape of the configuration: the keys every table may have and the
+//! type of their values. `Config::load` checks the settings against it
+//! before reading them, so that a typo such as `tag_formt` or a value of
+//! the wrong type is reported with its position in the file instead of
+//! being ignored.
+use toml_edit::{Item, Table, Value};
+
+/// The type of a setting.
+pub enum Kind {
+    Bool,
+    Integer,
+    String,
+    Strings,
+    /// A command line: a string or a list of strings.
+    Command,
+    /// A table with the given keys.
+    Table(&'static [(&'static str, Kind)]),
+    /// An array of tables (`[[name]]`) with the given keys.
+    Tables(&'static [(&'static str, Kind)]),
+    /// A table of any keys, with values of the given kind.
+    Map(&'static Kind),
+}
+
+/// The settings of `.semvercli.toml`, as documented in `config`.
+pub const SETTINGS: &[(&str, Kind)] = &[
+    ("protected", Kind::Strings),
+    ("deterministic", Kind::Bool),
+    ("epoch", Kind::Integer),
+    ("zero_major", Kind::Bool),
+    ("skip_merges", Kind::Bool),
+    ("expand_squash", Kind::Bool),
+    ("forbid_build_metadata", Kind::Bool),
+    ("sync", Kind::Strings),
+    ("packages", Kind::Strings),
+    ("plugins", Kind::String),
+    ("versioning", Kind::String),
+    ("default_command", Kind::Command),
+    ("alias", Kind::Map(&Kind::Command)),
+    (
+        "fields",
+        Kind::Map(&Kind::Table(&[
+            ("file", Kind::String),
+            ("key", Kind::String),
+        ])),
+    ),
+    (
+        "limits",
+        Kind::Table(&[
+            ("major", Kind::Integer),
+            ("minor", Kind::Integer),
+            ("patch", Kind::Integer),
+            ("overflow", Kind::String),
+        ]),
+    ),
+    (
+        "reset",
+        Kind::Table(&[
+            ("lower", Kind::Bool),
+            ("pre", Kind::Bool),
+            ("build", Kind::Bool),
+        ]),
+    ),
+    (
+        "stamp",
+        Kind::Table(&[("variables", Kind::Strings), ("env_prefix", Kind::String)]),
+    ),
+    (
+        "links",
+        Kind::Table(&[
+            ("forge", Kind::String),
+            ("url", Kind::String),
+            ("commit", Kind::String),
+            ("issue", Kind::String),
+            ("merge_request", Kind::String),
+        ]),
+    ),
+    (
+        "pre_release",
+        Kind::Table(&[
+            ("labels", Kind::Strings),
+            ("max_identifiers", Kind::Integer),
+            ("numeric_suffix", Kind::Bool),
+        ]),
+    ),
+    (
+        "commit_convention",
+        Kind::Tables(&[
+            ("pattern", Kind::String),
+            ("bump", Kind::String),
+            ("section", Kind::String),
+        ]),
+    ),
+    (
+        "branch_policy",
+        Kind::Tables(&[
+            ("branch", Kind::String),
+            ("stable", Kind::Bool),
+            ("pre", Kind::Strings),
+        ]),
+    ),
+];
+
+/// Settings that were renamed, by their dotted path, with the setting
+/// replacing them; using one is reported as a warning rather than as an
+/// unknown key, and is left to `Config` to read.
+const DEPRECATED: &[(&str, &str)] = &[("zero_ver", "zero_major")];
+
+/// What is wrong with the settings: the problems found (unknown keys and
+/// values of the wrong type) and the deprecated settings used, each with
+/// its position in `contents` when it can be found.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub problems: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Checks `settings` against the schema. `contents` is the text of the
+/// file at `path` they were parsed from, and `prefix` the path of their
+/// table within it (e.g. `workspace.metadata.semvercli`).
+pub fn check(settings: &Table, contents: &str, path: &str, prefix: &[&str]) -> Report {
+    let mut checker = Checker {
+        contents,
+        path,
+        prefix: prefix.iter().map(|segment| segment.to_string()).collect(),
+        report: Report::default(),
+    };
+    checker.table(&[], entries(settings), SETTINGS);
+
+    checker.report
+}
+
+/// The entries of a table, without the empty ones.
+fn entries(table: &Table) -> Vec<(&str, Entry<'_>)> {
+    table
+        .iter()
+        .filter(|(_, item)| !item.is_none())
+        .map(|(key, item)| (key, Entry::Item(item)))
+        .collect()
+}
+
+/// A setting's value: an item of a table or a value of an inline table.
+#[derive(Clone, Copy)]
+enum Entry<'a> {
+    Item(&'a Item),
+    Value(&'a Value),
+}
+
+impl<'a> Entry<'a> {
+    fn value(self) -> Option<&'a Value> {
+        match self {
+            Entry::Item(Item::Value(value)) | Entry::Value(value) => Some(value),
+            Entry::Item(_) => None,
+        }
+    }
+
+    /// The entries of a table or an inline table.
+    fn entries(self) -> Option<Vec<(&'a str, Entry<'a>)>> {
+        match self {
+            Entry::Item(Item::Table(table)) => Some(entries(table)),
+            _ => Some(
+                self.value()?
+                    .as_inline_table()?
+                    .iter()
+                    .map(|(key, value)| (key, Entry::Value(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn type_name(self) -> &'static str {
+        match (self, self.value()) {
+            (Entry::Item(Item::Table(_)), _) => "a table",
+            (Entry::Item(Item::ArrayOfTables(_)), _) => "an array of tables",
+            (_, Some(Value::Integer(_))) => "an integer",
+            (_, Some(Value::String(_))) => "a string",
+            (_, Some(Value::Float(_))) => "a float",
+            (_, Some(Value::DateTime(_))) => "a date-time",
+            (_, Some(Value::Boolean(_))) => "a boolean",
+            (_, Some(Value::Array(_))) => "an array",
+            (_, Some(Value::InlineTable(_))) => "an inline table",
+            (_, None) => "nothing",
+        }
+    }
+}
+
+struct Checker<'a> {
+    contents: &'a str,
+    path: &'a str,
+    prefix: Vec<String>,
+    report: Report,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks the entries of the table at `at` against its `keys`.
+    fn table(&mut self, at: &[String], entries: Vec<(&str, Entry<'_>)>, keys: &[(&str, Kind)]) {
+        for (key, entry) in entries {
+            let mut path = at.to_vec();
+            path.push(key.to_string());
+            let name = dotted(&path);
+            if let Some((_, replacement)) = DEPRECATED.iter().find(|(old, _)| *old == name) {
+                let warning = format!("`{}` is deprecated, use `{}` instead", name, replacement);
+                let warning = self.at(&path, warning);
+                self.report.warnings.push(warning);
+                continue;
+            }
+            match keys.iter().find(|(name, _)| *name == key) {
+                Some((_, kind)) => self.entry(&path, entry, kind),
+                None => {
+                    let mut problem = format!("unknown key `{}`", dotted(&path));
+                    let closest = keys
+                        .iter()
+                        .map(|(name, _)| (distance(name, key), name))
+                        .filter(|(distance, _)| *distance <= 2)
+                        .min();
+                    if let Some((_, name)) = closest {
+                        problem.push_str(&format!(", did you mean `{}`?", name));
+                    }
+                    self.problem(&path, problem);
+                }
+            }
+        }
+    }
+
+    /// Checks that `entry`, at `path`, is of `kind`.
+    fn entry(&mut self, path: &[String], entry: Entry, kind: &Kind) {
+        let value = entry.value();
+        let strings = || {
+            value
+                .and_then(Value::as_array)
+                .is_some_and(|array| array.iter().all(Value::is_str))
+        };
+        let (valid, expected) = match kind {
+            Kind::Bool => (value.is_some_and(Value::is_bool), "a boolean"),
+            Kind::Integer => (value.is_some_and(Value::is_integer), "an integer"),
+            Kind::String => (value.is_some_and(Value::is_str), "a string"),
+            Kind::Strings => (strings(), "a list of strings"),
+            Kind::Command => (
+                value.is_some_and(Value::is_str) || strings(),
+                "a string or a list of strings",
+            ),
+            Kind::Table(keys) => match entry.entries() {
+                Some(entries) => return self.table(path, entries, keys),
+                None => (false, "a table"),
+            },
+            Kind::Map(kind) => match entry.entries() {
+                Some(entries) => {
+                    for (key, entry) in entries {
+                        let mut path = path.to_vec();
+                        path.push(key.to_string());
+                        self.entry(&path, entry, kind);
+                    }
+                    return;
+                }
+                None => (false, "a table"),
+            },
+            Kind::Tables(keys) => match entry {
+                Entry::Item(Item::ArrayOfTables(tables)) => {
+                    for (index, table) in tables.iter().enumerate() {
+                        let mut path = path.to_vec();
+                        let last = path.pop().unwrap();
+                        path.push(format!("{}[{}]", last, index));
+                        self.table(&path, entries(table), keys);
+                    }
+                    return;
+                }
+                _ => (false, "an array of tables"),
+            },
+        };
+        if !valid {
+            let problem = format!(
+                "`{}` must be {}, not {}",
+                dotted(path),
+                expected,
+                entry.type_name()
+            );
+            self.problem(path, problem);
+        }
+    }
+
+    fn problem(&mut self, path: &[String], problem: String) {
+        let problem = self.at(path, problem);
+        self.report.problems.push(problem);
+    }
+
+    /// `message` prefixed with the file and the position of the setting at
+    /// `path`, or of the closest enclosing one that can be found.
+    fn at(&self, path: &[String], message: String) -> String {
+        let mut full = self.prefix.clone();
+        full.extend(path.iter().cloned());
+        let position = (self.prefix.len() + 1..=full.len())
+            .rev()
+            .find_map(|len| locate(self.contents, &full[..len]));
+
+        match position {
+            Some((line, column)) => format!("{}:{}:{}: {}", self.path, line, column, message),
+            None => format!("{}: {}", self.path, message),
+        }
+    }
+}
+
+/// The dotted path of a setting, without array indexes.
+fn dotted(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| segment.split('[').next().unwrap())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The line and column of the key or table header of the setting at
+/// `path` in the TOML `contents`; the `n`th table of an array of tables
+/// is the segment `name[n]`.
+fn locate(contents: &str, path: &[String]) -> Option<(usize, usize)> {
+    let mut table: Vec<String> = vec![];
+    let mut counts: Vec<(String, usize)> = vec![];
+
+    for (number, line) in contents.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let column = line[..line.len() - trimmed.len()].chars().count() + 1;
+        let position = Some((number + 1, column));
+
+        if let Some(header) = trimmed.strip_prefix("[[") {
+            let header = header.split("]]").next().unwrap_or_default();
+            let mut segments = split_key(header);
+            let count = match counts.iter_mut().find(|(name, _)| *name == header.trim()) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    counts.push((header.trim().to_string(), 0));
+                    0
+                }
+            };
+            if let Some(last) = segments.pop() {
+                segments.push(format!("{}[{}]", last, count));
+            }
+            table = segments;
+            if table == path {
+                return position;
+            }
+        } else if let Some(header) = trimmed.strip_prefix('[') {
+            table = split_key(header.split(']').next().unwrap_or_default());
+            if table == path {
+                return position;
+            }
+        } else if let Some((key, _)) = trimmed.split_once('=') {
+            let key = split_key(key);
+            if key.len() == 1
+                && path.len() == table.len() + 1
+                && path.starts_with(&table)
+                && key[0] == path[table.len()]
+            {
+                return position;
+            }
+        }
+    }
+
+    None
+}
+
+/// The segments of a dotted TOML key, unquoted.
+fn split_key(key: &str) -> Vec<String> {
+    key.split('.')
+        .map(|segment| segment.trim().trim_matches(['"', '\'']).to_string())
+        .collect()
+}
+
+/// The edit distance between two keys.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use toml_edit::Document;
+
+    use super::*;
+
+    /// Tests that unknown keys and mistyped values are reported at their
+    /// positions, in nested tables and arrays of tables too.
+    #[test]
+    fn test_check() {
+        let contents = r#"
+protected = ["vendor/"]
+deterministic = "yes"
+tag_formt = "v{version}"
+
+[fields]
+app = { file = "Chart.yaml", ky = "appVersion" }
+
+[alias]
+rc = 1
+
+[[branch_policy]]
+branch = "main"
+stable = true
+
+[[branch_policy]]
+branch = "release/*"
+stabel = true
+"#;
+        let document = contents.parse::<Document>().unwrap();
+        let report = check(document.as_table(), contents, ".semvercli.toml", &[]);
+
+        assert_eq!(
+            report.problems,
+            [
+                ".semvercli.toml:3:1: `deterministic` must be a boolean, not a string",
+                ".semvercli.toml:4:1: unknown key `tag_formt`",
+                ".semvercli.toml:7:1: unknown key `fields.app.ky`, did you mean `key`?",
+                ".semvercli.toml:10:1: `alias.rc` must be a string or a list of strings, not an integer",
+                ".semvercli.toml:18:1: unknown key `branch_policy.stabel`, did you mean `stable`?",
+            ]
+        );
+        assert!(report.warnings.is_empty());
+    }
+
+    /// Tests that settings kept in the manifest are located under their
+    /// table.
+    #[test]
+    fn test_check_manifest() {
+        let contents = "[package]\nname = \"app\"\nedition = \"2021\"\n\n\
+                        [workspace.metadata.semvercli]\nepoc = 2\n";
+        let document = contents.parse::<Document>().unwrap();
+        let settings = document["workspace"]["metadata"]["semvercli"]
+            .as_table()
+            .unwrap();
+        let report = check(
+            settings,
+            contents,
+            "Cargo.toml",
+            &["workspace", "metadata", "semvercli"],
+        );
+
+        assert_eq!(
+            report.problems,
+            ["Cargo.toml:6:1: unknown key `epoc`, did you mean `epoch`?"]
+        );
+    }
+}
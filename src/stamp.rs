@@ -0,0 +1,112 @@
+//! Stamping the version into built artifacts: `stamp` prints linker flags
+//! setting version variables (Go's `-X`) or writes an env file of the
+//! version and its components, which `cargo`, `docker`, `make`, npm scripts
+//! and CI systems all read, so every ecosystem embeds the same version.
+use std::fs;
+
+use semver::Version;
+
+use crate::error::{Code, Error};
+
+/// The `[stamp]` settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamp {
+    /// The fully qualified string variables `--ldflags` sets.
+    pub variables: Vec<String>,
+    /// Prefix of the env file's variable names.
+    pub env_prefix: String,
+}
+
+impl Default for Stamp {
+    fn default() -> Stamp {
+        Stamp {
+            variables: vec![String::from("main.version")],
+            env_prefix: String::new(),
+        }
+    }
+}
+
+impl Stamp {
+    /// Linker flags setting every variable to the version, e.g.
+    /// `-X main.version=1.4.0`.
+    pub fn ldflags(&self, version: &Version) -> String {
+        self.variables
+            .iter()
+            .map(|variable| format!("-X {}={}", variable, version))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The env file of the version and its components, e.g.:
+    ///
+    /// ```sh
+    /// VERSION=1.4.0-rc.1
+    /// VERSION_MAJOR=1
+    /// VERSION_MINOR=4
+    /// VERSION_PATCH=0
+    /// VERSION_PRE=rc.1
+    /// VERSION_BUILD=
+    /// ```
+    pub fn env_file(&self, version: &Version) -> String {
+        let join = |identifiers: &[semver::Identifier]| {
+            identifiers
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(".")
+        };
+
+        [
+            ("VERSION", version.to_string()),
+            ("VERSION_MAJOR", version.major.to_string()),
+            ("VERSION_MINOR", version.minor.to_string()),
+            ("VERSION_PATCH", version.patch.to_string()),
+            ("VERSION_PRE", join(&version.pre)),
+            ("VERSION_BUILD", join(&version.build)),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}{}={}\n", self.env_prefix, name, value))
+        .collect()
+    }
+
+    /// Writes the env file to `path`.
+    pub fn write_env_file(&self, path: &str, version: &Version) -> Result<(), Error> {
+        fs::write(path, self.env_file(version)).map_err(|err| {
+            Error::new(
+                Code::Io,
+                format!("Failed to write env file {}: {}", path, err),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stamp() {
+        let version = Version::parse("1.4.0-rc.1+7").unwrap();
+        let stamp = Stamp {
+            variables: vec![
+                String::from("main.version"),
+                String::from("app/build.Version"),
+            ],
+            env_prefix: String::from("APP_"),
+        };
+
+        assert_eq!(
+            Stamp::default().ldflags(&version),
+            "-X main.version=1.4.0-rc.1+7"
+        );
+        assert_eq!(
+            stamp.ldflags(&version),
+            "-X main.version=1.4.0-rc.1+7 -X app/build.Version=1.4.0-rc.1+7"
+        );
+        assert_eq!(
+            stamp.env_file(&version),
+            "APP_VERSION=1.4.0-rc.1+7\nAPP_VERSION_MAJOR=1\nAPP_VERSION_MINOR=4\n\
+             APP_VERSION_PATCH=0\nAPP_VERSION_PRE=rc.1\nAPP_VERSION_BUILD=7\n"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Terminal styling for output meant for humans, chosen with `--color`:
+//! `auto` colors only a terminal and honors `NO_COLOR`
+//! (https://no-color.org), `always` and `never` override both.
+use std::env;
+
+/// ANSI escape for red text.
+pub const RED: &str = "31";
+/// ANSI escape for green text.
+pub const GREEN: &str = "32";
+/// ANSI escape for yellow text.
+pub const YELLOW: &str = "33";
+/// ANSI escape for bold text.
+pub const BOLD: &str = "1";
+
+/// Whether to color output going to a stream, given the `--color` choice,
+/// whether the stream is a terminal and whether `NO_COLOR` is set (to
+/// anything but the empty string).
+pub fn enabled(choice: &str, terminal: bool, no_color: bool) -> bool {
+    match choice {
+        "always" => true,
+        "never" => false,
+        _ => terminal && !no_color,
+    }
+}
+
+/// Whether `NO_COLOR` is set to a non-empty value.
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// `text` in the given ANSI style if `color` is set, unchanged otherwise.
+pub fn paint(color: bool, style: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        String::from(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_enabled() {
+        assert!(enabled("auto", true, false));
+        assert!(!enabled("auto", true, true));
+        assert!(!enabled("auto", false, false));
+        assert!(enabled("always", false, true));
+        assert!(!enabled("never", true, false));
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint(true, RED, "- a"), "\x1b[31m- a\x1b[0m");
+        assert_eq!(paint(false, RED, "- a"), "- a");
+    }
+}
@@ -0,0 +1,222 @@
+//! Staged multi-file writes. Operations that touch more than one file
+//! stage every new file content up front and commit them together, so that
+//! a failure halfway through (an unwritable file, a failing post-commit step
+//! such as tagging) restores every file to what it was before, instead of
+//! leaving the repository half-bumped.
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Code, Error};
+use crate::patch;
+
+/// Where a dry run reports the changes it would make, from `--dry-run`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DryRun {
+    /// One combined diff of every file on stdout.
+    Stdout,
+    /// One patch file per changed file in the directory, from
+    /// `--emit-patch-dir`.
+    PatchDir(PathBuf),
+}
+
+#[derive(Debug, Default)]
+pub struct Transaction {
+    staged: Vec<(String, String)>,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+
+    /// Queues `contents` to be written to `path` on commit; staging the
+    /// same path twice keeps only the latest contents.
+    pub fn stage(&mut self, path: &str, contents: String) {
+        match self.staged.iter_mut().find(|(staged, _)| staged == path) {
+            Some(entry) => entry.1 = contents,
+            None => self.staged.push((path.to_string(), contents)),
+        }
+    }
+
+    /// The paths of the staged files, in staging order.
+    pub fn paths(&self) -> Vec<String> {
+        self.staged.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    /// Writes every staged file and then runs `post_commit`. If any write
+    /// or the post-commit step fails, all files are rolled back to their
+    /// original contents (files that did not exist are removed again) and
+    /// the failure is returned. Nothing is written if an existing file
+    /// cannot be read, since it could not be restored.
+    pub fn commit<F>(self, post_commit: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> Result<(), Error>,
+    {
+        let originals = self
+            .staged
+            .iter()
+            .map(|(path, _)| original(path))
+            .collect::<Result<Vec<Option<Vec<u8>>>, Error>>()?;
+
+        for (index, (path, contents)) in self.staged.iter().enumerate() {
+            if let Err(err) = fs::write(path, contents) {
+                rollback(&self.staged[..=index], &originals);
+                return Err(Error::new(
+                    Code::Io,
+                    format!("Failed to write {}: {}", path, err),
+                ));
+            }
+        }
+
+        post_commit().inspect_err(|_| rollback(&self.staged, &originals))
+    }
+
+    /// Reports what committing would change instead of writing the staged
+    /// files: a unified diff of each against its current contents, with its
+    /// path relative to `root`. Files left unchanged get no diff. With a
+    /// patch directory, the patches are named after their order and file
+    /// (`0001-Cargo.toml.patch`) and their paths are printed.
+    pub fn preview(
+        self,
+        dry_run: &DryRun,
+        root: &Path,
+        stdout: &mut dyn Write,
+    ) -> Result<(), Error> {
+        let mut count = 0;
+        for (path, contents) in &self.staged {
+            let relative = Path::new(path)
+                .strip_prefix(root)
+                .unwrap_or(Path::new(path));
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let original =
+                original(path)?.map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+            let diff = patch::unified(&relative, original.as_deref(), contents);
+            if diff.is_empty() {
+                continue;
+            }
+            count += 1;
+
+            match dry_run {
+                DryRun::Stdout => write!(stdout, "{}", diff).unwrap(),
+                DryRun::PatchDir(dir) => {
+                    let file =
+                        dir.join(format!("{:04}-{}.patch", count, relative.replace('/', "-")));
+                    fs::create_dir_all(dir)
+                        .and_then(|_| fs::write(&file, diff))
+                        .map_err(|err| {
+                            Error::new(
+                                Code::Io,
+                                format!("Failed to write {}: {}", file.display(), err),
+                            )
+                        })?;
+                    writeln!(stdout, "{}", file.display()).unwrap();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The current contents of `path`, or `None` if it does not exist; any
+/// other failure to read it is an error rather than a missing file.
+fn original(path: &str) -> Result<Option<Vec<u8>>, Error> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::new(
+            Code::Io,
+            format!("Could not read {}: {}", path, err),
+        )),
+    }
+}
+
+/// Best-effort restore of the given files; a failure to restore one file
+/// must not prevent restoring the rest, so errors are only reported.
+fn rollback(written: &[(String, String)], originals: &[Option<Vec<u8>>]) {
+    for ((path, _), original) in written.iter().zip(originals) {
+        let restored = match original {
+            Some(contents) => fs::write(path, contents),
+            None => fs::remove_file(path).or_else(|err| match err.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            }),
+        };
+        if let Err(err) = restored {
+            eprintln!("Failed to roll back {}: {}", path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Tests that a write failure on a later file restores the files
+    /// written before it.
+    #[test]
+    fn test_rollback_on_write_failure() {
+        let tmpdir = tempdir().unwrap();
+        let first = tmpdir.path().join("first.toml");
+        let second = tmpdir.path().join("missing").join("second.toml");
+        fs::write(&first, "old").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.stage(first.to_str().unwrap(), String::from("new"));
+        transaction.stage(second.to_str().unwrap(), String::from("new"));
+
+        assert!(transaction.commit(|| Ok(())).is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "old");
+    }
+
+    /// Tests that a failing post-commit step restores every written file.
+    #[test]
+    fn test_rollback_on_post_commit_failure() {
+        let tmpdir = tempdir().unwrap();
+        let existing = tmpdir.path().join("existing.toml");
+        let created = tmpdir.path().join("created.toml");
+        fs::write(&existing, "old").unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.stage(existing.to_str().unwrap(), String::from("new"));
+        transaction.stage(created.to_str().unwrap(), String::from("new"));
+
+        assert!(transaction
+            .commit(|| Err(Error::new(Code::Command, "hook failed")))
+            .is_err());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!created.exists());
+    }
+
+    /// Tests that a file that is not UTF-8 is restored byte for byte, and
+    /// that nothing is written when an existing file cannot be read.
+    #[test]
+    fn test_rollback_unreadable() {
+        let tmpdir = tempdir().unwrap();
+        let binary = tmpdir.path().join("binary.toml");
+        let first = tmpdir.path().join("first.toml");
+        let unreadable = tmpdir.path().join("unreadable.toml");
+        fs::write(&binary, b"\xff\xfe").unwrap();
+        fs::write(&first, "old").unwrap();
+        fs::create_dir(&unreadable).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.stage(binary.to_str().unwrap(), String::from("new"));
+        assert!(transaction
+            .commit(|| Err(Error::new(Code::Command, "hook failed")))
+            .is_err());
+        assert_eq!(fs::read(&binary).unwrap(), b"\xff\xfe");
+
+        let mut transaction = Transaction::new();
+        transaction.stage(first.to_str().unwrap(), String::from("new"));
+        transaction.stage(unreadable.to_str().unwrap(), String::from("new"));
+        assert_eq!(transaction.commit(|| Ok(())).unwrap_err().code, Code::Io);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "old");
+        assert!(unreadable.is_dir());
+    }
+}
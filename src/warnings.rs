@@ -0,0 +1,95 @@
+//! Warnings: conditions worth a look that do not stop a run, such as build
+//! metadata in a bumped version. Each is reported on stderr as one JSON
+//! object per line, e.g. `{"warning": "W_BUILD_METADATA", "message": "..."}`,
+//! or as a colored `warning[W_BUILD_METADATA]:` line with `--color always`,
+//! so stdout only ever carries results. With `--deny-warnings` the first
+//! warning fails the run with `E_WARNING` instead.
+use std::fmt;
+
+use crate::config::Config;
+use crate::error::{json_string, Code, Error};
+use crate::style;
+
+/// The kinds of warnings, each with a stable machine readable name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A bumped version carries build metadata, which precedence ignores.
+    BuildMetadata,
+    /// A workspace member inherits the workspace's version and is left out.
+    WorkspaceInheritance,
+    /// A PATCH release follows changes that break dependents.
+    BreakingPatch,
+    /// The configuration uses a deprecated setting.
+    Deprecated,
+    /// A protected package was skipped.
+    Protected,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::BuildMetadata => "W_BUILD_METADATA",
+            Kind::WorkspaceInheritance => "W_WORKSPACE_INHERITANCE",
+            Kind::BreakingPatch => "W_BREAKING_PATCH",
+            Kind::Deprecated => "W_DEPRECATED",
+            Kind::Protected => "W_PROTECTED",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The warning as a single line JSON object.
+pub fn to_json(kind: Kind, message: &str) -> String {
+    format!(
+        "{{\"warning\": {}, \"message\": {}}}",
+        json_string(kind.name()),
+        json_string(message)
+    )
+}
+
+/// Reports a warning on stderr, or fails with it under `--deny-warnings`.
+pub fn warn<S: Into<String>>(config: &Config, kind: Kind, message: S) -> Result<(), Error> {
+    let message = message.into();
+    if config.deny_warnings {
+        return Err(Error::new(
+            Code::Warning,
+            format!("{} (warning {}, denied)", message, kind),
+        ));
+    }
+
+    if config.stderr_color {
+        let prefix = format!("warning[{}]:", kind);
+        eprintln!("{} {}", style::paint(true, style::YELLOW, &prefix), message);
+    } else {
+        eprintln!("{}", to_json(kind, &message));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_warn() {
+        assert_eq!(
+            to_json(Kind::BuildMetadata, "1.0.0+\"abc\" carries build metadata"),
+            "{\"warning\": \"W_BUILD_METADATA\", \"message\": \"1.0.0+\\\"abc\\\" carries build metadata\"}"
+        );
+
+        let mut config = Config::default();
+        assert!(warn(&config, Kind::Deprecated, "`x` is deprecated").is_ok());
+        config.deny_warnings = true;
+        let err = warn(&config, Kind::Deprecated, "`x` is deprecated").unwrap_err();
+        assert_eq!(err.code, Code::Warning);
+        assert_eq!(
+            err.message,
+            "`x` is deprecated (warning W_DEPRECATED, denied)"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Watching the synced version files during development: whenever the
+//! manifest or one of the `sync` files changes on disk, every synced version
+//! is compared with the manifest's, and those that drifted are reported, or
+//! rewritten to the manifest's version with `--fix`.
+use std::fs;
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use semver::Version;
+
+use super::read_manifest_version;
+use crate::adapter;
+use crate::config::Config;
+use crate::error::Error;
+use crate::style;
+use crate::transaction::Transaction;
+
+/// The synced files whose version differs from `version`, with theirs.
+fn drifted(config: &Config, version: &Version) -> Result<Vec<(String, Version)>, Error> {
+    let mut drifted = vec![];
+    for path in adapter::sync_files(config) {
+        let synced = adapter::read_file_version(&path)?;
+        if synced != *version || synced.build != version.build {
+            drifted.push((path, synced));
+        }
+    }
+
+    Ok(drifted)
+}
+
+/// Reports the synced files that drifted from the manifest, after rewriting
+/// them to its version if `fix` is set; returns how many had drifted.
+pub fn check(
+    manifest_path: &str,
+    config: &Config,
+    fix: bool,
+    stdout: &mut dyn Write,
+) -> Result<usize, Error> {
+    let version = read_manifest_version(manifest_path, config)?;
+    let drifted = drifted(config, &version)?;
+    let mut transaction = Transaction::new();
+
+    for (path, synced) in &drifted {
+        let action = if fix {
+            adapter::stage_version(&mut transaction, path, &version)?;
+            format!("{}:", style::paint(config.color, style::GREEN, "fixed"))
+        } else {
+            format!("{}:", style::paint(config.color, style::YELLOW, "warning"))
+        };
+        writeln!(
+            stdout,
+            "{} {} is at {}, but {} is at {}",
+            action, path, synced, manifest_path, version
+        )
+        .unwrap();
+    }
+    transaction.commit(|| Ok(()))?;
+
+    Ok(drifted.len())
+}
+
+/// The modification times of the manifest and the synced files, to tell
+/// when any of them changed.
+fn fingerprint(manifest_path: &str, config: &Config) -> Vec<Option<SystemTime>> {
+    Some(manifest_path.to_string())
+        .into_iter()
+        .chain(adapter::sync_files(config))
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+/// Checks the synced files every time one of the files changes, polling
+/// every `interval`. Failures, e.g. a file saved half-way, are reported and
+/// watching goes on; it only stops when the process does.
+pub fn run(
+    manifest_path: &str,
+    config: &Config,
+    fix: bool,
+    interval: Duration,
+    stdout: &mut dyn Write,
+) -> ! {
+    let mut last = None;
+
+    loop {
+        let current = fingerprint(manifest_path, config);
+        if last.as_ref() != Some(&current) {
+            if let Err(err) = check(manifest_path, config, fix, stdout) {
+                writeln!(
+                    stdout,
+                    "{} {}",
+                    style::paint(config.color, style::RED, "error:"),
+                    err
+                )
+                .unwrap();
+            }
+            stdout.flush().unwrap();
+            // The fixes changed the files again.
+            last = Some(fingerprint(manifest_path, config));
+        }
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,1574 @@
+//! Workspaces: groups of packages that `bump --workspace` bumps together in
+//! one transaction. By default these are the packages of the JavaScript
+//! workspace next to the manifest, declared by the `workspaces` of its
+//! `package.json` (npm, yarn) or by a `pnpm-workspace.yaml`; the `packages`
+//! configuration instead lists version files of any ecosystem, so a single
+//! bump can span e.g. Cargo crates, npm workspaces and a Helm chart.
+//! With `versioning = "independent"` every package is bumped from its own
+//! version, with `"fixed"` all of them move to the bumped highest version.
+//! The crates of a Cargo workspace and their dependencies on each other are
+//! read separately, to publish them in order.
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use clap::ArgMatches;
+use semver::{Version, VersionReq};
+use toml_edit::{value, Document};
+
+use super::{bumped_version, condition_holds, enforce_branch_policy, read_manifest};
+use crate::adapter::{self, Format};
+use crate::config::{glob_match, Config, Versioning};
+use crate::error::{json_string, Code, Error};
+use crate::events;
+use crate::git;
+use crate::pipeline::Component;
+use crate::transaction::Transaction;
+use crate::warnings;
+
+/// The npm lockfiles, which record the version of the package next to them
+/// and of every package of the workspace they are the root of.
+const LOCKFILES: [&str; 2] = ["package-lock.json", "npm-shrinkwrap.json"];
+
+/// The dependency tables of a Cargo manifest, also nested per target.
+const DEPENDENCY_SECTIONS: [&str; 3] = ["dependencies", "build-dependencies", "dev-dependencies"];
+
+/// The dependency objects of a `package.json`.
+const NPM_DEPENDENCY_SECTIONS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// The version file of a workspace package.
+struct Member {
+    path: String,
+    /// The root of the npm workspace the package belongs to, if any.
+    npm_root: Option<PathBuf>,
+}
+
+struct Package {
+    member: Member,
+    format: Format,
+    contents: String,
+    version: Version,
+    name: Option<String>,
+    /// The packages it depends on, by name.
+    dependencies: Vec<String>,
+}
+
+/// The package patterns of the JavaScript workspace declared in `root`,
+/// e.g. `packages/*`; patterns starting with `!` exclude packages.
+fn patterns(root: &Path) -> Option<Vec<String>> {
+    if let Ok(contents) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        return Some(yaml_list(&contents, "packages"));
+    }
+
+    let contents = fs::read_to_string(root.join("package.json")).ok()?;
+    adapter::json_strings_at(&contents, &["workspaces"])
+        .or_else(|| adapter::json_strings_at(&contents, &["workspaces", "packages"]))
+}
+
+/// The `package.json` paths of the packages of the JavaScript workspace
+/// declared in `root`, sorted.
+pub fn members(root: &Path) -> Result<Vec<String>, Error> {
+    let patterns = patterns(root).ok_or_else(|| {
+        Error::new(
+            Code::Workspace,
+            format!(
+                "No Cargo, npm, yarn or pnpm workspace is declared in {}",
+                display_dir(root)
+            ),
+        )
+    })?;
+
+    let members = matching(root, &patterns)
+        .into_iter()
+        .map(|dir| dir.join("package.json"))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if members.is_empty() {
+        return Err(Error::new(
+            Code::Workspace,
+            format!("The workspace in {} has no packages", display_dir(root)),
+        ));
+    }
+
+    Ok(members)
+}
+
+fn npm_members(root: &Path) -> Result<Vec<Member>, Error> {
+    Ok(members(root)?
+        .into_iter()
+        .map(|path| Member {
+            path,
+            npm_root: Some(root.to_path_buf()),
+        })
+        .collect())
+}
+
+/// The manifests of the crates of the Cargo workspace declared by the
+/// `Cargo.toml` in the configuration's root, if it declares one. Crates
+/// inheriting the workspace's version have no version of their own and are
+/// left out with a warning.
+fn cargo_members(config: &Config) -> Result<Option<Vec<Member>>, Error> {
+    let manifest_path = config
+        .root
+        .join("Cargo.toml")
+        .to_string_lossy()
+        .into_owned();
+    let root_manifest = match fs::read_to_string(&manifest_path) {
+        Ok(contents) => contents.parse::<Document>().map_err(|err| {
+            Error::new(
+                Code::ParseManifest,
+                format!("Invalid {}: {}", manifest_path, err),
+            )
+        })?,
+        Err(_) => return Ok(None),
+    };
+    if root_manifest["workspace"].is_none() {
+        return Ok(None);
+    }
+
+    let mut members = vec![];
+    for path in cargo_paths(&manifest_path, &root_manifest)? {
+        let manifest = read_manifest(&path)?;
+        if manifest["package"]["version"]["workspace"].as_bool() == Some(true) {
+            warnings::warn(
+                config,
+                warnings::Kind::WorkspaceInheritance,
+                format!("Skipped {}: it inherits the workspace's version", path),
+            )?;
+            continue;
+        }
+        members.push(Member {
+            path,
+            npm_root: None,
+        });
+    }
+
+    Ok(Some(members))
+}
+
+/// The version files of the workspace: the configured `packages`, in which
+/// a `package.json` declaring an npm workspace stands for the packages of
+/// that workspace, or else the crates of the Cargo workspace declared by
+/// the manifest or the packages of the JavaScript workspace next to it.
+fn workspace_members(config: &Config) -> Result<Vec<Member>, Error> {
+    if config.packages.is_empty() {
+        if let Some(members) = cargo_members(config)? {
+            return Ok(members);
+        }
+        return npm_members(&config.root);
+    }
+
+    let mut found = vec![];
+    for path in matching(&config.root, &config.packages) {
+        if !path.is_file() {
+            continue;
+        }
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let path = path.to_string_lossy().into_owned();
+        if matches!(Format::detect(&path), Ok(Format::PackageJson)) && patterns(dir).is_some() {
+            found.extend(npm_members(dir)?);
+        } else {
+            found.push(Member {
+                path,
+                npm_root: None,
+            });
+        }
+    }
+    if found.is_empty() {
+        return Err(Error::new(
+            Code::Workspace,
+            "The configured `packages` match no version file",
+        ));
+    }
+
+    Ok(found)
+}
+
+/// The changelog of every unprotected workspace package (`CHANGELOG.md`
+/// next to its version file) and the git pathspecs of the package's files:
+/// its directory without the directories of packages nested in it. Only
+/// independently versioned packages have changelogs of their own.
+pub fn changelogs(config: &Config) -> Result<Vec<(String, Vec<String>)>, Error> {
+    if config.versioning == Versioning::Fixed {
+        return Err(Error::new(
+            Code::Workspace,
+            "Packages versioned together share a changelog; per-package changelogs need `versioning = \"independent\"`",
+        ));
+    }
+    let mut dirs = vec![];
+    for member in workspace_members(config)? {
+        let format = Format::detect(&member.path)?;
+        let contents = adapter::read_file(&member.path)?;
+        if config.is_protected(format.package_name(&contents).as_deref(), &member.path) {
+            continue;
+        }
+        let dir = Path::new(&member.path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        dirs.push(if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        });
+    }
+
+    Ok(dirs
+        .iter()
+        .map(|dir| {
+            let mut pathspecs = vec![dir.to_string_lossy().into_owned()];
+            pathspecs.extend(
+                dirs.iter()
+                    .filter(|other| other.starts_with(dir) && *other != dir)
+                    .map(|other| format!(":(exclude){}", other.display())),
+            );
+            (
+                dir.join("CHANGELOG.md").to_string_lossy().into_owned(),
+                pathspecs,
+            )
+        })
+        .collect())
+}
+
+/// A workspace package whose version was changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub name: String,
+    pub path: String,
+    pub old: Version,
+    pub new: Version,
+}
+
+/// The name (or directory, for formats without names), path and version
+/// of every workspace package, in the order of `workspace_members`.
+pub fn versions(config: &Config) -> Result<Vec<(String, String, Version)>, Error> {
+    workspace_members(config)?
+        .into_iter()
+        .map(|member| {
+            let format = Format::detect(&member.path)?;
+            let contents = adapter::read_file(&member.path)?;
+            let version = format.read_version(&contents, &member.path)?;
+            let name = format.package_name(&contents).unwrap_or_else(|| {
+                display_dir(
+                    Path::new(&member.path)
+                        .parent()
+                        .unwrap_or_else(|| Path::new("")),
+                )
+            });
+            Ok((name, member.path, version))
+        })
+        .collect()
+}
+
+/// The changes as a single line JSON array of `{name, path, old, new}`
+/// objects, e.g. for a GitHub Actions matrix.
+pub fn matrix(changes: &[Change]) -> String {
+    let objects = changes
+        .iter()
+        .map(|change| {
+            format!(
+                "{{\"name\": {}, \"path\": {}, \"old\": {}, \"new\": {}}}",
+                json_string(&change.name),
+                json_string(&change.path),
+                json_string(&change.old.to_string()),
+                json_string(&change.new.to_string())
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("[{}]", objects.join(", "))
+}
+
+/// Bumps every workspace package as chosen on the command line and updates
+/// the versions recorded in npm lockfiles, writing all files together.
+/// Protected packages are left alone; in a dry run, the changes are
+/// previewed on `stdout` instead. Returns the changed packages and the
+/// paths of the protected ones.
+pub fn bump(
+    config: &Config,
+    matches: &ArgMatches,
+    stdout: &mut dyn Write,
+) -> Result<(Vec<Change>, Vec<String>), Error> {
+    let mut packages = vec![];
+    let mut skipped = vec![];
+    for member in workspace_members(config)? {
+        let format = Format::detect(&member.path)?;
+        let contents = adapter::read_file(&member.path)?;
+        if config.is_protected(format.package_name(&contents).as_deref(), &member.path) {
+            skipped.push(member.path);
+            continue;
+        }
+        let version = format.read_version(&contents, &member.path)?;
+        events::parsed(config, &member.path, &version);
+        packages.push(Package {
+            name: format.package_name(&contents),
+            dependencies: package_dependencies(format, &contents),
+            member,
+            format,
+            contents,
+            version,
+        });
+    }
+    let names = packages
+        .iter()
+        .map(|package| package.name.as_deref().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let dependencies = packages
+        .iter()
+        .map(|package| package.dependencies.clone())
+        .collect::<Vec<_>>();
+
+    let mut selected = vec![true; packages.len()];
+    if let Some(since) = matches.value_of("changed-since") {
+        config.forbid_in_deterministic("Git (--changed-since)")?;
+        let dirs = packages
+            .iter()
+            .map(|package| {
+                Path::new(&package.member.path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+            })
+            .collect::<Vec<_>>();
+        selected = changed_since(since, &dirs)?;
+        if matches.is_present("with-dependents") {
+            selected = with_dependents(selected, &names, &dependencies);
+        }
+    }
+    let chosen = packages
+        .iter()
+        .zip(&selected)
+        .filter(|(_, &chosen)| chosen)
+        .map(|(package, _)| package)
+        .collect::<Vec<_>>();
+
+    let mut bumped = match config.versioning {
+        Versioning::Independent => chosen
+            .iter()
+            .filter(|package| condition_holds(&package.version, matches))
+            .map(|package| Ok((*package, bumped_version(&package.version, matches, config)?)))
+            .collect::<Result<Vec<_>, Error>>()?,
+        Versioning::Fixed => match chosen.iter().map(|package| &package.version).max() {
+            Some(highest) if condition_holds(highest, matches) => {
+                let version = bumped_version(highest, matches, config)?;
+                chosen
+                    .iter()
+                    .map(|package| (*package, version.clone()))
+                    .collect()
+            }
+            _ => vec![],
+        },
+    };
+    let cascade = matches.is_present("cascade");
+    if cascade {
+        let was_bumped = packages
+            .iter()
+            .map(|package| bumped.iter().any(|(bumped, _)| ptr::eq(*bumped, package)))
+            .collect::<Vec<_>>();
+        let cascaded = with_dependents(was_bumped.clone(), &names, &dependencies);
+        for (index, package) in packages.iter().enumerate() {
+            if cascaded[index] && !was_bumped[index] {
+                let mut version = package.version.clone();
+                config
+                    .limits
+                    .increment(&mut version, Component::Patch)
+                    .map_err(|err| Error::new(Code::Overflow, err))?;
+                bumped.push((package, version));
+            }
+        }
+    }
+    let requirements = bumped
+        .iter()
+        .filter_map(|(package, version)| Some((package.name.as_deref()?, version)))
+        .collect::<Vec<_>>();
+
+    let mut transaction = Transaction::new();
+    let mut lockfiles: Vec<(PathBuf, Vec<(String, &Version)>)> = vec![];
+    for (package, version) in &bumped {
+        let path = &package.member.path;
+        events::computed(config, path, &package.version, version);
+        enforce_branch_policy(config, version)?;
+        let mut contents = package
+            .format
+            .write_version(&package.contents, version, path)?;
+        if cascade {
+            contents = with_requirements(package.format, &contents, &requirements);
+        }
+        transaction.stage(path, contents);
+
+        if package.format != Format::PackageJson {
+            continue;
+        }
+        for (lockfile, key) in lockfile_entries(&package.member) {
+            match lockfiles.iter_mut().find(|(staged, _)| *staged == lockfile) {
+                Some((_, entries)) => entries.push((key, version)),
+                None => lockfiles.push((lockfile, vec![(key, version)])),
+            }
+        }
+    }
+    for (lockfile, entries) in lockfiles {
+        let path = lockfile.to_string_lossy();
+        let contents = adapter::read_file(&path)?;
+        transaction.stage(&path, lockfile_versions(&contents, &entries));
+    }
+    if let Some(ref dry_run) = config.dry_run {
+        transaction.preview(dry_run, &config.root, stdout)?;
+    } else {
+        let paths = transaction.paths();
+        transaction.commit(|| Ok(()))?;
+        events::written(config, &paths);
+    }
+
+    let changes = bumped
+        .iter()
+        .map(|(package, version)| {
+            let path = &package.member.path;
+            Change {
+                name: package.name.clone().unwrap_or_else(|| {
+                    display_dir(Path::new(path).parent().unwrap_or_else(|| Path::new("")))
+                }),
+                path: path.clone(),
+                old: package.version.clone(),
+                new: version.clone(),
+            }
+        })
+        .collect();
+
+    Ok((changes, skipped))
+}
+
+/// The existing lockfiles that record the version of the `package.json`
+/// member, each with the key of its `"packages"` entry: the directory of
+/// the package relative to the workspace root, or `""` for the lockfile
+/// next to a package outside of any workspace.
+fn lockfile_entries(member: &Member) -> Vec<(PathBuf, String)> {
+    let dir = Path::new(&member.path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let (root, key) = match member.npm_root {
+        Some(ref root) => (
+            root.as_path(),
+            dir.strip_prefix(root)
+                .unwrap_or(dir)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        ),
+        None => (dir, String::new()),
+    };
+
+    LOCKFILES
+        .iter()
+        .map(|name| root.join(name))
+        .filter(|lockfile| lockfile.is_file())
+        .map(|lockfile| (lockfile, key.clone()))
+        .collect()
+}
+
+/// Returns the lockfile `contents` with the `"packages"` entries of the
+/// given keys set to their new versions; the entry `""` is the package
+/// next to the lockfile, whose version is also recorded at the top level.
+fn lockfile_versions(contents: &str, entries: &[(String, &Version)]) -> String {
+    let mut replacements = entries
+        .iter()
+        .flat_map(|(key, version)| {
+            let top_level = if key.is_empty() {
+                adapter::json_string_at(contents, &["version"])
+            } else {
+                None
+            };
+            adapter::json_string_at(contents, &["packages", key, "version"])
+                .into_iter()
+                .chain(top_level)
+                .map(move |range| (range, version.to_string()))
+        })
+        .collect::<Vec<_>>();
+    replacements.sort_by_key(|(range, _)| range.start);
+
+    let mut updated = contents.to_string();
+    for (range, version) in replacements.into_iter().rev() {
+        updated.replace_range(range, &version);
+    }
+
+    updated
+}
+
+/// The paths below `root` matching any of the patterns and none of the
+/// patterns starting with `!`, sorted.
+fn matching(root: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    let (excluded, included): (Vec<&String>, Vec<&String>) = patterns
+        .iter()
+        .partition(|pattern| pattern.starts_with('!'));
+    let excluded = excluded
+        .iter()
+        .flat_map(|pattern| expand(root, &pattern[1..]))
+        .collect::<Vec<_>>();
+
+    let mut paths = included
+        .iter()
+        .flat_map(|pattern| expand(root, pattern))
+        .filter(|path| !excluded.contains(path))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    paths
+}
+
+/// The paths below `root` matching `pattern`, in which `*` matches within a
+/// path segment and a `**` segment any number of directories. Only the last
+/// segment may match files.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let segments = pattern
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let mut found = vec![];
+    expand_segments(root.to_path_buf(), &segments, &mut found);
+
+    found
+}
+
+fn expand_segments(path: PathBuf, segments: &[&str], found: &mut Vec<PathBuf>) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            found.push(path);
+            return;
+        }
+    };
+    if !segment.contains('*') {
+        let child = path.join(segment);
+        if child.is_dir() || rest.is_empty() && child.exists() {
+            expand_segments(child, rest, found);
+        }
+        return;
+    }
+    if *segment == "**" {
+        expand_segments(path.clone(), rest, found);
+    }
+
+    let listed = if path.as_os_str().is_empty() {
+        fs::read_dir(".")
+    } else {
+        fs::read_dir(&path)
+    };
+    let mut entries = match listed {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| {
+                (
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.path().is_dir(),
+                )
+            })
+            .filter(|(name, _)| name != "node_modules" && !name.starts_with('.'))
+            .collect::<Vec<_>>(),
+        Err(_) => return,
+    };
+    entries.sort();
+
+    for (name, is_dir) in entries {
+        if *segment == "**" {
+            if is_dir {
+                expand_segments(path.join(&name), segments, found);
+            }
+        } else if (is_dir || rest.is_empty()) && glob_match(segment, &name) {
+            expand_segments(path.join(&name), rest, found);
+        }
+    }
+}
+
+/// The items of the block sequence under a top-level key of a YAML
+/// document, e.g. the `packages` of a `pnpm-workspace.yaml`.
+fn yaml_list(contents: &str, key: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut in_list = false;
+
+    for line in contents.lines() {
+        let content = line.split(" #").next().unwrap_or_default().trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) && !content.starts_with('-') {
+            in_list = content == format!("{}:", key);
+        } else if let Some(item) = content.strip_prefix('-').filter(|_| in_list) {
+            items.push(
+                item.trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_string(),
+            );
+        }
+    }
+
+    items
+}
+
+/// A package of a Cargo workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crate {
+    pub name: String,
+    /// Path of its `Cargo.toml`.
+    pub path: String,
+    pub version: Version,
+    /// Whether it may be published, i.e. it does not set `publish = false`.
+    pub publish: bool,
+    /// The other workspace crates it depends on, by name; path-only
+    /// dev-dependencies are left out since `cargo publish` drops them.
+    pub dependencies: Vec<String>,
+}
+
+/// The manifests of the crates of the Cargo workspace whose root manifest
+/// `root_manifest` is at `manifest_path`: the root package, if any, and the
+/// `members` of its `[workspace]` that are not `exclude`d, sorted by path.
+pub fn cargo_paths(manifest_path: &str, root_manifest: &Document) -> Result<Vec<String>, Error> {
+    let workspace = root_manifest["workspace"].as_table().ok_or_else(|| {
+        Error::new(
+            Code::Workspace,
+            format!("{} declares no [workspace]", manifest_path),
+        )
+    })?;
+    let root = Path::new(manifest_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let patterns = |key: &str| {
+        workspace[key]
+            .as_array()
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|pattern| pattern.as_str().map(String::from))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    let excluded = patterns("exclude")
+        .into_iter()
+        .map(|pattern| format!("!{}", pattern));
+
+    let mut paths = matching(
+        root,
+        &patterns("members")
+            .into_iter()
+            .chain(excluded)
+            .collect::<Vec<_>>(),
+    )
+    .into_iter()
+    .map(|dir| dir.join("Cargo.toml"))
+    .filter(|path| path.is_file())
+    .map(|path| path.to_string_lossy().into_owned())
+    .collect::<Vec<_>>();
+    if !root_manifest["package"].is_none() {
+        paths.insert(0, manifest_path.to_string());
+    }
+
+    Ok(paths)
+}
+
+/// The crates of the Cargo workspace whose root manifest is at
+/// `manifest_path`, see `cargo_paths`.
+pub fn cargo_crates(manifest_path: &str) -> Result<Vec<Crate>, Error> {
+    let root_manifest = read_manifest(manifest_path)?;
+    let paths = cargo_paths(manifest_path, &root_manifest)?;
+    let workspace = &root_manifest["workspace"];
+
+    let manifests = paths
+        .into_iter()
+        .map(|path| Ok((read_manifest(&path)?, path)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let names = manifests
+        .iter()
+        .filter_map(|(manifest, _)| manifest["package"]["name"].as_str())
+        .collect::<Vec<_>>();
+    let inherited = workspace["package"]["version"].as_str();
+
+    manifests
+        .iter()
+        .map(|(manifest, path)| {
+            let package = &manifest["package"];
+            let version = match package["version"].as_str() {
+                _ if package["version"]["workspace"].as_bool() == Some(true) => inherited,
+                version => version,
+            };
+            let version = version
+                .and_then(|version| Version::parse(version).ok())
+                .ok_or_else(|| {
+                    Error::new(
+                        Code::InvalidVersion,
+                        format!("{} has no valid package version", path),
+                    )
+                })?;
+            let mut dependencies = dependency_names(manifest)
+                .into_iter()
+                .filter(|name| names.contains(&name.as_str()))
+                .collect::<Vec<_>>();
+            dependencies.sort();
+            dependencies.dedup();
+
+            Ok(Crate {
+                name: package["name"].as_str().unwrap_or_default().to_string(),
+                path: path.clone(),
+                version,
+                publish: package["publish"].as_bool() != Some(false),
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+/// The root manifest of the Cargo workspace the crate at `manifest_path`
+/// belongs to: the nearest `Cargo.toml` declaring a `[workspace]` in its
+/// directory or above, as Cargo finds it.
+fn cargo_workspace_root(manifest_path: &str) -> Result<Option<String>, Error> {
+    let mut dir = Path::new(manifest_path)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_path_buf();
+
+    loop {
+        let candidate = dir.join("Cargo.toml");
+        if candidate.is_file() {
+            let candidate = candidate.to_string_lossy().into_owned();
+            if !read_manifest(&candidate)?["workspace"].is_none() {
+                return Ok(Some(candidate));
+            }
+        }
+        if dir.file_name().is_some() {
+            dir.pop();
+        } else {
+            let current = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir.as_path()
+            };
+            match fs::canonicalize(current) {
+                Ok(absolute) if absolute.parent().is_some() => dir.push(".."),
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Stages raising the requirements on the crate `name` at `manifest_path`
+/// that its new `version` no longer satisfies in the other crates of its
+/// Cargo workspace and in the overrides of the workspace's root manifest,
+/// for `bump --update-dependents`; returns the paths of the manifests
+/// changed.
+pub fn update_dependents(
+    transaction: &mut Transaction,
+    manifest_path: &str,
+    name: &str,
+    version: &Version,
+) -> Result<Vec<String>, Error> {
+    let root = cargo_workspace_root(manifest_path)?.ok_or_else(|| {
+        Error::new(
+            Code::Workspace,
+            format!("{} belongs to no Cargo workspace", manifest_path),
+        )
+    })?;
+    let own = fs::canonicalize(manifest_path).ok();
+    let mut paths = cargo_paths(&root, &read_manifest(&root)?)?;
+    if !paths.contains(&root) {
+        paths.insert(0, root.clone());
+    }
+
+    let mut updated = vec![];
+    for path in paths {
+        if fs::canonicalize(&path).ok() == own {
+            continue;
+        }
+        let contents = adapter::read_file(&path)?;
+        let raised = with_requirements(Format::Cargo, &contents, &[(name, version)]);
+        if raised != contents {
+            transaction.stage(&path, raised);
+            updated.push(path);
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Returns the version file `contents` with its requirements on the given
+/// packages that the new versions no longer satisfy raised to them: to the
+/// version itself in a Cargo manifest, to `^version` in a `package.json`.
+/// Requirements that cannot be parsed, e.g. `workspace:*`, are kept. The
+/// overrides of a Cargo manifest are advanced as well: the requirements of
+/// its `[patch.<registry>]` entries, and the `[replace]` entries whose
+/// `name:version` spec names an older version.
+fn with_requirements(format: Format, contents: &str, versions: &[(&str, &Version)]) -> String {
+    let outdated = |requirement: Option<&str>, version: &Version| {
+        requirement
+            .and_then(|requirement| VersionReq::parse(requirement).ok())
+            .is_some_and(|requirement| !requirement.matches(version))
+    };
+
+    match format {
+        Format::Cargo => {
+            let mut manifest = match contents.parse::<Document>() {
+                Ok(manifest) => manifest,
+                Err(_) => return contents.to_string(),
+            };
+            let targets = manifest["target"]
+                .as_table()
+                .map(|targets| {
+                    targets
+                        .iter()
+                        .map(|(target, _)| target.to_string())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let mut tables = vec![];
+            for section in DEPENDENCY_SECTIONS.iter() {
+                tables.push(vec![section.to_string()]);
+                for target in &targets {
+                    tables.push(vec![
+                        String::from("target"),
+                        target.clone(),
+                        section.to_string(),
+                    ]);
+                }
+            }
+            if let Some(registries) = manifest["patch"].as_table() {
+                for (registry, _) in registries.iter() {
+                    tables.push(vec![String::from("patch"), registry.to_string()]);
+                }
+            }
+            for path in tables {
+                let keys = path
+                    .iter()
+                    .fold(&manifest.root, |item, key| &item[key.as_str()])
+                    .as_table()
+                    .map(|table| {
+                        table
+                            .iter()
+                            .map(|(key, dependency)| {
+                                (
+                                    key.to_string(),
+                                    dependency["package"].as_str().unwrap_or(key).to_string(),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                for (key, name) in keys {
+                    let version = match versions.iter().find(|(package, _)| *package == name) {
+                        Some((_, version)) => version,
+                        None => continue,
+                    };
+                    let dependency = path
+                        .iter()
+                        .fold(&mut manifest.root, |item, key| &mut item[key.as_str()]);
+                    let dependency = &mut dependency[key.as_str()];
+                    if dependency.is_str() {
+                        if outdated(dependency.as_str(), version) {
+                            *dependency = value(version.to_string());
+                        }
+                    } else if outdated(dependency["version"].as_str(), version) {
+                        dependency["version"] = value(version.to_string());
+                        if let Some(table) = dependency.as_inline_table_mut() {
+                            table.fmt();
+                        }
+                    }
+                }
+            }
+
+            // Keys cannot be renamed in place, so outdated [replace] specs
+            // are replaced in the text, where they are quoted.
+            let specs = manifest["replace"]
+                .as_table()
+                .map(|table| {
+                    table
+                        .iter()
+                        .filter_map(|(spec, _)| replaced_spec(spec, versions))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let mut updated = manifest.to_string();
+            for (spec, advanced) in specs {
+                for quote in &["\"", "'"] {
+                    updated = updated.replace(
+                        &format!("{}{}{}", quote, spec, quote),
+                        &format!("{}{}{}", quote, advanced, quote),
+                    );
+                }
+            }
+
+            updated
+        }
+        Format::PackageJson => {
+            let mut replacements = vec![];
+            for section in NPM_DEPENDENCY_SECTIONS.iter() {
+                for (name, version) in versions {
+                    if let Some(range) = adapter::json_string_at(contents, &[section, name]) {
+                        if outdated(Some(&contents[range.clone()]), version) {
+                            replacements.push((range, format!("^{}", version)));
+                        }
+                    }
+                }
+            }
+            replacements.sort_by_key(|(range, _)| range.start);
+
+            let mut updated = contents.to_string();
+            for (range, requirement) in replacements.into_iter().rev() {
+                updated.replace_range(range, &requirement);
+            }
+
+            updated
+        }
+        _ => contents.to_string(),
+    }
+}
+
+/// The `[replace]` spec (`name:version`, optionally after a source URL and
+/// `#`) advanced to the new version of its package, if it names an older
+/// one.
+fn replaced_spec(spec: &str, versions: &[(&str, &Version)]) -> Option<(String, String)> {
+    let (source, package) = match spec.rsplit_once('#') {
+        Some((_, package)) => (&spec[..spec.len() - package.len()], package),
+        None => ("", spec),
+    };
+    let (name, old) = package.split_once(':')?;
+    let old = Version::parse(old).ok()?;
+    let (_, version) = versions.iter().find(|(package, _)| *package == name)?;
+    if old >= **version {
+        return None;
+    }
+
+    Some((spec.to_string(), format!("{}{}:{}", source, name, version)))
+}
+
+/// The names of the packages a version file depends on: the dependencies of
+/// a Cargo manifest or `package.json`; none for other formats.
+fn package_dependencies(format: Format, contents: &str) -> Vec<String> {
+    match format {
+        Format::Cargo => contents
+            .parse::<Document>()
+            .map(|manifest| dependency_names(&manifest))
+            .unwrap_or_default(),
+        Format::PackageJson => NPM_DEPENDENCY_SECTIONS
+            .iter()
+            .flat_map(|section| adapter::json_keys_at(contents, &[section]).unwrap_or_default())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Which of the packages in the given directories have files that changed
+/// since the git ref `since`, in commits or in the work tree (untracked
+/// files aside). A file belongs to the
+/// innermost package directory containing it, so that a root package does
+/// not count as changed with every member.
+pub fn changed_since(since: &str, dirs: &[&Path]) -> Result<Vec<bool>, Error> {
+    let toplevel = PathBuf::from(git::run(&["rev-parse", "--show-toplevel"])?);
+    let dirs = dirs
+        .iter()
+        .map(|dir| {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            fs::canonicalize(dir).ok()
+        })
+        .collect::<Vec<_>>();
+    let mut changed = vec![false; dirs.len()];
+
+    for path in git::run(&["diff", "--name-only", since])?.lines() {
+        let path = toplevel.join(path);
+        let owner = dirs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, dir)| Some((index, dir.as_ref()?)))
+            .filter(|(_, dir)| path.starts_with(dir))
+            .max_by_key(|(_, dir)| dir.components().count());
+        if let Some((index, _)) = owner {
+            changed[index] = true;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Adds to the selected packages every package that depends on one of
+/// them, directly or transitively.
+pub fn with_dependents(
+    mut selected: Vec<bool>,
+    names: &[&str],
+    dependencies: &[Vec<String>],
+) -> Vec<bool> {
+    loop {
+        let dependents = (0..names.len())
+            .filter(|&index| !selected[index])
+            .filter(|&index| {
+                dependencies[index].iter().any(|dependency| {
+                    names
+                        .iter()
+                        .zip(&selected)
+                        .any(|(name, &chosen)| chosen && name == dependency)
+                })
+            })
+            .collect::<Vec<_>>();
+        if dependents.is_empty() {
+            return selected;
+        }
+        for index in dependents {
+            selected[index] = true;
+        }
+    }
+}
+
+/// The names of the packages a manifest depends on, including target
+/// specific dependencies; renamed dependencies count by their `package`.
+fn dependency_names(manifest: &Document) -> Vec<String> {
+    let mut tables = vec![];
+    for section in DEPENDENCY_SECTIONS.iter() {
+        tables.push((*section, &manifest[*section]));
+        if let Some(targets) = manifest["target"].as_table() {
+            tables.extend(
+                targets
+                    .iter()
+                    .map(|(_, target)| (*section, &target[*section])),
+            );
+        }
+    }
+
+    let mut names = vec![];
+    for (section, table) in tables {
+        let table = match table.as_table() {
+            Some(table) => table,
+            None => continue,
+        };
+        for (key, dependency) in table.iter() {
+            if section == "dev-dependencies"
+                && dependency["version"].is_none()
+                && !dependency.is_str()
+            {
+                continue;
+            }
+            names.push(dependency["package"].as_str().unwrap_or(key).to_string());
+        }
+    }
+
+    names
+}
+
+fn display_dir(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        String::from(".")
+    } else {
+        dir.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::parser;
+
+    fn write_package(dir: &Path, name: &str, version: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            format!(
+                "{{\n  \"name\": \"{}\",\n  \"version\": \"{}\"\n}}\n",
+                name, version
+            ),
+        )
+        .unwrap();
+    }
+
+    /// Tests that npm/yarn and pnpm declarations are found, including
+    /// `**` and excluded patterns, and that directories without a
+    /// `package.json` are no packages.
+    #[test]
+    fn test_members() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        write_package(&root.join("packages/a"), "a", "1.0.0");
+        write_package(&root.join("packages/b"), "b", "1.0.0");
+        write_package(&root.join("apps/web/site"), "site", "1.0.0");
+        fs::create_dir_all(root.join("packages/docs")).unwrap();
+        fs::write(
+            root.join("package.json"),
+            "{\"private\": true, \"workspaces\": {\"packages\": [\"packages/*\"]}}",
+        )
+        .unwrap();
+        let relative = |members: Vec<String>| {
+            members
+                .iter()
+                .map(|path| {
+                    Path::new(path)
+                        .strip_prefix(root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            relative(members(root).unwrap()),
+            ["packages/a/package.json", "packages/b/package.json"]
+        );
+
+        fs::write(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  # every app\n  - 'apps/**'\n  - \"packages/*\"\n  - '!packages/b'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            relative(members(root).unwrap()),
+            ["apps/web/site/package.json", "packages/a/package.json"]
+        );
+
+        assert_eq!(
+            members(&root.join("packages")).unwrap_err().code,
+            Code::Workspace
+        );
+    }
+
+    /// Tests both versioning modes, including the versions recorded in the
+    /// root lockfile.
+    #[test]
+    fn test_bump() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        fs::write(
+            root.join("package.json"),
+            "{\"workspaces\": [\"packages/*\"]}",
+        )
+        .unwrap();
+        fs::write(
+            root.join("package-lock.json"),
+            "{\"packages\": {\"\": {}, \"packages/a\": {\"version\": \"1.2.0\"}, \"packages/b\": {\"version\": \"2.0.1\"}}}",
+        )
+        .unwrap();
+        let matches = parser().get_matches_from(["semvercli", "bump", "--minor"]);
+        let bump_matches = matches.subcommand_matches("bump").unwrap();
+        let version = |name: &str| {
+            adapter::read_file_version(&root.join(name).join("package.json").to_string_lossy())
+                .unwrap()
+                .to_string()
+        };
+
+        for versioning in [Versioning::Independent, Versioning::Fixed].iter() {
+            write_package(&root.join("packages/a"), "a", "1.2.0");
+            write_package(&root.join("packages/b"), "b", "2.0.1");
+            let config = Config {
+                root: root.to_path_buf(),
+                versioning: *versioning,
+                ..Config::default()
+            };
+            bump(&config, bump_matches, &mut Vec::new()).unwrap();
+
+            let expected = match versioning {
+                Versioning::Independent => ("1.3.0", "2.1.0"),
+                Versioning::Fixed => ("2.1.0", "2.1.0"),
+            };
+            assert_eq!(
+                (
+                    version("packages/a").as_str(),
+                    version("packages/b").as_str()
+                ),
+                expected
+            );
+        }
+        assert_eq!(
+            fs::read_to_string(root.join("package-lock.json")).unwrap(),
+            "{\"packages\": {\"\": {}, \"packages/a\": {\"version\": \"2.1.0\"}, \"packages/b\": {\"version\": \"2.1.0\"}}}"
+        );
+    }
+
+    /// Tests that configured packages of different ecosystems are bumped
+    /// together, with npm workspaces expanded to their packages.
+    #[test]
+    fn test_configured_packages() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        fs::create_dir_all(root.join("crates/core")).unwrap();
+        fs::create_dir_all(root.join("charts/app")).unwrap();
+        fs::write(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\nversion = \"1.4.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("charts/app/Chart.yaml"),
+            "name: app\nversion: 0.9.2\n",
+        )
+        .unwrap();
+        write_package(&root.join("web/packages/ui"), "ui", "1.2.0");
+        fs::write(
+            root.join("web/package.json"),
+            "{\"private\": true, \"workspaces\": [\"packages/*\"]}",
+        )
+        .unwrap();
+        fs::write(
+            root.join("web/package-lock.json"),
+            "{\"packages\": {\"packages/ui\": {\"version\": \"1.2.0\"}}}",
+        )
+        .unwrap();
+        let document = "packages = [\"crates/*/Cargo.toml\", \"web/package.json\", \"charts/app/Chart.yaml\"]\n\
+                        versioning = \"fixed\"\n"
+            .parse::<toml_edit::Document>()
+            .unwrap();
+        let config = Config::from_document(root.to_path_buf(), &document).unwrap();
+        let matches = parser().get_matches_from(["semvercli", "bump", "--patch"]);
+
+        bump(
+            &config,
+            matches.subcommand_matches("bump").unwrap(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        for path in [
+            "crates/core/Cargo.toml",
+            "charts/app/Chart.yaml",
+            "web/packages/ui/package.json",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                adapter::read_file_version(&root.join(path).to_string_lossy())
+                    .unwrap()
+                    .to_string(),
+                "1.4.1"
+            );
+        }
+        assert_eq!(
+            fs::read_to_string(root.join("web/package-lock.json")).unwrap(),
+            "{\"packages\": {\"packages/ui\": {\"version\": \"1.4.1\"}}}"
+        );
+    }
+    /// Tests that every package has a changelog scoped to its directory,
+    /// without the packages nested in it, and that fixed versioning has none.
+    #[test]
+    fn test_changelogs() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        write_package(root, "app", "1.0.0");
+        write_package(&root.join("packages/ui"), "ui", "1.2.0");
+        let document = "packages = [\"package.json\", \"packages/*/package.json\"]\n"
+            .parse::<toml_edit::Document>()
+            .unwrap();
+        let config = Config::from_document(root.to_path_buf(), &document).unwrap();
+        let root = root.display();
+
+        assert_eq!(
+            changelogs(&config).unwrap(),
+            [
+                (
+                    format!("{}/CHANGELOG.md", root),
+                    vec![root.to_string(), format!(":(exclude){}/packages/ui", root)]
+                ),
+                (
+                    format!("{}/packages/ui/CHANGELOG.md", root),
+                    vec![format!("{}/packages/ui", root)]
+                ),
+            ]
+        );
+        let fixed = Config {
+            versioning: Versioning::Fixed,
+            ..config
+        };
+        assert!(changelogs(&fixed).is_err());
+    }
+
+    /// Tests that the crates of a Cargo workspace are found with their
+    /// dependencies on each other, including renamed and inherited ones.
+    #[test]
+    fn test_cargo_crates() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        let write = |path: &str, contents: &str| {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n\
+             [workspace.package]\nversion = \"2.0.0\"\n",
+        );
+        write(
+            "crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion = { workspace = true }\n\
+             [dev-dependencies]\ntesting = { path = \"../testing\" }\n",
+        );
+        write(
+            "crates/app/Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\
+             [dependencies]\nbase = { package = \"core\", path = \"../core\", version = \"2\" }\nserde = \"1\"\n\
+             [target.'cfg(unix)'.dependencies]\ntesting = { path = \"../testing\", version = \"0.1\" }\n",
+        );
+        write(
+            "crates/testing/Cargo.toml",
+            "[package]\nname = \"testing\"\nversion = \"0.1.0\"\npublish = false\n",
+        );
+        write(
+            "crates/old/Cargo.toml",
+            "[package]\nname = \"old\"\nversion = \"0.1.0\"\n",
+        );
+
+        let crates = cargo_crates(&root.join("Cargo.toml").to_string_lossy()).unwrap();
+        let summary = crates
+            .iter()
+            .map(|krate| {
+                (
+                    krate.name.as_str(),
+                    krate.version.to_string(),
+                    krate.publish,
+                    krate.dependencies.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            [
+                (
+                    "app",
+                    String::from("1.0.0"),
+                    true,
+                    vec![String::from("core"), String::from("testing")]
+                ),
+                ("core", String::from("2.0.0"), true, vec![]),
+                ("testing", String::from("0.1.0"), false, vec![]),
+            ]
+        );
+        assert_eq!(
+            cargo_crates(&root.join("crates/app/Cargo.toml").to_string_lossy())
+                .unwrap_err()
+                .code,
+            Code::Workspace
+        );
+    }
+
+    /// Tests that the crates of the Cargo workspace the manifest declares
+    /// are bumped, except those inheriting the workspace's version.
+    #[test]
+    fn test_cargo_workspace() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        let write = |path: &str, contents: &str| {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"1.0.0\"\n\
+             [workspace]\nmembers = [\"crates/*\"]\n[workspace.package]\nversion = \"2.0.0\"\n",
+        );
+        write(
+            "crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion = \"0.3.1\"\n",
+        );
+        write(
+            "crates/macros/Cargo.toml",
+            "[package]\nname = \"macros\"\nversion = { workspace = true }\n",
+        );
+        let config = Config {
+            root: root.to_path_buf(),
+            ..Config::default()
+        };
+        let matches = parser().get_matches_from(["semvercli", "bump", "--minor"]);
+        bump(
+            &config,
+            matches.subcommand_matches("bump").unwrap(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let versions = versions(&config)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _, version)| format!("{} {}", name, version))
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["cli 1.1.0", "core 0.4.0"]);
+    }
+
+    #[test]
+    fn test_matrix() {
+        let change = |name: &str, old: &str, new: &str| Change {
+            name: name.to_string(),
+            path: format!("crates/{}/Cargo.toml", name),
+            old: Version::parse(old).unwrap(),
+            new: Version::parse(new).unwrap(),
+        };
+
+        assert_eq!(matrix(&[]), "[]");
+        assert_eq!(
+            matrix(&[change("core", "1.4.0", "1.4.1"), change("app", "0.2.0", "0.2.1")]),
+            "[{\"name\": \"core\", \"path\": \"crates/core/Cargo.toml\", \"old\": \"1.4.0\", \"new\": \"1.4.1\"}, \
+             {\"name\": \"app\", \"path\": \"crates/app/Cargo.toml\", \"old\": \"0.2.0\", \"new\": \"0.2.1\"}]"
+        );
+    }
+    #[test]
+    fn test_with_dependents() {
+        let names = ["core", "app", "cli", "docs"];
+        let dependencies = vec![
+            vec![],
+            vec![String::from("core")],
+            vec![String::from("app"), String::from("serde")],
+            vec![],
+        ];
+
+        assert_eq!(
+            with_dependents(vec![true, false, false, false], &names, &dependencies),
+            [true, true, true, false]
+        );
+        assert_eq!(
+            with_dependents(vec![false, true, false, false], &names, &dependencies),
+            [false, true, true, false]
+        );
+    }
+    /// Tests that dependents of a bumped package get a PATCH bump and
+    /// requirements it no longer satisfies.
+    #[test]
+    fn test_cascade() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        let write = |name: &str, version: &str, dependencies: &str| {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(
+                root.join(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"{}\"\n\n[dependencies]\n{}",
+                    name, version, dependencies
+                ),
+            )
+            .unwrap();
+        };
+        write("core", "1.4.0-rc.1", "");
+        write(
+            "app",
+            "0.2.0",
+            "core = { path = \"../core\", version = \"1.4.0-rc.1\" }\n",
+        );
+        write(
+            "cli",
+            "0.2.0",
+            "app = { path = \"../app\", version = \"0.2\" }\n",
+        );
+        write("docs", "1.0.0", "serde = \"1\"\n");
+        let document = "packages = [\"*/Cargo.toml\"]\n"
+            .parse::<Document>()
+            .unwrap();
+        let config = Config::from_document(root.to_path_buf(), &document).unwrap();
+        let matches = parser().get_matches_from([
+            "semvercli",
+            "bump",
+            "--workspace",
+            "--major",
+            "--if-pre",
+            "--cascade",
+        ]);
+        let manifest = |name: &str| fs::read_to_string(root.join(name).join("Cargo.toml")).unwrap();
+
+        let (changes, _) = bump(
+            &config,
+            matches.subcommand_matches("bump").unwrap(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(changes.len(), 3);
+        assert_eq!(
+            manifest("app"),
+            "[package]\nname = \"app\"\nversion = \"0.2.1\"\n\n[dependencies]\n\
+             core = { path = \"../core\", version = \"2.0.0\" }\n"
+        );
+        assert_eq!(
+            manifest("cli"),
+            "[package]\nname = \"cli\"\nversion = \"0.2.1\"\n\n[dependencies]\n\
+             app = { path = \"../app\", version = \"0.2\" }\n"
+        );
+        assert!(manifest("core").contains("version = \"2.0.0\""));
+        assert!(manifest("docs").contains("version = \"1.0.0\""));
+    }
+    /// Tests that bumping one crate with --update-dependents raises the
+    /// requirements of the other workspace crates on it, and nothing else.
+    #[test]
+    fn test_update_dependents() {
+        let tmpdir = tempdir().unwrap();
+        let root = tmpdir.path();
+        let write = |path: &str, contents: &str| {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[patch.crates-io]\n\
+             foo = { git = \"https://example.com/foo\", version = \"0.3\" }\n\n\
+             [replace]\n\"foo:0.3.2\" = { path = \"vendor/foo\" }\n\"bar:0.1.0\" = { path = \"vendor/bar\" }\n",
+        );
+        write(
+            "crates/foo/Cargo.toml",
+            "[package]\nname = \"foo\"\nversion = \"0.3.2\"\n",
+        );
+        write(
+            "crates/bar/Cargo.toml",
+            "[package]\nname = \"bar\"\nversion = \"1.0.0\"\n\n[dependencies]\nfoo = { path = \"../foo\", version = \"0.3\" }\n\n[dev-dependencies]\nfoo-test = { package = \"foo\", path = \"../foo\", version = \"0.3.1\" }\n",
+        );
+        write(
+            "crates/baz/Cargo.toml",
+            "[package]\nname = \"baz\"\nversion = \"1.0.0\"\n\n[dependencies]\nfoo = \"0.4\"\n",
+        );
+        let manifest = |name: &str| {
+            fs::read_to_string(root.join("crates").join(name).join("Cargo.toml")).unwrap()
+        };
+        let foo = root.join("crates/foo/Cargo.toml");
+        let matches = parser().get_matches_from([
+            "semvercli",
+            "--manifest-path",
+            foo.to_str().unwrap(),
+            "bump",
+            "--minor",
+            "--update-dependents",
+        ]);
+
+        crate::execute(&matches, &mut Vec::new()).unwrap();
+
+        assert!(manifest("foo").contains("version = \"0.4.0\""));
+        assert_eq!(
+            manifest("bar"),
+            "[package]\nname = \"bar\"\nversion = \"1.0.0\"\n\n[dependencies]\n\
+             foo = { path = \"../foo\", version = \"0.4.0\" }\n\n[dev-dependencies]\n\
+             foo-test = { package = \"foo\", path = \"../foo\", version = \"0.4.0\" }\n"
+        );
+        assert!(manifest("baz").ends_with("foo = \"0.4\"\n"));
+        assert_eq!(
+            fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[patch.crates-io]\n\
+             foo = { git = \"https://example.com/foo\", version = \"0.4.0\" }\n\n\
+             [replace]\n\"foo:0.4.0\" = { path = \"vendor/foo\" }\n\"bar:0.1.0\" = { path = \"vendor/bar\" }\n"
+        );
+        assert_eq!(
+            replaced_spec(
+                "https://github.com/rust-lang/crates.io-index#foo:0.3.2",
+                &[("foo", &Version::parse("0.4.0").unwrap())]
+            ),
+            Some((
+                String::from("https://github.com/rust-lang/crates.io-index#foo:0.3.2"),
+                String::from("https://github.com/rust-lang/crates.io-index#foo:0.4.0")
+            ))
+        );
+        assert_eq!(
+            cargo_workspace_root(foo.to_str().unwrap()).unwrap(),
+            Some(root.join("Cargo.toml").to_string_lossy().into_owned())
+        );
+    }
+}